@@ -0,0 +1,80 @@
+use std::hint::black_box;
+use std::sync::Arc;
+
+use bts_rs::engine::{Candle, CandleBuilder, CandleFrame};
+use bts_rs::metrics::Metrics;
+use chrono::{DateTime, Duration};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+/// Generates deterministic minute candles.
+fn generate_candles(count: usize) -> Vec<Candle> {
+    let start = DateTime::default();
+    (0..count)
+        .map(|i| {
+            let open = 100.0 + (i as f64 * 0.01).sin() * 10.0;
+            let close = 100.0 + ((i + 1) as f64 * 0.01).sin() * 10.0;
+            let open_time = start + Duration::minutes(i as i64);
+            CandleBuilder::builder()
+                .open(open)
+                .high(open.max(close) + 0.5)
+                .low(open.min(close) - 0.5)
+                .close(close)
+                .volume(1000.0)
+                .bid(500.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::minutes(1))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+fn scans(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    for count in [100_000, 1_000_000] {
+        let candles = generate_candles(count);
+        let frame = CandleFrame::from(candles.as_slice());
+
+        group.bench_with_input(BenchmarkId::new("max_high/candles", count), &candles, |b, candles| {
+            b.iter(|| {
+                black_box(candles)
+                    .iter()
+                    .map(|c| c.high())
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("max_high/frame", count), &frame, |b, frame| {
+            b.iter(|| {
+                black_box(frame)
+                    .high()
+                    .iter()
+                    .copied()
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("mean_close/candles", count), &candles, |b, candles| {
+            b.iter(|| black_box(candles).iter().map(|c| c.close()).sum::<f64>() / count as f64)
+        });
+        group.bench_with_input(BenchmarkId::new("mean_close/frame", count), &frame, |b, frame| {
+            b.iter(|| black_box(frame).close().iter().sum::<f64>() / count as f64)
+        });
+    }
+    group.finish();
+}
+
+fn metrics(c: &mut Criterion) {
+    let mut group = c.benchmark_group("metrics");
+    group.sample_size(10);
+    for count in [100_000, 1_000_000] {
+        let frame = Arc::new(CandleFrame::from(generate_candles(count).as_slice()));
+        let metrics = Metrics::new(Vec::new(), 1000.0, 1000.0, 0.0, 0.0).with_frame(frame);
+
+        group.bench_with_input(BenchmarkId::new("equity_curve", count), &metrics, |b, metrics| {
+            b.iter(|| black_box(metrics).equity_curve())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scans, metrics);
+criterion_main!(benches);
@@ -19,6 +19,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     #[cfg(feature = "draws")]
     let mut ema_history = vec![];
+    #[cfg(feature = "draws")]
+    let mut histogram_history = vec![];
 
     bts.run(|bt, candle| {
         let close = candle.close();
@@ -26,7 +28,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         let MovingAverageConvergenceDivergenceOutput { histogram, .. } = macd.next(close);
 
         #[cfg(feature = "draws")]
-        ema_history.push(output);
+        {
+            ema_history.push(output);
+            histogram_history.push(histogram);
+        }
 
         let balance = bt.free_balance()?;
         // 21: minimum to trade
@@ -78,10 +83,11 @@ fn main() -> Result<(), Box<dyn Error>> {
             .draw_output(DrawOutput::Svg("bts.svg".to_owned()))
             .show_volume(true);
         #[cfg(feature = "metrics")]
-        let options = options.show_metrics(true);
+        let options = options.show_metrics(true).show_trades(true);
         let draw = Draw::from(&bts)
             .with_options(options)
-            .append_series(Series::Lines(ema_history));
+            .append_series(Series::Lines(ema_history))
+            .append_panel("MACD histogram", Series::Lines(histogram_history));
         draw.plot()?;
     }
 
@@ -0,0 +1,95 @@
+//! REST service running backtests with the built-in strategies.
+//!
+//! Usage: `bts-service [ADDRESS]` (defaults to `127.0.0.1:8080`).
+//!
+//! Built-in strategies:
+//! - `buy_and_hold`: buys with a percentage of the balance (`percent`, default 100) on the first candle.
+//! - `sma_cross`: goes long when the `fast` simple moving average crosses above the `slow` one and closes
+//!   the position when it crosses back, investing a percentage of the free balance (`percent`, default 100).
+
+use std::collections::VecDeque;
+
+use bts_rs::errors::{Error, Result};
+use bts_rs::prelude::*;
+
+fn percent(params: &serde_json::Value) -> Result<f64> {
+    let percent = params["percent"].as_f64().unwrap_or(100.0);
+    if percent <= 0.0 || percent > 100.0 {
+        return Err(Error::Msg(format!("Invalid percent: {percent}")));
+    }
+    Ok(percent)
+}
+
+fn period(params: &serde_json::Value, name: &'static str) -> Result<usize> {
+    params[name]
+        .as_u64()
+        .filter(|period| *period > 0)
+        .map(|period| period as usize)
+        .ok_or(Error::MissingField(name))
+}
+
+fn buy_and_hold(params: &serde_json::Value) -> Result<BoxedStrategy> {
+    let percent = percent(params)?;
+    Ok(Box::new(move |bt, candle| {
+        if bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(percent) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    }))
+}
+
+fn sma_cross(params: &serde_json::Value) -> Result<BoxedStrategy> {
+    let percent = percent(params)?;
+    let fast = period(params, "fast")?;
+    let slow = period(params, "slow")?;
+    if fast >= slow {
+        return Err(Error::Msg(
+            "The fast period must be lower than the slow period".to_string(),
+        ));
+    }
+
+    let mut closes = VecDeque::with_capacity(slow);
+    Ok(Box::new(move |bt, candle| {
+        if closes.len() == slow {
+            closes.pop_front();
+        }
+        closes.push_back(candle.close());
+        if closes.len() < slow {
+            return Ok(());
+        }
+
+        let fast_sma = closes.iter().rev().take(fast).sum::<f64>() / fast as f64;
+        let slow_sma = closes.iter().sum::<f64>() / slow as f64;
+        let position = bt.positions().next().copied();
+        match position {
+            None if fast_sma > slow_sma && bt.orders().count() == 0 => {
+                let quantity = bt.free_balance()?.how_many(percent) / candle.close();
+                bt.place_order(
+                    candle,
+                    (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+                )?;
+            }
+            Some(position) if fast_sma < slow_sma => {
+                bt.close_position(candle, &position, candle.close(), true)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }))
+}
+
+fn main() -> Result<()> {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let registry = StrategyRegistry::new()
+        .register("buy_and_hold", buy_and_hold)
+        .register("sma_cross", sma_cross);
+
+    println!("bts-service listening on {addr}");
+    Service::new(registry)
+        .with_error_handler(|e| eprintln!("bts-service: {e}"))
+        .serve(addr)
+}
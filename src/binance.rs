@@ -0,0 +1,305 @@
+//! Binance klines downloader.
+//!
+//! This module provides a blocking client that downloads the klines (candles) of a symbol from the
+//! public market data API of Binance, without any API key:
+//! - The date range is split into as many requests as needed (pagination).
+//! - The rate limits are respected: the client waits when the used request weight reaches the limit
+//!   and retries after the delay given by Binance when a request is rejected (HTTP 429/418).
+//!
+//! It needs to enable `data-binance` feature to use it.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::engine::{Candle, CandleBuilder};
+use crate::errors::{Error, Result};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde_json::Value;
+use ureq::Agent;
+
+/// The kline intervals supported by Binance.
+const INTERVALS: &[&str] = &[
+    "1s", "1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w", "1M",
+];
+
+/// Blocking client downloading klines from Binance.
+///
+/// ### Example
+/// ```rust,no_run
+/// use bts_rs::prelude::*;
+/// use chrono::{TimeZone, Utc};
+///
+/// let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+/// let end = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+/// let candles = BinanceClient::spot().klines("BTCUSDT", "1h", start, end).unwrap();
+/// let bts = Backtest::new(candles.into(), 1000.0, None).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct BinanceClient {
+    agent: Agent,
+    base_url: String,
+    path: &'static str,
+    limit: usize,
+    weight_limit: u32,
+    max_retries: u32,
+}
+
+impl BinanceClient {
+    /// Creates a client of the spot market (`api.binance.com`).
+    pub fn spot() -> Self {
+        Self::new("https://api.binance.com", "/api/v3/klines", 1000, 6000)
+    }
+
+    /// Creates a client of the USD-M futures market (`fapi.binance.com`).
+    pub fn usdm_futures() -> Self {
+        Self::new("https://fapi.binance.com", "/fapi/v1/klines", 1500, 2400)
+    }
+
+    fn new(base_url: &str, path: &'static str, limit: usize, weight_limit: u32) -> Self {
+        let config = Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(Duration::from_secs(30)))
+            .build();
+        Self {
+            agent: Agent::new_with_config(config),
+            base_url: base_url.to_string(),
+            path,
+            limit,
+            weight_limit,
+            max_retries: 5,
+        }
+    }
+
+    /// Sets the base URL of the API (e.g., a mirror such as `https://data-api.binance.vision`).
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the number of klines requested at once (1000 at most for the spot market).
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    /// Sets the number of retries of a request rejected by the rate limits.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Downloads the klines of a symbol.
+    ///
+    /// ### Arguments
+    /// * `symbol` - The symbol (e.g., `BTCUSDT`).
+    /// * `interval` - The Binance interval (e.g., `1m`, `4h`, `1d`).
+    /// * `start` - The open time of the first kline (inclusive).
+    /// * `end` - The end of the range (exclusive).
+    ///
+    /// ### Returns
+    /// The candles sorted by time, or an error if the interval is unknown or a request failed.
+    /// The close time of a candle is the open time of the next one.
+    pub fn klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Candle>> {
+        if !INTERVALS.contains(&interval) {
+            return Err(Error::Binance(format!("Unknown interval: {interval}")));
+        }
+        if start >= end {
+            return Err(Error::InvalideTimes(start, end));
+        }
+
+        let end = end.timestamp_millis() - 1;
+        let mut cursor = start.timestamp_millis();
+        let mut candles = Vec::new();
+        while cursor <= end {
+            let body = self.request(&[
+                ("symbol", symbol.to_string()),
+                ("interval", interval.to_string()),
+                ("startTime", cursor.to_string()),
+                ("endTime", end.to_string()),
+                ("limit", self.limit.to_string()),
+            ])?;
+            let page = parse_klines(&body)?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = last.open_time().timestamp_millis() + 1;
+            let full = page.len() >= self.limit;
+            candles.extend(page);
+            if !full {
+                break;
+            }
+        }
+
+        Ok(candles)
+    }
+
+    /// Sends a request, waiting for the rate limits.
+    fn request(&self, query: &[(&str, String)]) -> Result<String> {
+        let url = format!("{}{}", self.base_url, self.path);
+        let mut retries = 0;
+        loop {
+            let mut response = self
+                .agent
+                .get(&url)
+                .query_pairs(query.iter().map(|(k, v)| (*k, v.as_str())))
+                .call()
+                .map_err(|e| Error::Binance(e.to_string()))?;
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+            };
+            let used_weight = header("x-mbx-used-weight-1m");
+            let retry_after = header("retry-after");
+
+            let status = response.status().as_u16();
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .map_err(|e| Error::Binance(e.to_string()))?;
+            match status {
+                200 => {
+                    if used_weight.is_some_and(|w| w >= self.weight_limit as u64) {
+                        sleep(until_next_minute());
+                    }
+                    return Ok(body);
+                }
+                //? 429 when the limit is exceeded, 418 when the IP is banned for ignoring the 429
+                429 | 418 if retries < self.max_retries => {
+                    retries += 1;
+                    sleep(retry_after.map_or_else(until_next_minute, Duration::from_secs));
+                }
+                _ => return Err(Error::Binance(format!("HTTP {status}: {body}"))),
+            }
+        }
+    }
+}
+
+/// Returns the duration until the request weight is reset (every minute).
+fn until_next_minute() -> Duration {
+    let millis = Utc::now().timestamp_millis().rem_euclid(60_000);
+    Duration::from_millis((60_000 - millis) as u64)
+}
+
+/// Parses the klines returned by Binance.
+///
+/// A kline is an array `[open time, open, high, low, close, volume, close time, quote volume,
+/// trades, taker buy volume, taker buy quote volume, ignore]`. The taker buy volume is the bid
+/// volume of the candle.
+fn parse_klines(body: &str) -> Result<Vec<Candle>> {
+    let klines = serde_json::from_str::<Vec<Vec<Value>>>(body).map_err(|e| Error::Binance(e.to_string()))?;
+    klines
+        .iter()
+        .map(|kline| {
+            let number = |i: usize| {
+                let value = kline.get(i).ok_or(Error::Binance(format!("Missing kline field {i}")))?;
+                match value {
+                    Value::String(s) => s.parse::<f64>().ok(),
+                    v => v.as_f64(),
+                }
+                .ok_or(Error::Binance(format!("Invalid kline field {i}: {value}")))
+            };
+            let time = |i: usize| {
+                DateTime::from_timestamp_millis(number(i)? as i64)
+                    .ok_or(Error::Binance(format!("Invalid kline time {i}")))
+            };
+            CandleBuilder::builder()
+                .open(number(1)?)
+                .high(number(2)?)
+                .low(number(3)?)
+                .close(number(4)?)
+                .volume(number(5)?)
+                .bid(number(9)?)
+                .open_time(time(0)?)
+                .close_time(time(6)? + TimeDelta::milliseconds(1))
+                .build()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn parse_binance_klines() {
+    let body = r#"[[1704067200000,"42283.58","42554.57","42261.02","42475.23","1271.68108",1704070799999,"53957248.97",47134,"682.57581","28957416.82","0"]]"#;
+    let candles = parse_klines(body).unwrap();
+    assert_eq!(candles.len(), 1);
+    let candle = candles[0];
+    assert_eq!(
+        (candle.open(), candle.high(), candle.low(), candle.close()),
+        (42283.58, 42554.57, 42261.02, 42475.23)
+    );
+    assert_eq!((candle.volume(), candle.bid()), (1271.68108, 682.57581));
+    assert_eq!(candle.open_time().timestamp(), 1704067200);
+    assert_eq!(candle.close_time().timestamp(), 1704070800);
+
+    assert!(parse_klines(r#"{"code":-1121,"msg":"Invalid symbol."}"#).is_err());
+    assert!(parse_klines(r#"[[1704067200000,"42283.58"]]"#).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn klines_pagination_and_rate_limit() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let kline = |minute: i64| {
+        let open_time = minute * 60_000;
+        format!(
+            r#"[{open_time},"100","110","90","105","1",{},"105",1,"0.5","52.5","0"]"#,
+            open_time + 59_999
+        )
+    };
+    let responses = [
+        ("429 Too Many Requests", "Retry-After: 0\r\n", "{}".to_string()),
+        ("200 OK", "", format!("[{},{}]", kline(0), kline(1))),
+        ("200 OK", "", format!("[{}]", kline(2))),
+    ];
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for (status, headers, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            requests.push(line.clone());
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            let response = format!(
+                "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+
+    let client = BinanceClient::spot()
+        .with_base_url(&format!("http://{addr}/"))
+        .with_limit(2);
+    let start = DateTime::from_timestamp_millis(0).unwrap();
+    let end = DateTime::from_timestamp_millis(5 * 60_000).unwrap();
+    let candles = client.klines("BTCUSDT", "1m", start, end).unwrap();
+    assert_eq!(candles.len(), 3);
+    assert_eq!(candles[2].open_time().timestamp(), 120);
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].contains("symbol=BTCUSDT") && requests[0].contains("startTime=0"));
+    // the second page starts after the last kline of the first one
+    assert!(requests[2].contains("startTime=60001"));
+
+    assert!(client.klines("BTCUSDT", "2m", start, end).is_err());
+}
@@ -0,0 +1,611 @@
+//! Validation and repair of candle data.
+//!
+//! The engine expects candles in chronological order, one per interval. A duplicate, a candle out of
+//! order or a missing interval silently skews the timeframe aggregation and the drawdown timing, so
+//! the data should be checked with [`validate`] and fixed with [`repair`] before running a backtest.
+//!
+//! The candles can also be transformed into [`heikin_ashi`] candles or [`renko`] bricks, and run by
+//! the same `Backtest`. The orders are then filled at the prices of the transformed candles, not at the
+//! traded prices.
+//!
+//! The [`fingerprint`] of the candles and their [`Provenance`] identify the input data of a run.
+
+use std::sync::Arc;
+
+use crate::engine::{Candle, CandleBuilder};
+use crate::errors::{Error, Result};
+use crate::utils::{FNV_OFFSET, fnv1a};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// An issue found in a series of candles.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CandleIssue {
+    /// The candle at `index` opens at the same time as the previous one.
+    Duplicate {
+        /// Index of the candle.
+        index: usize,
+        /// Open time of the candle.
+        time: DateTime<Utc>,
+    },
+
+    /// The candle at `index` opens before the previous one.
+    OutOfOrder {
+        /// Index of the candle.
+        index: usize,
+        /// Open time of the candle.
+        time: DateTime<Utc>,
+    },
+
+    /// Some intervals are missing before the candle at `index`.
+    Gap {
+        /// Index of the candle after the gap.
+        index: usize,
+        /// Open time of the first missing candle.
+        from: DateTime<Utc>,
+        /// Open time of the candle after the gap.
+        to: DateTime<Utc>,
+        /// Number of missing candles.
+        missing: usize,
+    },
+}
+
+/// Strategy used by [`repair`] to fix a series of candles.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repair {
+    /// Drops the duplicate and out-of-order candles, and leaves the gaps.
+    Drop,
+    /// Sorts the candles, drops the duplicates and fills the gaps with copies of the previous candle.
+    ForwardFill,
+    /// Sorts the candles, drops the duplicates and fills the gaps with flat candles (open, high, low and
+    /// close at the previous close, without volume).
+    Flat,
+}
+
+/// Size of the bricks built by [`renko`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrickSize {
+    /// A fixed price move.
+    Fixed(f64),
+    /// The Average True Range of the first candles (the period); the bricks start after them.
+    Atr(usize),
+}
+
+/// Returns the interval of the candles, as the most frequent time between two consecutive candles (the
+/// shortest one on a tie).
+///
+/// ### Returns
+/// None if there are less than two candles in chronological order.
+pub fn interval(candles: &[Candle]) -> Option<TimeDelta> {
+    let mut deltas = candles
+        .windows(2)
+        .map(|w| w[1].open_time() - w[0].open_time())
+        .filter(|delta| *delta > TimeDelta::zero())
+        .collect::<Vec<_>>();
+    deltas.sort();
+    deltas
+        .chunk_by(|a, b| a == b)
+        .min_by_key(|chunk| std::cmp::Reverse(chunk.len()))
+        .map(|chunk| chunk[0])
+}
+
+/// Detects the duplicate timestamps, the out-of-order candles and the missing intervals.
+///
+/// The interval is inferred with [`interval`]. A candle is compared with the latest candle before it
+/// in chronological order, so one candle out of order is reported once.
+///
+/// ### Arguments
+/// * `candles` - The candles to validate.
+///
+/// ### Returns
+/// The issues in the order of the candles, empty if the candles are valid.
+pub fn validate(candles: &[Candle]) -> Vec<CandleIssue> {
+    let interval = interval(candles);
+    let mut issues = Vec::new();
+    let mut last = match candles.first() {
+        Some(candle) => candle.open_time(),
+        None => return issues,
+    };
+
+    for (index, candle) in candles.iter().enumerate().skip(1) {
+        let time = candle.open_time();
+        if time == last {
+            issues.push(CandleIssue::Duplicate { index, time });
+            continue;
+        }
+        if time < last {
+            issues.push(CandleIssue::OutOfOrder { index, time });
+            continue;
+        }
+        if let Some(interval) = interval {
+            let missing = missing(last, time, interval);
+            if missing > 0 {
+                issues.push(CandleIssue::Gap {
+                    index,
+                    from: last + interval,
+                    to: time,
+                    missing,
+                });
+            }
+        }
+        last = time;
+    }
+
+    issues
+}
+
+/// Repairs the candles with the given strategy.
+///
+/// ### Arguments
+/// * `candles` - The candles to repair.
+/// * `strategy` - How to handle the issues.
+///
+/// ### Returns
+/// The repaired candles, in chronological order.
+///
+/// ### Example
+/// ```
+/// use bts_rs::data::{Repair, repair, validate};
+/// use bts_rs::engine::CandleBuilder;
+/// use chrono::{DateTime, Duration};
+///
+/// let candle = |hours| {
+///     let open_time = DateTime::default() + Duration::hours(hours);
+///     CandleBuilder::builder()
+///         .open(100.0)
+///         .high(110.0)
+///         .low(90.0)
+///         .close(105.0)
+///         .volume(1.0)
+///         .open_time(open_time)
+///         .close_time(open_time + Duration::hours(1))
+///         .build()
+///         .unwrap()
+/// };
+/// // the candles of the 2nd and 3rd hours are missing
+/// let candles = [candle(0), candle(1), candle(4), candle(5)];
+/// assert_eq!(validate(&candles).len(), 1);
+///
+/// let repaired = repair(&candles, Repair::Flat).unwrap();
+/// assert_eq!(repaired.len(), 6);
+/// assert!(validate(&repaired).is_empty());
+/// ```
+pub fn repair(candles: &[Candle], strategy: Repair) -> Result<Vec<Candle>> {
+    let mut sorted = candles.to_vec();
+    if strategy != Repair::Drop {
+        sorted.sort_by_key(|c| c.open_time());
+    }
+    let interval = interval(&sorted);
+    let mut repaired: Vec<Candle> = Vec::with_capacity(candles.len());
+
+    for candle in sorted {
+        let Some(previous) = repaired.last().copied() else {
+            repaired.push(candle);
+            continue;
+        };
+        if candle.open_time() <= previous.open_time() {
+            continue;
+        }
+        if let Some(interval) = interval
+            && strategy != Repair::Drop
+        {
+            let duration = previous.close_time() - previous.open_time();
+            let missing = missing(previous.open_time(), candle.open_time(), interval);
+            for i in 1..=missing as i32 {
+                let open_time = previous.open_time() + interval * i;
+                let builder = CandleBuilder::builder()
+                    .open_time(open_time)
+                    .close_time(open_time + duration);
+                let filled = match strategy {
+                    Repair::ForwardFill => builder
+                        .open(previous.open())
+                        .high(previous.high())
+                        .low(previous.low())
+                        .close(previous.close())
+                        .volume(previous.volume())
+                        .bid(previous.bid()),
+                    _ => builder
+                        .open(previous.close())
+                        .high(previous.close())
+                        .low(previous.close())
+                        .close(previous.close())
+                        .volume(0.0),
+                };
+                repaired.push(filled.build()?);
+            }
+        }
+        repaired.push(candle);
+    }
+
+    Ok(repaired)
+}
+
+/// Converts candles into Heikin-Ashi candles.
+///
+/// The close is the average of the open, high, low and close, the open is the midpoint of the previous
+/// Heikin-Ashi candle (of the open and close for the first one), and the high and low include the new
+/// open and close. The volumes and the times are kept.
+///
+/// ### Arguments
+/// * `candles` - The candles, in chronological order.
+///
+/// ### Returns
+/// The Heikin-Ashi candles, or an error if a candle cannot be built.
+pub fn heikin_ashi(candles: &[Candle]) -> Result<Arc<[Candle]>> {
+    let mut previous: Option<(f64, f64)> = None;
+    candles
+        .iter()
+        .map(|candle| {
+            let close = (candle.open() + candle.high() + candle.low() + candle.close()) / 4.0;
+            let open = match previous {
+                Some((open, close)) => (open + close) / 2.0,
+                None => (candle.open() + candle.close()) / 2.0,
+            };
+            previous = Some((open, close));
+            CandleBuilder::builder()
+                .open(open)
+                .high(candle.high().max(open).max(close))
+                .low(candle.low().min(open).min(close))
+                .close(close)
+                .volume(candle.volume())
+                .bid(candle.bid())
+                .open_time(candle.open_time())
+                .close_time(candle.close_time())
+                .build()
+        })
+        .collect()
+}
+
+/// Converts candles into Renko bricks.
+///
+/// A brick is added each time the close moves by the brick size beyond the last brick, and a reversal
+/// needs a move of two bricks. The bricks formed by a candle share its duration and its volume, so
+/// their times stay in chronological order; a candle without new brick is dropped.
+///
+/// ### Arguments
+/// * `candles` - The candles, in chronological order.
+/// * `size` - The size of the bricks.
+///
+/// ### Returns
+/// The bricks, or an error if the size is not positive or there are not enough candles to compute it.
+///
+/// ### Example
+/// ```
+/// use bts_rs::data::{BrickSize, renko};
+/// use bts_rs::engine::CandleBuilder;
+/// use chrono::{DateTime, Duration};
+///
+/// let candle = |hours, close| {
+///     let open_time = DateTime::default() + Duration::hours(hours);
+///     CandleBuilder::builder()
+///         .open(close)
+///         .high(close)
+///         .low(close)
+///         .close(close)
+///         .volume(1.0)
+///         .open_time(open_time)
+///         .close_time(open_time + Duration::hours(1))
+///         .build()
+///         .unwrap()
+/// };
+/// let candles = [candle(0, 100.0), candle(1, 125.0), candle(2, 112.0), candle(3, 95.0)];
+///
+/// // two bricks up, then two bricks down from 110
+/// let bricks = renko(&candles, BrickSize::Fixed(10.0)).unwrap();
+/// let closes = bricks.iter().map(|brick| brick.close()).collect::<Vec<_>>();
+/// assert_eq!(closes, vec![110.0, 120.0, 100.0]);
+/// ```
+pub fn renko(candles: &[Candle], size: BrickSize) -> Result<Arc<[Candle]>> {
+    let (size, start) = match size {
+        BrickSize::Fixed(size) => (size, 0),
+        BrickSize::Atr(period) => {
+            let period = period.max(1);
+            if candles.len() <= period {
+                return Err(Error::Msg(format!(
+                    "Not enough candles for the ATR: {} available, {} required",
+                    candles.len(),
+                    period + 1
+                )));
+            }
+            let true_ranges = candles[..period].iter().enumerate().map(|(i, candle)| {
+                let range = candle.high() - candle.low();
+                match i.checked_sub(1).map(|i| candles[i].close()) {
+                    Some(close) => range
+                        .max((candle.high() - close).abs())
+                        .max((candle.low() - close).abs()),
+                    None => range,
+                }
+            });
+            (true_ranges.sum::<f64>() / period as f64, period - 1)
+        }
+    };
+    if size.is_nan() || size <= 0.0 {
+        return Err(Error::Msg(format!("The brick size must be positive (got: {size})")));
+    }
+
+    let Some(first) = candles.get(start) else {
+        return Ok(Arc::from([]));
+    };
+    let (mut top, mut bottom) = (first.close(), first.close());
+    let mut bricks = Vec::new();
+    for candle in &candles[start + 1..] {
+        let close = candle.close();
+        //? the bricks go either up or down on a candle
+        let (count, step, from) = if close >= top + size {
+            (((close - top) / size).floor(), size, top)
+        } else if close <= bottom - size {
+            (((bottom - close) / size).floor(), -size, bottom)
+        } else {
+            continue;
+        };
+
+        let count = count as i32;
+        let duration = (candle.close_time() - candle.open_time()) / count;
+        for i in 0..count {
+            let open = from + step * i as f64;
+            let close = open + step;
+            let open_time = candle.open_time() + duration * i;
+            bricks.push(
+                CandleBuilder::builder()
+                    .open(open)
+                    .high(open.max(close))
+                    .low(open.min(close))
+                    .close(close)
+                    .volume(candle.volume() / count as f64)
+                    .open_time(open_time)
+                    .close_time(open_time + duration)
+                    .build()?,
+            );
+            (top, bottom) = (open.max(close), open.min(close));
+        }
+    }
+
+    Ok(Arc::from(bricks))
+}
+
+/// Returns the number of missing candles between two open times.
+fn missing(last: DateTime<Utc>, time: DateTime<Utc>, interval: TimeDelta) -> usize {
+    let (elapsed, interval) = ((time - last).num_milliseconds(), interval.num_milliseconds());
+    if interval <= 0 {
+        return 0;
+    }
+    (elapsed / interval).saturating_sub(1).max(0) as usize
+}
+
+/// Returns the fingerprint of the candles, a hash of their times and OHLCV values.
+///
+/// Two runs used identical input data when the fingerprints of their candles are equal (see
+/// `Backtest::fingerprint`). The hash is stable across platforms and releases, so it can be stored.
+pub fn fingerprint(candles: &[Candle]) -> u64 {
+    candles.iter().fold(FNV_OFFSET, |hash, candle| {
+        let times = [candle.open_time(), candle.close_time()].map(|time| time.timestamp_millis());
+        let values = [
+            candle.open(),
+            candle.high(),
+            candle.low(),
+            candle.close(),
+            candle.volume(),
+        ];
+        let hash = times.iter().fold(hash, |hash, time| fnv1a(hash, &time.to_le_bytes()));
+        values
+            .iter()
+            .fold(hash, |hash, value| fnv1a(hash, &value.to_bits().to_le_bytes()))
+    })
+}
+
+/// Origin of the candle data of a backtest: where it was loaded from, the symbol and the timeframe.
+///
+/// Attach it with `Backtest::with_provenance` to keep it with the results (e.g., in a
+/// `manifest::RunManifest`).
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::data::Provenance;
+/// use chrono::TimeDelta;
+///
+/// let provenance = Provenance::new("binance")
+///     .with_symbol("BTCUSDT")
+///     .with_timeframe(TimeDelta::hours(1));
+/// assert_eq!(provenance.source(), "binance");
+/// assert_eq!(provenance.symbol(), Some("BTCUSDT"));
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    source: String,
+    symbol: Option<String>,
+    timeframe: Option<TimeDelta>,
+}
+
+impl Provenance {
+    /// Creates the provenance of the data.
+    ///
+    /// ### Arguments
+    /// * `source` - The path of the file or the exchange the candles come from.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            symbol: None,
+            timeframe: None,
+        }
+    }
+
+    /// Sets the traded symbol (e.g., `BTCUSDT`).
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Sets the timeframe of the candles.
+    pub fn with_timeframe(mut self, timeframe: TimeDelta) -> Self {
+        self.timeframe = Some(timeframe);
+        self
+    }
+
+    /// Returns the path of the file or the exchange the candles come from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the traded symbol, if any.
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+
+    /// Returns the timeframe of the candles, if any.
+    pub fn timeframe(&self) -> Option<TimeDelta> {
+        self.timeframe
+    }
+}
+
+#[cfg(test)]
+fn candles(hours: &[i64]) -> Vec<Candle> {
+    hours
+        .iter()
+        .map(|hours| {
+            let open_time = DateTime::default() + TimeDelta::hours(*hours);
+            CandleBuilder::builder()
+                .open(100.0 + *hours as f64)
+                .high(110.0 + *hours as f64)
+                .low(90.0)
+                .close(105.0 + *hours as f64)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + TimeDelta::hours(1))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn validate_candles() {
+    assert!(validate(&[]).is_empty());
+    assert!(validate(&candles(&[0, 1, 2, 3])).is_empty());
+    assert_eq!(interval(&candles(&[0, 1, 2, 4, 5])), Some(TimeDelta::hours(1)));
+
+    let time = |hours| DateTime::default() + TimeDelta::hours(hours);
+    let issues = validate(&candles(&[0, 1, 1, 0, 2, 5, 6]));
+    assert_eq!(
+        issues,
+        vec![
+            CandleIssue::Duplicate {
+                index: 2,
+                time: time(1)
+            },
+            CandleIssue::OutOfOrder {
+                index: 3,
+                time: time(0)
+            },
+            CandleIssue::Gap {
+                index: 5,
+                from: time(3),
+                to: time(5),
+                missing: 2
+            },
+        ]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn repair_candles() {
+    let data = candles(&[0, 2, 1, 1, 4]);
+
+    let dropped = repair(&data, Repair::Drop).unwrap();
+    let times = dropped
+        .iter()
+        .map(|c| c.open_time().timestamp() / 3600)
+        .collect::<Vec<_>>();
+    assert_eq!(times, vec![0, 2, 4]);
+
+    let filled = repair(&data, Repair::ForwardFill).unwrap();
+    assert_eq!(filled.len(), 5);
+    assert!(validate(&filled).is_empty());
+    assert_eq!(filled[3].open(), filled[2].open());
+    assert_eq!(filled[3].volume(), 1.0);
+
+    let flat = repair(&data, Repair::Flat).unwrap();
+    assert_eq!(flat.len(), 5);
+    assert_eq!(flat[3].high(), flat[2].close());
+    assert_eq!(flat[3].low(), flat[2].close());
+    assert_eq!(flat[3].volume(), 0.0);
+    assert_eq!(flat[3].close_time(), flat[4].open_time());
+}
+
+#[cfg(test)]
+#[test]
+fn transform_candles() {
+    let data = candles(&[0, 1, 2]);
+
+    let ha = heikin_ashi(&data).unwrap();
+    assert_eq!(ha.len(), 3);
+    assert_eq!(ha[0].open(), 102.5);
+    assert_eq!(ha[0].close(), 101.25);
+    // the open is the midpoint of the previous candle
+    assert_eq!(ha[1].open(), (102.5 + 101.25) / 2.0);
+    assert_eq!(ha[1].close(), 102.0);
+    assert_eq!(ha[1].high(), 111.0);
+    assert_eq!(ha[1].low(), 90.0);
+    assert_eq!(ha[2].open_time(), data[2].open_time());
+
+    let bar = |hours: i64, close: f64| {
+        let open_time = DateTime::default() + TimeDelta::hours(hours);
+        CandleBuilder::builder()
+            .open(close)
+            .high(close)
+            .low(close)
+            .close(close)
+            .volume(1.0)
+            .open_time(open_time)
+            .close_time(open_time + TimeDelta::hours(1))
+            .build()
+            .unwrap()
+    };
+    let data = [bar(0, 100.0), bar(1, 126.0), bar(2, 112.0), bar(3, 94.0)];
+
+    let bricks = renko(&data, BrickSize::Fixed(10.0)).unwrap();
+    let closes = bricks.iter().map(|brick| brick.close()).collect::<Vec<_>>();
+    // the reversal starts from the open of the last brick up
+    assert_eq!(closes, vec![110.0, 120.0, 100.0]);
+    // the bricks formed by a candle share its duration and its volume
+    assert_eq!(bricks[1].open_time(), bricks[0].close_time());
+    assert_eq!(bricks[1].close_time(), data[1].close_time());
+    assert_eq!(bricks[0].volume(), 0.5);
+
+    // true ranges of the first 3 candles: 0, 26 and 14
+    let bricks = renko(&data, BrickSize::Atr(3)).unwrap();
+    assert_eq!(bricks.len(), 1);
+    assert_eq!(bricks[0].open(), 112.0);
+    assert_eq!(bricks[0].close(), 112.0 - 40.0 / 3.0);
+
+    assert!(renko(&data, BrickSize::Fixed(0.0)).is_err());
+    assert!(renko(&data, BrickSize::Atr(4)).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn candles_fingerprint() {
+    let data = candles(&[0, 1, 2]);
+    assert_eq!(fingerprint(&data), fingerprint(&candles(&[0, 1, 2])));
+    assert_ne!(fingerprint(&data), fingerprint(&data[..2]));
+    assert_ne!(fingerprint(&data), fingerprint(&candles(&[0, 1, 3])));
+    // a single changed value
+    let mut changed = data.clone();
+    changed[1] = CandleBuilder::builder()
+        .open(data[1].open())
+        .high(data[1].high())
+        .low(data[1].low())
+        .close(data[1].close())
+        .volume(2.0)
+        .open_time(data[1].open_time())
+        .close_time(data[1].close_time())
+        .build()
+        .unwrap();
+    assert_ne!(fingerprint(&data), fingerprint(&changed));
+    assert_eq!(fingerprint(&[]), FNV_OFFSET);
+}
@@ -2,34 +2,57 @@
 //!
 //! It needs to enable `draws` feature to use it. Take a look at [trailing stop](https://github.com/raonagos/bts-rs/blob/master/examples/trailing_stop.rs#L70) for example.
 
-use crate::engine::{Backtest, Candle};
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::engine::{Backtest, Candle, CandleBuilder, CandleFrame};
+#[cfg(feature = "metrics")]
+use crate::engine::{OrderType, PositionSide};
 use crate::errors::{Error, Result};
 #[cfg(feature = "metrics")]
 use crate::metrics::{Event, Metrics};
 
-use charming::component::{Axis, DataZoom, DataZoomType, Grid, Title};
-use charming::element::{AxisLabel, ItemStyle, Symbol, Tooltip, Trigger};
-use charming::series::{Bar, Candlestick, Line, Scatter};
+use charming::component::{Axis, DataZoom, DataZoomType, Grid, Title, VisualMap, VisualMapChannel};
+#[cfg(feature = "metrics")]
+use charming::datatype::DataPointItem;
+use charming::datatype::{DataFrame, DataPoint};
+#[cfg(feature = "metrics")]
+use charming::element::SplitLine;
+use charming::element::{AxisLabel, AxisType, ItemStyle, JsFunction, Label, Orient, Symbol, Tooltip, Trigger};
+use charming::series::{Bar, Candlestick, Heatmap as HeatmapSeries, Line, Scatter};
 use charming::{Chart, HtmlRenderer};
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use plotters::backend::{BitMapBackend, DrawingBackend, SVGBackend};
+use plotters::chart::MeshStyle;
 use plotters::coord::Shift;
 use plotters::prelude::*;
 use plotters::style::WHITE;
 use plotters::style::full_palette::{LIME, ORANGE, PINK, PURPLE, TEAL};
+use plotters::style::text_anchor::{HPos, Pos, VPos};
 
 /// Size of the X-axis.
 const WIDTH: u32 = 1280;
 /// Size of the Y-axis.
 const HEIGHT: u32 = 900;
-/// Size of the X-axis labels.
-const X_LABEL_SIZE: i32 = 20;
-/// Size of the Y-axis labels.
-const Y_LABEL_SIZE: i32 = 20;
+/// Size of the axis labels.
+const LABEL_SIZE: i32 = 20;
+/// Size of the titles.
+const TITLE_SIZE: i32 = 30;
+/// Number of columns of the text charts, if the terminal width is unknown.
+const TEXT_WIDTH: usize = 100;
+/// Number of rows of the text candlestick chart.
+const TEXT_HEIGHT: usize = 24;
+/// Width of the value axis labels of the text charts.
+const TEXT_AXIS_WIDTH: usize = 12;
+/// Colors of the additional series, in the order they are added.
+const COLORS: [RGBColor; 12] = [
+    BLUE, GREEN, RED, CYAN, MAGENTA, YELLOW, BLACK, ORANGE, PURPLE, PINK, LIME, TEAL,
+];
 
 /// Output formats for the generated charts with output filename.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub enum DrawOutput {
     /// Save to the output SVG file.
     Svg(String),
@@ -37,14 +60,28 @@ pub enum DrawOutput {
     Png(String),
     /// Save to the output HTML file.
     Html(String),
-    /// Print to the current console (not implemented).
+    /// Print to the current console, as text.
     #[default]
     Inner,
 }
 
+/// Red, green and blue components of a color.
+pub type Rgb = (u8, u8, u8);
+
+/// Color theme of the charts.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Theme {
+    /// Dark text on a white background (the default).
+    #[default]
+    Light,
+    /// Light text on a dark background.
+    Dark,
+}
+
 /// Configuration options for chart generation.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct DrawOptions {
     /// Chart title.
     title: Option<String>,
@@ -52,9 +89,45 @@ pub struct DrawOptions {
     output: DrawOutput,
     /// Whether to show the volume chart.
     show_volume: bool,
+    /// Maximum number of points drawn per series.
+    #[cfg_attr(feature = "serde", serde(default))]
+    max_points: Option<usize>,
+    /// Color theme.
+    #[cfg_attr(feature = "serde", serde(default))]
+    theme: Theme,
+    /// Width and height of the figure, in pixels.
+    #[cfg_attr(feature = "serde", serde(default))]
+    dimensions: Option<(u32, u32)>,
+    /// Font sizes of the title and of the labels.
+    #[cfg_attr(feature = "serde", serde(default))]
+    font_sizes: Option<(u32, u32)>,
+    /// Colors of the rising and falling candles.
+    #[cfg_attr(feature = "serde", serde(default))]
+    candle_colors: Option<(Rgb, Rgb)>,
+    /// Colors of the additional series, in the order they are added.
+    #[cfg_attr(feature = "serde", serde(default))]
+    series_colors: Vec<Rgb>,
+    /// Whether to hide the grid lines.
+    #[cfg_attr(feature = "serde", serde(default))]
+    hide_grid: bool,
     #[cfg(feature = "metrics")]
     /// Whether to show the metrics chart.
     show_metrics: bool,
+    #[cfg(feature = "metrics")]
+    /// Whether to show the drawdown chart.
+    show_drawdown: bool,
+    #[cfg(feature = "metrics")]
+    /// Whether to overlay the linear best fit of the equity.
+    show_best_fit: bool,
+    #[cfg(feature = "metrics")]
+    /// Whether to overlay the buy-and-hold equity.
+    show_buy_and_hold: bool,
+    #[cfg(feature = "metrics")]
+    /// Whether to overlay the high-water mark of the equity.
+    show_high_water_mark: bool,
+    #[cfg(feature = "metrics")]
+    /// Whether to mark the trades on the price chart.
+    show_trades: bool,
 }
 
 impl DrawOptions {
@@ -76,14 +149,398 @@ impl DrawOptions {
         self
     }
 
+    /// Limits the number of points drawn per series, for the charts of large datasets.
+    ///
+    /// Beyond `max_points` candles, the candles are merged into `max_points` buckets of consecutive
+    /// candles (first open, highest high, lowest low, last close and summed volume), and the line
+    /// series keep one point per bucket chosen with the largest triangle three buckets (LTTB)
+    /// algorithm, which preserves the peaks and troughs. The equity and drawdown curves are reduced
+    /// the same way, and at most `max_points` position and trade markers are drawn, evenly spaced.
+    pub fn max_points(mut self, max_points: usize) -> Self {
+        self.max_points = Some(max_points.max(2));
+        self
+    }
+
+    /// Sets the color theme (light by default). The HTML output only uses its background color.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets the width and height of the figure, in pixels (1280x900 by default).
+    pub fn dimensions(mut self, width: u32, height: u32) -> Self {
+        self.dimensions = Some((width, height));
+        self
+    }
+
+    /// Sets the font sizes of the title and of the axis labels (30 and 20 by default), except in the HTML output.
+    pub fn font_sizes(mut self, title: u32, labels: u32) -> Self {
+        self.font_sizes = Some((title, labels));
+        self
+    }
+
+    /// Sets the RGB colors of the rising and falling candles (green and red by default).
+    ///
+    /// The HTML output keeps its default candle colors.
+    pub fn candle_colors(mut self, up: Rgb, down: Rgb) -> Self {
+        self.candle_colors = Some((up, down));
+        self
+    }
+
+    /// Sets the RGB colors of the additional series and panels, in the order they are added.
+    pub fn series_colors(mut self, colors: impl IntoIterator<Item = Rgb>) -> Self {
+        self.series_colors = colors.into_iter().collect();
+        self
+    }
+
+    /// Shows or hides the grid lines (shown by default), except in the HTML output.
+    pub fn show_grid(mut self, show: bool) -> Self {
+        self.hide_grid = !show;
+        self
+    }
+
     #[cfg(feature = "metrics")]
-    /// Enables or disables the metrics chart.
+    /// Enables or disables the metrics chart, and the equity curve on a secondary axis of the price chart.
     pub fn show_metrics(mut self, show: bool) -> Self {
         self.show_metrics = show;
         self
     }
+
+    #[cfg(feature = "metrics")]
+    /// Enables or disables the drawdown chart (underwater balance curve).
+    pub fn show_drawdown(mut self, show: bool) -> Self {
+        self.show_drawdown = show;
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Enables or disables the linear best fit overlay on the equity (the steadier, the closer).
+    pub fn show_best_fit(mut self, show: bool) -> Self {
+        self.show_best_fit = show;
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Enables or disables the buy-and-hold equity overlay (the initial balance invested on the first candle).
+    pub fn show_buy_and_hold(mut self, show: bool) -> Self {
+        self.show_buy_and_hold = show;
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Enables or disables the high-water mark overlay on the equity.
+    pub fn show_high_water_mark(mut self, show: bool) -> Self {
+        self.show_high_water_mark = show;
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Enables or disables the trade markers on the price chart: the entry and exit arrows colored by side
+    /// (blue for long, orange for short), a dashed line from the entry to the exit and the net P&L label.
+    ///
+    /// The HTML output marks the entries and the exits (colored by outcome) with a tooltip giving the side,
+    /// the quantity, the price, the exit reason and the net P&L.
+    pub fn show_trades(mut self, show: bool) -> Self {
+        self.show_trades = show;
+        self
+    }
+}
+
+impl DrawOptions {
+    /// Returns the width and height of the figure.
+    fn size(&self) -> (u32, u32) {
+        self.dimensions.unwrap_or((WIDTH, HEIGHT))
+    }
+
+    /// Returns the background color of the theme.
+    fn background(&self) -> RGBColor {
+        match self.theme {
+            Theme::Light => WHITE,
+            Theme::Dark => RGBColor(24, 26, 32),
+        }
+    }
+
+    /// Returns the color of the text and axes of the theme.
+    fn foreground(&self) -> RGBColor {
+        match self.theme {
+            Theme::Light => BLACK,
+            Theme::Dark => RGBColor(220, 220, 220),
+        }
+    }
+
+    /// Returns the size of the axis labels.
+    fn label_size(&self) -> i32 {
+        self.font_sizes.map_or(LABEL_SIZE, |(_, labels)| labels as i32)
+    }
+
+    /// Returns a text style of the theme.
+    fn text_style(&self, size: i32) -> TextStyle<'static> {
+        ("sans-serif", size).into_font().color(&self.foreground())
+    }
+
+    /// Returns the text style of the axis labels.
+    fn label_style(&self) -> TextStyle<'static> {
+        self.text_style(self.label_size())
+    }
+
+    /// Returns the size of the titles.
+    fn title_size(&self) -> i32 {
+        self.font_sizes.map_or(TITLE_SIZE, |(title, _)| title as i32)
+    }
+
+    /// Returns the text style of the titles.
+    fn title_style(&self) -> TextStyle<'static> {
+        self.text_style(self.title_size())
+    }
+
+    /// Returns the colors of the rising and falling candles.
+    fn candle_palette(&self) -> (RGBColor, RGBColor) {
+        self.candle_colors.map_or((GREEN, RED), |((r, g, b), (r0, g0, b0))| {
+            (RGBColor(r, g, b), RGBColor(r0, g0, b0))
+        })
+    }
+
+    /// Returns the colors of the additional series.
+    fn palette(&self) -> Vec<RGBColor> {
+        if self.series_colors.is_empty() {
+            //? black is not readable on the dark theme
+            COLORS
+                .iter()
+                .map(|color| if *color == BLACK { self.foreground() } else { *color })
+                .collect()
+        } else {
+            self.series_colors
+                .iter()
+                .map(|(r, g, b)| RGBColor(*r, *g, *b))
+                .collect()
+        }
+    }
+
+    /// Returns the colors of the additional series in the HTML output.
+    fn html_palette(&self) -> Vec<String> {
+        self.palette()
+            .iter()
+            .map(|RGBColor(r, g, b)| format!("#{r:02x}{g:02x}{b:02x}"))
+            .collect()
+    }
+
+    /// Styles a mesh with the theme, the label font size and the grid visibility.
+    fn mesh<'m, 'a, 'b, X: Ranged, Y: Ranged, DB: DrawingBackend>(
+        &self,
+        mesh: &'m mut MeshStyle<'a, 'b, X, Y, DB>,
+    ) -> &'m mut MeshStyle<'a, 'b, X, Y, DB> {
+        let foreground = self.foreground();
+        mesh.axis_style(foreground)
+            .label_style(self.label_style())
+            .axis_desc_style(self.label_style())
+            .bold_line_style(foreground.mix(0.2))
+            .light_line_style(foreground.mix(0.05));
+        if self.hide_grid {
+            mesh.disable_mesh();
+        }
+        mesh
+    }
+
+    /// Creates the drawing area of an image output, filled with the background color.
+    fn fill<DB: DrawingBackend>(&self, root: DrawingArea<DB, Shift>) -> Result<DrawingArea<DB, Shift>> {
+        root.fill(&self.background())
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+        Ok(root)
+    }
+
+    /// Saves an HTML chart with the dimensions, the background of the theme and the series colors.
+    fn save_html(&self, chart: Chart, title: &str, path: &str) -> Result<()> {
+        let (width, height) = self.size();
+        let RGBColor(r, g, b) = self.background();
+        let chart = chart
+            .background_color(format!("#{r:02x}{g:02x}{b:02x}"))
+            .color(self.html_palette());
+        HtmlRenderer::new(title, width.into(), height.into()).save(&chart, path)?;
+        Ok(())
+    }
+}
+
+/// Inserts a suffix before the extension of a path (e.g., `chart.svg` to `chart-best-1.svg`).
+#[cfg(feature = "metrics")]
+fn with_suffix(path: &str, suffix: &str) -> String {
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{stem}-{suffix}.{extension}"),
+        None => format!("{stem}-{suffix}"),
+    };
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+/// Returns the width of the text charts: the terminal width (`COLUMNS`) without the axis, or `TEXT_WIDTH`.
+fn text_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .map_or(TEXT_WIDTH, |columns| {
+            columns.saturating_sub(TEXT_AXIS_WIDTH + 2).max(10)
+        })
+}
+
+/// Returns the row of a value in a text chart of `height` rows, from the top.
+fn text_row(value: f64, min: f64, max: f64, height: usize) -> usize {
+    if max > min {
+        ((max - value) / (max - min) * (height - 1) as f64).round() as usize
+    } else {
+        height / 2
+    }
+}
+
+/// Adds the value axis (labels of the top, middle and bottom rows) and the time axis (first and last
+/// dates) to the rows of a text chart.
+fn text_axes(grid: Vec<Vec<String>>, min: f64, max: f64, times: &[DateTime<Utc>]) -> String {
+    let height = grid.len();
+    let mut text = String::new();
+    for (y, line) in grid.into_iter().enumerate() {
+        let label = if y == 0 || y == height / 2 || y + 1 == height {
+            format!("{:.2}", max - (max - min) * y as f64 / (height - 1).max(1) as f64)
+        } else {
+            String::new()
+        };
+        let tick = if label.is_empty() { '│' } else { '┤' };
+        text.push_str(&format!("{label:>TEXT_AXIS_WIDTH$} {tick}{}\n", line.concat()));
+    }
+
+    let width = times.len();
+    text.push_str(&format!("{:>TEXT_AXIS_WIDTH$} └{}\n", "", "─".repeat(width)));
+    if let (Some(first), Some(last)) = (times.first(), times.last()) {
+        let (first, last) = (first.format("%Y-%m-%d %H:%M"), last.format("%Y-%m-%d %H:%M"));
+        text.push_str(&format!(
+            "{:>TEXT_AXIS_WIDTH$}  {first}{last:>fill$}\n",
+            "",
+            fill = width.saturating_sub(16)
+        ));
+    }
+    text
+}
+
+/// Splits `len` points into at most `n` buckets of consecutive points.
+fn buckets(len: usize, n: usize) -> Vec<Range<usize>> {
+    let n = n.min(len);
+    (0..n).map(|i| i * len / n..(i + 1) * len / n).collect()
+}
+
+/// Selects one point per bucket with the largest triangle three buckets (LTTB) algorithm.
+///
+/// The first and last points are kept; in between, each bucket keeps the point forming the largest
+/// triangle with the point kept in the previous bucket and the average of the next bucket.
+fn lttb(points: &[(f64, f64)], buckets: &[Range<usize>]) -> Vec<usize> {
+    let average = |bucket: &Range<usize>| {
+        let finite = points[bucket.clone()]
+            .iter()
+            .filter(|(_, y)| y.is_finite())
+            .collect::<Vec<_>>();
+        let n = finite.len().max(1) as f64;
+        (
+            finite.iter().map(|(x, _)| x).sum::<f64>() / n,
+            finite.iter().map(|(_, y)| y).sum::<f64>() / n,
+        )
+    };
+
+    let mut selected = Vec::with_capacity(buckets.len());
+    for (i, bucket) in buckets.iter().enumerate() {
+        let index = match (selected.last(), buckets.get(i + 1)) {
+            (Some(&previous), Some(next)) => {
+                let (ax, ay): (f64, f64) = points[previous];
+                let (cx, cy) = average(next);
+                bucket
+                    .clone()
+                    .map(|j| {
+                        let (bx, by) = points[j];
+                        ((ax - cx) * (by - ay) - (ax - bx) * (cy - ay)).abs()
+                    })
+                    .zip(bucket.clone())
+                    .filter(|(area, _)| area.is_finite())
+                    .fold(None, |best: Option<(f64, usize)>, (area, j)| match best {
+                        Some((best_area, _)) if best_area >= area => best,
+                        _ => Some((area, j)),
+                    })
+                    .map_or(bucket.start, |(_, j)| j)
+            }
+            (None, _) => bucket.start,
+            (Some(_), None) => bucket.end - 1,
+        };
+        selected.push(index);
+    }
+    selected
+}
+
+/// Merges the candles of each bucket into one candle.
+fn downsample_frame(frame: &CandleFrame, buckets: &[Range<usize>]) -> Result<CandleFrame> {
+    buckets
+        .iter()
+        .map(|bucket| {
+            CandleBuilder::builder()
+                .open(frame.open()[bucket.start])
+                .high(frame.max_high(bucket.clone()).ok_or(Error::CandleNotFound)?)
+                .low(frame.min_low(bucket.clone()).ok_or(Error::CandleNotFound)?)
+                .close(frame.close()[bucket.end - 1])
+                .volume(frame.volume()[bucket.clone()].iter().sum())
+                .bid(frame.bid()[bucket.clone()].iter().sum())
+                .open_time(frame.open_time()[bucket.start])
+                .close_time(frame.close_time()[bucket.end - 1])
+                .build()
+        })
+        .collect()
+}
+
+/// Keeps one value of a series per bucket of candles.
+fn downsample_series(series: &Series, buckets: &[Range<usize>]) -> Series {
+    match series {
+        Series::Lines(data) => {
+            let points = data.iter().enumerate().map(|(i, y)| (i as f64, *y)).collect::<Vec<_>>();
+            let buckets = buckets
+                .iter()
+                .map(|bucket| bucket.start.min(data.len())..bucket.end.min(data.len()))
+                .filter(|bucket| !bucket.is_empty())
+                .collect::<Vec<_>>();
+            Series::Lines(lttb(&points, &buckets).into_iter().map(|i| data[i]).collect())
+        }
+        //? the circles mark discrete points, the first one of each bucket is kept
+        Series::Circles(data) => Series::Circles(
+            buckets
+                .iter()
+                .map(|bucket| {
+                    data.get(bucket.start.min(data.len())..bucket.end.min(data.len()))
+                        .and_then(|values| values.iter().find(|y| y.is_finite()))
+                        .copied()
+                        .unwrap_or(f64::NAN)
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Adds a series to the HTML chart, on the grid of the given axis index.
+fn html_series(chart: Chart, series: &Series, axis_index: f64, color: &str) -> Chart {
+    match series {
+        Series::Lines(data) => chart.series(
+            Line::new()
+                .x_axis_index(axis_index)
+                .y_axis_index(axis_index)
+                .data(data.to_vec())
+                .item_style(ItemStyle::new().color(color)),
+        ),
+        Series::Circles(data) => chart.series(
+            Scatter::new()
+                .x_axis_index(axis_index)
+                .y_axis_index(axis_index)
+                .data(data.to_vec())
+                .symbol(Symbol::Circle)
+                .item_style(ItemStyle::new().color(color)),
+        ),
+    }
 }
 
+/// Equity overlays with their name, color and points.
+#[cfg(feature = "metrics")]
+type EquityOverlays = Vec<(&'static str, RGBColor, Vec<(DateTime<Utc>, f64)>)>;
+
 /// Represents additional data series that can be plotted on a chart.
 ///
 /// This enum is used to define custom visual elements (like technical indicators)
@@ -112,7 +569,9 @@ pub enum Series {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Draw {
     series: Vec<Series>,
-    candles: Vec<Candle>,
+    panels: Vec<(String, Vec<Series>)>,
+    candles: Arc<CandleFrame>,
+    symbols: Vec<(String, CandleFrame)>,
     #[cfg(feature = "metrics")]
     metrics: Metrics,
     options: DrawOptions,
@@ -122,10 +581,12 @@ impl From<&Backtest> for Draw {
     fn from(value: &Backtest) -> Self {
         Self {
             series: Vec::new(),
+            panels: Vec::new(),
+            symbols: Vec::new(),
             options: DrawOptions::default(),
             #[cfg(feature = "metrics")]
             metrics: Metrics::from(value),
-            candles: value.candles().cloned().collect(),
+            candles: value.frame(),
         }
     }
 }
@@ -134,8 +595,10 @@ impl Draw {
     /// Creates a new `Draw` instance.
     pub fn new(candles: Vec<Candle>, options: DrawOptions, #[cfg(feature = "metrics")] metrics: Metrics) -> Self {
         Self {
-            candles,
+            candles: Arc::new(CandleFrame::from(candles.as_slice())),
             series: Vec::new(),
+            panels: Vec::new(),
+            symbols: Vec::new(),
             #[cfg(feature = "metrics")]
             metrics,
             options,
@@ -162,12 +625,50 @@ impl Draw {
         self
     }
 
+    /// Adds a data series to be plotted in its own panel below the price chart.
+    ///
+    /// Unlike [`Draw::append_series`], the series gets its own y-axis, which suits indicators
+    /// that are not on the price scale (e.g., RSI, MACD). The series appended with the same name
+    /// share the same panel (e.g., the MACD and its signal line).
+    ///
+    /// ### Arguments
+    ///
+    /// * `name` - The name of the panel, used as the y-axis description.
+    /// * `series` - A `Series` enum variant containing the data to plot.
+    pub fn append_panel(mut self, name: impl ToString, series: Series) -> Self {
+        let name = name.to_string();
+        match self.panels.iter_mut().find(|(panel, _)| *panel == name) {
+            Some((_, panel)) => panel.push(series),
+            None => self.panels.push((name, vec![series])),
+        }
+        self
+    }
+
+    /// Adds the candles of another symbol, drawn in their own panel below the main one.
+    ///
+    /// With symbols, the chart stacks one panel per symbol (and the equity panel with the `metrics`
+    /// feature) sharing the same time axis, so trades across markets can be compared at a glance.
+    /// The HTML output draws the additional symbols as close price lines.
+    ///
+    /// ### Arguments
+    ///
+    /// * `name` - The name of the symbol, used as the panel caption.
+    /// * `candles` - The candles of the symbol.
+    pub fn append_symbol(mut self, name: impl ToString, candles: Vec<Candle>) -> Self {
+        self.symbols
+            .push((name.to_string(), CandleFrame::from(candles.as_slice())));
+        self
+    }
+
     /// Generates and saves the chart based on the configured options.
     pub fn plot(&self) -> Result<()> {
         let candles = &self.candles;
-        if candles.is_empty() {
+        if candles.is_empty() || self.symbols.iter().any(|(_, candles)| candles.is_empty()) {
             return Err(Error::CandleDataEmpty);
         }
+        if let Some(draw) = self.downsampled()? {
+            return draw.plot();
+        }
 
         match &self.options.output {
             DrawOutput::Svg(path) => self.plot_svg(path),
@@ -177,35 +678,312 @@ impl Draw {
         }
     }
 
+    /// Generates and saves the equity curve alone, without the candlesticks, based on the configured options.
+    ///
+    /// The chart draws the balance and the equity (the balance plus the value of the open positions)
+    /// over time, with the enabled equity overlays and the drawdown chart below when `show_drawdown`
+    /// is enabled. Unlike the candlestick chart, it stays readable on backtests of many candles.
+    ///
+    /// ### Example
+    ///
+    /// ```ignore
+    /// Draw::from(&backtest)
+    ///     .with_options(
+    ///         DrawOptions::default()
+    ///             .draw_output(DrawOutput::Svg("equity.svg".to_owned()))
+    ///             .show_drawdown(true),
+    ///     )
+    ///     .plot_equity_curve()?;
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn plot_equity_curve(&self) -> Result<()> {
+        if self.candles.is_empty() {
+            return Err(Error::CandleDataEmpty);
+        }
+        if let Some(draw) = self.downsampled()? {
+            return draw.plot_equity_curve();
+        }
+
+        match &self.options.output {
+            DrawOutput::Svg(path) => {
+                let root = self
+                    .options
+                    .fill(SVGBackend::new(path, self.options.size()).into_drawing_area())?;
+                self.draw_equity_chart(&root)
+            }
+            DrawOutput::Png(path) => {
+                let root = self
+                    .options
+                    .fill(BitMapBackend::new(path, self.options.size()).into_drawing_area())?;
+                self.draw_equity_chart(&root)
+            }
+            DrawOutput::Html(path) => self
+                .options
+                .save_html(self.with_html_equity_chart(), "BTS Equity Curve", path),
+            DrawOutput::Inner => {
+                print!("{}", self.text_equity(text_width(), TEXT_HEIGHT));
+                Ok(())
+            }
+        }
+    }
+
+    /// Plots one chart per notable trade, centered on its entry and exit.
+    ///
+    /// The `n_best` most profitable winners and the `n_worst` biggest losers are selected. Each chart
+    /// shows the candles held plus `bars_context` candles on each side, with the entry and exit prices
+    /// and the take-profit/stop-loss levels as lines. The files are named after the configured output
+    /// with a `-best-N` or `-worst-N` suffix (e.g., `chart-best-1.svg`).
+    ///
+    /// ### Arguments
+    ///
+    /// * `n_best` - The number of winning trades to plot.
+    /// * `n_worst` - The number of losing trades to plot.
+    /// * `bars_context` - The number of candles shown before the entry and after the exit.
+    ///
+    /// ### Returns
+    ///
+    /// The paths of the generated files.
+    #[cfg(feature = "metrics")]
+    pub fn plot_trade_windows(&self, n_best: usize, n_worst: usize, bars_context: usize) -> Result<Vec<String>> {
+        let mut trades = self.metrics.trades().to_vec();
+        trades.sort_by(|a, b| b.net_pnl().total_cmp(&a.net_pnl()));
+        let best = trades.iter().filter(|t| t.is_win()).take(n_best).map(|t| ("best", t));
+        let worst = trades
+            .iter()
+            .rev()
+            .filter(|t| !t.is_win())
+            .take(n_worst)
+            .map(|t| ("worst", t));
+
+        let mut ranks = std::collections::HashMap::new();
+        let mut paths = Vec::new();
+        for (kind, trade) in best.chain(worst) {
+            let rank = ranks.entry(kind).and_modify(|r| *r += 1).or_insert(1);
+            let suffix = format!("{kind}-{rank}");
+            let output = match &self.options.output {
+                DrawOutput::Svg(path) => DrawOutput::Svg(with_suffix(path, &suffix)),
+                DrawOutput::Png(path) => DrawOutput::Png(with_suffix(path, &suffix)),
+                DrawOutput::Html(path) => DrawOutput::Html(with_suffix(path, &suffix)),
+                DrawOutput::Inner => DrawOutput::Inner,
+            };
+
+            let held = self.candles.between(trade.entry_time(), trade.exit_time());
+            let start = held.start.saturating_sub(bars_context);
+            let end = (held.end + bars_context).min(self.candles.len());
+            let candles = (start..end).filter_map(|i| self.candles.get(i)).collect::<Vec<_>>();
+
+            let level = |price: f64| Series::Lines(vec![price; candles.len()]);
+            let mut levels = vec![level(trade.entry_price()), level(trade.exit_price())];
+            if let Some(OrderType::TakeProfitAndStopLoss(take_profit, stop_loss)) = trade.exit_rule() {
+                levels.extend(
+                    [take_profit, stop_loss]
+                        .into_iter()
+                        .filter(|p| **p > 0.0)
+                        .map(|p| level(*p)),
+                );
+            }
+
+            let title = format!(
+                "{} #{rank}: {:+.2} ({} to {})",
+                if kind == "best" { "Best trade" } else { "Worst trade" },
+                trade.net_pnl(),
+                trade.entry_time().format("%Y-%m-%d %H:%M"),
+                trade.exit_time().format("%Y-%m-%d %H:%M"),
+            );
+            let options = DrawOptions::default()
+                .title(title)
+                .draw_output(output)
+                .show_volume(self.options.show_volume);
+            let mut draw = Draw::new(candles, options, self.metrics.clone());
+            draw.series = levels;
+            draw.plot()?;
+
+            if let DrawOutput::Svg(path) | DrawOutput::Png(path) | DrawOutput::Html(path) = &draw.options.output {
+                paths.push(path.clone());
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Returns a copy of the chart with at most `max_points` candles per symbol, if it has more.
+    fn downsampled(&self) -> Result<Option<Draw>> {
+        let Some(max_points) = self.options.max_points else {
+            return Ok(None);
+        };
+        if self.candles.len() <= max_points && self.symbols.iter().all(|(_, candles)| candles.len() <= max_points) {
+            return Ok(None);
+        }
+
+        let candle_buckets = buckets(self.candles.len(), max_points);
+        let downsample = |series: &[Series]| {
+            series
+                .iter()
+                .map(|s| downsample_series(s, &candle_buckets))
+                .collect::<Vec<_>>()
+        };
+        Ok(Some(Draw {
+            candles: Arc::new(downsample_frame(&self.candles, &candle_buckets)?),
+            series: downsample(&self.series),
+            panels: self
+                .panels
+                .iter()
+                .map(|(name, series)| (name.clone(), downsample(series)))
+                .collect(),
+            symbols: self
+                .symbols
+                .iter()
+                .map(|(name, candles)| {
+                    downsample_frame(candles, &buckets(candles.len(), max_points))
+                        .map(|candles| (name.clone(), candles))
+                })
+                .collect::<Result<_>>()?,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            options: self.options.clone(),
+        }))
+    }
+
+    /// Reduces the points of a curve to `max_points` with the LTTB algorithm, if enabled.
+    #[cfg(feature = "metrics")]
+    fn thin(&self, points: Vec<(DateTime<Utc>, f64)>) -> Vec<(DateTime<Utc>, f64)> {
+        match self.options.max_points {
+            Some(max_points) if points.len() > max_points => {
+                let xy = points
+                    .iter()
+                    .map(|(datetime, y)| (datetime.timestamp_millis() as f64, *y))
+                    .collect::<Vec<_>>();
+                lttb(&xy, &buckets(points.len(), max_points))
+                    .into_iter()
+                    .map(|i| points[i])
+                    .collect()
+            }
+            _ => points,
+        }
+    }
+
+    /// Keeps at most `max_points` markers, evenly spaced, if enabled.
+    #[cfg(feature = "metrics")]
+    fn markers<T>(&self, markers: Vec<T>) -> impl Iterator<Item = T> {
+        let step = self
+            .options
+            .max_points
+            .map_or(1, |max_points| markers.len().div_ceil(max_points).max(1));
+        markers.into_iter().step_by(step)
+    }
+
+    /// Returns the balance of each `Event::WalletUpdate`.
+    #[cfg(feature = "metrics")]
+    fn balances(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.thin(
+            self.metrics
+                .events()
+                .filter_map(|evt| match evt {
+                    Event::WalletUpdate { datetime, balance, .. } => Some((*datetime, *balance)),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
     /// Saves the chart as an SVG file.
     fn plot_svg(&self, path: &str) -> Result<()> {
-        let root = SVGBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
-        root.fill(&WHITE).map_err(|e| Error::Plotters(e.to_string()))?;
+        let root = self
+            .options
+            .fill(SVGBackend::new(path, self.options.size()).into_drawing_area())?;
         self.draw_chart(&root)
     }
 
     /// Saves the chart as a PNG file.
     fn plot_png(&self, path: &str) -> Result<()> {
-        let root = BitMapBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
-        root.fill(&WHITE).map_err(|e| Error::Plotters(e.to_string()))?;
+        let root = self
+            .options
+            .fill(BitMapBackend::new(path, self.options.size()).into_drawing_area())?;
         self.draw_chart(&root)
     }
 
     /// Saves the chart as an HTML file.
     fn plot_html(&self, path: &str) -> Result<()> {
         let chart = self.with_html_chart();
-        let mut renderer = HtmlRenderer::new("BTS Chart", WIDTH.into(), HEIGHT.into());
-        renderer.save(&chart, path)?;
-        Ok(())
+        self.options.save_html(chart, "BTS Chart", path)
     }
 
-    /// Displays the chart in the current console (not implemented).
+    /// Prints the chart in the current console: the candlesticks and the equity curve below them
+    /// (with the `metrics` feature), one column per bucket of candles.
     fn plot_inner(&self) -> Result<()> {
-        Err(Error::Msg("Inner display is not implemented".to_string()))
+        let width = text_width();
+        #[allow(unused_mut)]
+        let mut text = self.text_candles(width)?;
+        #[cfg(feature = "metrics")]
+        text.push_str(&self.text_equity(width, TEXT_HEIGHT / 3));
+        print!("{text}");
+        Ok(())
+    }
+
+    /// Renders the candlesticks as text, the rising candles in green and the falling ones in red.
+    fn text_candles(&self, width: usize) -> Result<String> {
+        let candles = downsample_frame(&self.candles, &buckets(self.candles.len(), width))?;
+        let min = candles.low().iter().copied().fold(f64::INFINITY, f64::min);
+        let max = candles.high().iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let row = |price: f64| text_row(price, min, max, TEXT_HEIGHT);
+
+        let mut grid = vec![vec![String::from(" "); candles.len()]; TEXT_HEIGHT];
+        for (x, candle) in candles.iter().enumerate() {
+            let color = if candle.close() >= candle.open() { 32 } else { 31 };
+            let body = row(candle.open().max(candle.close()))..=row(candle.open().min(candle.close()));
+            for (y, line) in grid
+                .iter_mut()
+                .enumerate()
+                .take(row(candle.low()) + 1)
+                .skip(row(candle.high()))
+            {
+                let symbol = if body.contains(&y) { '┃' } else { '│' };
+                line[x] = format!("\x1b[{color}m{symbol}\x1b[0m");
+            }
+        }
+
+        let title = self.options.title.as_deref().unwrap_or("BTS Chart");
+        Ok(format!("{title}\n{}", text_axes(grid, min, max, candles.open_time())))
+    }
+
+    /// Renders the equity curve as text.
+    #[cfg(feature = "metrics")]
+    fn text_equity(&self, width: usize, height: usize) -> String {
+        let curve = self.metrics.equity_curve();
+        if curve.is_empty() {
+            return String::new();
+        }
+        //? the last equity of each bucket
+        let points = buckets(curve.len(), width)
+            .into_iter()
+            .map(|bucket| curve[bucket.end - 1])
+            .collect::<Vec<_>>();
+        let min = points.iter().map(|(_, e)| *e).fold(f64::INFINITY, f64::min);
+        let max = points.iter().map(|(_, e)| *e).fold(f64::NEG_INFINITY, f64::max);
+
+        let mut grid = vec![vec![String::from(" "); points.len()]; height];
+        let mut previous = None;
+        for (x, (_, equity)) in points.iter().enumerate() {
+            let y = text_row(*equity, min, max, height);
+            // a vertical line joins the previous point when they are more than one row apart
+            let (from, to) = previous.map_or((y, y), |p: usize| (p.min(y), p.max(y)));
+            for line in grid.iter_mut().take(to).skip(from + 1) {
+                line[x] = String::from("│");
+            }
+            grid[y][x] = String::from("•");
+            previous = Some(y);
+        }
+
+        let times = points.iter().map(|(datetime, _)| *datetime).collect::<Vec<_>>();
+        format!("Equity\n{}", text_axes(grid, min, max, &times))
     }
 
     /// Draws the main chart with price, volume, and metrics.
     fn draw_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
+        if !self.symbols.is_empty() {
+            return self.draw_symbols_chart(drawing_area);
+        }
+
         let total_height = drawing_area.dim_in_pixel().1 as f64;
         let mut volume_height = 0.0;
         if self.options.show_volume {
@@ -219,7 +997,15 @@ impl Draw {
             metrics_height = total_height * 0.2;
         }
 
-        let price_height = total_height - volume_height - metrics_height;
+        #[allow(unused_mut)]
+        let mut drawdown_height = 0.0;
+        #[cfg(feature = "metrics")]
+        if self.options.show_drawdown {
+            drawdown_height = total_height * 0.2;
+        }
+
+        let panels_height = total_height * 0.15 * self.panels.len() as f64;
+        let price_height = total_height - volume_height - metrics_height - drawdown_height - panels_height;
 
         #[allow(unused_mut)]
         #[allow(unused_variables)]
@@ -229,6 +1015,10 @@ impl Draw {
             (metrics_area, rest_area) = drawing_area.split_vertically(metrics_height as u32)
         }
 
+        let (rest_area, lower_area) = rest_area.split_vertically((price_height + volume_height) as u32);
+        #[allow(unused_variables)]
+        let (panels_area, drawdown_area) = lower_area.split_vertically(panels_height as u32);
+
         let (price_area, volume_area) = if self.options.show_volume {
             rest_area.split_vertically(price_height as u32)
         } else {
@@ -240,57 +1030,74 @@ impl Draw {
         if self.options.show_volume {
             self.draw_volume_chart(&volume_area)?;
         }
+        if !self.panels.is_empty() {
+            let areas = panels_area.split_evenly((self.panels.len(), 1));
+            for ((name, series), area) in self.panels.iter().zip(&areas) {
+                self.draw_panel_chart(area, name, series)?;
+            }
+        }
         #[cfg(feature = "metrics")]
         if self.options.show_metrics {
             self.draw_metrics_chart(&metrics_area)?;
         }
+        #[cfg(feature = "metrics")]
+        if self.options.show_drawdown {
+            self.draw_drawdown_chart(&drawdown_area)?;
+        }
 
         drawing_area.present().map_err(|e| Error::Plotters(e.to_string()))
     }
 
     /// Draws the price chart (candlesticks).
     fn draw_price_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
-        let min_price = self.candles.iter().map(|c| c.low()).fold(f64::INFINITY, f64::min);
-        let max_price = self.candles.iter().map(|c| c.high()).fold(f64::NEG_INFINITY, f64::max);
+        let min_price = self.candles.low().iter().copied().fold(f64::INFINITY, f64::min);
+        let max_price = self.candles.high().iter().copied().fold(f64::NEG_INFINITY, f64::max);
         let first_time = self.candles.first().ok_or(Error::CandleNotFound)?.open_time();
         let last_time = self.candles.last().ok_or(Error::CandleNotFound)?.close_time();
         let price_range = max_price - min_price;
         let price_padding = price_range * 0.1;
 
         #[cfg(feature = "metrics")]
-        let balances = self
-            .metrics
-            .events()
-            .filter_map(|evt| match evt {
-                Event::WalletUpdate { datetime, balance, .. } => Some((*datetime, *balance)),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
+        let balances = self.balances();
+
+        #[cfg(feature = "metrics")]
+        let overlays = if self.options.show_metrics {
+            self.equity_overlays(&balances)
+        } else {
+            Vec::new()
+        };
 
         #[cfg(not(feature = "metrics"))]
         let (min_balance, max_balance) = (0.0, 0.0);
         #[cfg(feature = "metrics")]
-        let (min_balance, max_balance) = (
-            balances.iter().map(|(_, b)| *b).fold(f64::INFINITY, f64::min),
-            balances.iter().map(|(_, b)| *b).fold(f64::NEG_INFINITY, f64::max),
-        );
+        let (min_balance, max_balance) = {
+            let values = || balances.iter().chain(overlays.iter().flat_map(|(_, _, points)| points));
+            (
+                values().map(|(_, b)| *b).fold(f64::INFINITY, f64::min),
+                values().map(|(_, b)| *b).fold(f64::NEG_INFINITY, f64::max),
+            )
+        };
 
         let (top, bottom) = if self.options.show_volume { (0, 0) } else { (10, 10) };
         let drawing_area = drawing_area.margin(top, bottom, 70, 70);
         let mut builder = ChartBuilder::on(&drawing_area);
         if !self.options.show_volume {
-            builder.x_label_area_size(X_LABEL_SIZE);
+            builder.x_label_area_size(self.options.label_size());
         }
 
+        //? the title is drawn by the metrics chart when it is shown
+        #[cfg(feature = "metrics")]
+        let show_title = !self.options.show_metrics;
         #[cfg(not(feature = "metrics"))]
-        {
+        let show_title = true;
+        if show_title {
             let title = self.options.title.as_deref().unwrap_or("BTS Chart");
-            builder.caption(title, ("sans-serif", 30).into_font());
+            builder.caption(title, self.options.title_style());
         }
 
         let mut chart = builder
-            .y_label_area_size(Y_LABEL_SIZE)
-            .right_y_label_area_size(Y_LABEL_SIZE)
+            .y_label_area_size(self.options.label_size())
+            .right_y_label_area_size(self.options.label_size())
             .build_cartesian_2d(
                 first_time..last_time,
                 min_price - price_padding..max_price + price_padding,
@@ -303,7 +1110,7 @@ impl Draw {
             chart
                 .configure_secondary_axes()
                 .y_desc("Balance")
-                .label_style(("sans-serif", Y_LABEL_SIZE))
+                .label_style(self.options.label_style())
                 .y_labels(5)
                 .draw()
                 .map_err(|e| Error::Plotters(e.to_string()))?;
@@ -312,15 +1119,16 @@ impl Draw {
         let candle_count = self.candles.len();
 
         let mut mesh = chart.configure_mesh();
+        self.options.mesh(&mut mesh);
         mesh.y_desc("Price")
-            .y_label_style(("sans-serif", Y_LABEL_SIZE))
+            .y_label_style(self.options.label_style())
             .y_labels(5);
 
         if self.options.show_volume {
             mesh.disable_x_axis();
         } else {
             mesh.x_desc("Time")
-                .x_label_style(("sans-serif", X_LABEL_SIZE))
+                .x_label_style(self.options.label_style())
                 .x_labels(5);
         }
 
@@ -328,10 +1136,11 @@ impl Draw {
 
         let candle_width = {
             let total_width = drawing_area.dim_in_pixel().0 as f64;
-            let available_width = total_width - (X_LABEL_SIZE * 2) as f64;
+            let available_width = total_width - (self.options.label_size() * 2) as f64;
             (available_width / candle_count as f64).max(5.0) as u32
         };
 
+        let (up, down) = self.options.candle_palette();
         chart
             .draw_series(self.candles.iter().map(|c| {
                 let x = c.open_time();
@@ -339,31 +1148,31 @@ impl Draw {
                 let high = c.high();
                 let low = c.low();
                 let close = c.close();
-                let color = if close >= open { GREEN.filled() } else { RED.filled() };
+                let color = if close >= open { up.filled() } else { down.filled() };
                 CandleStick::new(x, open, high, low, close, color, color, candle_width)
             }))
             .map_err(|e| Error::Plotters(e.to_string()))?;
 
         if !self.series.is_empty() {
-            let colors = [
-                BLUE, GREEN, RED, CYAN, MAGENTA, YELLOW, BLACK, ORANGE, PURPLE, PINK, LIME, TEAL,
-            ];
+            let palette = self.options.palette();
             let mut color_index = 0;
 
             self.series.iter().for_each(|s| {
-                let color = colors[color_index % colors.len()];
+                let color = palette[color_index % palette.len()];
                 color_index += 1;
 
                 match s {
                     Series::Lines(data) => {
-                        let lines =
-                            LineSeries::new(data.iter().zip(&self.candles).map(|(s, c)| (c.open_time(), *s)), color);
+                        let lines = LineSeries::new(
+                            data.iter().zip(self.candles.iter()).map(|(s, c)| (c.open_time(), *s)),
+                            color,
+                        );
                         chart.draw_series(lines).expect("Draw line series");
                     }
                     Series::Circles(data) => {
                         let circles = data
                             .iter()
-                            .zip(&self.candles)
+                            .zip(self.candles.iter())
                             .map(|(s, c)| Circle::new((c.open_time(), *s), 2.0, color));
                         chart.draw_series(circles).expect("Draw circle series");
                     }
@@ -380,12 +1189,15 @@ impl Draw {
             let blue_balances = balances.iter().filter(|(_, balance)| *balance >= initial_balance);
 
             let opened_positions = self
-                .metrics
-                .events()
-                .filter_map(|e| match e {
-                    Event::AddPosition(date_time, position) => Some((date_time, position.entry_price())),
-                    _ => None,
-                })
+                .markers(
+                    self.metrics
+                        .events()
+                        .filter_map(|e| match e {
+                            Event::AddPosition(date_time, position) => Some((date_time, position.entry_price())),
+                            _ => None,
+                        })
+                        .collect(),
+                )
                 .map(|(datetime, price)| {
                     Circle::new(
                         (*datetime, price.expect("Invalid price").addpercent(5.0)),
@@ -394,12 +1206,15 @@ impl Draw {
                     )
                 });
             let closed_positions = self
-                .metrics
-                .events()
-                .filter_map(|e| match e {
-                    Event::DelPosition(date_time, position) => Some((date_time, position.entry_price())),
-                    _ => None,
-                })
+                .markers(
+                    self.metrics
+                        .events()
+                        .filter_map(|e| match e {
+                            Event::DelPosition(date_time, position) => Some((date_time, position.entry_price())),
+                            _ => None,
+                        })
+                        .collect(),
+                )
                 .map(|(datetime, price)| {
                     Circle::new(
                         (*datetime, price.expect("Invalid price").addpercent(5.0)),
@@ -408,12 +1223,15 @@ impl Draw {
                     )
                 });
 
-            chart
-                .draw_series(opened_positions)
-                .map_err(|e| Error::Plotters(e.to_string()))?;
-            chart
-                .draw_series(closed_positions)
-                .map_err(|e| Error::Plotters(e.to_string()))?;
+            //? the trade markers replace the position circles
+            if !self.options.show_trades {
+                chart
+                    .draw_series(opened_positions)
+                    .map_err(|e| Error::Plotters(e.to_string()))?;
+                chart
+                    .draw_series(closed_positions)
+                    .map_err(|e| Error::Plotters(e.to_string()))?;
+            }
 
             chart
                 .draw_secondary_series(LineSeries::new(
@@ -428,54 +1246,455 @@ impl Draw {
                     RED,
                 ))
                 .map_err(|e| Error::Plotters(e.to_string()))?;
+
+            for (_, color, points) in overlays {
+                chart
+                    .draw_secondary_series(LineSeries::new(points, color))
+                    .map_err(|e| Error::Plotters(e.to_string()))?;
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if self.options.show_trades {
+            // arrow with its tip on the price, pointing up (below the price) for a buy and down for a sell
+            let arrow = |(datetime, price): (DateTime<Utc>, f64), buy: bool, color: RGBColor| {
+                let base = if buy { 12 } else { -12 };
+                EmptyElement::at((datetime, price)) + Polygon::new(vec![(0, 0), (-6, base), (6, base)], color.filled())
+            };
+
+            for trade in self.markers(self.metrics.trades().iter().collect()) {
+                let (color, long) = match trade.side() {
+                    PositionSide::Long => (BLUE, true),
+                    PositionSide::Short => (ORANGE, false),
+                };
+                let entry = (trade.entry_time(), trade.entry_price());
+                let exit = (trade.exit_time(), trade.exit_price());
+
+                chart
+                    .draw_series(DashedLineSeries::new([entry, exit], 6, 4, color.stroke_width(1)))
+                    .map_err(|e| Error::Plotters(e.to_string()))?;
+                chart
+                    .draw_series([arrow(entry, long, color), arrow(exit, !long, color)])
+                    .map_err(|e| Error::Plotters(e.to_string()))?;
+
+                let label_color = if trade.is_win() { GREEN } else { RED };
+                let offset = if long { -26 } else { 16 };
+                let label = EmptyElement::at(exit)
+                    + Text::new(
+                        format!("{:+.2}", trade.net_pnl()),
+                        (0, offset),
+                        ("sans-serif", 12)
+                            .into_font()
+                            .color(&label_color)
+                            .pos(Pos::new(HPos::Center, VPos::Top)),
+                    );
+                chart.draw_series([label]).map_err(|e| Error::Plotters(e.to_string()))?;
+            }
         }
 
         Ok(())
     }
 
-    /// Draws the volume chart.
-    fn draw_volume_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
-        let max_volume = self
-            .candles
-            .iter()
-            .map(|c| c.volume())
-            .fold(f64::NEG_INFINITY, f64::max);
+    /// Returns the equity overlays enabled in the options, with their name and color.
+    #[cfg(feature = "metrics")]
+    fn equity_overlays(&self, equity: &[(DateTime<Utc>, f64)]) -> EquityOverlays {
+        let mut overlays = Vec::new();
+
+        if self.options.show_best_fit
+            && let (Some((first_time, _)), Some((last_time, _))) = (equity.first(), equity.last())
+        {
+            // least squares of the equity over the elapsed seconds
+            let x = |datetime: &DateTime<Utc>| (*datetime - *first_time).num_seconds() as f64;
+            let n = equity.len() as f64;
+            let mean_x = equity.iter().map(|(t, _)| x(t)).sum::<f64>() / n;
+            let mean_y = equity.iter().map(|(_, e)| *e).sum::<f64>() / n;
+            let covariance = equity.iter().map(|(t, e)| (x(t) - mean_x) * (e - mean_y)).sum::<f64>();
+            let variance = equity.iter().map(|(t, _)| (x(t) - mean_x).powi(2)).sum::<f64>();
+            let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+            let fit = |datetime| mean_y + slope * (x(datetime) - mean_x);
+            let points = vec![(*first_time, fit(first_time)), (*last_time, fit(last_time))];
+            overlays.push(("Best fit", self.options.foreground(), points));
+        }
+
+        if self.options.show_buy_and_hold
+            && let Some(first) = self.candles.first()
+        {
+            let initial_balance = self.metrics.initial_balance();
+            let points = self
+                .candles
+                .iter()
+                .map(|c| (c.open_time(), initial_balance * c.close() / first.open()))
+                .collect();
+            overlays.push(("Buy and hold", MAGENTA, points));
+        }
+
+        if self.options.show_high_water_mark {
+            let mut high = f64::NEG_INFINITY;
+            let points = equity
+                .iter()
+                .map(|(datetime, value)| {
+                    high = high.max(*value);
+                    (*datetime, high)
+                })
+                .collect();
+            overlays.push(("High-water mark", CYAN, points));
+        }
+
+        overlays
+    }
+
+    /// Returns the lines of the equity curve chart: the balance, the equity and the enabled overlays.
+    #[cfg(feature = "metrics")]
+    fn equity_lines(&self) -> EquityOverlays {
+        let mut balances = self.balances();
+        //? the balance is only updated on changes, it is held until the last candle
+        if let (Some((_, balance)), Some(last)) = (balances.last(), self.candles.last()) {
+            balances.push((last.close_time(), *balance));
+        }
+        let equity = self.thin(self.metrics.equity_curve());
+        let overlays = self.equity_overlays(&equity);
+
+        [("Balance", BLUE, balances), ("Equity", GREEN, equity)]
+            .into_iter()
+            .chain(overlays)
+            .collect()
+    }
+
+    /// Draws the equity curve chart, with the drawdown chart below if enabled.
+    #[cfg(feature = "metrics")]
+    fn draw_equity_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
+        let (equity_area, drawdown_area) = if self.options.show_drawdown {
+            let equity_height = drawing_area.dim_in_pixel().1 as f64 * 0.75;
+            drawing_area.split_vertically(equity_height as u32)
+        } else {
+            (drawing_area.clone(), drawing_area.clone())
+        };
+
+        let lines = self.equity_lines();
+        let values = || {
+            lines
+                .iter()
+                .flat_map(|(_, _, points)| points)
+                .map(|(_, value)| *value)
+                .filter(|value| value.is_finite())
+        };
+        let min_value = values().fold(f64::INFINITY, f64::min);
+        let max_value = values().fold(f64::NEG_INFINITY, f64::max);
+        if !min_value.is_finite() {
+            return Err(Error::Msg("No equity to draw".to_string()));
+        }
+        let padding = ((max_value - min_value) * 0.1).max(1.0);
+        let first_time = self.candles.first().ok_or(Error::CandleNotFound)?.open_time();
+        let last_time = self.candles.last().ok_or(Error::CandleNotFound)?.close_time();
+        let title = self.options.title.as_deref().unwrap_or("BTS Equity Curve");
+
+        let equity_area = equity_area.margin(10, 10, 70, 70);
+        let mut chart = ChartBuilder::on(&equity_area)
+            .caption(title, self.options.title_style())
+            .x_label_area_size(self.options.label_size())
+            .y_label_area_size(self.options.label_size())
+            .build_cartesian_2d(first_time..last_time, min_value - padding..max_value + padding)
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        self.options
+            .mesh(&mut chart.configure_mesh())
+            .x_desc("Time")
+            .y_desc("Balance")
+            .x_label_style(self.options.label_style())
+            .y_label_style(self.options.label_style())
+            .x_labels(5)
+            .y_labels(5)
+            .draw()
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        for (name, color, points) in lines {
+            chart
+                .draw_series(LineSeries::new(points, color))
+                .map_err(|e| Error::Plotters(e.to_string()))?
+                .label(name)
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(self.options.background().mix(0.8))
+            .border_style(self.options.foreground())
+            .label_font(self.options.label_style())
+            .draw()
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        if self.options.show_drawdown {
+            self.draw_drawdown_chart(&drawdown_area)?;
+        }
+
+        drawing_area.present().map_err(|e| Error::Plotters(e.to_string()))
+    }
+
+    /// Draws the volume chart.
+    fn draw_volume_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
+        let max_volume = self.candles.volume().iter().copied().fold(f64::NEG_INFINITY, f64::max);
         let volume_padding = max_volume * 0.1;
         let first_time = self.candles.first().ok_or(Error::CandleNotFound)?.open_time();
         let last_time = self.candles.last().ok_or(Error::CandleNotFound)?.close_time();
         let drawing_area = drawing_area.margin(0, 10, 70, 70);
 
         let mut chart = ChartBuilder::on(&drawing_area)
-            .x_label_area_size(X_LABEL_SIZE)
-            .y_label_area_size(Y_LABEL_SIZE)
+            .x_label_area_size(self.options.label_size())
+            .y_label_area_size(self.options.label_size())
             .build_cartesian_2d(first_time..last_time, 0.0..max_volume + volume_padding)
             .map_err(|e| Error::Plotters(e.to_string()))?;
 
-        chart
-            .configure_mesh()
+        self.options
+            .mesh(&mut chart.configure_mesh())
             .x_desc("Time")
-            .x_label_style(("sans-serif", X_LABEL_SIZE))
-            .y_label_style(("sans-serif", Y_LABEL_SIZE))
+            .x_label_style(self.options.label_style())
+            .y_label_style(self.options.label_style())
             .x_labels(5)
             .y_labels(3)
             .draw()
             .map_err(|e| Error::Plotters(e.to_string()))?;
 
+        let (up, down) = self.options.candle_palette();
         chart
             .draw_series(self.candles.iter().map(|c| {
                 let x = c.open_time();
                 let volume = c.volume();
-                let color = if c.ask() >= c.bid() {
-                    GREEN.mix(0.3)
-                } else {
-                    RED.mix(0.3)
-                };
+                let color = if c.ask() >= c.bid() { up.mix(0.3) } else { down.mix(0.3) };
                 Rectangle::new([(x, 0.0), (x + Duration::days(1), volume)], color.filled())
             }))
             .map(|_| ())
             .map_err(|e| Error::Plotters(e.to_string()))
     }
 
+    /// Draws an indicator panel with its own y-axis.
+    fn draw_panel_chart<DB: DrawingBackend>(
+        &self,
+        drawing_area: &DrawingArea<DB, Shift>,
+        name: &str,
+        series: &[Series],
+    ) -> Result<()> {
+        // the indicators are usually undefined (NaN) during their warm-up
+        let points = |s: &Series| {
+            let (Series::Lines(data) | Series::Circles(data)) = s;
+            data.iter()
+                .zip(self.candles.iter())
+                .filter(|(value, _)| value.is_finite())
+                .map(|(value, c)| (c.open_time(), *value))
+                .collect::<Vec<_>>()
+        };
+        let values = series
+            .iter()
+            .flat_map(points)
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        let min_value = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max_value = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let (min_value, max_value) = if values.is_empty() {
+            (0.0, 1.0)
+        } else {
+            (min_value, max_value)
+        };
+        let value_padding = ((max_value - min_value) * 0.1).max(f64::EPSILON);
+        let first_time = self.candles.first().ok_or(Error::CandleNotFound)?.open_time();
+        let last_time = self.candles.last().ok_or(Error::CandleNotFound)?.close_time();
+        let drawing_area = drawing_area.margin(0, 10, 70, 70);
+
+        let mut chart = ChartBuilder::on(&drawing_area)
+            .x_label_area_size(self.options.label_size())
+            .y_label_area_size(self.options.label_size())
+            .build_cartesian_2d(
+                first_time..last_time,
+                min_value - value_padding..max_value + value_padding,
+            )
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        self.options
+            .mesh(&mut chart.configure_mesh())
+            .y_desc(name)
+            .x_label_style(self.options.label_style())
+            .y_label_style(self.options.label_style())
+            .x_labels(5)
+            .y_labels(3)
+            .draw()
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        for (s, color) in series.iter().zip(self.options.palette().iter().cycle()) {
+            match s {
+                Series::Lines(_) => chart.draw_series(LineSeries::new(points(s), color)),
+                Series::Circles(_) => {
+                    chart.draw_series(points(s).into_iter().map(|point| Circle::new(point, 2.0, color)))
+                }
+            }
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the main candles followed by the candles of each symbol, with their names.
+    fn panels(&self) -> Vec<(&str, &CandleFrame)> {
+        let title = self.options.title.as_deref().unwrap_or("BTS Chart");
+        std::iter::once((title, &*self.candles))
+            .chain(self.symbols.iter().map(|(name, candles)| (name.as_str(), candles)))
+            .collect()
+    }
+
+    /// Draws one candlestick panel per symbol (and the equity panel) on a shared time axis.
+    fn draw_symbols_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
+        let panels = self.panels();
+        let first_time = panels
+            .iter()
+            .flat_map(|(_, candles)| candles.open_time().iter().copied())
+            .min()
+            .ok_or(Error::CandleNotFound)?;
+        let last_time = panels
+            .iter()
+            .flat_map(|(_, candles)| candles.close_time().iter().copied())
+            .max()
+            .ok_or(Error::CandleNotFound)?;
+
+        #[allow(unused_mut)]
+        let mut count = panels.len();
+        #[cfg(feature = "metrics")]
+        {
+            count += 1;
+        }
+
+        let areas = drawing_area.split_evenly((count, 1));
+        for (i, ((name, candles), area)) in panels.iter().zip(&areas).enumerate() {
+            let area = area.margin(10, 10, 70, 70);
+            let mut builder = ChartBuilder::on(&area);
+            builder
+                .caption(*name, self.options.label_style())
+                .y_label_area_size(self.options.label_size());
+            if i == count - 1 {
+                builder.x_label_area_size(self.options.label_size());
+            }
+
+            let min_price = candles.low().iter().copied().fold(f64::INFINITY, f64::min);
+            let max_price = candles.high().iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let price_padding = (max_price - min_price) * 0.1;
+            let mut chart = builder
+                .build_cartesian_2d(
+                    first_time..last_time,
+                    min_price - price_padding..max_price + price_padding,
+                )
+                .map_err(|e| Error::Plotters(e.to_string()))?;
+
+            let mut mesh = chart.configure_mesh();
+            self.options.mesh(&mut mesh);
+            mesh.y_desc("Price")
+                .y_label_style(self.options.label_style())
+                .y_labels(3);
+            if i == count - 1 {
+                mesh.x_label_style(self.options.label_style()).x_labels(5);
+            } else {
+                mesh.disable_x_axis();
+            }
+            mesh.draw().map_err(|e| Error::Plotters(e.to_string()))?;
+
+            let candle_width = {
+                let available_width = area.dim_in_pixel().0 as f64 - (self.options.label_size() * 2) as f64;
+                (available_width / candles.len() as f64).max(5.0) as u32
+            };
+            let (up, down) = self.options.candle_palette();
+            chart
+                .draw_series(candles.iter().map(|c| {
+                    let color = if c.close() >= c.open() {
+                        up.filled()
+                    } else {
+                        down.filled()
+                    };
+                    CandleStick::new(
+                        c.open_time(),
+                        c.open(),
+                        c.high(),
+                        c.low(),
+                        c.close(),
+                        color,
+                        color,
+                        candle_width,
+                    )
+                }))
+                .map_err(|e| Error::Plotters(e.to_string()))?;
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(area) = areas.last() {
+            let equity = self.thin(self.metrics.equity_curve());
+            let overlays = self.equity_overlays(&equity);
+            let values = || equity.iter().chain(overlays.iter().flat_map(|(_, _, points)| points));
+            let min_equity = values().map(|(_, e)| *e).fold(f64::INFINITY, f64::min);
+            let max_equity = values().map(|(_, e)| *e).fold(f64::NEG_INFINITY, f64::max);
+            let equity_padding = ((max_equity - min_equity) * 0.1).max(1.0);
+
+            let area = area.margin(10, 10, 70, 70);
+            let mut chart = ChartBuilder::on(&area)
+                .caption("Equity", self.options.label_style())
+                .x_label_area_size(self.options.label_size())
+                .y_label_area_size(self.options.label_size())
+                .build_cartesian_2d(
+                    first_time..last_time,
+                    min_equity - equity_padding..max_equity + equity_padding,
+                )
+                .map_err(|e| Error::Plotters(e.to_string()))?;
+
+            self.options
+                .mesh(&mut chart.configure_mesh())
+                .y_desc("Balance")
+                .x_label_style(self.options.label_style())
+                .y_label_style(self.options.label_style())
+                .x_labels(5)
+                .y_labels(3)
+                .draw()
+                .map_err(|e| Error::Plotters(e.to_string()))?;
+
+            chart
+                .draw_series(LineSeries::new(equity, BLUE))
+                .map_err(|e| Error::Plotters(e.to_string()))?;
+            for (_, color, points) in overlays {
+                chart
+                    .draw_series(LineSeries::new(points, color))
+                    .map_err(|e| Error::Plotters(e.to_string()))?;
+            }
+        }
+
+        drawing_area.present().map_err(|e| Error::Plotters(e.to_string()))
+    }
+
+    /// Draws the drawdown chart (underwater balance curve).
+    #[cfg(feature = "metrics")]
+    fn draw_drawdown_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
+        let drawdowns = self.thin(self.metrics.drawdown_curve());
+        let min_drawdown = drawdowns.iter().map(|(_, dd)| *dd).fold(0.0, f64::min);
+        let drawdown_padding = (-min_drawdown * 0.1).max(1.0);
+        let first_time = self.candles.first().ok_or(Error::CandleNotFound)?.open_time();
+        let last_time = self.candles.last().ok_or(Error::CandleNotFound)?.close_time();
+        let drawing_area = drawing_area.margin(0, 10, 70, 70);
+
+        let mut chart = ChartBuilder::on(&drawing_area)
+            .x_label_area_size(self.options.label_size())
+            .y_label_area_size(self.options.label_size())
+            .build_cartesian_2d(first_time..last_time, min_drawdown - drawdown_padding..0.0)
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        self.options
+            .mesh(&mut chart.configure_mesh())
+            .x_desc("Time")
+            .y_desc("Drawdown (%)")
+            .x_label_style(self.options.label_style())
+            .y_label_style(self.options.label_style())
+            .x_labels(5)
+            .y_labels(3)
+            .draw()
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        chart
+            .draw_series(AreaSeries::new(drawdowns, 0.0, RED.mix(0.3)).border_style(RED))
+            .map(|_| ())
+            .map_err(|e| Error::Plotters(e.to_string()))
+    }
+
     /// Draws the metrics chart (if the "metrics" feature is enabled).
     #[cfg(feature = "metrics")]
     fn draw_metrics_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
@@ -487,13 +1706,13 @@ impl Draw {
 
         let drawing_area = drawing_area.margin(30, 0, 70, 70);
         let mut metrics_chart = ChartBuilder::on(&drawing_area)
-            .caption(title, ("sans-serif", 30).into_font())
+            .caption(title, self.options.title_style())
             .margin(20)
             .build_cartesian_2d(0.0..1.0, 0f64..100f64)
             .map_err(|e| Error::Plotters(e.to_string()))?;
 
-        metrics_chart
-            .configure_mesh()
+        self.options
+            .mesh(&mut metrics_chart.configure_mesh())
             .disable_x_mesh()
             .disable_y_mesh()
             .draw()
@@ -505,7 +1724,7 @@ impl Draw {
                 max_drawdown, profit_factor, sharpe_ratio, win_rate
             ),
             (0.0, 50.0),
-            ("sans-serif", 28).into_font(),
+            self.options.text_style(self.options.title_size() - 2),
         );
 
         metrics_chart
@@ -515,15 +1734,52 @@ impl Draw {
     }
 
     /// Rendered html version.
-    fn with_html_chart(&self) -> Chart {
-        let min_value = self.candles.iter().map(|c| c.low()).fold(f64::INFINITY, f64::min);
-        let max_value = self.candles.iter().map(|c| c.high()).fold(f64::NEG_INFINITY, f64::max);
+    pub(crate) fn with_html_chart(&self) -> Chart {
+        if !self.symbols.is_empty() {
+            return self.with_html_symbols_chart();
+        }
+
+        let min_value = self.candles.low().iter().copied().fold(f64::INFINITY, f64::min);
+        let max_value = self.candles.high().iter().copied().fold(f64::NEG_INFINITY, f64::max);
         let title = self.options.title.as_deref().unwrap_or("BTS Chart");
 
+        // the grids below the price grid (volume, panels and drawdown), stacked from the bottom
+        #[allow(unused_mut)]
+        let mut lower_grids = self.options.show_volume as usize + self.panels.len();
+        #[cfg(feature = "metrics")]
+        {
+            lower_grids += self.options.show_drawdown as usize;
+        }
+        let slot = if lower_grids > 0 {
+            (60.0 / lower_grids as f64).min(15.0)
+        } else {
+            15.0
+        };
+        let lower_top = (95.0 - slot * lower_grids as f64).min(65.0);
+        let mut grid_index = 0.0;
+        let mut next_grid = || {
+            grid_index += 1.0;
+            let top = lower_top + slot * (grid_index - 1.0);
+            (
+                grid_index,
+                Grid::new().top(format!("{top}%")).height(format!("{}%", slot - 5.0)),
+            )
+        };
+        let dates = || {
+            self.candles
+                .iter()
+                .map(|c| c.open_time().date_naive().to_string())
+                .collect()
+        };
+
         let mut chart = Chart::new()
             .title(Title::new().text(title).left("center"))
-            .data_zoom(DataZoom::new().x_axis_index(vec![0, 1]).type_(DataZoomType::Slider))
-            .grid(Grid::new().top("10%").height("50%"))
+            .data_zoom(
+                DataZoom::new()
+                    .x_axis_index((0..=lower_grids).map(|i| i as f64).collect::<Vec<_>>())
+                    .type_(DataZoomType::Slider),
+            )
+            .grid(Grid::new().top("10%").height(format!("{}%", lower_top - 15.0)))
             .x_axis(
                 Axis::new().grid_index(0).data(
                     self.candles
@@ -556,60 +1812,534 @@ impl Draw {
             );
 
         if self.options.show_volume {
+            let (axis_index, grid) = next_grid();
             chart = chart
-                .grid(Grid::new().top("65%").height("10%"))
-                .x_axis(
-                    Axis::new().grid_index(1).data(
-                        self.candles
+                .grid(grid)
+                .x_axis(Axis::new().grid_index(axis_index).data(dates()))
+                .y_axis(Axis::new().grid_index(axis_index))
+                .series(
+                    Bar::new()
+                        .x_axis_index(axis_index)
+                        .y_axis_index(axis_index)
+                        .data(self.candles.volume().to_vec()),
+                );
+        }
+
+        for (name, series) in &self.panels {
+            let (axis_index, grid) = next_grid();
+            chart = chart
+                .grid(grid)
+                .x_axis(Axis::new().grid_index(axis_index).data(dates()))
+                .y_axis(Axis::new().grid_index(axis_index).name(name.as_str()).scale(true));
+            for (s, color) in series.iter().zip(self.options.html_palette().iter().cycle()) {
+                chart = html_series(chart, s, axis_index, color);
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if self.options.show_drawdown {
+            let (axis_index, grid) = next_grid();
+            let drawdowns = self
+                .thin(self.metrics.drawdown_curve())
+                .into_iter()
+                .map(|(datetime, dd)| {
+                    let index = self.candles.open_time().partition_point(|time| *time < datetime);
+                    vec![index.min(self.candles.len() - 1) as f64, dd]
+                })
+                .collect();
+            chart = chart
+                .grid(grid)
+                .x_axis(Axis::new().grid_index(axis_index).data(dates()))
+                .y_axis(Axis::new().grid_index(axis_index).max(0))
+                .series(
+                    Line::new()
+                        .name("Drawdown (%)")
+                        .x_axis_index(axis_index)
+                        .y_axis_index(axis_index)
+                        .data(drawdowns)
+                        .item_style(ItemStyle::new().color("RED")),
+                );
+        }
+
+        for (s, color) in self.series.iter().zip(self.options.html_palette().iter().cycle()) {
+            chart = html_series(chart, s, 0.0, color);
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let times = self.candles.open_time();
+            let index =
+                |datetime: DateTime<Utc>| times.partition_point(|time| *time < datetime).min(times.len() - 1) as f64;
+
+            //? the equity axis is added after the axes of the lower grids, so their indexes don't change
+            if self.options.show_metrics {
+                let equity_axis = (lower_grids + 1) as f64;
+                chart = chart
+                    .y_axis(
+                        Axis::new()
+                            .grid_index(0)
+                            .name("Equity")
+                            .position("right")
+                            .scale(true)
+                            .split_line(SplitLine::new().show(false)),
+                    )
+                    .series(
+                        Line::new()
+                            .name("Equity")
+                            .show_symbol(false)
+                            .y_axis_index(equity_axis)
+                            .data(
+                                self.thin(self.metrics.equity_curve())
+                                    .into_iter()
+                                    .map(|(datetime, equity)| vec![index(datetime), equity])
+                                    .collect(),
+                            ),
+                    );
+            }
+
+            if self.options.show_trades {
+                let trades = self.metrics.trades();
+                let (entries, exits): (Vec<_>, Vec<_>) = self
+                    .markers(trades.iter().collect())
+                    .map(|trade| {
+                        let (open, close) = match trade.side() {
+                            PositionSide::Long => ("Buy", "Sell"),
+                            PositionSide::Short => ("Sell", "Buy"),
+                        };
+                        let color = if trade.is_win() { "GREEN" } else { "RED" };
+                        let entry = DataPointItem::new(vec![index(trade.entry_time()), trade.entry_price()])
+                            .name(format!("{open} {} @ {:.2}", trade.quantity(), trade.entry_price()));
+                        let exit = DataPointItem::new(vec![index(trade.exit_time()), trade.exit_price()])
+                            .name(format!(
+                                "{close} {} @ {:.2} ({}), P&L {:+.2}",
+                                trade.quantity(),
+                                trade.exit_price(),
+                                trade.exit_reason(),
+                                trade.net_pnl()
+                            ))
+                            .item_style(ItemStyle::new().color(color));
+                        (entry, exit)
+                    })
+                    .unzip();
+                chart = chart
+                    .series(
+                        Scatter::new()
+                            .name("Entries")
+                            .symbol(Symbol::Triangle)
+                            .symbol_size(12)
+                            .item_style(ItemStyle::new().color("BLUE"))
+                            .data(entries),
+                    )
+                    .series(
+                        Scatter::new()
+                            .name("Exits")
+                            .symbol(Symbol::Diamond)
+                            .symbol_size(12)
+                            .data(exits),
+                    );
+            }
+        }
+
+        //? the trade markers show their description, the other series their values
+        let formatter = JsFunction::new_with_args(
+            "params",
+            "return params[0].axisValueLabel + params.map(function (p) { \
+                var value = Array.isArray(p.value) ? p.value.slice(1).join(' / ') : p.value; \
+                return '<br/>' + p.marker + (p.data && p.data.name ? p.data.name : p.seriesName + ': ' + value); \
+            }).join('');",
+        );
+        chart.tooltip(Tooltip::new().trigger(Trigger::Axis).formatter(formatter))
+    }
+
+    /// Rendered html version with one grid per symbol (and the equity grid) on a shared, linked time axis.
+    fn with_html_symbols_chart(&self) -> Chart {
+        let panels = self.panels();
+        let times = panels
+            .iter()
+            .flat_map(|(_, candles)| candles.open_time().iter().copied())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let index = |time| match times.binary_search(&time) {
+            Ok(i) | Err(i) => i.min(times.len() - 1) as f64,
+        };
+        let labels = times
+            .iter()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .collect::<Vec<_>>();
+
+        #[allow(unused_mut)]
+        let mut count = panels.len();
+        #[cfg(feature = "metrics")]
+        {
+            count += 1;
+        }
+        let height = 85.0 / count as f64;
+
+        let mut chart = Chart::new()
+            .title(Title::new().text(panels[0].0).left("center"))
+            .data_zoom(
+                DataZoom::new()
+                    .x_axis_index((0..count).map(|i| i as f64).collect::<Vec<_>>())
+                    .type_(DataZoomType::Slider),
+            );
+        for i in 0..count {
+            chart = chart
+                .grid(
+                    Grid::new()
+                        .top(format!("{}%", 8.0 + i as f64 * height))
+                        .height(format!("{}%", height - 5.0)),
+                )
+                .x_axis(Axis::new().grid_index(i as f64).data(labels.clone()))
+                .y_axis(Axis::new().grid_index(i as f64).scale(true));
+        }
+
+        for (i, (name, candles)) in panels.iter().enumerate() {
+            chart = if i == 0 {
+                chart.series(
+                    Candlestick::new().name(*name).data(
+                        candles
                             .iter()
-                            .map(|c| c.open_time().date_naive().to_string())
+                            .map(|c| vec![index(c.open_time()), c.open(), c.high(), c.low(), c.close()])
                             .collect(),
                     ),
                 )
-                .y_axis(Axis::new().grid_index(1))
+            } else {
+                chart.series(
+                    Line::new()
+                        .name(*name)
+                        .x_axis_index(i as f64)
+                        .y_axis_index(i as f64)
+                        .data(candles.iter().map(|c| vec![index(c.open_time()), c.close()]).collect()),
+                )
+            };
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let equity = self.thin(self.metrics.equity_curve());
+            let overlays = self.equity_overlays(&equity);
+            for (name, points) in
+                std::iter::once(("Equity", equity)).chain(overlays.into_iter().map(|(n, _, p)| (n, p)))
+            {
+                chart = chart.series(
+                    Line::new()
+                        .name(name)
+                        .x_axis_index((count - 1) as f64)
+                        .y_axis_index((count - 1) as f64)
+                        .data(points.iter().map(|(t, e)| vec![index(*t), *e]).collect()),
+                );
+            }
+        }
+
+        chart.tooltip(Tooltip::new().trigger(Trigger::Axis))
+    }
+
+    /// Rendered html version of the equity curve chart, with the drawdown grid below if enabled.
+    #[cfg(feature = "metrics")]
+    fn with_html_equity_chart(&self) -> Chart {
+        let title = self.options.title.as_deref().unwrap_or("BTS Equity Curve");
+        let times = self.candles.open_time();
+        let index =
+            |datetime: DateTime<Utc>| times.partition_point(|time| *time < datetime).min(times.len() - 1) as f64;
+        let dates = || {
+            times
+                .iter()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .collect::<Vec<_>>()
+        };
+        let grids = 1 + self.options.show_drawdown as usize;
+        let equity_height = if self.options.show_drawdown { 55.0 } else { 75.0 };
+
+        let mut chart = Chart::new()
+            .title(Title::new().text(title).left("center"))
+            .legend(charming::component::Legend::new().top("5%"))
+            .data_zoom(
+                DataZoom::new()
+                    .x_axis_index((0..grids).map(|i| i as f64).collect::<Vec<_>>())
+                    .type_(DataZoomType::Slider),
+            )
+            .grid(Grid::new().top("12%").height(format!("{equity_height}%")))
+            .x_axis(Axis::new().grid_index(0).data(dates()))
+            .y_axis(Axis::new().grid_index(0).name("Balance").scale(true));
+
+        for (name, _, points) in self.equity_lines() {
+            chart = chart.series(
+                Line::new()
+                    .name(name)
+                    .show_symbol(false)
+                    .data(points.iter().map(|(t, value)| vec![index(*t), *value]).collect()),
+            );
+        }
+
+        if self.options.show_drawdown {
+            let drawdowns = self
+                .thin(self.metrics.drawdown_curve())
+                .into_iter()
+                .map(|(datetime, dd)| vec![index(datetime), dd])
+                .collect();
+            chart = chart
+                .grid(Grid::new().top("72%").height("15%"))
+                .x_axis(Axis::new().grid_index(1).data(dates()))
+                .y_axis(Axis::new().grid_index(1).max(0))
                 .series(
-                    Bar::new()
+                    Line::new()
+                        .name("Drawdown (%)")
+                        .show_symbol(false)
                         .x_axis_index(1)
                         .y_axis_index(1)
-                        .data(self.candles.iter().map(|c| c.volume()).collect()),
+                        .data(drawdowns)
+                        .item_style(ItemStyle::new().color("RED")),
                 );
         }
 
-        if !self.series.is_empty() {
-            let colors = [
-                "BLUE", "GREEN", "RED", "CYAN", "MAGENTA", "YELLOW", "BLACK", "ORANGE", "PURPLE", "PINK", "LIME",
-                "TEAL",
-            ];
-            let mut color_index = 0;
+        chart.tooltip(Tooltip::new().trigger(Trigger::Axis))
+    }
+}
 
-            self.series.iter().for_each(|s| {
-                let color = colors[color_index % colors.len()];
-                color_index += 1;
+/// Heatmap of an optimization objective over two parameters.
+///
+/// Each cell is a parameter pair with its objective (e.g., the final balance or the Sharpe ratio),
+/// colored from red (lowest) to green (highest). A wide green plateau points to robust parameters,
+/// while an isolated green cell is more likely a lucky spike.
+///
+/// ### Example
+///
+/// ```ignore
+/// let results = optimizer.with_filter(combinator, strategy, |bt| Some(bt.total_balance()))?;
+/// Heatmap::new("Fast", "Slow", results.iter().map(|((fast, slow), balance)| (*fast, *slow, *balance)))
+///     .with_options(DrawOptions::default().draw_output(DrawOutput::Svg("heatmap.svg".to_owned())))
+///     .plot()?;
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Heatmap {
+    x_label: String,
+    y_label: String,
+    cells: Vec<(f64, f64, f64)>,
+    options: DrawOptions,
+}
 
-                match s {
-                    Series::Lines(data) => {
-                        let lines = Line::new()
-                            .x_axis_index(0)
-                            .y_axis_index(0)
-                            .data(data.to_vec())
-                            .item_style(ItemStyle::new().color(color));
+impl Heatmap {
+    /// Creates a new `Heatmap` instance.
+    ///
+    /// ### Arguments
+    ///
+    /// * `x_label` - The name of the parameter on the X-axis.
+    /// * `y_label` - The name of the parameter on the Y-axis.
+    /// * `cells` - The `(x, y, objective)` cells. When a parameter pair appears more than once, the last one is drawn.
+    pub fn new(
+        x_label: impl ToString,
+        y_label: impl ToString,
+        cells: impl IntoIterator<Item = (f64, f64, f64)>,
+    ) -> Self {
+        Self {
+            x_label: x_label.to_string(),
+            y_label: y_label.to_string(),
+            cells: cells.into_iter().filter(|(_, _, value)| value.is_finite()).collect(),
+            options: DrawOptions::default(),
+        }
+    }
 
-                        chart = chart.clone().series(lines);
-                    }
-                    Series::Circles(data) => {
-                        let circles = Scatter::new()
-                            .x_axis_index(0)
-                            .y_axis_index(0)
-                            .data(data.to_vec())
-                            .symbol(Symbol::Circle)
-                            .item_style(ItemStyle::new().color(color));
+    /// Sets the drawing options (only the title and the output are used).
+    pub fn with_options(mut self, options: DrawOptions) -> Self {
+        self.options = options;
+        self
+    }
 
-                        chart = chart.clone().series(circles);
-                    }
-                }
-            });
+    /// Generates and saves the heatmap based on the configured options.
+    pub fn plot(&self) -> Result<()> {
+        if self.cells.is_empty() {
+            return Err(Error::Msg("No heatmap cells to draw".to_string()));
         }
 
-        chart.tooltip(Tooltip::new().trigger(Trigger::Axis))
+        match &self.options.output {
+            DrawOutput::Svg(path) => {
+                let root = self
+                    .options
+                    .fill(SVGBackend::new(path, self.options.size()).into_drawing_area())?;
+                self.draw_chart(&root)
+            }
+            DrawOutput::Png(path) => {
+                let root = self
+                    .options
+                    .fill(BitMapBackend::new(path, self.options.size()).into_drawing_area())?;
+                self.draw_chart(&root)
+            }
+            DrawOutput::Html(path) => self.options.save_html(self.with_html_chart(), "BTS Heatmap", path),
+            DrawOutput::Inner => Err(Error::Msg("Inner display is not implemented".to_string())),
+        }
+    }
+
+    /// Returns the sorted distinct values of each parameter.
+    fn axes(&self) -> (Vec<f64>, Vec<f64>) {
+        let distinct = |values: Vec<f64>| {
+            let mut values = values;
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            values
+        };
+        (
+            distinct(self.cells.iter().map(|(x, _, _)| *x).collect()),
+            distinct(self.cells.iter().map(|(_, y, _)| *y).collect()),
+        )
+    }
+
+    /// Returns the cells with their position on each axis.
+    fn indexed_cells(&self, xs: &[f64], ys: &[f64]) -> Vec<(usize, usize, f64)> {
+        let position = |values: &[f64], value: f64| values.partition_point(|v| *v < value);
+        self.cells
+            .iter()
+            .map(|(x, y, value)| (position(xs, *x), position(ys, *y), *value))
+            .collect()
+    }
+
+    /// Returns the lowest and highest objectives.
+    fn range(&self) -> (f64, f64) {
+        let values = self.cells.iter().map(|(_, _, value)| *value);
+        (
+            values.clone().fold(f64::INFINITY, f64::min),
+            values.fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+
+    /// Draws the heatmap with its color scale.
+    fn draw_chart<DB: DrawingBackend>(&self, drawing_area: &DrawingArea<DB, Shift>) -> Result<()> {
+        let title = self.options.title.as_deref().unwrap_or("Parameter Heatmap");
+        let (xs, ys) = self.axes();
+        let cells = self.indexed_cells(&xs, &ys);
+        let (min_value, max_value) = self.range();
+        let color = |value: f64| {
+            let ratio = if max_value > min_value {
+                (value - min_value) / (max_value - min_value)
+            } else {
+                1.0
+            };
+            HSLColor(ratio / 3.0, 0.75, 0.5)
+        };
+
+        let (heatmap_area, scale_area) = drawing_area.split_horizontally(self.options.size().0.saturating_sub(160));
+        //? two units per cell, so it can be labeled at its center
+        let axis = |len: usize| (0..2 * len as i32).with_key_points((0..len as i32).map(|i| 2 * i + 1).collect());
+
+        let mut chart = ChartBuilder::on(&heatmap_area)
+            .caption(title, self.options.title_style())
+            .margin(20)
+            .x_label_area_size(60)
+            .y_label_area_size(80)
+            .build_cartesian_2d(axis(xs.len()), axis(ys.len()))
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        let label = |values: &[f64], value: &i32| {
+            values
+                .get(*value as usize / 2)
+                .map(|v| v.to_string())
+                .unwrap_or_default()
+        };
+        self.options
+            .mesh(&mut chart.configure_mesh())
+            .disable_mesh()
+            .x_desc(&self.x_label)
+            .y_desc(&self.y_label)
+            .x_label_style(self.options.label_style())
+            .y_label_style(self.options.label_style())
+            .x_label_formatter(&|v| label(&xs, v))
+            .y_label_formatter(&|v| label(&ys, v))
+            .draw()
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        chart
+            .draw_series(cells.iter().map(|(x, y, value)| {
+                let (x, y) = (2 * *x as i32, 2 * *y as i32);
+                Rectangle::new([(x, y), (x + 2, y + 2)], color(*value).filled())
+            }))
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        //? the values are only readable on small grids
+        if xs.len() <= 20 && ys.len() <= 20 {
+            let style = TextStyle::from(("sans-serif", 16).into_font()).pos(Pos::new(HPos::Center, VPos::Center));
+            chart
+                .draw_series(cells.iter().map(|(x, y, value)| {
+                    Text::new(
+                        format!("{value:.2}"),
+                        (2 * *x as i32 + 1, 2 * *y as i32 + 1),
+                        style.clone(),
+                    )
+                }))
+                .map_err(|e| Error::Plotters(e.to_string()))?;
+        }
+
+        let mut scale = ChartBuilder::on(&scale_area)
+            .margin_top(70)
+            .margin_bottom(80)
+            .margin_right(20)
+            .y_label_area_size(80)
+            .build_cartesian_2d(0.0..1.0, min_value..max_value.max(min_value + f64::EPSILON))
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        self.options
+            .mesh(&mut scale.configure_mesh())
+            .disable_mesh()
+            .disable_x_axis()
+            .y_label_style(self.options.label_style())
+            .y_labels(5)
+            .draw()
+            .map_err(|e| Error::Plotters(e.to_string()))?;
+
+        let steps = 100;
+        let step = (max_value - min_value) / steps as f64;
+        scale
+            .draw_series((0..steps).map(|i| {
+                let value = min_value + step * i as f64;
+                Rectangle::new([(0.0, value), (1.0, value + step)], color(value).filled())
+            }))
+            .map(|_| ())
+            .map_err(|e| Error::Plotters(e.to_string()))
+    }
+
+    /// Rendered html version.
+    fn with_html_chart(&self) -> Chart {
+        let title = self.options.title.as_deref().unwrap_or("Parameter Heatmap");
+        let (xs, ys) = self.axes();
+        let cells = self.indexed_cells(&xs, &ys);
+        let (min_value, max_value) = self.range();
+        let labels = |values: &[f64]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        Chart::new()
+            .title(Title::new().text(title).left("center"))
+            .tooltip(Tooltip::new().position("top"))
+            .grid(Grid::new().left("10%").right("10%").top("10%").bottom("15%"))
+            .x_axis(
+                Axis::new()
+                    .type_(AxisType::Category)
+                    .name(&self.x_label)
+                    .data(labels(&xs)),
+            )
+            .y_axis(
+                Axis::new()
+                    .type_(AxisType::Category)
+                    .name(&self.y_label)
+                    .data(labels(&ys)),
+            )
+            .visual_map(
+                VisualMap::new()
+                    .min(min_value)
+                    .max(max_value)
+                    .calculable(true)
+                    .orient(Orient::Horizontal)
+                    .left("center")
+                    .bottom("2%")
+                    .in_range(VisualMapChannel::new().color(vec!["#d73027", "#fee08b", "#1a9850"])),
+            )
+            .series(
+                HeatmapSeries::new()
+                    .name(title)
+                    .label(Label::new().show(xs.len() <= 20 && ys.len() <= 20))
+                    .data(
+                        cells
+                            .iter()
+                            .map(|(x, y, value)| [*x as f64, *y as f64, *value].map(DataPoint::from).to_vec())
+                            .collect::<Vec<DataFrame>>(),
+                    ),
+            )
     }
 }
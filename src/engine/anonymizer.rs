@@ -0,0 +1,162 @@
+use crate::engine::{Candle, CandleBuilder};
+use crate::errors::{Error, Result};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Rescales and time-shifts candle data so it can be shared without leaking the original series.
+///
+/// Prices are multiplied by the same factor, so the returns (and therefore the backtest results in
+/// percentage) are preserved. Volumes and bids are multiplied by another factor and the times are
+/// shifted by a constant delta, so the spacing between the candles is preserved.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candle = CandleBuilder::builder()
+///     .open(25_000.0)
+///     .high(26_000.0)
+///     .low(24_000.0)
+///     .close(25_500.0)
+///     .volume(12.0)
+///     .open_time(DateTime::from_timestamp_secs(1_700_000_000).unwrap())
+///     .close_time(DateTime::from_timestamp_secs(1_700_003_600).unwrap())
+///     .build()
+///     .unwrap();
+///
+/// let start = DateTime::from_timestamp_secs(0).unwrap();
+/// let anonymizer = Anonymizer::rebase(&[candle], 100.0, start, 1.0).unwrap();
+/// let candles = anonymizer.anonymize(&[candle]).unwrap();
+/// assert_eq!(candles[0].open(), 100.0);
+/// assert_eq!(candles[0].close(), 102.0);
+/// assert_eq!(candles[0].open_time(), start);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anonymizer {
+    price_scale: f64,
+    volume_scale: f64,
+    time_shift: TimeDelta,
+}
+
+impl Anonymizer {
+    /// Creates a new anonymizer.
+    ///
+    /// ### Arguments
+    /// * `price_scale` - The factor applied to the prices.
+    /// * `volume_scale` - The factor applied to the volumes and bids.
+    /// * `time_shift` - The delta added to the open and close times.
+    ///
+    /// ### Returns
+    /// The anonymizer, or an error if a scale is not positive.
+    pub fn new(price_scale: f64, volume_scale: f64, time_shift: TimeDelta) -> Result<Self> {
+        if !(price_scale > 0.0 && price_scale.is_finite() && volume_scale > 0.0 && volume_scale.is_finite()) {
+            return Err(Error::Msg(format!(
+                "Scales must be positive (got: price={price_scale}, volume={volume_scale})"
+            )));
+        }
+        Ok(Self {
+            price_scale,
+            volume_scale,
+            time_shift,
+        })
+    }
+
+    /// Creates an anonymizer moving the first candle of `candles` to `start_price` and `start_time`.
+    ///
+    /// ### Returns
+    /// The anonymizer, or an error if the candles are empty or a scale is not positive.
+    pub fn rebase(candles: &[Candle], start_price: f64, start_time: DateTime<Utc>, volume_scale: f64) -> Result<Self> {
+        let first = candles.first().ok_or(Error::CandleDataEmpty)?;
+        Self::new(start_price / first.open(), volume_scale, start_time - first.open_time())
+    }
+
+    /// Creates an anonymizer with random scales (between 0.1 and 10) and a random shift of up to ten years.
+    pub fn random() -> Self {
+        let scale = || 10f64.powf(rand::random_range(-1.0..1.0));
+        Self {
+            price_scale: scale(),
+            volume_scale: scale(),
+            time_shift: TimeDelta::days(rand::random_range(-3650..=3650)),
+        }
+    }
+
+    /// Returns the factor applied to the prices.
+    pub fn price_scale(&self) -> f64 {
+        self.price_scale
+    }
+
+    /// Returns the factor applied to the volumes and bids.
+    pub fn volume_scale(&self) -> f64 {
+        self.volume_scale
+    }
+
+    /// Returns the delta added to the times.
+    pub fn time_shift(&self) -> TimeDelta {
+        self.time_shift
+    }
+
+    /// Anonymizes one candle.
+    pub fn apply(&self, candle: &Candle) -> Result<Candle> {
+        CandleBuilder::builder()
+            .open(candle.open() * self.price_scale)
+            .high(candle.high() * self.price_scale)
+            .low(candle.low() * self.price_scale)
+            .close(candle.close() * self.price_scale)
+            .volume(candle.volume() * self.volume_scale)
+            .bid(candle.bid() * self.volume_scale)
+            .open_time(candle.open_time() + self.time_shift)
+            .close_time(candle.close_time() + self.time_shift)
+            .build()
+    }
+
+    /// Anonymizes a series of candles.
+    pub fn anonymize(&self, candles: &[Candle]) -> Result<Vec<Candle>> {
+        candles.iter().map(|candle| self.apply(candle)).collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn anonymizer_preserves_returns() {
+    use crate::PercentCalculus;
+
+    let start = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let candles = [(100.0, 110.0), (110.0, 99.0), (99.0, 120.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (open, close))| {
+            CandleBuilder::builder()
+                .open(open)
+                .high(f64::max(open, close))
+                .low(f64::min(open, close))
+                .close(close)
+                .volume(10.0)
+                .bid(4.0)
+                .open_time(start + TimeDelta::hours(i as i64))
+                .close_time(start + TimeDelta::hours(i as i64 + 1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    for anonymizer in [
+        Anonymizer::random(),
+        Anonymizer::rebase(&candles, 1.0, DateTime::default(), 2.0).unwrap(),
+    ] {
+        let anonymized = anonymizer.anonymize(&candles).unwrap();
+        for (original, anonymized) in candles.iter().zip(&anonymized) {
+            let change = original.open().change(original.close());
+            assert!((anonymized.open().change(anonymized.close()) - change).abs() < 1e-9);
+            assert_eq!(
+                anonymized.close_time() - anonymized.open_time(),
+                original.close_time() - original.open_time()
+            );
+            assert!((anonymized.bid() / anonymized.volume() - 0.4).abs() < 1e-9);
+        }
+    }
+
+    assert!(Anonymizer::new(0.0, 1.0, TimeDelta::zero()).is_err());
+    assert!(Anonymizer::rebase(&[], 1.0, DateTime::default(), 1.0).is_err());
+}
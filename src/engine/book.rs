@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, btree_map::Values};
+
+use super::{Order, OrderSide, OrderType, TimeInForce};
+
+use chrono::{DateTime, Utc};
+
+/// Price key ordered with `f64::total_cmp`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Price(f64);
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Price levels of one kind of resting order, each level in time priority.
+type Levels = BTreeSet<(Price, u64)>;
+
+/// Pending orders indexed by price, with price-time priority.
+///
+/// Each order gets a sequence number when it is placed, which gives its time priority. The resting
+/// limit and stop orders are indexed by price and side, so the orders reached by a candle are found
+/// without scanning the whole book. The market and immediate (IOC/FOK) orders are handled on every
+/// candle, and the good-till-date orders are also indexed by expiration.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OrderBook {
+    next_seq: u64,
+    orders: BTreeMap<u64, Order>,
+    seqs: HashMap<u32, u64>,
+    bids: Levels,
+    asks: Levels,
+    buy_stops: Levels,
+    sell_stops: Levels,
+    expirations: BTreeSet<(DateTime<Utc>, u64)>,
+    always: BTreeSet<u64>,
+}
+
+impl OrderBook {
+    /// Returns the number of pending orders.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Returns true if there is no pending order.
+    #[cfg(test)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Returns the pending orders in time priority.
+    pub(crate) fn iter(&self) -> Values<'_, u64, Order> {
+        self.orders.values()
+    }
+
+    /// Adds an order behind the orders already placed.
+    pub(crate) fn push(&mut self, order: Order) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.insert(seq, order);
+    }
+
+    /// Adds back an order with its original time priority.
+    pub(crate) fn insert(&mut self, seq: u64, order: Order) {
+        match (order.entry_type(), order.side()) {
+            _ if order.is_immediate() => self.always.insert(seq),
+            (OrderType::Limit(price), OrderSide::Buy) => self.bids.insert((Price(*price), seq)),
+            (OrderType::Limit(price), OrderSide::Sell) => self.asks.insert((Price(*price), seq)),
+            (OrderType::Stop(price), OrderSide::Buy) => self.buy_stops.insert((Price(*price), seq)),
+            (OrderType::Stop(price), OrderSide::Sell) => self.sell_stops.insert((Price(*price), seq)),
+            _ => self.always.insert(seq),
+        };
+        if let TimeInForce::Gtd(expiration) = order.time_in_force() {
+            self.expirations.insert((*expiration, seq));
+        }
+        self.seqs.insert(order.id(), seq);
+        self.orders.insert(seq, order);
+    }
+
+    /// Removes an order, returning it with its time priority.
+    pub(crate) fn remove(&mut self, id: u32) -> Option<(u64, Order)> {
+        let seq = self.seqs.remove(&id)?;
+        let order = self.orders.remove(&seq)?;
+        match (order.entry_type(), order.side()) {
+            _ if order.is_immediate() => self.always.remove(&seq),
+            (OrderType::Limit(price), OrderSide::Buy) => self.bids.remove(&(Price(*price), seq)),
+            (OrderType::Limit(price), OrderSide::Sell) => self.asks.remove(&(Price(*price), seq)),
+            (OrderType::Stop(price), OrderSide::Buy) => self.buy_stops.remove(&(Price(*price), seq)),
+            (OrderType::Stop(price), OrderSide::Sell) => self.sell_stops.remove(&(Price(*price), seq)),
+            _ => self.always.remove(&seq),
+        };
+        if let TimeInForce::Gtd(expiration) = order.time_in_force() {
+            self.expirations.remove(&(*expiration, seq));
+        }
+        Some((seq, order))
+    }
+
+    /// Removes all the orders.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the IDs of the orders to handle on a candle, in time priority: the market and immediate
+    /// orders, the limit orders within the quotes of their side, the triggered stop orders and the
+    /// expired orders.
+    ///
+    /// ### Arguments
+    /// * `time` - The open time of the candle.
+    /// * `buy` - The low and high quotes of the candle for the buy orders (the ask).
+    /// * `sell` - The low and high quotes of the candle for the sell orders (the bid).
+    pub(crate) fn reached(&self, time: DateTime<Utc>, buy: (f64, f64), sell: (f64, f64)) -> Vec<u32> {
+        let within = |levels: &Levels, (low, high): (f64, f64)| {
+            levels
+                .range((Price(low), u64::MIN)..=(Price(high), u64::MAX))
+                .map(|(_, seq)| *seq)
+                .collect::<Vec<_>>()
+        };
+        let mut seqs = self.always.clone();
+        seqs.extend(within(&self.bids, buy));
+        seqs.extend(within(&self.asks, sell));
+        seqs.extend(within(&self.buy_stops, (f64::NEG_INFINITY, buy.1)));
+        seqs.extend(within(&self.sell_stops, (sell.0, f64::INFINITY)));
+        seqs.extend(self.expirations.range(..(time, u64::MIN)).map(|(_, seq)| *seq));
+        seqs.into_iter()
+            .filter_map(|seq| self.orders.get(&seq))
+            .map(Order::id)
+            .collect()
+    }
+
+    /// Returns the resting limit and stop orders at `price`, in time priority.
+    pub(crate) fn at_price(&self, price: f64) -> impl Iterator<Item = &Order> {
+        let level = (Price(price), u64::MIN)..=(Price(price), u64::MAX);
+        let mut seqs = BTreeSet::new();
+        for levels in [&self.bids, &self.asks, &self.buy_stops, &self.sell_stops] {
+            seqs.extend(levels.range(level.clone()).map(|(_, seq)| *seq));
+        }
+        seqs.into_iter().filter_map(|seq| self.orders.get(&seq))
+    }
+
+    /// Returns the highest price of the resting buy limit orders.
+    pub(crate) fn best_bid(&self) -> Option<f64> {
+        self.bids.last().map(|(price, _)| price.0)
+    }
+
+    /// Returns the lowest price of the resting sell limit orders.
+    pub(crate) fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|(price, _)| price.0)
+    }
+}
+
+/// The order book is serialized as the list of its orders in time priority.
+#[cfg(feature = "serde")]
+impl serde::Serialize for OrderBook {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.orders.values())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for OrderBook {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let mut book = Self::default();
+        for order in Vec::<Order>::deserialize(deserializer)? {
+            book.push(order);
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn order_book() {
+    let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+    let bid_95 = Order::from((OrderType::Limit(95.0), 1.0, OrderSide::Buy));
+    let bid_98 = Order::from((OrderType::Limit(98.0), 1.0, OrderSide::Buy));
+    let bid_98_later = Order::from((OrderType::Limit(98.0), 2.0, OrderSide::Buy));
+    let ask_105 = Order::from((OrderType::Limit(105.0), 1.0, OrderSide::Sell));
+    let buy_stop = Order::from((OrderType::Stop(110.0), 1.0, OrderSide::Buy));
+    let market = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+    let gtd = Order::from((OrderType::Limit(80.0), 1.0, OrderSide::Buy)).with_time_in_force(TimeInForce::Gtd(time(10)));
+
+    let mut book = OrderBook::default();
+    for order in [bid_95, bid_98, bid_98_later, ask_105, buy_stop, market, gtd] {
+        book.push(order);
+    }
+    assert_eq!(book.len(), 7);
+    assert_eq!(book.best_bid(), Some(98.0));
+    assert_eq!(book.best_ask(), Some(105.0));
+    let level = book.at_price(98.0).map(Order::id).collect::<Vec<_>>();
+    assert_eq!(level, vec![bid_98.id(), bid_98_later.id()]);
+
+    // a candle from 97 to 104 reaches the bids at 98 and the market order, in time priority
+    let reached = book.reached(time(0), (97.0, 104.0), (97.0, 104.0));
+    assert_eq!(reached, vec![bid_98.id(), bid_98_later.id(), market.id()]);
+    // a candle from 94 to 111, after the expiration
+    let reached = book.reached(time(20), (94.0, 111.0), (94.0, 111.0));
+    assert_eq!(reached.len(), 7);
+
+    // the removed order keeps its priority when it is added back
+    let (seq, order) = book.remove(bid_98.id()).unwrap();
+    assert_eq!(book.best_bid(), Some(98.0));
+    book.insert(seq, order);
+    assert_eq!(book.at_price(98.0).next().unwrap().id(), bid_98.id());
+    assert!(book.remove(0).is_none());
+    book.clear();
+    assert!(book.is_empty());
+}
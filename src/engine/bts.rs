@@ -1,15 +1,22 @@
 use std::{
-    collections::{VecDeque, vec_deque::Iter},
-    sync::Arc,
+    collections::{HashMap, HashSet, VecDeque, vec_deque::Iter},
+    ops::Range,
+    sync::{Arc, OnceLock},
 };
 
-#[cfg(feature = "metrics")]
-use crate::metrics::*;
 use crate::{
     PercentCalculus,
+    data::Provenance,
     engine::*,
     errors::{Error, Result},
+    utils::{IdSequence, unit_sample},
 };
+#[cfg(feature = "metrics")]
+use crate::{metrics::*, sinks::EventSink};
+#[cfg(feature = "metrics")]
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeDelta, Utc};
 
 #[cfg(test)]
 impl Iterator for Backtest {
@@ -62,8 +69,158 @@ pub trait Aggregation {
     fn should_aggregate(&self, factor: usize, candles: &[&Candle]) -> bool {
         candles.len() == factor
     }
+
+    /// Returns the candles of the window to aggregate, if any, and the number of candles then
+    /// removed from the front of the window.
+    ///
+    /// The default aggregates the whole window when `should_aggregate` returns `true` and removes
+    /// the oldest candle, so the aggregated candle is a rolling window of `factor` candles.
+    fn ready(&self, factor: usize, candles: &[&Candle]) -> Option<(Range<usize>, usize)> {
+        self.should_aggregate(factor, candles).then_some((0..candles.len(), 1))
+    }
+}
+
+/// Aggregation of the candles by duration, in buckets aligned to the clock.
+///
+/// The buckets are aligned to the Unix epoch: an hourly bucket starts on the hour and a daily bucket
+/// at midnight UTC. The candle of a bucket is aggregated once the bucket is complete, that is when a
+/// candle closes at its end or, if that candle is missing, when a candle of a later bucket comes. So
+/// the gaps in the data don't shift the buckets as they do with a count of candles.
+///
+/// The factors are the indexes of the timeframes: the strategy receives the last complete candle of
+/// each timeframe, in the given order. The timeframes should be multiples of the candle interval.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candles = (0..8)
+///     .map(|i| {
+///         let open_time = DateTime::default() + Duration::minutes(15 * i);
+///         CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(105.0)
+///             .volume(1.0)
+///             .open_time(open_time)
+///             .close_time(open_time + Duration::minutes(15))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Arc<[_]>>();
+///
+/// // the candle itself and the hourly candle
+/// let aggregator = DurationAggregation::new(&[Duration::minutes(15), Duration::hours(1)]).unwrap();
+/// let mut bts = Backtest::new(candles, 1000.0, None).unwrap();
+/// bts.run_with_aggregator(&aggregator, |_bts, candles| {
+///     if let Some(hour) = candles.get(1) {
+///         assert_eq!(hour.close_time() - hour.open_time(), Duration::hours(1));
+///     }
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DurationAggregation {
+    timeframes: Vec<TimeDelta>,
+    factors: Vec<usize>,
+}
+
+impl DurationAggregation {
+    /// Creates a new `DurationAggregation`.
+    ///
+    /// ### Arguments
+    /// * `timeframes` - The durations of the aggregated candles (e.g., 1 hour, 4 hours).
+    ///
+    /// ### Returns
+    /// The aggregation, or an error if there is no timeframe or if a timeframe is not positive.
+    pub fn new(timeframes: &[TimeDelta]) -> Result<Self> {
+        if timeframes.is_empty() || timeframes.iter().any(|t| t.num_milliseconds() <= 0) {
+            return Err(Error::InvalidFactor);
+        }
+        Ok(Self {
+            timeframes: timeframes.to_vec(),
+            factors: (0..timeframes.len()).collect(),
+        })
+    }
+
+    /// Returns the durations of the aggregated candles.
+    pub fn timeframes(&self) -> &[TimeDelta] {
+        &self.timeframes
+    }
+}
+
+impl Aggregation for DurationAggregation {
+    fn factors(&self) -> &[usize] {
+        &self.factors
+    }
+
+    fn ready(&self, factor: usize, candles: &[&Candle]) -> Option<(Range<usize>, usize)> {
+        let millis = self.timeframes.get(factor)?.num_milliseconds();
+        let bucket = |candle: &Candle| candle.open_time().timestamp_millis().div_euclid(millis);
+        let last = candles.last()?;
+        let current = bucket(last);
+        let start = candles.iter().position(|c| bucket(c) == current).unwrap_or_default();
+
+        if last.close_time().timestamp_millis() >= (current + 1) * millis {
+            return Some((start..candles.len(), candles.len()));
+        }
+        //? the last candle of the previous bucket is missing, the candles before `start` are complete
+        let previous = bucket(candles.get(start.checked_sub(1)?)?);
+        let first = candles.iter().position(|c| bucket(c) == previous).unwrap_or_default();
+        Some((first..start, start))
+    }
+}
+
+/// Wilder's Average True Range of the candles processed by the backtest.
+///
+/// The first `period` true ranges are averaged, then the value is smoothed by `1 / period`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy)]
+struct AverageTrueRange {
+    period: usize,
+    count: usize,
+    value: f64,
+    prev_close: Option<f64>,
+}
+
+impl AverageTrueRange {
+    fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            count: 0,
+            value: 0.0,
+            prev_close: None,
+        }
+    }
+
+    fn next(&mut self, candle: &Candle) {
+        let range = candle.high() - candle.low();
+        let true_range = match self.prev_close {
+            Some(close) => range
+                .max((candle.high() - close).abs())
+                .max((candle.low() - close).abs()),
+            None => range,
+        };
+        self.count = (self.count + 1).min(self.period);
+        self.value += (true_range - self.value) / self.count as f64;
+        self.prev_close = Some(candle.close());
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.value)
+    }
 }
 
+/// Callback notified of each event during the run.
+#[cfg(feature = "metrics")]
+type Observer = Arc<Mutex<dyn FnMut(&Event) + Send>>;
+
 /// Backtesting engine for trading strategies.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone)]
@@ -72,11 +229,65 @@ pub struct Backtest {
     index: usize,
     wallet: Wallet,
     data: Arc<[Candle]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    frame: OnceLock<Arc<CandleFrame>>,
     #[cfg(feature = "metrics")]
     events: Vec<Event>,
-    orders: VecDeque<Order>,
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    event_sink: Option<Arc<Mutex<dyn EventSink>>>,
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observers: Vec<Observer>,
+    #[cfg(feature = "metrics")]
+    equity_sampling: EquitySampling,
+    #[cfg(feature = "metrics")]
+    unmarked_candles: usize,
+    #[cfg(feature = "metrics")]
+    last_mark: Option<DateTime<Utc>>,
+    orders: OrderBook,
     positions: VecDeque<Position>,
-    market_fees: Option<(f64, f64)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    fee_model: Option<Arc<dyn FeeModel>>,
+    traded_volume: f64,
+    borrow_rates: Option<BorrowRates>,
+    slippage: Option<Slippage>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    depth: Option<MarketDepth>,
+    spread: Option<Spread>,
+    fill_model: FillModel,
+    #[cfg_attr(feature = "serde", serde(default))]
+    gap_fill: GapFill,
+    limit_fill: LimitFill,
+    latency: Latency,
+    warmup: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    min_quantity: f64,
+    position_mode: PositionMode,
+    risk_manager: Option<RiskManager>,
+    candle_index: usize,
+    placed: HashMap<u32, (usize, DateTime<Utc>)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    queue_volumes: HashMap<u32, (usize, f64)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    opened: HashSet<u32>,
+    fee_currency: FeeCurrency,
+    #[cfg_attr(feature = "serde", serde(default))]
+    fee_basis: FeeBasis,
+    exchange_profile: Option<ExchangeProfile>,
+    instrument: Option<Instrument>,
+    calendar: Option<TradingCalendar>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    conversion_rates: Option<ConversionRates>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    provenance: Option<Provenance>,
+    queued: HashSet<u32>,
+    timeframe: Option<TimeDelta>,
+    strict_timeframe: bool,
+    last_open: Option<DateTime<Utc>>,
+    atr: AverageTrueRange,
+    atr_override: Option<f64>,
+    deterministic_ids: bool,
 }
 
 impl std::ops::Deref for Backtest {
@@ -87,6 +298,308 @@ impl std::ops::Deref for Backtest {
     }
 }
 
+/// Builder for creating configured `Backtest` instances.
+///
+/// Only the initial balance is required. Without candle data, the backtest is created with
+/// `Backtest::streaming` to be run with `Backtest::run_from_source`.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candle = CandleBuilder::builder()
+///     .open(100.0)
+///     .high(110.0)
+///     .low(95.0)
+///     .close(105.0)
+///     .volume(1.0)
+///     .open_time(DateTime::default())
+///     .close_time(DateTime::default() + Duration::days(1))
+///     .build()
+///     .unwrap();
+///
+/// let bts = BacktestBuilder::builder()
+///     .data(Arc::from_iter(vec![candle]))
+///     .initial_balance(1000.0)
+///     .fees(0.1, 0.05)
+///     .slippage(Slippage::Percent(0.1))
+///     .fill_model(FillModel::Pessimistic)
+///     .build()
+///     .unwrap();
+/// assert_eq!(bts.balance(), 1000.0);
+/// ```
+#[derive(Default)]
+pub struct BacktestBuilder {
+    data: Option<Arc<[Candle]>>,
+    initial_balance: Option<f64>,
+    base_balance: Option<f64>,
+    fees: Option<(f64, f64)>,
+    fee_model: Option<Arc<dyn FeeModel>>,
+    fee_currency: Option<FeeCurrency>,
+    fee_basis: Option<FeeBasis>,
+    borrow_rates: Option<BorrowRates>,
+    slippage: Option<Slippage>,
+    depth: Option<MarketDepth>,
+    spread: Option<Spread>,
+    fill_model: Option<FillModel>,
+    gap_fill: Option<GapFill>,
+    limit_fill: Option<LimitFill>,
+    latency: Option<Latency>,
+    warmup: Option<usize>,
+    min_quantity: Option<f64>,
+    position_mode: Option<PositionMode>,
+    risk_manager: Option<RiskManager>,
+    exchange_profile: Option<ExchangeProfile>,
+    instrument: Option<Instrument>,
+    calendar: Option<TradingCalendar>,
+    conversion_rates: Option<ConversionRates>,
+    provenance: Option<Provenance>,
+    strict_timeframe: bool,
+    #[cfg(feature = "metrics")]
+    event_sink: Option<Arc<Mutex<dyn EventSink>>>,
+    #[cfg(feature = "metrics")]
+    equity_sampling: Option<EquitySampling>,
+}
+
+impl BacktestBuilder {
+    /// Creates a new `BacktestBuilder`.
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    /// Sets the candle data.
+    pub fn data(mut self, data: Arc<[Candle]>) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Sets the initial wallet balance.
+    pub fn initial_balance(mut self, initial_balance: f64) -> Self {
+        self.initial_balance = Some(initial_balance);
+        self
+    }
+
+    /// Sets the initial balance of the base asset (see `Backtest::with_base_balance`).
+    pub fn base_balance(mut self, base: f64) -> Self {
+        self.base_balance = Some(base);
+        self
+    }
+
+    /// Sets the market and limit fee percentages (e.g., 0.1 for 0.1%).
+    pub fn fees(mut self, market_fee: f64, limit_fee: f64) -> Self {
+        self.fees = Some((market_fee, limit_fee));
+        self
+    }
+
+    /// Sets the commission model, replacing the fee percentages (see `Backtest::with_fee_model`).
+    pub fn fee_model(mut self, fee_model: impl FeeModel + 'static) -> Self {
+        self.fee_model = Some(Arc::new(fee_model));
+        self
+    }
+
+    /// Sets the currency in which the market fees are charged.
+    pub fn fee_currency(mut self, fee_currency: FeeCurrency) -> Self {
+        self.fee_currency = Some(fee_currency);
+        self
+    }
+
+    /// Sets the notional on which the market fees are computed.
+    pub fn fee_basis(mut self, fee_basis: FeeBasis) -> Self {
+        self.fee_basis = Some(fee_basis);
+        self
+    }
+
+    /// Sets the annualized interest rates charged on the open positions.
+    pub fn borrow_rates(mut self, borrow_rates: BorrowRates) -> Self {
+        self.borrow_rates = Some(borrow_rates);
+        self
+    }
+
+    /// Sets the slippage model.
+    pub fn slippage(mut self, slippage: Slippage) -> Self {
+        self.slippage = Some(slippage);
+        self
+    }
+
+    /// Sets the synthetic order book depth of the market orders.
+    pub fn depth(mut self, depth: MarketDepth) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Sets the bid/ask spread.
+    pub fn spread(mut self, spread: Spread) -> Self {
+        self.spread = Some(spread);
+        self
+    }
+
+    /// Sets the intrabar path assumption of the exits.
+    pub fn fill_model(mut self, fill_model: FillModel) -> Self {
+        self.fill_model = Some(fill_model);
+        self
+    }
+
+    /// Sets the exit price of the positions whose exit level is gapped over.
+    pub fn gap_fill(mut self, gap_fill: GapFill) -> Self {
+        self.gap_fill = Some(gap_fill);
+        self
+    }
+
+    /// Sets the fill condition of the limit orders.
+    pub fn limit_fill(mut self, limit_fill: LimitFill) -> Self {
+        self.limit_fill = Some(limit_fill);
+        self
+    }
+
+    /// Sets the delay before the placed orders can be filled.
+    pub fn latency(mut self, latency: Latency) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Sets the number of warm-up candles.
+    pub fn warmup(mut self, candles: usize) -> Self {
+        self.warmup = Some(candles);
+        self
+    }
+
+    /// Sets the minimum quantity of the orders.
+    pub fn min_quantity(mut self, min_quantity: f64) -> Self {
+        self.min_quantity = Some(min_quantity);
+        self
+    }
+
+    /// Sets the position mode.
+    pub fn position_mode(mut self, position_mode: PositionMode) -> Self {
+        self.position_mode = Some(position_mode);
+        self
+    }
+
+    /// Sets the portfolio-level risk limits.
+    pub fn risk_manager(mut self, risk_manager: RiskManager) -> Self {
+        self.risk_manager = Some(risk_manager);
+        self
+    }
+
+    /// Sets the exchange profile (see `Backtest::with_exchange_profile`).
+    pub fn exchange_profile(mut self, profile: ExchangeProfile) -> Self {
+        self.exchange_profile = Some(profile);
+        self
+    }
+
+    /// Sets the traded instrument.
+    pub fn instrument(mut self, instrument: Instrument) -> Self {
+        self.instrument = Some(instrument);
+        self
+    }
+
+    /// Sets the trading calendar.
+    pub fn calendar(mut self, calendar: TradingCalendar) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// Sets the conversion rates of the realized P&L into the account currency.
+    pub fn conversion_rates(mut self, conversion_rates: ConversionRates) -> Self {
+        self.conversion_rates = Some(conversion_rates);
+        self
+    }
+
+    /// Sets the provenance of the candle data.
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Requires the candles to be regularly spaced by the timeframe.
+    pub fn strict_timeframe(mut self) -> Self {
+        self.strict_timeframe = true;
+        self
+    }
+
+    /// Sets the sink receiving the events.
+    #[cfg(feature = "metrics")]
+    pub fn event_sink<S: EventSink + 'static>(mut self, sink: S) -> Self {
+        self.event_sink = Some(Arc::new(Mutex::new(sink)));
+        self
+    }
+
+    /// Sets the sampling of the wallet updates.
+    #[cfg(feature = "metrics")]
+    pub fn equity_sampling(mut self, equity_sampling: EquitySampling) -> Self {
+        self.equity_sampling = Some(equity_sampling);
+        self
+    }
+
+    /// Builds the `Backtest`.
+    ///
+    /// The exchange profile is applied before the fee settings, so the fees set on the builder
+    /// replace the ones of the profile.
+    ///
+    /// ### Returns
+    /// The configured backtest, or an error if the initial balance is missing or a setting is invalid.
+    pub fn build(self) -> Result<Backtest> {
+        let initial_balance = self.initial_balance.ok_or(Error::MissingField("initial balance"))?;
+        let mut backtest = match self.data {
+            Some(data) => Backtest::new(data, initial_balance, None)?,
+            None => Backtest::streaming(initial_balance, None)?,
+        };
+        if let Some(profile) = self.exchange_profile {
+            backtest = backtest.with_exchange_profile(profile);
+        }
+        if let Some((market_fee, limit_fee)) = self.fees {
+            if market_fee <= 0.0 || limit_fee <= 0.0 {
+                return Err(Error::NegZeroFees);
+            }
+            backtest.fee_model = Some(Arc::new(PercentFees::new(market_fee, limit_fee)));
+        }
+        if let Some(fee_model) = self.fee_model {
+            backtest.fee_model = Some(fee_model);
+        }
+        if let Some(fee_currency) = self.fee_currency {
+            backtest.fee_currency = fee_currency;
+        }
+        if let Some(fee_basis) = self.fee_basis {
+            backtest.fee_basis = fee_basis;
+        }
+        if let Some(base) = self.base_balance {
+            backtest = backtest.with_base_balance(base)?;
+        }
+        backtest.borrow_rates = self.borrow_rates.or(backtest.borrow_rates);
+        backtest.slippage = self.slippage.or(backtest.slippage);
+        backtest.depth = self.depth.or(backtest.depth);
+        backtest.spread = self.spread.or(backtest.spread);
+        backtest.fill_model = self.fill_model.unwrap_or(backtest.fill_model);
+        backtest.gap_fill = self.gap_fill.unwrap_or(backtest.gap_fill);
+        if let Some(limit_fill) = self.limit_fill {
+            backtest = backtest.with_limit_fill(limit_fill)?;
+        }
+        backtest.latency = self.latency.unwrap_or(backtest.latency);
+        backtest.warmup = self.warmup.unwrap_or(backtest.warmup);
+        backtest.min_quantity = self.min_quantity.unwrap_or(backtest.min_quantity);
+        backtest.position_mode = self.position_mode.unwrap_or(backtest.position_mode);
+        if let Some(risk_manager) = self.risk_manager {
+            backtest = backtest.with_risk_manager(risk_manager);
+        }
+        backtest.instrument = self.instrument.or(backtest.instrument);
+        backtest.calendar = self.calendar.or(backtest.calendar);
+        backtest.conversion_rates = self.conversion_rates;
+        backtest.provenance = self.provenance;
+        if self.strict_timeframe {
+            backtest = backtest.with_strict_timeframe()?;
+        }
+        #[cfg(feature = "metrics")]
+        {
+            backtest.event_sink = self.event_sink;
+            backtest.equity_sampling = self.equity_sampling.unwrap_or(backtest.equity_sampling);
+        }
+        Ok(backtest)
+    }
+}
+
 impl Backtest {
     /// Creates a new backtest instance.
     ///
@@ -103,6 +616,8 @@ impl Backtest {
     ///   and deducted from the wallet when the position is opened.
     /// - **Order Cancellation**: No fees are charged if an order is cancelled before execution.
     ///
+    /// Other commission schedules are configured with `Backtest::with_fee_model`.
+    ///
     /// ### Returns
     /// The new backtest instance or an error.
     ///
@@ -134,60 +649,88 @@ impl Backtest {
             return Err(Error::CandleDataEmpty);
         }
 
+        let mut backtest = Self::streaming(initial_balance, market_fees)?;
+        backtest.timeframe = crate::data::interval(&data);
+        backtest.data = data;
+        Ok(backtest)
+    }
+
+    /// Creates a new backtest instance without candle data, to be run with `Backtest::run_from_source`.
+    ///
+    /// The streamed candles are not kept, so the metrics have no candles to compare with (e.g., the
+    /// buy-and-hold benchmark) and the charts have no price data.
+    ///
+    /// ### Arguments
+    /// * `initial_balance` - Initial wallet balance.
+    /// * `market_fees` - Market *(market and limit)* fee percentages, as in `Backtest::new`.
+    ///
+    /// ### Returns
+    /// The new backtest instance or an error.
+    pub fn streaming(initial_balance: f64, market_fees: Option<(f64, f64)>) -> Result<Self> {
         if let Some((market_fee, limit_fee)) = market_fees
             && (market_fee <= 0.0 || limit_fee <= 0.0)
         {
             return Err(Error::NegZeroFees);
         }
 
-        let market_fees = market_fees.map(|(mf, lf)| (mf / 100.0, lf / 100.0));
+        let fee_model = market_fees.map(|(mf, lf)| Arc::new(PercentFees::new(mf, lf)) as Arc<dyn FeeModel>);
 
         Ok(Self {
-            data,
+            data: Arc::from([]),
+            frame: OnceLock::new(),
             #[cfg(test)]
             index: 0,
-            market_fees,
+            fee_model,
+            traded_volume: 0.0,
+            borrow_rates: None,
             #[cfg(feature = "metrics")]
             events: Vec::new(),
-            orders: VecDeque::new(),
+            #[cfg(feature = "metrics")]
+            event_sink: None,
+            #[cfg(feature = "metrics")]
+            observers: Vec::new(),
+            #[cfg(feature = "metrics")]
+            equity_sampling: EquitySampling::default(),
+            #[cfg(feature = "metrics")]
+            unmarked_candles: 0,
+            #[cfg(feature = "metrics")]
+            last_mark: None,
+            orders: OrderBook::default(),
             positions: VecDeque::new(),
+            slippage: None,
+            depth: None,
+            spread: None,
+            fill_model: FillModel::default(),
+            gap_fill: GapFill::default(),
+            limit_fill: LimitFill::default(),
+            latency: Latency::default(),
+            warmup: 0,
+            min_quantity: 0.0,
+            position_mode: PositionMode::default(),
+            risk_manager: None,
+            candle_index: 0,
+            placed: HashMap::new(),
+            queue_volumes: HashMap::new(),
+            opened: HashSet::new(),
+            fee_currency: FeeCurrency::default(),
+            fee_basis: FeeBasis::default(),
+            exchange_profile: None,
+            instrument: None,
+            calendar: None,
+            conversion_rates: None,
+            provenance: None,
+            queued: HashSet::new(),
+            timeframe: None,
+            strict_timeframe: false,
+            last_open: None,
+            atr: AverageTrueRange::new(14),
+            atr_override: None,
+            deterministic_ids: false,
             wallet: Wallet::new(initial_balance)?,
         })
     }
 
-    /// Returns the market fees.
-    pub fn market_fees(&self) -> Option<&(f64, f64)> {
-        self.market_fees.as_ref()
-    }
-
-    /// Returns an iterator over the data.
-    pub fn candles(&self) -> std::slice::Iter<'_, Candle> {
-        self.data.iter()
-    }
-
-    /// Returns an iterator over the pending orders.
-    pub fn orders(&self) -> Iter<'_, Order> {
-        self.orders.iter()
-    }
-
-    /// Returns an iterator over the open positions.
-    pub fn positions(&self) -> Iter<'_, Position> {
-        self.positions.iter()
-    }
-
-    /// Returns an iterator over the recorded events.
-    #[cfg(feature = "metrics")]
-    pub fn events(&self) -> std::slice::Iter<'_, Event> {
-        self.events.iter()
-    }
-
-    /// Places a new order.
-    ///
-    /// ### Arguments
-    /// * `order` - The order to place.
-    ///
-    /// ### Returns
-    /// Ok if successful, or an error.
+    /// Sets the slippage model applied to the entry price of the filled orders.
     ///
     /// ### Example
     /// ```rust
@@ -202,35 +745,27 @@ impl Backtest {
     ///     .low(95.0)
     ///     .close(105.0)
     ///     .volume(1.0)
-    ///     .bid(0.5)
     ///     .open_time(DateTime::default())
     ///     .close_time(DateTime::default() + Duration::days(1))
     ///     .build()
     ///     .unwrap();
     ///
-    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
-    /// let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
-    /// bts.place_order(&candle, order).unwrap();
+    /// let bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_slippage(Slippage::VolumeParticipation(0.1));
     /// ```
-    pub fn place_order(&mut self, _candle: &Candle, order: Order) -> Result<()> {
-        self.wallet.lock(order.cost()?)?;
-        self.orders.push_back(order);
-        #[cfg(feature = "metrics")]
-        {
-            let open_time = _candle.open_time();
-            self.events.push(Event::from((open_time, &self.wallet)));
-            self.events.push(Event::AddOrder(open_time, order));
-        }
-        Ok(())
+    pub fn with_slippage(mut self, slippage: Slippage) -> Self {
+        self.slippage = Some(slippage);
+        self
     }
 
-    /// Deletes a pending order.
-    ///
-    /// ### Arguments
-    /// * `order` - Reference to the order to delete.
-    ///
-    /// ### Returns
-    /// Ok if successful, or an error.
+    /// Returns the slippage model.
+    pub fn slippage(&self) -> Option<&Slippage> {
+        self.slippage.as_ref()
+    }
+
+    /// Sets the synthetic order book depth: the market orders walk the price levels built from the
+    /// candle volume and are filled at their average price, before the slippage is applied.
     ///
     /// ### Example
     /// ```rust
@@ -245,66 +780,26 @@ impl Backtest {
     ///     .low(95.0)
     ///     .close(105.0)
     ///     .volume(1.0)
-    ///     .bid(0.5)
     ///     .open_time(DateTime::default())
     ///     .close_time(DateTime::default() + Duration::days(1))
     ///     .build()
     ///     .unwrap();
     ///
-    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
-    /// let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
-    /// bts.place_order(&candle, order).unwrap();
-    /// // if you call this function, always put `true` to delete
-    /// bts.delete_order(&candle, &order, true).unwrap();
+    /// let bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_depth(MarketDepth::new(20, 0.1));
     /// ```
-    pub fn delete_order(&mut self, _candle: &Candle, order: &Order, force_remove: bool) -> Result<()> {
-        if force_remove {
-            let order_idx = self
-                .orders
-                .iter()
-                .position(|o| o == order)
-                .ok_or(Error::OrderNotFound)?;
-            self.orders.remove(order_idx).ok_or(Error::RemoveOrder)?;
-        }
-        self.wallet.unlock(order.cost()?)?;
-        #[cfg(feature = "metrics")]
-        {
-            let open_time = _candle.open_time();
-            self.events.push(Event::DelOrder(open_time, *order));
-            self.events.push(Event::from((open_time, &self.wallet)));
-        }
-        Ok(())
+    pub fn with_depth(mut self, depth: MarketDepth) -> Self {
+        self.depth = Some(depth);
+        self
     }
 
-    /// Opens a new position.
-    fn open_position(&mut self, _candle: &Candle, position: Position) -> Result<()> {
-        self.wallet.sub(position.cost()?)?;
-        if let Some((market_fee, limit_fee)) = self.market_fees {
-            if position.is_market_type() {
-                self.wallet.sub_fees(position.cost()? * market_fee)?;
-            } else {
-                self.wallet.sub_fees(position.cost()? * limit_fee)?;
-            };
-        }
-        self.positions.push_back(position);
-        #[cfg(feature = "metrics")]
-        {
-            let open_time = _candle.open_time();
-            self.events.push(Event::from((open_time, &self.wallet)));
-            self.events.push(Event::AddPosition(open_time, position));
-        }
-        Ok(())
+    /// Returns the synthetic order book depth.
+    pub fn depth(&self) -> Option<&MarketDepth> {
+        self.depth.as_ref()
     }
 
-    /// Closes an existing position.
-    ///
-    /// ### Arguments
-    /// * `position` - Reference to the position to close.
-    /// * `exit_price` - The price at which to close the position.
-    /// * `force_remove` - If true, removes the position without checking conditions.
-    ///
-    /// ### Returns
-    /// The profit/loss from closing the position, or an error.
+    /// Sets the bid/ask spread: the buy orders execute at the ask and the sell orders at the bid.
     ///
     /// ### Example
     /// ```rust
@@ -319,74 +814,134 @@ impl Backtest {
     ///     .low(95.0)
     ///     .close(105.0)
     ///     .volume(1.0)
-    ///     .bid(0.5)
     ///     .open_time(DateTime::default())
     ///     .close_time(DateTime::default() + Duration::days(1))
     ///     .build()
     ///     .unwrap();
     ///
-    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
-    /// bts.run(|_bts, candle| {
-    ///   let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
-    ///   _bts.place_order(&candle, order).unwrap();
-    ///   
-    ///   let last_position = _bts.positions().last().cloned();
-    ///   if let Some(position) = last_position {
-    ///     // if you call this function, always put `true` to delete
-    ///     _bts.close_position(candle, &position, 110.0, true).unwrap();
-    ///   }
-    ///
-    ///   Ok(())
-    /// }).unwrap();
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_spread(Spread::Fixed(0.2));
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Buy).into()))
+    ///     .unwrap();
+    /// assert_eq!(bts.positions().next().unwrap().entry_price().unwrap(), 100.1);
     /// ```
-    pub fn close_position(
-        &mut self,
-        _candle: &Candle,
-        position: &Position,
-        exit_price: f64,
-        force_remove: bool,
-    ) -> Result<f64> {
-        if exit_price <= 0.0 || !exit_price.is_finite() {
-            return Err(Error::ExitPrice(exit_price));
+    pub fn with_spread(mut self, spread: Spread) -> Self {
+        self.spread = Some(spread);
+        self
+    }
+
+    /// Returns the spread model.
+    pub fn spread(&self) -> Option<&Spread> {
+        self.spread.as_ref()
+    }
+
+    /// Returns the open, low and high quotes of the candle for an order on `side`: the ask for a buy
+    /// and the bid for a sell, or the candle prices without spread.
+    fn quotes(&self, candle: &Candle, side: &OrderSide) -> (f64, f64, f64) {
+        match self.spread {
+            Some(spread) => (
+                spread.quote(candle.open(), candle, side),
+                spread.quote(candle.low(), candle, side),
+                spread.quote(candle.high(), candle, side),
+            ),
+            None => (candle.open(), candle.low(), candle.high()),
         }
-        if force_remove {
-            let pos_idx = self
-                .positions
-                .iter()
-                .position(|p| p == position)
-                .ok_or(Error::PositionNotFound)?;
-            self.positions.remove(pos_idx).ok_or(Error::RemovePosition)?;
-        }
-        // Calculate profit/loss and update wallet
-        let pnl = position.estimate_pnl(exit_price)?;
-        let total_amount = pnl + position.cost()?;
-        self.wallet.add(total_amount)?;
-        self.wallet.sub_pnl(total_amount);
-        if let Some((market_fee, limit_fee)) = self.market_fees {
-            if position.is_market_type() {
-                self.wallet.sub_fees(position.cost()? * market_fee)?;
-            } else {
-                self.wallet.sub_fees(position.cost()? * limit_fee)?;
-            };
-        }
-        #[cfg(feature = "metrics")]
-        {
-            let mut _position = *position;
-            _position.set_exit_price(exit_price)?;
-            let open_time = _candle.open_time();
-            self.events.push(Event::from((open_time, &self.wallet)));
-            self.events.push(Event::DelPosition(open_time, _position));
-        }
-        Ok(pnl)
     }
 
-    /// Closes all open positions at the given exit price.
+    /// Sets the fill model used when a take profit and a stop loss are both reached within the same candle.
     ///
-    /// ### Arguments
-    /// * `exit_price` - The price at which to close all positions.
+    /// Defaults to `FillModel::Optimistic`.
+    pub fn with_fill_model(mut self, fill_model: FillModel) -> Self {
+        self.fill_model = fill_model;
+        self
+    }
+
+    /// Returns the fill model.
+    pub fn fill_model(&self) -> &FillModel {
+        &self.fill_model
+    }
+
+    /// Sets the exit price of the positions whose take profit or stop is gapped over by the open of
+    /// a candle.
+    ///
+    /// Defaults to `GapFill::Open`: the position is closed at the open. The positions opened during
+    /// the candle are not concerned, as they did not exist at its open.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candles = [(100.0, 100.0), (90.0, 92.0)]
+    ///     .into_iter()
+    ///     .enumerate()
+    ///     .map(|(i, (open, close))| {
+    ///         let open_time = DateTime::default() + Duration::days(i as i64);
+    ///         CandleBuilder::builder()
+    ///             .open(open)
+    ///             .high(open.max(close) + 1.0)
+    ///             .low(open.min(close) - 1.0)
+    ///             .close(close)
+    ///             .volume(1.0)
+    ///             .open_time(open_time)
+    ///             .close_time(open_time + Duration::days(1))
+    ///             .build()
+    ///             .unwrap()
+    ///     })
+    ///     .collect::<Arc<[_]>>();
+    ///
+    /// let strategy = |bts: &mut Backtest, candle: &Candle| {
+    ///     if bts.orders().len() == 0 && bts.positions().len() == 0 && candle.open() == 100.0 {
+    ///         let exit = OrderType::TakeProfitAndStopLoss(0.0, 95.0);
+    ///         bts.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())?;
+    ///     }
+    ///     Ok(())
+    /// };
+    ///
+    /// // the second candle opens at 90, below the stop at 95
+    /// let mut bts = Backtest::new(Arc::clone(&candles), 1000.0, None).unwrap();
+    /// bts.run(strategy).unwrap();
+    /// assert_eq!(bts.balance(), 990.0);
+    ///
+    /// let mut bts = Backtest::new(candles, 1000.0, None).unwrap().with_gap_fill(GapFill::GuaranteedStop);
+    /// bts.run(strategy).unwrap();
+    /// assert_eq!(bts.balance(), 995.0);
+    /// ```
+    pub fn with_gap_fill(mut self, gap_fill: GapFill) -> Self {
+        self.gap_fill = gap_fill;
+        self
+    }
+
+    /// Returns the exit price policy of the gapped exits.
+    pub fn gap_fill(&self) -> GapFill {
+        self.gap_fill
+    }
+
+    /// Sets the condition for the limit orders to be filled when the price reaches them.
+    ///
+    /// Defaults to `LimitFill::Touch`.
     ///
     /// ### Returns
-    /// Ok if successful, or an error.
+    /// The backtest, or an error if the share of volume of `LimitFill::Queue` is not in (0, 1].
+    pub fn with_limit_fill(mut self, limit_fill: LimitFill) -> Result<Self> {
+        if let LimitFill::Queue(share) = limit_fill
+            && !(share > 0.0 && share <= 1.0)
+        {
+            return Err(Error::InvalidQueueShare(share));
+        }
+        self.limit_fill = limit_fill;
+        Ok(self)
+    }
+
+    /// Returns the limit fill policy.
+    pub fn limit_fill(&self) -> &LimitFill {
+        &self.limit_fill
+    }
+
+    /// Sets the delay before the placed orders can be filled.
     ///
     /// ### Example
     /// ```rust
@@ -401,143 +956,149 @@ impl Backtest {
     ///     .low(95.0)
     ///     .close(105.0)
     ///     .volume(1.0)
-    ///     .bid(0.5)
     ///     .open_time(DateTime::default())
     ///     .close_time(DateTime::default() + Duration::days(1))
     ///     .build()
     ///     .unwrap();
     ///
-    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
-    /// bts.close_all_positions(&candle, 110.0).unwrap();
+    /// // the order placed on the close of the first candle is filled at the open of the second one
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 2]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_latency(Latency::Candles(1));
+    /// bts.run(|bts, candle| {
+    ///     if bts.orders().count() == 0 && bts.positions().count() == 0 {
+    ///         bts.place_order(candle, (OrderType::Market(candle.close()), 1.0, OrderSide::Buy).into())?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(bts.positions().next().unwrap().entry_price().unwrap(), 100.0);
     /// ```
-    pub fn close_all_positions(&mut self, candle: &Candle, exit_price: f64) -> Result<()> {
-        while let Some(position) = self.positions.pop_front() {
-            self.close_position(candle, &position, exit_price, false)?;
-        }
-        Ok(())
+    pub fn with_latency(mut self, latency: Latency) -> Self {
+        self.latency = latency;
+        self
     }
 
-    /// Executes pending orders based on current candle data.
-    fn execute_orders(&mut self, candle: &Candle) -> Result<()> {
-        let mut orders = VecDeque::with_capacity(self.orders.len());
-        while let Some(order) = self.orders.pop_front() {
-            let price = order.entry_price()?;
-            if price >= candle.low() && price <= candle.high() {
-                self.open_position(candle, Position::from(order))?;
-            } else {
-                //? if order is market type and does not between `high` and `low`, delete
-                if order.is_market_type() {
-                    self.delete_order(candle, &order, false)?;
-                } else {
-                    orders.push_back(order);
-                }
-            }
-        }
-        self.orders.append(&mut orders);
-        Ok(())
+    /// Returns the delay before the placed orders can be filled.
+    pub fn latency(&self) -> &Latency {
+        &self.latency
     }
 
-    /// Executes position management (take-profit, stop-loss, trailing stop).
-    fn execute_positions(&mut self, candle: &Candle) -> Result<()> {
-        let mut positions = VecDeque::with_capacity(self.positions.len());
-
-        while let Some(mut position) = self.positions.pop_front() {
-            let should_close = match position.exit_rule() {
-                Some(OrderType::TakeProfitAndStopLoss(take_profit, stop_loss)) => {
-                    if *take_profit < 0.0 || *stop_loss < 0.0 {
-                        return Err(Error::NegTakeProfitAndStopLoss);
-                    }
-
-                    match position.side() {
-                        PositionSide::Long => {
-                            if *take_profit > 0.0 && take_profit <= &candle.high() {
-                                Some(*take_profit)
-                            } else if *stop_loss > 0.0 && stop_loss >= &candle.low() {
-                                Some(*stop_loss)
-                            } else {
-                                None
-                            }
-                        }
-                        PositionSide::Short => {
-                            if *take_profit > 0.0 && take_profit >= &candle.low() {
-                                Some(*take_profit)
-                            } else if *stop_loss > 0.0 && stop_loss <= &candle.high() {
-                                Some(*stop_loss)
-                            } else {
-                                None
-                            }
-                        }
-                    }
-                }
-                Some(OrderType::TrailingStop(price, percent)) => {
-                    if *price <= 0.0 || *percent <= 0.0 {
-                        return Err(Error::NegZeroTrailingStop);
-                    }
+    /// Sets the number of warm-up candles.
+    ///
+    /// The first `candles` candles are still given to the strategy so it can prime its indicators, but
+    /// the orders placed during the warm-up are rejected with `Error::WarmUp`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 3]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_warmup(2);
+    /// let mut seen = 0;
+    /// bts.run(|bts, candle| {
+    ///     seen += 1;
+    ///     if !bts.is_warming_up() {
+    ///         bts.place_order(candle, (OrderType::Market(candle.close()), 1.0, OrderSide::Buy).into())?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(seen, 3);
+    /// assert_eq!(bts.positions().count(), 1);
+    /// ```
+    pub fn with_warmup(mut self, candles: usize) -> Self {
+        self.warmup = candles;
+        self
+    }
 
-                    match position.side() {
-                        PositionSide::Long => {
-                            let execute_price = price.subpercent(*percent);
-                            if execute_price >= candle.low() {
-                                Some(execute_price)
-                            } else {
-                                if &candle.high() > price {
-                                    position.set_trailingstop(candle.high());
-                                }
-                                None
-                            }
-                        }
-                        PositionSide::Short => {
-                            let execute_price = price.addpercent(*percent);
-                            if execute_price <= candle.high() {
-                                Some(execute_price)
-                            } else {
-                                if &candle.low() < price {
-                                    position.set_trailingstop(candle.low());
-                                }
-                                None
-                            }
-                        }
-                    }
-                }
-                None => None,
-                _ => {
-                    return Err(Error::MismatchedOrderType);
-                }
-            };
+    /// Returns the number of warm-up candles.
+    pub fn warmup(&self) -> usize {
+        self.warmup
+    }
 
-            match should_close {
-                Some(exit_price) => {
-                    self.close_position(candle, &position, exit_price, false)?;
-                }
-                None => positions.push_back(position),
-            }
-        }
+    /// Sets the minimum quantity of the orders: a smaller order is rejected when it is placed.
+    pub fn with_min_quantity(mut self, min_quantity: f64) -> Self {
+        self.min_quantity = min_quantity;
+        self
+    }
 
-        let mut total_unrealized_pnl = 0.0;
-        for position in &positions {
-            // calculate unrealized P&L for this position
-            let current_price = candle.close();
-            let pnl = position.estimate_pnl(current_price)?;
-            total_unrealized_pnl += pnl;
-        }
+    /// Returns the minimum quantity of the orders (0.0 for none).
+    pub fn min_quantity(&self) -> f64 {
+        self.min_quantity
+    }
 
-        self.positions.append(&mut positions);
-        self.wallet.set_unrealized_pnl(total_unrealized_pnl);
-        Ok(())
+    /// Returns true while the current candle is in the warm-up period.
+    pub fn is_warming_up(&self) -> bool {
+        self.candle_index < self.warmup
     }
 
-    /// Runs the backtest, executing the provided function for each candle.
+    /// Sets how the fills are turned into positions (hedging by default).
     ///
-    /// ### Arguments
-    /// * `strategy` - A closure that takes the backtest and current candle.
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
     ///
-    /// ### Returns
-    /// Ok if successful, or an error.
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // the sell of 1.5 closes the long of 1 and opens a short of 0.5
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 2]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_position_mode(PositionMode::Netting);
+    /// bts.run(|bts, candle| {
+    ///     let side = if bts.positions().count() == 0 { OrderSide::Buy } else { OrderSide::Sell };
+    ///     let quantity = if side == OrderSide::Buy { 1.0 } else { 1.5 };
+    ///     bts.place_order(candle, (OrderType::Market(candle.close()), quantity, side).into())
+    /// })
+    /// .unwrap();
+    /// let position = bts.positions().next().unwrap();
+    /// assert_eq!(position.side(), &PositionSide::Short);
+    /// assert_eq!(position.quantity(), 0.5);
+    /// ```
+    pub fn with_position_mode(mut self, position_mode: PositionMode) -> Self {
+        self.position_mode = position_mode;
+        self
+    }
+
+    /// Returns how the fills are turned into positions.
+    pub fn position_mode(&self) -> &PositionMode {
+        &self.position_mode
+    }
+
+    /// Sets the risk manager checking the portfolio-level limits before an order is placed.
     ///
     /// ### Example
     /// ```rust
     /// use std::sync::Arc;
     ///
+    /// use bts_rs::errors::Error;
     /// use bts_rs::prelude::*;
     /// use chrono::{DateTime, Duration};
     ///
@@ -547,42 +1108,167 @@ impl Backtest {
     ///     .low(95.0)
     ///     .close(105.0)
     ///     .volume(1.0)
-    ///     .bid(0.5)
     ///     .open_time(DateTime::default())
     ///     .close_time(DateTime::default() + Duration::days(1))
     ///     .build()
     ///     .unwrap();
     ///
-    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
-    /// bts.run(|_bts, candle| {
-    ///   let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
-    ///   _bts.place_order(&candle, order)
-    /// }).unwrap();
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_risk_manager(RiskManager::default().with_max_exposure(50.0));
+    /// // 6 × 100 is 60% of the equity
+    /// let order = Order::from((OrderType::Limit(100.0), 6.0, OrderSide::Buy));
+    /// assert!(matches!(bts.place_order(&candle, order), Err(Error::RiskLimit(_))));
     /// ```
-    pub fn run<S>(&mut self, mut strategy: S) -> Result<()>
-    where
-        S: FnMut(&mut Self, &Candle) -> Result<()>,
-    {
-        let candles = Arc::clone(&self.data);
-        for candle in candles.iter() {
-            strategy(self, candle)?;
-            self.execute_orders(candle)?;
-            self.execute_positions(candle)?;
-        }
-        Ok(())
+    pub fn with_risk_manager(mut self, mut risk_manager: RiskManager) -> Self {
+        risk_manager.reset(self.equity());
+        self.risk_manager = Some(risk_manager);
+        self
     }
 
-    /// Runs the backtest with aggregation, executing the provided function for each candle
-    /// and its aggregated versions.
+    /// Returns the risk manager, if any.
+    pub fn risk_manager(&self) -> Option<&RiskManager> {
+        self.risk_manager.as_ref()
+    }
+
+    /// Places the order moving the position of a symbol to its target weight of the equity.
+    ///
+    /// The held quantity is the net quantity of the open positions (negative when short), valued
+    /// with the equity at the close of the candle. The backtest must be in `PositionMode::Netting`, so a sell
+    /// reduces the long positions instead of opening a short one.
     ///
     /// ### Arguments
-    /// * `aggregator` - An aggregator that defines how to group candles (e.g., by timeframe).
-    /// * `strategy` - A closure that takes the backtest and a vector of candle references.
+    /// * `candle` - The current candle.
+    /// * `rebalancer` - The target weights, tolerance band and minimum trade value.
+    /// * `symbol` - The symbol traded by the backtest.
     ///
-    /// The vector contains the current candle followed by any aggregated candles.
+    /// ### Returns
+    /// The placed order, `None` if the position is within the tolerance band or the trade is below the
+    /// minimum, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candles = [100.0, 200.0, 200.0].map(|price| {
+    ///     CandleBuilder::builder()
+    ///         .open(price)
+    ///         .high(price)
+    ///         .low(price)
+    ///         .close(price)
+    ///         .volume(1.0)
+    ///         .open_time(DateTime::default())
+    ///         .close_time(DateTime::default() + Duration::days(1))
+    ///         .build()
+    ///         .unwrap()
+    /// });
+    ///
+    /// let rebalancer = Rebalancer::new(&[("BTC", 0.5)]).unwrap().with_tolerance(0.05);
+    /// let mut bts = Backtest::new(Arc::from_iter(candles), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_position_mode(PositionMode::Netting);
+    /// bts.run(|bts, candle| {
+    ///     bts.rebalance(candle, &rebalancer, "BTC")?;
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// // 5 BTC bought at 100, then 1000 of 1500 in BTC, so 1.25 BTC sold at 200
+    /// assert_eq!(bts.positions().next().unwrap().quantity(), 3.75);
+    /// ```
+    pub fn rebalance(&mut self, candle: &Candle, rebalancer: &Rebalancer, symbol: &str) -> Result<Option<Order>> {
+        if self.position_mode != PositionMode::Netting {
+            return Err(Error::NettingRequired);
+        }
+        let price = candle.close();
+        let equity = self.marked_equity(Some(price));
+        let quantity = self
+            .positions
+            .iter()
+            .map(|position| match position.side() {
+                PositionSide::Long => position.quantity(),
+                PositionSide::Short => -position.quantity(),
+            })
+            .sum::<f64>();
+        let Some(order) = rebalancer.order(symbol, equity, quantity, price) else {
+            return Ok(None);
+        };
+        self.place_order(candle, order)?;
+        Ok(Some(order))
+    }
+
+    /// Returns the equity: the balance plus the value of the open positions at the last close.
+    ///
+    /// Unlike `free_balance`, it accounts for the capital held in the open positions and their
+    /// unrealized P&L, which makes it the base for sizing positions on the whole account.
+    pub fn equity(&self) -> f64 {
+        self.marked_equity(None)
+    }
+
+    /// Returns the equity with the open positions marked at `price`, or at the last close without price.
+    fn marked_equity(&self, price: Option<f64>) -> f64 {
+        let value = self.positions.iter().map(|p| p.cost().unwrap_or_default()).sum::<f64>();
+        let pnl = match price {
+            Some(price) => self
+                .positions
+                .iter()
+                .map(|p| p.estimate_pnl(price).unwrap_or_default())
+                .sum(),
+            None => self.wallet.unrealized_pnl(),
+        };
+        self.wallet.balance() + value + pnl
+    }
+
+    /// Checks an order against the limits of the risk manager.
+    fn check_risk(&mut self, candle: &Candle, order: &Order) -> Result<()> {
+        self.check_risk_with(candle, order, true)
+    }
+
+    /// Checks the limits of the risk manager, counting the open positions only if the order opens one.
+    fn check_risk_with(&mut self, candle: &Candle, order: &Order, opens: bool) -> Result<()> {
+        if self.risk_manager.is_none() {
+            return Ok(());
+        }
+        let open = if opens {
+            self.positions.len() + self.orders.iter().len()
+        } else {
+            0
+        };
+        let notional = self
+            .positions
+            .iter()
+            .map(|p| p.order())
+            .chain(self.orders.iter())
+            .chain([order])
+            .map(|o| o.cost().unwrap_or_default())
+            .sum::<f64>();
+        let equity = self.equity();
+        let Some(risk_manager) = self.risk_manager.as_mut() else {
+            return Ok(());
+        };
+        let Err(limit) = risk_manager.check(candle.open_time(), open, notional, equity) else {
+            return Ok(());
+        };
+        #[cfg(feature = "metrics")]
+        self.record(Event::RiskRejected(candle.open_time(), *order, limit))?;
+        Err(Error::RiskLimit(limit))
+    }
+
+    /// Sets the initial inventory of the base asset (e.g., BTC for a BTC/USDT backtest).
     ///
     /// ### Returns
-    /// Ok if successful, or an error.
+    /// The backtest, or an error if the amount is negative.
+    pub fn with_base_balance(mut self, base: f64) -> Result<Self> {
+        self.wallet = self.wallet.with_base(base)?;
+        Ok(self)
+    }
+
+    /// Sets the currency in which the market fees are charged.
+    ///
+    /// With `FeeCurrency::Received`, the opening fee of a long position is taken from the bought
+    /// base asset, so the position quantity is reduced by the fee.
     ///
     /// ### Example
     /// ```rust
@@ -597,158 +1283,2364 @@ impl Backtest {
     ///     .low(95.0)
     ///     .close(105.0)
     ///     .volume(1.0)
-    ///     .bid(0.5)
     ///     .open_time(DateTime::default())
     ///     .close_time(DateTime::default() + Duration::days(1))
     ///     .build()
     ///     .unwrap();
     ///
-    /// struct Aggregator;
-    /// impl Aggregation for Aggregator {
-    ///   fn factors(&self) -> &[usize] {
-    ///     // return (1) the normal candle
-    ///     &[1]
-    ///   }
-    /// }
-    ///
-    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
-    /// bts.run_with_aggregator(&Aggregator, |_bts, candles| {
-    ///   let _candle = candles.last().unwrap();
-    ///   Ok(())
-    /// }).unwrap();
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, Some((1.0, 1.0)))
+    ///     .unwrap()
+    ///     .with_fee_currency(FeeCurrency::Received);
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Buy).into()))
+    ///     .unwrap();
+    /// assert_eq!(bts.base_balance(), 0.99);
     /// ```
-    pub fn run_with_aggregator<A, S>(&mut self, aggregator: &A, mut strategy: S) -> Result<()>
-    where
-        A: Aggregation,
-        S: FnMut(&mut Self, Vec<&Candle>) -> Result<()>,
-    {
-        use std::collections::BTreeMap;
+    pub fn with_fee_currency(mut self, fee_currency: FeeCurrency) -> Self {
+        self.fee_currency = fee_currency;
+        self
+    }
 
-        let factors = aggregator.factors();
-        if factors.is_empty() {
-            return Err(Error::InvalidFactor);
-        }
+    /// Returns the currency in which the market fees are charged.
+    pub fn fee_currency(&self) -> &FeeCurrency {
+        &self.fee_currency
+    }
 
-        let mut current_candles = BTreeMap::new();
-        let mut aggregated_candles_map = BTreeMap::new();
+    /// Sets the notional on which the market fees are computed (see [`FeeBasis`]).
+    ///
+    /// By default, the closing fee is computed on the exit notional, so a winning position pays
+    /// more fees at the close than at the opening.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, Some((1.0, 1.0)))
+    ///     .unwrap()
+    ///     .with_fee_basis(FeeBasis::Entry);
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Buy).into()))
+    ///     .unwrap();
+    /// bts.close_all_positions(&candle, 110.0).unwrap();
+    /// // 1% of 100 at the opening and at the close
+    /// assert_eq!(bts.fees_paid(), 2.0);
+    /// ```
+    pub fn with_fee_basis(mut self, fee_basis: FeeBasis) -> Self {
+        self.fee_basis = fee_basis;
+        self
+    }
 
-        // Initialize the map with empty queues for each factor
-        for &factor in factors {
-            current_candles.insert(factor, VecDeque::with_capacity(factor));
-            aggregated_candles_map.insert(factor, VecDeque::with_capacity(1));
+    /// Returns the notional on which the market fees are computed.
+    pub fn fee_basis(&self) -> FeeBasis {
+        self.fee_basis
+    }
+
+    /// Applies the configuration preset of an exchange.
+    ///
+    /// The market fees and the fee currency are replaced by the ones of the profile, and its tick/lot
+    /// rules and minimum notional are enforced by the instrument of the backtest (see
+    /// `Backtest::with_instrument`). A profile with a session installs it as the trading calendar
+    /// (see `Backtest::with_calendar`), and a profile with a funding interval charges the funding
+    /// rate on the notional of the open positions at each payment: the long positions pay it and
+    /// the short ones receive it.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_exchange_profile(ExchangeProfile::binance_usdm_futures());
+    /// let fee = bts.fee_model().unwrap().fee(&FeeLeg::new(100.0, 1.0, true, 0.0));
+    /// assert_eq!(fee, 0.05);
+    /// ```
+    pub fn with_exchange_profile(mut self, profile: ExchangeProfile) -> Self {
+        self.fee_model = Some(Arc::new(PercentFees::new(profile.taker_fee(), profile.maker_fee())));
+        self.fee_currency = *profile.fee_currency();
+        self.instrument = Some(profile.instrument());
+        if let Some(calendar) = profile.calendar() {
+            self.calendar = Some(calendar);
         }
+        self.exchange_profile = Some(profile);
+        self
+    }
 
-        let candles = Arc::clone(&self.data);
-        for candle in candles.iter() {
-            for (_, deque) in current_candles.iter_mut() {
-                deque.push_back(candle);
-            }
+    /// Returns the exchange profile, if any.
+    pub fn exchange_profile(&self) -> Option<&ExchangeProfile> {
+        self.exchange_profile.as_ref()
+    }
 
-            for (factor, agg) in aggregated_candles_map.iter_mut() {
-                let deque = current_candles.get_mut(factor).ok_or(Error::CandleDataEmpty)?;
-                let contiguous_candles = deque.make_contiguous();
-                if aggregator.should_aggregate(*factor, contiguous_candles) {
-                    let candle = aggregator.aggregate(contiguous_candles)?;
-                    agg.pop_front();
-                    deque.pop_front();
-                    agg.push_back(candle);
-                }
-            }
+    /// Attaches the metadata of the traded instrument.
+    ///
+    /// The placed orders are rounded to its tick and lot sizes and take its contract multiplier, which
+    /// scales their cost, the P&L and the fees of their positions. The orders whose quantity is below
+    /// the lot size or whose value is below its minimum notional are rejected.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let instrument = Instrument::new("MINI").with_tick_size(0.5).with_multiplier(10.0);
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 10_000.0, None)
+    ///     .unwrap()
+    ///     .with_instrument(instrument);
+    /// let order = Order::from((OrderType::Limit(99.8), 1.0, OrderSide::Buy));
+    /// bts.place_order(&candle, order).unwrap();
+    /// // 1 contract at 100.0, worth 10 times the price
+    /// assert_eq!(bts.orders().next().unwrap().cost().unwrap(), 1000.0);
+    /// ```
+    pub fn with_instrument(mut self, instrument: Instrument) -> Self {
+        self.instrument = Some(instrument);
+        self
+    }
 
-            let agg_candles = aggregated_candles_map.values().flatten().collect();
-            strategy(self, agg_candles)?;
-            self.execute_orders(candle)?;
-            self.execute_positions(candle)?;
-        }
+    /// Returns the instrument, if any.
+    pub fn instrument(&self) -> Option<&Instrument> {
+        self.instrument.as_ref()
+    }
 
-        Ok(())
+    /// Attaches a trading calendar enforced by the engine.
+    ///
+    /// While the session is closed, the pending orders are not filled and the exit rules of the
+    /// positions are not triggered. The orders placed outside the session are queued to the next open
+    /// or rejected, according to `TradingCalendar::out_of_session`, and the positions are closed at
+    /// the end of each session if `TradingCalendar::flatten_at_close` is set.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// // Saturday 1970-01-03
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default() + Duration::days(2))
+    ///     .close_time(DateTime::default() + Duration::days(3))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let calendar = TradingCalendar::new_york().with_out_of_session(OutOfSession::Reject);
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_calendar(calendar);
+    /// let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Buy));
+    /// assert!(bts.place_order(&candle, order).is_err());
+    /// ```
+    pub fn with_calendar(mut self, calendar: TradingCalendar) -> Self {
+        self.calendar = Some(calendar);
+        self
     }
 
-    /// Resets the backtest to its initial state.
-    pub fn reset(&mut self) {
-        #[cfg(test)]
-        {
-            self.index = 0;
-        }
-        #[cfg(feature = "metrics")]
-        {
-            self.events = Vec::new();
+    /// Returns the trading calendar, if any.
+    pub fn calendar(&self) -> Option<&TradingCalendar> {
+        self.calendar.as_ref()
+    }
+
+    /// Converts the realized P&L into an account currency other than the quote currency.
+    ///
+    /// The quote currency is the one of the instrument (see `Instrument::with_quote_currency`). The
+    /// net P&L of each closed position is converted at the rate known when it is closed and
+    /// accumulated in `Wallet::account_pnl`; the balance, the costs and the metrics stay in the quote
+    /// currency.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(150.0)
+    ///     .high(155.0)
+    ///     .low(145.0)
+    ///     .close(152.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // an account in USD trading an instrument quoted in JPY
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 100_000.0, None)
+    ///     .unwrap()
+    ///     .with_instrument(Instrument::new("NKD").with_quote_currency("JPY"))
+    ///     .with_conversion_rates(ConversionRates::new("USD").with_rate("USD", "JPY", 100.0));
+    /// bts.run(|bts, candle| {
+    ///     let order = Order::from((OrderType::Market(150.0), OrderType::TakeProfitAndStopLoss(154.0, 0.0), 10.0, OrderSide::Buy));
+    ///     bts.place_order(candle, order)
+    /// })
+    /// .unwrap();
+    /// // 40 JPY of profit
+    /// assert_eq!(bts.account_pnl(), 0.4);
+    /// ```
+    pub fn with_conversion_rates(mut self, conversion_rates: ConversionRates) -> Self {
+        self.conversion_rates = Some(conversion_rates);
+        self
+    }
+
+    /// Returns the conversion rates into the account currency, if any.
+    pub fn conversion_rates(&self) -> Option<&ConversionRates> {
+        self.conversion_rates.as_ref()
+    }
+
+    /// Records the provenance of the candle data (source, symbol and timeframe).
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Returns the provenance of the candle data, if any.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Returns the fingerprint of the candle data (see `data::fingerprint`).
+    ///
+    /// Two backtests with the same fingerprint were run on identical candles.
+    pub fn fingerprint(&self) -> u64 {
+        crate::data::fingerprint(&self.data)
+    }
+
+    /// Converts an amount of the quote currency into the account currency at a date.
+    fn to_account_currency(&self, amount: f64, datetime: DateTime<Utc>) -> Result<f64> {
+        match (&self.conversion_rates, &self.instrument) {
+            (Some(rates), Some(instrument)) => rates.convert(amount, instrument.quote_currency(), datetime),
+            _ => Ok(amount),
         }
+    }
 
-        self.wallet.reset();
-        self.orders = VecDeque::new();
-        self.positions = VecDeque::new();
+    /// Returns the interval of the candles (e.g., 1 hour), inferred from their open times.
+    ///
+    /// It is the most frequent time between two consecutive candles of the data, or the time
+    /// between the first two candles of a source run with `Backtest::run_from_source`.
+    ///
+    /// ### Returns
+    /// None if the interval is not known yet (less than two candles).
+    pub fn timeframe(&self) -> Option<TimeDelta> {
+        self.timeframe
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::sync::Arc;
+    /// Requires the candles to be regularly spaced by the timeframe.
+    ///
+    /// The candle data is checked immediately, and the candles of a source are checked as they are
+    /// read. A duplicate, a candle out of order or a gap is an `Error::IrregularSpacing`; fix the data
+    /// with `data::repair` beforehand if needed.
+    ///
+    /// ### Returns
+    /// The backtest, or an error if the candle data is irregular.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = |hours| {
+    ///     CandleBuilder::builder()
+    ///         .open(100.0)
+    ///         .high(110.0)
+    ///         .low(95.0)
+    ///         .close(105.0)
+    ///         .volume(1.0)
+    ///         .open_time(DateTime::default() + Duration::hours(hours))
+    ///         .close_time(DateTime::default() + Duration::hours(hours + 1))
+    ///         .build()
+    ///         .unwrap()
+    /// };
+    ///
+    /// let bts = Backtest::new(Arc::from_iter(vec![candle(0), candle(1), candle(2)]), 1000.0, None).unwrap();
+    /// assert_eq!(bts.timeframe(), Some(Duration::hours(1)));
+    /// assert!(bts.with_strict_timeframe().is_ok());
+    ///
+    /// // the candle of the 2nd hour is missing
+    /// let bts = Backtest::new(Arc::from_iter(vec![candle(0), candle(1), candle(3), candle(4)]), 1000.0, None).unwrap();
+    /// assert!(bts.with_strict_timeframe().is_err());
+    /// ```
+    pub fn with_strict_timeframe(mut self) -> Result<Self> {
+        self.strict_timeframe = true;
+        let data = Arc::clone(&self.data);
+        for candle in data.iter() {
+            self.check_spacing(candle)?;
+        }
+        self.last_open = None;
+        Ok(self)
+    }
 
-    use crate::PercentCalculus;
-    use crate::engine::*;
+    /// Returns `true` if the candles must be regularly spaced by the timeframe.
+    pub fn strict_timeframe(&self) -> bool {
+        self.strict_timeframe
+    }
 
-    use chrono::DateTime;
+    /// Checks the time since the previous candle, and infers the timeframe from it if unknown.
+    fn check_spacing(&mut self, candle: &Candle) -> Result<()> {
+        let open_time = candle.open_time();
+        let Some(last) = self.last_open.replace(open_time) else {
+            return Ok(());
+        };
+        let elapsed = open_time - last;
+        let timeframe = match self.timeframe {
+            Some(timeframe) => timeframe,
+            None if elapsed > TimeDelta::zero() => *self.timeframe.insert(elapsed),
+            None => elapsed,
+        };
+        if self.strict_timeframe && elapsed != timeframe {
+            return Err(Error::IrregularSpacing(open_time, elapsed, timeframe));
+        }
+        Ok(())
+    }
 
-    fn get_data() -> Arc<[Candle]> {
-        let candle = CandleBuilder::builder()
-            .open(100.0)
-            .high(111.0)
-            .low(99.0)
-            .close(110.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
-            .unwrap();
+    /// Returns `true` if the session of the calendar, if any, is open at the given date.
+    fn is_session_open(&self, datetime: DateTime<Utc>) -> bool {
+        self.calendar.as_ref().is_none_or(|calendar| calendar.is_open(datetime))
+    }
 
-        Arc::from_iter(vec![candle])
+    /// Makes the order and position IDs sequential (1, 2, 3, ...) during the runs instead of random,
+    /// so two runs of the same strategy produce the same events.
+    ///
+    /// The sequence restarts with each run and only covers the orders created within it.
+    pub fn with_deterministic_ids(mut self) -> Self {
+        self.deterministic_ids = true;
+        self
     }
 
-    fn get_long_data() -> Arc<[Candle]> {
-        let candle1 = CandleBuilder::builder()
-            .open(90.0)
-            .high(110.0)
-            .low(80.0)
-            .close(100.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
-            .unwrap();
-        let candle2 = CandleBuilder::builder()
-            .open(100.0)
-            .high(119.0)
-            .low(90.0)
-            .close(110.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
-            .unwrap();
-        let candle3 = CandleBuilder::builder()
-            .open(110.0)
-            .high(129.0)
-            .low(100.0)
-            .close(120.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
-            .unwrap();
+    /// Sets the period of the Average True Range used by `OrderType::TrailingStopAtr` (14 by default).
+    pub fn with_atr_period(mut self, period: usize) -> Self {
+        self.atr = AverageTrueRange::new(period);
+        self
+    }
 
-        let iter = vec![candle1, candle2, candle3];
-        Arc::from_iter(iter)
+    /// Returns the Average True Range used by the trailing stops on the current candle.
+    ///
+    /// It is the value supplied with `set_atr` if any, otherwise the one computed from the
+    /// previous candles (`None` before the first candle is closed).
+    pub fn atr(&self) -> Option<f64> {
+        self.atr_override.or(self.atr.value())
     }
 
-    fn get_short_data() -> Arc<[Candle]> {
-        let candle1 = CandleBuilder::builder()
-            .open(150.0)
-            .high(160.0)
-            .low(131.0)
-            .close(140.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+    /// Supplies the Average True Range used by the trailing stops on the current candle,
+    /// e.g., from the strategy's own indicator.
+    pub fn set_atr(&mut self, atr: f64) {
+        self.atr_override = Some(atr);
+    }
+
+    /// Sets the commission model charged when positions are opened and closed.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // 0.005 per share with a minimum of 1.0 per trade
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_fee_model(PerShareFees::new(0.005, 1.0));
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Buy).into()))
+    ///     .unwrap();
+    /// assert_eq!(bts.fees_paid(), 1.0);
+    /// ```
+    pub fn with_fee_model(mut self, fee_model: impl FeeModel + 'static) -> Self {
+        self.fee_model = Some(Arc::new(fee_model));
+        self
+    }
+
+    /// Returns the commission model, if any.
+    pub fn fee_model(&self) -> Option<&dyn FeeModel> {
+        self.fee_model.as_deref()
+    }
+
+    /// Sets the annualized interest rates charged on the open positions.
+    ///
+    /// The interest is recorded as fees of the wallet and of the position at the close of each candle.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(100.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // 36.5% a year to borrow the shorted asset, i.e. 0.1% a day
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_borrow_rates(BorrowRates::new(36.5, 0.0));
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Sell).into()))
+    ///     .unwrap();
+    /// assert!((bts.fees_paid() - 0.1).abs() < 1e-9);
+    /// ```
+    pub fn with_borrow_rates(mut self, borrow_rates: BorrowRates) -> Self {
+        self.borrow_rates = Some(borrow_rates);
+        self
+    }
+
+    /// Returns the interest rates charged on the open positions, if any.
+    pub fn borrow_rates(&self) -> Option<&BorrowRates> {
+        self.borrow_rates.as_ref()
+    }
+
+    /// Returns the shared commission model, to configure other backtests with it.
+    #[cfg(feature = "optimizer")]
+    pub(crate) fn shared_fee_model(&self) -> Option<Arc<dyn FeeModel>> {
+        self.fee_model.clone()
+    }
+
+    /// Sets the shared commission model.
+    #[cfg(feature = "optimizer")]
+    pub(crate) fn with_shared_fee_model(mut self, fee_model: Option<Arc<dyn FeeModel>>) -> Self {
+        self.fee_model = fee_model;
+        self
+    }
+
+    /// Returns the candle data in columnar storage, built on the first call.
+    pub fn frame(&self) -> Arc<CandleFrame> {
+        Arc::clone(self.frame.get_or_init(|| Arc::new(CandleFrame::from(&self.data[..]))))
+    }
+
+    /// Returns an iterator over the data.
+    pub fn candles(&self) -> std::slice::Iter<'_, Candle> {
+        self.data.iter()
+    }
+
+    /// Returns an iterator over the pending orders, in the order they were placed.
+    pub fn orders(&self) -> impl DoubleEndedIterator<Item = &Order> + ExactSizeIterator {
+        self.orders.iter()
+    }
+
+    /// Returns an iterator over the resting limit and stop orders at `price`, in time priority.
+    pub fn orders_at_price(&self, price: f64) -> impl Iterator<Item = &Order> {
+        self.orders.at_price(price)
+    }
+
+    /// Returns the highest price of the resting buy limit orders.
+    pub fn best_resting_bid(&self) -> Option<f64> {
+        self.orders.best_bid()
+    }
+
+    /// Returns the lowest price of the resting sell limit orders.
+    pub fn best_resting_ask(&self) -> Option<f64> {
+        self.orders.best_ask()
+    }
+
+    /// Returns an iterator over the open positions.
+    pub fn positions(&self) -> Iter<'_, Position> {
+        self.positions.iter()
+    }
+
+    /// Returns the open position with the given identifier, if any.
+    pub fn position_by_id(&self, id: u32) -> Option<&Position> {
+        self.positions.iter().find(|p| p.id() == id)
+    }
+
+    /// Returns an iterator over the recorded events.
+    ///
+    /// It is empty when an event sink is configured.
+    #[cfg(feature = "metrics")]
+    pub fn events(&self) -> std::slice::Iter<'_, Event> {
+        self.events.iter()
+    }
+
+    /// Sends the events to a sink instead of keeping them in memory.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::{Arc, mpsc};
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_event_sink(sender);
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Limit(99.0), 1.0, OrderSide::Buy).into()))
+    ///     .unwrap();
+    /// assert_eq!(bts.events().count(), 0);
+    /// assert!(receiver.try_iter().count() > 0);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn with_event_sink<S: EventSink + 'static>(mut self, sink: S) -> Self {
+        self.event_sink = Some(Arc::new(Mutex::new(sink)));
+        self
+    }
+
+    /// Registers a callback invoked with each event as soon as it happens during the run
+    /// (order placed, filled, cancelled or expired, position opened or closed, wallet update).
+    ///
+    /// The callback cannot act on the backtest; to react on the next candle (e.g., re-enter after
+    /// a stop-out), share a state with the strategy.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let stopped_out = Arc::new(Mutex::new(false));
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 2]), 1000.0, None)
+    ///     .unwrap()
+    ///     .on_event({
+    ///         let stopped_out = Arc::clone(&stopped_out);
+    ///         move |event| {
+    ///             if let Event::DelPosition(_, position) = event {
+    ///                 *stopped_out.lock().unwrap() = position.exit_price() == Some(&96.0);
+    ///             }
+    ///         }
+    ///     });
+    /// bts.run(|bts, candle| {
+    ///     if bts.positions().count() == 0 && !*stopped_out.lock().unwrap() {
+    ///         let exit = OrderType::TakeProfitAndStopLoss(0.0, 96.0);
+    ///         bts.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert!(*stopped_out.lock().unwrap());
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn on_event<F: FnMut(&Event) + Send + 'static>(mut self, callback: F) -> Self {
+        self.observers.push(Arc::new(Mutex::new(callback)));
+        self
+    }
+
+    /// Sets how often the wallet updates are recorded as events.
+    ///
+    /// The orders and positions events are always recorded; sampling the equity per candle or
+    /// per day instead of after every trade cuts the events of high-frequency strategies.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 10]), 1000.0, None)
+    ///     .unwrap()
+    ///     .with_equity_sampling(EquitySampling::EveryNCandles(4));
+    /// bts.run(|_, _| Ok(())).unwrap();
+    /// let marks = bts.events().filter(|e| matches!(e, Event::WalletUpdate { .. })).count();
+    /// // after the 4th and 8th candles, then the last one
+    /// assert_eq!(marks, 3);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn with_equity_sampling(mut self, equity_sampling: EquitySampling) -> Self {
+        self.equity_sampling = equity_sampling;
+        self
+    }
+
+    /// Records an event into the sink, or in memory when there is no sink.
+    #[cfg(feature = "metrics")]
+    fn record(&mut self, event: Event) -> Result<()> {
+        for observer in &self.observers {
+            (observer.lock().map_err(|e| Error::EventSink(e.to_string()))?)(&event);
+        }
+        match self.event_sink.as_mut() {
+            Some(sink) => sink.record(event),
+            None => {
+                self.events.push(event);
+                Ok(())
+            }
+        }
+    }
+
+    /// Records a wallet update after a trade, unless the equity is sampled per candle.
+    #[cfg(feature = "metrics")]
+    fn record_wallet(&mut self, datetime: DateTime<Utc>) -> Result<()> {
+        if self.equity_sampling != EquitySampling::EveryTrade {
+            return Ok(());
+        }
+        self.record(Event::from((datetime, &self.wallet)))
+    }
+
+    /// Records a wallet update once a candle is processed, if the equity sampling requires it.
+    #[cfg(feature = "metrics")]
+    fn mark_equity(&mut self, candle: &Candle) -> Result<()> {
+        let datetime = candle.open_time();
+        self.unmarked_candles += 1;
+        let mark = match self.equity_sampling {
+            EquitySampling::EveryTrade => false,
+            EquitySampling::EveryCandle => true,
+            EquitySampling::EveryNCandles(n) => self.unmarked_candles >= n,
+            EquitySampling::Daily => self
+                .last_mark
+                .is_none_or(|last| last.date_naive() != datetime.date_naive()),
+        };
+        if mark {
+            self.unmarked_candles = 0;
+            self.last_mark = Some(datetime);
+            self.record(Event::from((datetime, &self.wallet)))?;
+        }
+        Ok(())
+    }
+
+    /// Records the last wallet update if the last candles were not sampled, then flushes the event sink.
+    fn flush_events(&mut self) -> Result<()> {
+        let data = Arc::clone(&self.data);
+        self.flush_events_at(data.last())
+    }
+
+    /// Same as `flush_events`, with the last candle of the run.
+    #[allow(unused_variables)]
+    pub(crate) fn flush_events_at(&mut self, last: Option<&Candle>) -> Result<()> {
+        #[cfg(feature = "metrics")]
+        {
+            if self.equity_sampling != EquitySampling::EveryTrade
+                && self.unmarked_candles > 0
+                && let Some(candle) = last
+            {
+                self.unmarked_candles = 0;
+                self.record(Event::from((candle.open_time(), &self.wallet)))?;
+            }
+            if let Some(sink) = self.event_sink.as_mut() {
+                sink.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Places a new order.
+    ///
+    /// ### Arguments
+    /// * `order` - The order to place.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error (e.g., `InvalidPrice` for a NaN or negative price, see
+    /// `Order::validate`).
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
+    /// bts.place_order(&candle, order).unwrap();
+    /// ```
+    pub fn place_order(&mut self, candle: &Candle, mut order: Order) -> Result<()> {
+        order.set_placed_at(candle.open_time());
+        let closed = match self.admit_order(candle, &mut order) {
+            Ok(closed) => closed,
+            Err(error) => {
+                //? the risk manager records its own rejections
+                #[cfg(feature = "metrics")]
+                if !matches!(error, Error::RiskLimit(_)) {
+                    let mut rejected = order;
+                    rejected.set_status(OrderStatus::Rejected);
+                    let reason = RejectReason::from(&error);
+                    self.record(Event::OrderRejected(candle.open_time(), rejected, reason))?;
+                }
+                return Err(error);
+            }
+        };
+        self.orders.push(order);
+        if self.latency != Latency::default() {
+            self.placed.insert(order.id(), (self.candle_index, candle.close_time()));
+        }
+        self.enqueue(candle, &order);
+        if closed {
+            self.queued.insert(order.id());
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let open_time = candle.open_time();
+            self.record_wallet(open_time)?;
+            self.record(Event::AddOrder(open_time, order))?;
+        }
+        Ok(())
+    }
+
+    /// Checks an order before it is placed, conforms it to the instrument and locks its funds.
+    ///
+    /// ### Returns
+    /// `true` if the session is closed, so the order is queued, or the reason of the rejection.
+    fn admit_order(&mut self, candle: &Candle, order: &mut Order) -> Result<bool> {
+        order.validate(self.min_quantity)?;
+        if self.is_warming_up() {
+            return Err(Error::WarmUp(self.warmup));
+        }
+        if let Some(rule) = order.exit_rule() {
+            rule.resolve(order.side(), order.entry_price()?, self.atr())?;
+        }
+        let closed = !self.is_session_open(candle.open_time());
+        if closed && let Some(OutOfSession::Reject) = self.calendar.as_ref().map(TradingCalendar::out_of_session) {
+            return Err(Error::SessionClosed(candle.open_time()));
+        }
+        if let Some(instrument) = &self.instrument {
+            instrument.conform(order)?;
+        }
+        self.check_risk(candle, order)?;
+        self.wallet.lock(order.cost()?)?;
+        Ok(closed)
+    }
+
+    /// Deletes a pending order.
+    ///
+    /// ### Arguments
+    /// * `order` - Reference to the order to delete.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
+    /// bts.place_order(&candle, order).unwrap();
+    /// // if you call this function, always put `true` to delete
+    /// bts.delete_order(&candle, &order, true).unwrap();
+    /// ```
+    pub fn delete_order(&mut self, _candle: &Candle, order: &Order, force_remove: bool) -> Result<()> {
+        //? the pending order may differ from the given one (e.g., rounded to the instrument increments)
+        let order = if force_remove {
+            self.orders.remove(order.id()).ok_or(Error::OrderNotFound)?.1
+        } else {
+            *order
+        };
+        self.wallet.unlock(order.cost()?)?;
+        self.placed.remove(&order.id());
+        self.queued.remove(&order.id());
+        self.queue_volumes.remove(&order.id());
+        #[cfg(feature = "metrics")]
+        {
+            let mut order = order;
+            order.set_status(OrderStatus::Cancelled);
+            let open_time = _candle.open_time();
+            self.record(Event::DelOrder(open_time, order))?;
+            self.record_wallet(open_time)?;
+        }
+        Ok(())
+    }
+
+    /// Amends the price and the quantity of a pending limit or stop order, keeping its ID.
+    ///
+    /// The locked funds are adjusted to the new cost of the order. As on an exchange, the order
+    /// keeps its time priority if only its quantity is reduced, and goes behind the orders at its
+    /// price otherwise. If the amendment is rejected (e.g., insufficient funds), the order is left
+    /// unchanged.
+    ///
+    /// ### Arguments
+    /// * `candle` - The current candle.
+    /// * `order` - The pending order to amend.
+    /// * `new_price` - The new limit or trigger price.
+    /// * `new_quantity` - The new quantity.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// let order = Order::from((OrderType::Limit(90.0), 2.0, OrderSide::Buy));
+    /// bts.place_order(&candle, order).unwrap();
+    /// bts.amend_order(&candle, &order, 92.0, 1.0).unwrap();
+    ///
+    /// let amended = bts.orders().next().unwrap();
+    /// assert_eq!(amended.id(), order.id());
+    /// assert_eq!(amended.cost().unwrap(), 92.0);
+    /// assert_eq!(bts.free_balance().unwrap(), 908.0);
+    /// ```
+    pub fn amend_order(&mut self, candle: &Candle, order: &Order, new_price: f64, new_quantity: f64) -> Result<()> {
+        if new_price <= 0.0 || !new_price.is_finite() || new_quantity <= 0.0 || !new_quantity.is_finite() {
+            return Err(Error::InvalidAmend(new_price, new_quantity));
+        }
+        let (seq, pending) = self.orders.remove(order.id()).ok_or(Error::OrderNotFound)?;
+        let amended = match self.amend(candle, &pending, new_price, new_quantity) {
+            Ok(amended) => amended,
+            Err(e) => {
+                self.orders.insert(seq, pending);
+                return Err(e);
+            }
+        };
+
+        if amended.entry_price()? == pending.entry_price()? && amended.quantity() <= pending.quantity() {
+            self.orders.insert(seq, amended);
+            if let Some((_, volume)) = self.queue_volumes.get_mut(&amended.id()) {
+                *volume -= pending.quantity() - amended.quantity();
+            }
+        } else {
+            self.orders.push(amended);
+            self.enqueue(candle, &amended);
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let open_time = candle.open_time();
+            self.record(Event::AmendOrder(open_time, amended))?;
+            self.record_wallet(open_time)?;
+        }
+        Ok(())
+    }
+
+    /// Queues a limit order at the back of its price level, with the `LimitFill::Queue` policy.
+    ///
+    /// The order must already be in the book, behind the resting orders placed before it.
+    fn enqueue(&mut self, candle: &Candle, order: &Order) {
+        if let OrderType::Limit(price) = order.entry_type()
+            && let Some(volume) = self.limit_fill.queue_volume(order.quantity(), candle)
+        {
+            //? the own limit orders resting at the same price are also ahead of the order
+            let own = self
+                .orders
+                .at_price(*price)
+                .take_while(|resting| resting.id() != order.id())
+                .filter(|resting| resting.side() == order.side() && matches!(resting.entry_type(), OrderType::Limit(_)))
+                .map(Order::quantity)
+                .sum::<f64>();
+            self.queue_volumes.insert(order.id(), (self.candle_index, volume + own));
+        }
+    }
+
+    /// Trades the volume of a candle touching the price of a queued limit order.
+    ///
+    /// The volume of the candle the order is queued on is the volume ahead of it, so the queue is
+    /// traded from the next candle.
+    ///
+    /// ### Returns
+    /// `true` if the volume ahead of the order and its quantity are traded, so it is filled.
+    fn trade_queue(&mut self, candle: &Candle, order: &Order) -> bool {
+        let LimitFill::Queue(share) = self.limit_fill else {
+            return false;
+        };
+        let index = self.candle_index;
+        let (queued_on, volume) = self
+            .queue_volumes
+            .entry(order.id())
+            .or_insert((index, order.quantity()));
+        if *queued_on == index {
+            return false;
+        }
+        *volume -= share * candle.volume();
+        *volume <= 0.0
+    }
+
+    /// Checks the amended order and moves the locked funds from the pending order to it.
+    fn amend(&mut self, candle: &Candle, pending: &Order, price: f64, quantity: f64) -> Result<Order> {
+        if !matches!(pending.entry_type(), OrderType::Limit(_) | OrderType::Stop(_)) {
+            return Err(Error::MismatchedOrderType);
+        }
+        let mut amended = *pending;
+        amended.set_entry_price(price);
+        amended.set_quantity(quantity);
+        if let Some(instrument) = &self.instrument {
+            instrument.conform(&mut amended)?;
+        }
+        self.check_risk(candle, &amended)?;
+
+        let cost = pending.cost()?;
+        self.wallet.unlock(cost)?;
+        if let Err(e) = amended.cost().and_then(|cost| self.wallet.lock(cost)) {
+            self.wallet.relock(0.0, cost);
+            return Err(e);
+        }
+        Ok(amended)
+    }
+
+    /// Cancels a pending order whose time-in-force has elapsed and releases its locked funds.
+    fn expire_order(&mut self, _candle: &Candle, order: &Order) -> Result<()> {
+        self.wallet.unlock(order.cost()?)?;
+        self.placed.remove(&order.id());
+        self.queued.remove(&order.id());
+        self.queue_volumes.remove(&order.id());
+        #[cfg(feature = "metrics")]
+        {
+            let open_time = _candle.open_time();
+            let mut order = *order;
+            order.set_status(OrderStatus::Expired);
+            self.record(Event::OrderExpired(open_time, order))?;
+            self.record_wallet(open_time)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the market fees charged on one leg of the position at `price`, if a fee model is set.
+    fn leg_fees(&mut self, position: &Position, price: f64) -> Option<f64> {
+        //? a contract is priced as its value, so the percentage fees apply to the notional
+        let leg = FeeLeg::new(
+            price * position.multiplier(),
+            position.quantity(),
+            position.is_market_type(),
+            self.traded_volume,
+        );
+        self.traded_volume += leg.notional();
+        self.fee_model.as_ref().map(|fee_model| fee_model.fee(&leg))
+    }
+
+    /// Returns the market fees charged when the position is opened, if any.
+    fn opening_fees(&mut self, position: &Position) -> Result<Option<f64>> {
+        Ok(self.leg_fees(position, position.entry_price()?))
+    }
+
+    /// Returns the market fees charged when the position is closed at `exit_price`, if any.
+    fn closing_fees(&mut self, position: &Position, exit_price: f64) -> Result<Option<f64>> {
+        let entry_price = position.entry_price()?;
+        Ok(match self.fee_basis {
+            FeeBasis::Entry => self.leg_fees(position, entry_price),
+            FeeBasis::BothLegs => self.leg_fees(position, exit_price),
+            FeeBasis::Exit => {
+                //? the opening leg was charged on the entry notional, it is adjusted to the exit notional
+                let leg = |price: f64| {
+                    FeeLeg::new(
+                        price * position.multiplier(),
+                        position.quantity(),
+                        position.is_market_type(),
+                        self.traded_volume,
+                    )
+                };
+                let adjustment = self
+                    .fee_model
+                    .as_ref()
+                    .map(|fee_model| fee_model.fee(&leg(exit_price)) - fee_model.fee(&leg(entry_price)));
+                let closing = self.leg_fees(position, exit_price);
+                closing
+                    .zip(adjustment)
+                    .map(|(closing, adjustment)| closing + adjustment)
+            }
+        })
+    }
+
+    /// Opens a new position.
+    fn open_position(&mut self, candle: &Candle, mut position: Position) -> Result<()> {
+        if let Some(rule) = position.exit_rule() {
+            //? the exits relative to the entry are placed from the actual fill price
+            let rule = rule.resolve(position.order().side(), position.entry_price()?, self.atr())?;
+            position.set_exit_rule(rule);
+        }
+        position.set_opened_at(self.candle_index, candle.open_time());
+        self.buy_position(&mut position)?;
+        self.opened.insert(position.id());
+        self.positions.push_back(position);
+        #[cfg(feature = "metrics")]
+        {
+            let open_time = candle.open_time();
+            self.record_wallet(open_time)?;
+            self.record(Event::AddPosition(open_time, position))?;
+        }
+        Ok(())
+    }
+
+    /// Pays the cost and the opening fee of a position, and adds the bought base asset of a long position.
+    fn buy_position(&mut self, position: &mut Position) -> Result<()> {
+        self.wallet.sub(position.cost()?)?;
+        if let Some(fee) = self.opening_fees(position)? {
+            match (self.fee_currency, position.side()) {
+                (FeeCurrency::Received, PositionSide::Long) => {
+                    // the fee is taken from the bought base asset
+                    let quantity = position.quantity() - fee / position.entry_price()?;
+                    position.set_quantity(quantity);
+                    self.wallet.add_fees(fee);
+                }
+                _ => {
+                    self.wallet.sub_fees(fee)?;
+                }
+            }
+            position.add_fees(fee);
+        }
+        if position.side() == &PositionSide::Long {
+            self.wallet.add_base(position.quantity());
+        }
+        Ok(())
+    }
+
+    /// Increases an open position (scale-in), e.g., to pyramid into a trend.
+    ///
+    /// The added quantity is bought at `price` and its fees are charged on the added notional. The
+    /// position keeps its ID and exit rule, and its entry price becomes the volume-weighted average
+    /// of the entries.
+    ///
+    /// ### Arguments
+    /// * `candle` - The current candle.
+    /// * `position` - The position to increase.
+    /// * `quantity` - The quantity to add.
+    /// * `price` - The price of the added quantity.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error if the position is not found, the quantity or the price is not
+    /// positive, or the funds or the risk limits do not allow it.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 2]), 1000.0, None).unwrap();
+    /// bts.run(|bts, candle| {
+    ///     match bts.positions().next().copied() {
+    ///         Some(position) => bts.add_to_position(candle, &position, 1.0, 110.0),
+    ///         None => bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Buy).into()),
+    ///     }
+    /// })
+    /// .unwrap();
+    /// let position = bts.positions().next().unwrap();
+    /// assert_eq!(position.quantity(), 2.0);
+    /// assert_eq!(position.entry_price().unwrap(), 105.0);
+    /// ```
+    pub fn add_to_position(&mut self, candle: &Candle, position: &Position, quantity: f64, price: f64) -> Result<()> {
+        if quantity <= 0.0 || price <= 0.0 || !quantity.is_finite() || !price.is_finite() {
+            return Err(Error::InvalidScaleIn(quantity, price));
+        }
+        let index = self
+            .positions
+            .iter()
+            .position(|p| p == position)
+            .ok_or(Error::PositionNotFound)?;
+        let current = self.positions[index];
+
+        let mut order = Order::from((OrderType::Market(price), quantity, *current.order().side()));
+        if let Some(instrument) = &self.instrument {
+            instrument.conform(&mut order)?;
+        }
+        order.set_multiplier(current.multiplier());
+        //? the added quantity does not open a new position
+        self.check_risk_with(candle, &order, false)?;
+        self.wallet.lock(order.cost()?)?;
+        let mut added = Position::from(order);
+        self.buy_position(&mut added)?;
+
+        let mut increased = current;
+        let total = current.quantity() + added.quantity();
+        let entry_price =
+            (current.entry_price()? * current.quantity() + added.entry_price()? * added.quantity()) / total;
+        increased.set_entry_price(entry_price);
+        increased.set_quantity(total);
+        increased.add_fees(added.fees());
+        self.positions[index] = increased;
+        #[cfg(feature = "metrics")]
+        {
+            let open_time = candle.open_time();
+            self.record_wallet(open_time)?;
+            self.record(Event::IncreasePosition(open_time, increased))?;
+        }
+        Ok(())
+    }
+
+    /// Offsets the opposite positions with a new position, oldest first, and returns the residual
+    /// position to open, if any.
+    fn net_position(&mut self, candle: &Candle, mut position: Position) -> Result<Option<Position>> {
+        let price = position.entry_price()?;
+        while let Some(idx) = self.positions.iter().position(|p| p.side() != position.side()) {
+            let quantity = position.quantity();
+            let opposite = &mut self.positions[idx];
+            let closed = if quantity < opposite.quantity() {
+                opposite.split_off(quantity)
+            } else {
+                self.positions.remove(idx).ok_or(Error::RemovePosition)?
+            };
+            self.close_position(candle, &closed, price, false)?;
+
+            //? the offset part of the fill does not open a position
+            let cost = position.cost()?;
+            position.set_quantity(quantity - closed.quantity());
+            if position.quantity() <= f64::EPSILON * quantity.max(1.0) {
+                self.wallet.relock(cost, 0.0);
+                return Ok(None);
+            }
+            self.wallet.relock(cost, position.cost()?);
+        }
+        Ok(Some(position))
+    }
+
+    /// Closes an existing position.
+    ///
+    /// ### Arguments
+    /// * `position` - Reference to the position to close.
+    /// * `exit_price` - The price at which to close the position.
+    /// * `force_remove` - If true, removes the position without checking conditions.
+    ///
+    /// ### Returns
+    /// The profit/loss from closing the position, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.run(|_bts, candle| {
+    ///   let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
+    ///   _bts.place_order(&candle, order).unwrap();
+    ///   
+    ///   let last_position = _bts.positions().last().cloned();
+    ///   if let Some(position) = last_position {
+    ///     // if you call this function, always put `true` to delete
+    ///     _bts.close_position(candle, &position, 110.0, true).unwrap();
+    ///   }
+    ///
+    ///   Ok(())
+    /// }).unwrap();
+    /// ```
+    pub fn close_position(
+        &mut self,
+        _candle: &Candle,
+        position: &Position,
+        exit_price: f64,
+        force_remove: bool,
+    ) -> Result<f64> {
+        if exit_price <= 0.0 || !exit_price.is_finite() {
+            return Err(Error::ExitPrice(exit_price));
+        }
+        if force_remove {
+            let pos_idx = self
+                .positions
+                .iter()
+                .position(|p| p == position)
+                .ok_or(Error::PositionNotFound)?;
+            self.positions.remove(pos_idx).ok_or(Error::RemovePosition)?;
+        }
+        // Calculate profit/loss and update wallet
+        let pnl = position.estimate_pnl(exit_price)?;
+        let total_amount = pnl + position.cost()?;
+        if position.side() == &PositionSide::Long {
+            self.wallet.sub_base(position.quantity())?;
+        }
+        self.wallet.add(total_amount)?;
+        self.wallet.sub_pnl(total_amount);
+        let mut _position = *position;
+        _position.set_status(PositionStatus::Closed);
+        _position.track_excursion(exit_price, exit_price)?;
+        if let Some(fee) = self.closing_fees(position, exit_price)? {
+            self.wallet.sub_fees(fee)?;
+            _position.add_fees(fee);
+        }
+        let account_pnl = self.to_account_currency(pnl - _position.fees(), _candle.open_time())?;
+        self.wallet.add_account_pnl(account_pnl);
+        #[cfg(feature = "metrics")]
+        {
+            _position.set_exit_price(exit_price)?;
+            let open_time = _candle.open_time();
+            self.record_wallet(open_time)?;
+            self.record(Event::DelPosition(open_time, _position))?;
+        }
+        Ok(pnl)
+    }
+
+    /// Closes an open position by its identifier, without cloning it beforehand.
+    ///
+    /// ### Arguments
+    /// * `candle` - The current candle.
+    /// * `id` - The identifier of the position (see `Position::id`).
+    /// * `exit_price` - The price at which to close the position.
+    ///
+    /// ### Returns
+    /// The realized P&L, or an error if the position is not found.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.run(|bts, candle| {
+    ///     if let Some(id) = bts.positions().last().map(|p| p.id()) {
+    ///         bts.close_position_by_id(candle, id, candle.close())?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn close_position_by_id(&mut self, candle: &Candle, id: u32, exit_price: f64) -> Result<f64> {
+        if exit_price <= 0.0 || !exit_price.is_finite() {
+            return Err(Error::ExitPrice(exit_price));
+        }
+        let pos_idx = self
+            .positions
+            .iter()
+            .position(|p| p.id() == id)
+            .ok_or(Error::PositionNotFound)?;
+        let position = self.positions.remove(pos_idx).ok_or(Error::RemovePosition)?;
+        self.close_position(candle, &position, exit_price, false)
+    }
+
+    /// Closes all open positions at the given exit price.
+    ///
+    /// ### Arguments
+    /// * `exit_price` - The price at which to close all positions.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.close_all_positions(&candle, 110.0).unwrap();
+    /// ```
+    pub fn close_all_positions(&mut self, candle: &Candle, exit_price: f64) -> Result<()> {
+        while let Some(position) = self.positions.pop_front() {
+            self.close_position(candle, &position, exit_price, false)?;
+        }
+        Ok(())
+    }
+
+    /// Closes all the open positions at the close of the last candle, when the data ends.
+    ///
+    /// The positions are recorded with `ExitReason::EndOfData`, to tell them apart from the
+    /// positions closed by the strategy.
+    ///
+    /// ### Arguments
+    /// * `candle` - The last candle.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.run(|bts, candle| bts.place_order(candle, (OrderType::Market(100.0), 1.0, OrderSide::Buy).into()))
+    ///     .unwrap();
+    /// bts.flatten_at_end(&candle).unwrap();
+    /// assert_eq!(bts.positions().len(), 0);
+    /// assert_eq!(bts.balance(), 1005.0);
+    /// ```
+    pub fn flatten_at_end(&mut self, candle: &Candle) -> Result<()> {
+        for position in self.positions.iter_mut() {
+            position.set_exit_reason(ExitReason::EndOfData);
+        }
+        self.close_all_positions(candle, candle.close())?;
+        self.update_unrealized_pnl(candle)
+    }
+
+    /// Replaces the exit rule of an open position (e.g., to move its stop or its target).
+    ///
+    /// ### Arguments
+    /// * `candle` - The current candle, whose close is the market price.
+    /// * `position` - The position to modify.
+    /// * `new_exit` - The new exit rule (take-profit/stop-loss or trailing stop). The rules relative
+    ///   to the entry are resolved from the entry price of the position.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error if the position is not found or the rule is invalid: a long
+    /// position needs its take-profit above and its stop-loss below the market price, and the
+    /// reverse for a short position.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle; 2]), 1000.0, None).unwrap();
+    /// bts.run(|bts, candle| {
+    ///     if let Some(position) = bts.positions().next().copied() {
+    ///         // tighten the stop below the close
+    ///         bts.modify_exit_rule(candle, &position, OrderType::TakeProfitAndStopLoss(120.0, 104.0))?;
+    ///     } else if bts.orders().count() == 0 {
+    ///         let exit_rule = OrderType::TakeProfitAndStopLoss(120.0, 90.0);
+    ///         bts.place_order(candle, (OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy).into())?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn modify_exit_rule(&mut self, candle: &Candle, position: &Position, new_exit: OrderType) -> Result<()> {
+        let market = candle.close();
+        let long = matches!(position.side(), PositionSide::Long);
+        let new_exit = new_exit.resolve(position.order().side(), position.entry_price()?, self.atr())?;
+        match new_exit {
+            OrderType::TakeProfitAndStopLoss(take_profit, stop_loss) => {
+                if take_profit < 0.0 || stop_loss < 0.0 {
+                    return Err(Error::NegTakeProfitAndStopLoss);
+                }
+                if take_profit > 0.0 && (take_profit > market) != long {
+                    return Err(Error::InvalidExitRule(take_profit, market));
+                }
+                if stop_loss > 0.0 && (stop_loss < market) != long {
+                    return Err(Error::InvalidExitRule(stop_loss, market));
+                }
+            }
+            OrderType::TrailingStop(price, offset)
+            | OrderType::TrailingStopDistance(price, offset)
+            | OrderType::TrailingStopAtr(price, offset) => {
+                if price <= 0.0 || offset <= 0.0 {
+                    return Err(Error::NegZeroTrailingStop);
+                }
+            }
+            _ => return Err(Error::MismatchedOrderType),
+        }
+
+        let _position = self
+            .positions
+            .iter_mut()
+            .find(|p| *p == position)
+            .ok_or(Error::PositionNotFound)?;
+        _position.set_exit_rule(new_exit);
+        #[cfg(feature = "metrics")]
+        {
+            let position = *_position;
+            self.record(Event::ModifyPosition(candle.open_time(), position))?;
+        }
+        Ok(())
+    }
+
+    /// Moves the stop-loss of an open position to its entry price (breakeven), keeping its take-profit.
+    ///
+    /// ### Arguments
+    /// * `candle` - The current candle, whose close is the market price.
+    /// * `position` - The position to modify.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error if the position has a trailing stop or is not in profit
+    /// (see `modify_exit_rule`).
+    pub fn move_stop_to_entry(&mut self, candle: &Candle, position: &Position) -> Result<()> {
+        let take_profit = match position.exit_rule() {
+            Some(OrderType::TakeProfitAndStopLoss(take_profit, _)) => *take_profit,
+            None => 0.0,
+            _ => return Err(Error::MismatchedOrderType),
+        };
+        let exit_rule = OrderType::TakeProfitAndStopLoss(take_profit, position.entry_price()?);
+        self.modify_exit_rule(candle, position, exit_rule)
+    }
+
+    /// Executes pending orders based on current candle data.
+    ///
+    /// Only the orders reached by the candle are looked up in the order book, in time priority.
+    fn execute_orders(&mut self, candle: &Candle) -> Result<()> {
+        if !self.is_session_open(candle.open_time()) {
+            return Ok(());
+        }
+        let (_, buy_low, buy_high) = self.quotes(candle, &OrderSide::Buy);
+        let (_, sell_low, sell_high) = self.quotes(candle, &OrderSide::Sell);
+        let reached = self
+            .orders
+            .reached(candle.open_time(), (buy_low, buy_high), (sell_low, sell_high));
+        for id in reached {
+            let (seq, mut order) = self.orders.remove(id).ok_or(Error::OrderNotFound)?;
+            if order.is_expired(candle.open_time()) {
+                self.expire_order(candle, &order)?;
+                continue;
+            }
+
+            let mut delayed = self.queued.remove(&order.id());
+            if let Some(&placed) = self.placed.get(&order.id()) {
+                if !self
+                    .latency
+                    .has_arrived(placed, (self.candle_index, candle.close_time()))
+                {
+                    self.orders.insert(seq, order);
+                    continue;
+                }
+                self.placed.remove(&order.id());
+                delayed = true;
+            }
+            if delayed && order.is_market_type() {
+                //? a delayed or queued market order is filled at the open of the candle it reaches
+                let cost = order.cost()?;
+                order.set_entry_price(candle.open());
+                self.wallet.relock(cost, order.cost()?);
+            }
+
+            let (open, low, high) = self.quotes(candle, order.side());
+            if let Some(fill_price) = order.stop_fill_price(open, high, low) {
+                let cost = order.cost()?;
+                order.trigger_stop(fill_price);
+                self.wallet.relock(cost, order.cost()?);
+            } else if order.is_market_type()
+                && let Some(spread) = self.spread
+            {
+                //? the market orders are priced at the mid and pay half the spread
+                let cost = order.cost()?;
+                order.set_entry_price(spread.quote(order.entry_price()?, candle, order.side()));
+                self.wallet.relock(cost, order.cost()?);
+            }
+
+            let price = order.entry_price()?;
+            let fillable = !matches!(order.time_in_force(), TimeInForce::Fok) || candle.volume() >= order.quantity();
+            let touched = price >= low && price <= high;
+            let filled = order.is_market_type() || {
+                let draw = unit_sample(((order.id() as u64) << 32) ^ candle.open_time().timestamp() as u64);
+                self.limit_fill
+                    .is_filled(price, order.quantity(), order.side(), candle, draw)
+                    || (touched && self.trade_queue(candle, &order))
+            };
+            if touched && filled && fillable {
+                self.queue_volumes.remove(&order.id());
+                order.set_status(OrderStatus::Filled);
+                order.set_filled_at(candle.open_time());
+                let mut position = Position::from(order);
+                let depth = self.depth.filter(|_| order.is_market_type());
+                if depth.is_some() || self.slippage.is_some() {
+                    let mut fill_price = price;
+                    if let Some(depth) = depth {
+                        fill_price = depth.fill_price(fill_price, order.quantity(), candle, order.side());
+                    }
+                    if let Some(slippage) = self.slippage {
+                        fill_price = slippage.fill_price(fill_price, order.quantity(), candle.volume(), order.side());
+                    }
+                    position.set_entry_price(fill_price);
+                    self.wallet.relock(order.cost()?, position.cost()?);
+                }
+                let residual = match self.position_mode {
+                    PositionMode::Hedging => Some(position),
+                    PositionMode::Netting => self.net_position(candle, position)?,
+                };
+                if let Some(position) = residual {
+                    self.open_position(candle, position)?;
+                }
+            } else {
+                //? if order is market type and does not between `high` and `low`, delete
+                if order.is_market_type() {
+                    self.delete_order(candle, &order, false)?;
+                } else if order.is_immediate() {
+                    self.expire_order(candle, &order)?;
+                } else {
+                    self.orders.insert(seq, order);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes position management (take-profit, stop-loss, trailing stop) and closes the candle.
+    fn execute_positions(&mut self, candle: &Candle) -> Result<()> {
+        self.execute_session_exits(candle)?;
+        self.flatten_session(candle)?;
+        self.close_candle(candle)
+    }
+
+    /// Executes the exit rules while the session is open, otherwise only updates the unrealized P&L.
+    fn execute_session_exits(&mut self, candle: &Candle) -> Result<()> {
+        if self.is_session_open(candle.open_time()) {
+            self.execute_exits(candle)
+        } else {
+            self.update_unrealized_pnl(candle)
+        }
+    }
+
+    /// Closes all the positions on the last candle of a session, if the calendar flattens at close.
+    fn flatten_session(&mut self, candle: &Candle) -> Result<()> {
+        if self
+            .calendar
+            .as_ref()
+            .is_some_and(|calendar| calendar.flatten_at_close() && calendar.closes_session(candle))
+        {
+            for position in self.positions.iter_mut() {
+                position.set_exit_reason(ExitReason::SessionClose);
+            }
+            self.close_all_positions(candle, candle.close())?;
+            self.wallet.set_unrealized_pnl(0.0);
+        }
+        Ok(())
+    }
+
+    /// Executes the exit rules of the positions reached by the candle and updates the unrealized P&L.
+    fn execute_exits(&mut self, candle: &Candle) -> Result<()> {
+        let mut positions = VecDeque::with_capacity(self.positions.len());
+
+        while let Some(mut position) = self.positions.pop_front() {
+            //? the exits are reached by the quote of the closing side
+            let close_side = match position.side() {
+                PositionSide::Long => OrderSide::Sell,
+                PositionSide::Short => OrderSide::Buy,
+            };
+            let (open, low, high) = self.quotes(candle, &close_side);
+            //? a position held through the open is closed at the open when it gaps past an exit level
+            let held = !self.opened.contains(&position.id());
+            //? the holding period is checked at the open, before the exit levels can be reached
+            if held && position.is_expired(self.candle_index, candle.open_time()) {
+                position.set_exit_reason(ExitReason::TimeExit);
+                self.close_position(candle, &position, open, false)?;
+                continue;
+            }
+            position.track_excursion(low, high)?;
+            let gapped = |level: f64, above: bool| held && if above { open >= level } else { open <= level };
+            let gapped_stop = |stop: f64| match self.gap_fill {
+                GapFill::Open => open,
+                GapFill::GuaranteedStop => stop,
+            };
+            let should_close = match position.exit_rule() {
+                Some(OrderType::TakeProfitAndStopLoss(take_profit, stop_loss)) => {
+                    if *take_profit < 0.0 || *stop_loss < 0.0 {
+                        return Err(Error::NegTakeProfitAndStopLoss);
+                    }
+
+                    let (take_profit_hit, stop_loss_hit) = match position.side() {
+                        PositionSide::Long => (
+                            *take_profit > 0.0 && take_profit <= &high,
+                            *stop_loss > 0.0 && stop_loss >= &low,
+                        ),
+                        PositionSide::Short => (
+                            *take_profit > 0.0 && take_profit >= &low,
+                            *stop_loss > 0.0 && stop_loss <= &high,
+                        ),
+                    };
+
+                    let long = matches!(position.side(), PositionSide::Long);
+                    let take_profit_gapped = take_profit_hit && gapped(*take_profit, long);
+                    let stop_loss_gapped = stop_loss_hit && gapped(*stop_loss, !long);
+
+                    match (take_profit_hit, stop_loss_hit) {
+                        _ if stop_loss_gapped => Some((gapped_stop(*stop_loss), ExitReason::StopLoss)),
+                        _ if take_profit_gapped => Some((open, ExitReason::TakeProfit)),
+                        (true, true) if self.fill_model.take_profit_first(position.side()) => {
+                            Some((*take_profit, ExitReason::TakeProfit))
+                        }
+                        (true, true) => Some((*stop_loss, ExitReason::StopLoss)),
+                        (true, false) => Some((*take_profit, ExitReason::TakeProfit)),
+                        (false, true) => Some((*stop_loss, ExitReason::StopLoss)),
+                        (false, false) => None,
+                    }
+                }
+                Some(
+                    &rule @ (OrderType::TrailingStop(price, offset)
+                    | OrderType::TrailingStopDistance(price, offset)
+                    | OrderType::TrailingStopAtr(price, offset)),
+                ) => {
+                    if price <= 0.0 || offset <= 0.0 {
+                        return Err(Error::NegZeroTrailingStop);
+                    }
+
+                    let long = matches!(position.side(), PositionSide::Long);
+                    let stop = match rule {
+                        OrderType::TrailingStop(..) if long => Some(price.subpercent(offset)),
+                        OrderType::TrailingStop(..) => Some(price.addpercent(offset)),
+                        OrderType::TrailingStopDistance(..) if long => Some(price - offset),
+                        OrderType::TrailingStopDistance(..) => Some(price + offset),
+                        //? without ATR yet, the stop only trails the price
+                        _ => self.atr().map(|atr| {
+                            if long {
+                                price - atr * offset
+                            } else {
+                                price + atr * offset
+                            }
+                        }),
+                    };
+
+                    match position.side() {
+                        PositionSide::Long => {
+                            if let Some(execute_price) = stop
+                                && execute_price >= low
+                            {
+                                let execute_price = if gapped(execute_price, false) {
+                                    gapped_stop(execute_price)
+                                } else {
+                                    execute_price
+                                };
+                                Some((execute_price, ExitReason::TrailingStop))
+                            } else {
+                                if high > price {
+                                    position.set_trailingstop(high);
+                                }
+                                None
+                            }
+                        }
+                        PositionSide::Short => {
+                            if let Some(execute_price) = stop
+                                && execute_price <= high
+                            {
+                                let execute_price = if gapped(execute_price, true) {
+                                    gapped_stop(execute_price)
+                                } else {
+                                    execute_price
+                                };
+                                Some((execute_price, ExitReason::TrailingStop))
+                            } else {
+                                if low < price {
+                                    position.set_trailingstop(low);
+                                }
+                                None
+                            }
+                        }
+                    }
+                }
+                None => None,
+                _ => {
+                    return Err(Error::MismatchedOrderType);
+                }
+            };
+
+            match should_close {
+                Some((exit_price, exit_reason)) => {
+                    position.set_exit_reason(exit_reason);
+                    self.close_position(candle, &position, exit_price, false)?;
+                }
+                None => {
+                    //? the breakeven stop is reached from the next candle, the path within the candle is unknown
+                    position.arm_breakeven()?;
+                    if self.execute_scale_out(candle, &mut position, (open, low, high), held)? {
+                        positions.push_back(position);
+                    }
+                }
+            }
+        }
+
+        self.positions.append(&mut positions);
+        self.update_unrealized_pnl(candle)
+    }
+
+    /// Fills the rungs of the scale-out ladder of a position reached by the candle, closing its parts.
+    ///
+    /// ### Returns
+    /// `true` if a part of the position is still open, or an error.
+    fn execute_scale_out(
+        &mut self,
+        candle: &Candle,
+        position: &mut Position,
+        (open, low, high): (f64, f64, f64),
+        held: bool,
+    ) -> Result<bool> {
+        while let Some((price, share)) = position.next_rung()? {
+            let long = matches!(position.side(), PositionSide::Long);
+            if (long && high < price) || (!long && low > price) {
+                break;
+            }
+            //? a rung gapped over by the open is filled at the open
+            let exit_price = match (held, long) {
+                (true, true) => price.max(open),
+                (true, false) => price.min(open),
+                (false, _) => price,
+            };
+            position.fill_rung();
+            let mut part = if share < 1.0 {
+                position.split_off(position.quantity() * share)
+            } else {
+                *position
+            };
+            part.set_exit_reason(ExitReason::TakeProfit);
+            self.close_position(candle, &part, exit_price, false)?;
+            if share >= 1.0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Updates the unrealized P&L of the open positions at the close of the candle.
+    fn update_unrealized_pnl(&mut self, candle: &Candle) -> Result<()> {
+        let mut total_unrealized_pnl = 0.0;
+        for position in &self.positions {
+            // calculate unrealized P&L for this position
+            let current_price = candle.close();
+            let pnl = position.estimate_pnl(current_price)?;
+            total_unrealized_pnl += pnl;
+        }
+
+        self.wallet.set_unrealized_pnl(total_unrealized_pnl);
+        Ok(())
+    }
+
+    /// Charges the interest accrued during the candle on the open positions.
+    fn accrue_interest(&mut self, candle: &Candle) -> Result<()> {
+        let Some(borrow_rates) = self.borrow_rates else {
+            return Ok(());
+        };
+        let elapsed = candle.close_time() - candle.open_time();
+        for position in self.positions.iter_mut() {
+            let notional = position.quantity() * candle.close() * position.multiplier();
+            let interest = borrow_rates.interest(position.side(), notional, elapsed);
+            if interest > 0.0 {
+                self.wallet.sub_fees(interest)?;
+                position.add_fees(interest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Charges the funding payments due during the candle on the open positions.
+    fn accrue_funding(&mut self, candle: &Candle) -> Result<()> {
+        let Some(profile) = &self.exchange_profile else {
+            return Ok(());
+        };
+        let payments = profile.funding_payments(candle.open_time(), candle.close_time());
+        if payments == 0 {
+            return Ok(());
+        }
+        let rate = profile.funding_rate() / 100.0 * payments as f64;
+        for position in self.positions.iter_mut() {
+            let notional = position.quantity() * candle.close() * position.multiplier();
+            //? the longs pay a positive rate to the shorts
+            let funding = match position.side() {
+                PositionSide::Long => notional * rate,
+                PositionSide::Short => -notional * rate,
+            };
+            self.wallet.sub_fees(funding)?;
+            position.add_fees(funding);
+        }
+        Ok(())
+    }
+
+    /// Updates the indicators, the interest, the funding and the equity sampling once the candle is closed.
+    fn close_candle(&mut self, candle: &Candle) -> Result<()> {
+        self.accrue_interest(candle)?;
+        self.accrue_funding(candle)?;
+        self.candle_index += 1;
+        self.opened.clear();
+        //? the ATR of a candle is only known once it is closed
+        self.atr.next(candle);
+        self.atr_override = None;
+        let equity = self.equity();
+        if let Some(risk_manager) = self.risk_manager.as_mut() {
+            risk_manager.mark(candle.open_time(), equity);
+        }
+        #[cfg(feature = "metrics")]
+        self.mark_equity(candle)?;
+        Ok(())
+    }
+
+    /// Runs the backtest, executing the provided function for each candle.
+    ///
+    /// ### Arguments
+    /// * `strategy` - A closure that takes the backtest and current candle.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.run(|_bts, candle| {
+    ///   let order = Order::from((OrderType::Limit(99.0), 1.0, OrderSide::Sell));
+    ///   _bts.place_order(&candle, order)
+    /// }).unwrap();
+    /// ```
+    pub fn run<S>(&mut self, mut strategy: S) -> Result<()>
+    where
+        S: FnMut(&mut Self, &Candle) -> Result<()>,
+    {
+        self.run_with_context(|bts, ctx| strategy(bts, ctx.candle()))
+    }
+
+    /// Runs the backtest, executing the provided function for each candle with the history of the run.
+    ///
+    /// ### Arguments
+    /// * `strategy` - A closure that takes the backtest and the context of the current candle
+    ///   (its index, the previous candles and the lookbacks).
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.run_with_context(|bts, ctx| {
+    ///     // buys when the close is above the average close of the last 20 candles
+    ///     let lookback = ctx.candles_back(20);
+    ///     let average = lookback.iter().map(Candle::close).sum::<f64>() / lookback.len() as f64;
+    ///     let candle = ctx.candle();
+    ///     if candle.close() >= average {
+    ///         let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+    ///         bts.place_order(candle, order)?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn run_with_context<S>(&mut self, mut strategy: S) -> Result<()>
+    where
+        S: FnMut(&mut Self, &Context<'_>) -> Result<()>,
+    {
+        let _ids = self.deterministic_ids.then(IdSequence::start);
+        let candles = Arc::clone(&self.data);
+        for (index, candle) in candles.iter().enumerate() {
+            strategy(self, &Context::new(&candles[..=index]))?;
+            self.execute_orders(candle)?;
+            self.execute_positions(candle)?;
+        }
+        self.flush_events()
+    }
+
+    /// Runs the backtest on the candles of a source, executing the provided function for each candle.
+    ///
+    /// The candles are read one at a time and not kept, so the whole series does not need to fit in
+    /// memory. The backtest is usually created with `Backtest::streaming`; its own candle data, if
+    /// any, is ignored.
+    ///
+    /// ### Arguments
+    /// * `source` - The source of the candles, in chronological order.
+    /// * `strategy` - A closure that takes the backtest and current candle.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error (including the errors of the source).
+    ///
+    /// ### Example
+    /// ```rust
+    /// use bts_rs::prelude::*;
+    ///
+    /// let csv = "0,60000,100,110,90,105,1\n60000,120000,105,112,101,110,1\n";
+    /// let mut bts = Backtest::streaming(1000.0, None).unwrap();
+    /// bts.run_from_source(CsvCandles::new(csv.as_bytes()), |bts, candle| {
+    ///     if bts.orders().len() == 0 && bts.positions().len() == 0 {
+    ///         let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+    ///         bts.place_order(candle, order)?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(bts.positions().len(), 1);
+    /// ```
+    pub fn run_from_source<C, S>(&mut self, mut source: C, mut strategy: S) -> Result<()>
+    where
+        C: CandleSource,
+        S: FnMut(&mut Self, &Candle) -> Result<()>,
+    {
+        let _ids = self.deterministic_ids.then(IdSequence::start);
+        let mut last = None;
+        while let Some(candle) = source.next_candle()? {
+            self.step(&candle, &mut strategy)?;
+            last = Some(candle);
+        }
+        self.flush_events_at(last.as_ref())
+    }
+
+    /// Processes a candle that is not part of the backtest data: runs the strategy, then fills the
+    /// orders and manages the positions.
+    pub(crate) fn step<S>(&mut self, candle: &Candle, strategy: &mut S) -> Result<()>
+    where
+        S: FnMut(&mut Self, &Candle) -> Result<()>,
+    {
+        self.check_spacing(candle)?;
+        strategy(self, candle)?;
+        self.execute_orders(candle)?;
+        self.execute_positions(candle)
+    }
+
+    /// Runs the backtest with aggregation, executing the provided function for each candle
+    /// and its aggregated versions.
+    ///
+    /// ### Arguments
+    /// * `aggregator` - An aggregator that defines how to group candles (e.g., by timeframe).
+    /// * `strategy` - A closure that takes the backtest and a vector of candle references.
+    ///
+    /// The vector contains the current candle followed by any aggregated candles.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, Duration};
+    ///
+    /// let candle = CandleBuilder::builder()
+    ///     .open(100.0)
+    ///     .high(110.0)
+    ///     .low(95.0)
+    ///     .close(105.0)
+    ///     .volume(1.0)
+    ///     .bid(0.5)
+    ///     .open_time(DateTime::default())
+    ///     .close_time(DateTime::default() + Duration::days(1))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// struct Aggregator;
+    /// impl Aggregation for Aggregator {
+    ///   fn factors(&self) -> &[usize] {
+    ///     // return (1) the normal candle
+    ///     &[1]
+    ///   }
+    /// }
+    ///
+    /// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+    /// bts.run_with_aggregator(&Aggregator, |_bts, candles| {
+    ///   let _candle = candles.last().unwrap();
+    ///   Ok(())
+    /// }).unwrap();
+    /// ```
+    pub fn run_with_aggregator<A, S>(&mut self, aggregator: &A, mut strategy: S) -> Result<()>
+    where
+        A: Aggregation,
+        S: FnMut(&mut Self, Vec<&Candle>) -> Result<()>,
+    {
+        use std::collections::BTreeMap;
+
+        let factors = aggregator.factors();
+        if factors.is_empty() {
+            return Err(Error::InvalidFactor);
+        }
+
+        let _ids = self.deterministic_ids.then(IdSequence::start);
+        let mut current_candles = BTreeMap::new();
+        let mut aggregated_candles_map = BTreeMap::new();
+
+        // Initialize the map with empty queues for each factor
+        for &factor in factors {
+            current_candles.insert(factor, VecDeque::with_capacity(factor));
+            aggregated_candles_map.insert(factor, VecDeque::with_capacity(1));
+        }
+
+        let candles = Arc::clone(&self.data);
+        for candle in candles.iter() {
+            for (_, deque) in current_candles.iter_mut() {
+                deque.push_back(candle);
+            }
+
+            for (factor, agg) in aggregated_candles_map.iter_mut() {
+                let deque = current_candles.get_mut(factor).ok_or(Error::CandleDataEmpty)?;
+                let contiguous_candles = deque.make_contiguous();
+                if let Some((range, consumed)) = aggregator.ready(*factor, contiguous_candles) {
+                    let candles = contiguous_candles.get(range).ok_or(Error::CandleNotFound)?;
+                    let candle = aggregator.aggregate(candles)?;
+                    agg.pop_front();
+                    deque.drain(..consumed.min(deque.len()));
+                    agg.push_back(candle);
+                }
+            }
+
+            let agg_candles = aggregated_candles_map.values().flatten().collect();
+            strategy(self, agg_candles)?;
+            self.execute_orders(candle)?;
+            self.execute_positions(candle)?;
+        }
+
+        self.flush_events()
+    }
+
+    /// Runs the backtest on ticks, executing the provided function for each tick.
+    ///
+    /// The candles of `interval` are built on the fly for the indicators: the strategy receives the
+    /// tick and the candle being built (including the tick). The orders and the exit rules are filled
+    /// against the tick prices, as if the price moved straight from the previous tick to the current one,
+    /// so the intrabar sequencing hidden by the candles is kept. The trading calendar, if any, applies
+    /// to the ticks, and the positions are flattened when the last candle of a session is closed.
+    ///
+    /// The backtest data should be the candles of the ticks (see `Tick::candles`), so that the
+    /// metrics and the charts match the run.
+    ///
+    /// ### Arguments
+    /// * `ticks` - The ticks sorted by time.
+    /// * `interval` - The interval of the candles built from the ticks.
+    /// * `strategy` - A closure that takes the backtest, the current tick and the current candle.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use bts_rs::prelude::*;
+    /// use chrono::{DateTime, TimeDelta};
+    ///
+    /// let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+    /// let ticks = [
+    ///     Tick::new(100.0, 1.0, OrderSide::Buy, time(0)).unwrap(),
+    ///     Tick::new(99.0, 1.0, OrderSide::Sell, time(10)).unwrap(),
+    ///     Tick::new(101.0, 1.0, OrderSide::Buy, time(70)).unwrap(),
+    /// ];
+    /// let interval = TimeDelta::minutes(1);
+    /// let candles = Tick::candles(&ticks, interval).unwrap();
+    ///
+    /// let mut bts = Backtest::new(Arc::from(candles), 1000.0, None).unwrap();
+    /// bts.run_ticks(&ticks, interval, |bts, tick, _candle| {
+    ///     if bts.orders().count() == 0 && bts.positions().count() == 0 {
+    ///         let order = Order::from((OrderType::Limit(tick.price() - 1.0), 1.0, OrderSide::Buy));
+    ///         bts.place_order(_candle, order)?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(bts.positions().count(), 1);
+    /// ```
+    pub fn run_ticks<S>(&mut self, ticks: &[Tick], interval: TimeDelta, mut strategy: S) -> Result<()>
+    where
+        S: FnMut(&mut Self, &Tick, &Candle) -> Result<()>,
+    {
+        let first = ticks.first().ok_or(Error::CandleDataEmpty)?;
+        let _ids = self.deterministic_ids.then(IdSequence::start);
+        let mut bars = TickBars::new(interval)?;
+        let mut previous_price = first.price();
+
+        for tick in ticks {
+            if let Some(candle) = bars.push(tick)? {
+                self.flatten_session(&candle)?;
+                self.close_candle(&candle)?;
+            }
+            let candle = *bars.current().ok_or(Error::CandleNotFound)?;
+            strategy(self, tick, &candle)?;
+
+            //? the path between two ticks, at the time of the tick
+            let price = tick.price();
+            let path = CandleBuilder::builder()
+                .open(previous_price)
+                .high(previous_price.max(price))
+                .low(previous_price.min(price))
+                .close(price)
+                .volume(tick.size())
+                .open_time(tick.timestamp())
+                .close_time(tick.timestamp())
+                .build()?;
+            self.execute_orders(&path)?;
+            self.execute_session_exits(&path)?;
+            previous_price = price;
+        }
+        if let Some(candle) = bars.finish() {
+            self.flatten_session(&candle)?;
+            self.close_candle(&candle)?;
+        }
+
+        self.flush_events()
+    }
+
+    /// Resets the backtest to its initial state.
+    pub fn reset(&mut self) {
+        #[cfg(test)]
+        {
+            self.index = 0;
+        }
+        #[cfg(feature = "metrics")]
+        {
+            self.events = Vec::new();
+            self.unmarked_candles = 0;
+            self.last_mark = None;
+        }
+
+        self.wallet.reset();
+        self.orders.clear();
+        self.positions = VecDeque::new();
+        self.atr = AverageTrueRange::new(self.atr.period);
+        self.atr_override = None;
+        self.traded_volume = 0.0;
+        self.candle_index = 0;
+        self.placed.clear();
+        self.queued.clear();
+        self.queue_volumes.clear();
+        self.opened.clear();
+        self.last_open = None;
+        let equity = self.equity();
+        if let Some(risk_manager) = self.risk_manager.as_mut() {
+            risk_manager.reset(equity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::PercentCalculus;
+    use crate::engine::*;
+
+    use chrono::{DateTime, TimeDelta};
+
+    fn get_data() -> Arc<[Candle]> {
+        let candle = CandleBuilder::builder()
+            .open(100.0)
+            .high(111.0)
+            .low(99.0)
+            .close(110.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+
+        Arc::from_iter(vec![candle])
+    }
+
+    fn get_long_data() -> Arc<[Candle]> {
+        let candle1 = CandleBuilder::builder()
+            .open(90.0)
+            .high(110.0)
+            .low(80.0)
+            .close(100.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+        let candle2 = CandleBuilder::builder()
+            .open(100.0)
+            .high(119.0)
+            .low(90.0)
+            .close(110.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+        let candle3 = CandleBuilder::builder()
+            .open(110.0)
+            .high(129.0)
+            .low(100.0)
+            .close(120.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+
+        let iter = vec![candle1, candle2, candle3];
+        Arc::from_iter(iter)
+    }
+
+    fn get_short_data() -> Arc<[Candle]> {
+        let candle1 = CandleBuilder::builder()
+            .open(150.0)
+            .high(160.0)
+            .low(131.0)
+            .close(140.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
             .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
             .build()
             .unwrap();
@@ -773,544 +3665,2436 @@ mod tests {
             .build()
             .unwrap();
 
-        let iter = vec![candle1, candle2, candle3];
-        Arc::from_iter(iter)
+        let iter = vec![candle1, candle2, candle3];
+        Arc::from_iter(iter)
+    }
+
+    fn get_long_data_trailing_stop() -> Arc<[Candle]> {
+        let candle1 = CandleBuilder::builder()
+            .open(99.0)
+            .high(101.0)
+            .low(98.0)
+            .close(100.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+        let candle2 = CandleBuilder::builder()
+            .open(100.0)
+            .high(110.0)
+            .low(99.0)
+            .close(108.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+        let candle3 = CandleBuilder::builder()
+            .open(108.0)
+            .high(140.0)
+            .low(108.0)
+            .close(135.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+        let candle4 = CandleBuilder::builder()
+            .open(135.0)
+            .high(139.9)
+            .low(126.0)
+            .close(130.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+
+        let iter = vec![candle1, candle2, candle3, candle4];
+        Arc::from_iter(iter)
+    }
+
+    fn get_long_data_trailing_stop_loss() -> Arc<[Candle]> {
+        let candle1 = CandleBuilder::builder()
+            .open(99.0)
+            .high(100.0)
+            .low(98.0)
+            .close(100.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+        let candle2 = CandleBuilder::builder()
+            .open(100.0)
+            .high(100.0)
+            .low(90.0)
+            .close(100.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .build()
+            .unwrap();
+
+        let iter = vec![candle1, candle2];
+        Arc::from_iter(iter)
+    }
+
+    #[test]
+    fn scenario_place_and_delete_order_with_market_fees() {
+        let data = get_data();
+        let balance = 1000.0;
+        let market_fee = 0.1; // 0.1%
+        let mut bt = Backtest::new(data, balance, Some((market_fee, 0.01))).unwrap();
+        let candle = bt.next().unwrap();
+        let price = candle.close(); // 110
+
+        let expected_fee = price * 1.0 * market_fee; // 110 * 1.0 * 0.001 = 0.11
+        let _expected_total_cost = price + expected_fee; // 110 + 0.11 = 110.11
+
+        let order = Order::from((OrderType::Market(price), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+
+        assert!(!bt.orders.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 890.0); // 890 with fees \ 900 without fees
+
+        bt.delete_order(&candle, &order, true).unwrap();
+
+        assert!(bt.orders.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+
+        // Open long, take-profit
+        {
+            let data = get_long_data();
+            let balance = 1000.0;
+            let market_fee = 1.0; // 1%
+            let mut bt = Backtest::new(data, balance, Some((market_fee, 1.0)))
+                .unwrap()
+                .with_fee_basis(FeeBasis::Entry);
+
+            let candle = bt.next().unwrap();
+            let price = candle.close(); // 100
+            let take_profit = OrderType::TakeProfitAndStopLoss(price.addpercent(20.0), 0.0);
+            let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Buy));
+
+            let open_fee = price * 1.0 * (market_fee / 100.0);
+            let expected_total_cost = price + open_fee; // 100 + 1.0% = 101.0
+
+            bt.place_order(&candle, order).unwrap();
+            bt.execute_orders(&candle).unwrap();
+
+            assert!(!bt.positions.is_empty());
+            assert_eq!(bt.balance(), 899.0);
+            assert_eq!(bt.total_balance(), 899.0);
+            assert_eq!(bt.free_balance().unwrap(), 1000.0 - expected_total_cost);
+
+            let candle = bt.next().unwrap();
+            bt.execute_positions(&candle).unwrap(); // close = 110, p&l brut = +10
+            assert!(!bt.positions.is_empty());
+
+            let candle = bt.next().unwrap();
+            bt.execute_positions(&candle).unwrap(); // close = 120, take profit
+
+            assert!(bt.positions.is_empty());
+            assert_eq!(bt.balance(), 1018.0); // balance = 1020 - (1 * 2) (fees)
+            assert_eq!(bt.total_balance(), 1018.0);
+            assert_eq!(bt.free_balance().unwrap(), 1018.0);
+        }
+    }
+
+    #[test]
+    fn scenario_open_position_with_market_fees() {
+        let data = get_long_data();
+        let balance = 1000.0;
+        let market_fee = 1.0; // 1%
+        let mut bt = Backtest::new(data, balance, Some((market_fee, 1.0))).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close(); // 100
+        let take_profit = OrderType::TakeProfitAndStopLoss(price.addpercent(20.0), 0.0);
+        let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Buy));
+
+        let open_fee = price * 1.0 * (market_fee / 100.0);
+        let expected_total_cost = price + open_fee; // 100 + 1.0% = 101.0
+
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 899.0);
+        assert_eq!(bt.total_balance(), 899.0);
+        assert_eq!(bt.free_balance().unwrap(), 1000.0 - expected_total_cost);
+
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 110, p&l brut = +10
+        assert!(!bt.positions.is_empty());
+
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 120, take profit
+
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1017.8); // balance = 1020 - 1 - 1.2 (fees on 100 and on 120)
+        assert_eq!(bt.total_balance(), 1017.8);
+        assert_eq!(bt.free_balance().unwrap(), 1017.8);
+    }
+
+    #[test]
+    fn scenario_place_and_delete_auto_a_market_order() {
+        let data = get_data();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close(); // 110
+
+        let order = Order::from((OrderType::Market(price * 3.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap(); // lock amount 110
+
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 670.0);
+
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(bt.orders.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+    }
+
+    #[test]
+    fn scenario_place_and_delete_order() {
+        let data = get_data();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close(); // 110
+
+        let order = Order::from((OrderType::Market(price), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap(); // lock amount 110
+
+        assert!(!bt.orders.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 890.0);
+
+        bt.delete_order(&candle, &order, true).unwrap(); // unlock amount 110
+
+        assert!(bt.orders.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+    }
+
+    #[test]
+    fn scenario_open_long_position_and_take_profit() {
+        let data = get_long_data();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close();
+
+        let take_profit = OrderType::TakeProfitAndStopLoss(price.addpercent(20.0), 0.0);
+        let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+
+        assert!(!bt.orders.is_empty());
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(bt.orders.is_empty());
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 900.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        bt.execute_positions(&candle).unwrap();
+        assert!(!bt.positions.is_empty());
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 110, p&l = +10
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 910.0); // balance + p&l
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 120, take profit matched
+
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1020.0);
+        assert_eq!(bt.total_balance(), 1020.0);
+        assert_eq!(bt.free_balance().unwrap(), 1020.0);
+    }
+
+    #[test]
+    fn scenario_open_long_position_and_stop_loss() {
+        let data = get_short_data();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close();
+
+        let stop_loss = OrderType::TakeProfitAndStopLoss(0.0, price - 20.0);
+        let order = Order::from((OrderType::Market(price), stop_loss, 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+
+        assert!(!bt.orders.is_empty());
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(bt.orders.is_empty());
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 860.0);
+        assert_eq!(bt.total_balance(), 860.0);
+        assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+        bt.execute_positions(&candle).unwrap();
+        assert!(!bt.positions.is_empty());
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 130, p&l = -10
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 860.0);
+        assert_eq!(bt.total_balance(), 850.0); // balance + p&l
+        assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 120, stop loss matched
+
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 980.0);
+        assert_eq!(bt.total_balance(), 980.0);
+        assert_eq!(bt.free_balance().unwrap(), 980.0);
+    }
+
+    #[test]
+    fn scenario_open_short_position_and_take_profit() {
+        let data = get_short_data();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close();
+
+        let take_profit = OrderType::TakeProfitAndStopLoss(price - 20.0, 0.0);
+        let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+
+        assert!(!bt.orders.is_empty());
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(bt.orders.is_empty());
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 860.0);
+        assert_eq!(bt.total_balance(), 860.0);
+        assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+        bt.execute_positions(&candle).unwrap();
+        assert!(!bt.positions.is_empty());
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 130, p&l = +10
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 860.0);
+        assert_eq!(bt.total_balance(), 870.0); // balance + p&l
+        assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 120, take profit matched
+
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1020.0);
+        assert_eq!(bt.total_balance(), 1020.0);
+        assert_eq!(bt.free_balance().unwrap(), 1020.0);
+    }
+
+    #[test]
+    fn scenario_open_short_position_and_stop_loss() {
+        let data = get_long_data();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close();
+
+        let stop_loss = OrderType::TakeProfitAndStopLoss(0.0, price.addpercent(20.0));
+        let order = Order::from((OrderType::Market(price), stop_loss, 1.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+
+        assert!(!bt.orders.is_empty());
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(bt.orders.is_empty());
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 900.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        bt.execute_positions(&candle).unwrap();
+        assert!(!bt.positions.is_empty());
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 110, p&l = -10
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 890.0); // balance + p&l
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap(); // close = 120, stop loss matched
+
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 980.0);
+        assert_eq!(bt.total_balance(), 980.0);
+        assert_eq!(bt.free_balance().unwrap(), 980.0);
+    }
+
+    #[test]
+    fn scenario_open_long_position_with_trailing_stop_profit() {
+        // enter at 100
+        // the high is 140 and the trailing stop is set to 10%
+        // exit at 126
+        let data = get_long_data_trailing_stop();
+        let balance = 1000.0;
+        let mut bt = Backtest::new(data, balance, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let price = candle.close();
+
+        let trailing_stop = OrderType::TrailingStop(price, 10.0);
+        let order = Order::from((OrderType::Market(price), trailing_stop, 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 900.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        bt.execute_positions(&candle).unwrap();
+        assert!(!bt.positions.is_empty());
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap();
+
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 908.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 935.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+        // next tick
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1026.0);
+        assert_eq!(bt.total_balance(), 1026.0);
+        assert_eq!(bt.free_balance().unwrap(), 1026.0);
+    }
+
+    #[test]
+    fn scenario_deterministic_ids() {
+        let mut bt = Backtest::new(get_long_data(), 1000.0, None)
+            .unwrap()
+            .with_deterministic_ids();
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let mut run_ids = Vec::new();
+            bt.run(|bt, candle| {
+                let order = Order::from((OrderType::Limit(candle.low()), 1.0, OrderSide::Buy));
+                run_ids.push(order.id());
+                bt.place_order(candle, order)
+            })
+            .unwrap();
+            ids.push(run_ids);
+            bt.reset();
+        }
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[0][0], 1);
+        assert!(ids[0].windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn scenario_trailing_stop_distance_and_atr() {
+        // enter at 100, the first candle range (ATR) is 2 and the second candle low is 90
+        let cases = [
+            (OrderType::TrailingStopDistance(100.0, 5.0), None, 995.0),
+            (OrderType::TrailingStopAtr(100.0, 2.0), None, 996.0),
+            (OrderType::TrailingStopAtr(100.0, 2.0), Some(4.0), 992.0),
+        ];
+
+        for (trailing_stop, atr, balance) in cases {
+            let data = get_long_data_trailing_stop_loss();
+            let mut bt = Backtest::new(data, 1000.0, None).unwrap().with_atr_period(3);
+            assert_eq!(bt.atr(), None);
+
+            let candle = bt.next().unwrap();
+            let order = Order::from((OrderType::Market(100.0), trailing_stop, 1.0, OrderSide::Buy));
+            bt.place_order(&candle, order).unwrap();
+            bt.execute_orders(&candle).unwrap();
+            bt.execute_positions(&candle).unwrap();
+            assert!(matches!(
+                bt.positions[0].exit_rule(),
+                Some(OrderType::TrailingStopDistance(..) | OrderType::TrailingStopAtr(..))
+            ));
+            assert_eq!(bt.atr(), Some(2.0));
+
+            // next tick
+            let candle = bt.next().unwrap();
+            if let Some(atr) = atr {
+                bt.set_atr(atr);
+                assert_eq!(bt.atr(), Some(atr));
+            }
+            bt.execute_positions(&candle).unwrap();
+            assert!(bt.positions.is_empty());
+            assert_eq!(bt.balance(), balance);
+            // the true range of the second candle is 10
+            assert_eq!(bt.atr(), Some(6.0));
+        }
+    }
+
+    #[test]
+    fn scenario_fee_model() {
+        // the second leg reaches the lower tier after 100 of traded value
+        let fees = TieredFees::new(vec![
+            (0.0, PercentFees::new(1.0, 1.0)),
+            (100.0, PercentFees::new(0.5, 0.5)),
+        ]);
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap().with_fee_model(fees);
+
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        assert_eq!(bt.fees_paid(), 1.0);
+
+        let position = *bt.positions.front().unwrap();
+        bt.close_position(&candle, &position, 110.0, true).unwrap();
+        // 0.5% of the exit notional
+        assert!((bt.fees_paid() - 1.55).abs() < 1e-9);
+        assert!((bt.balance() - 1008.45).abs() < 1e-9);
     }
 
-    fn get_long_data_trailing_stop() -> Arc<[Candle]> {
-        let candle1 = CandleBuilder::builder()
-            .open(99.0)
-            .high(101.0)
-            .low(98.0)
-            .close(100.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+    #[test]
+    fn scenario_fee_basis() {
+        // (fee basis, fees paid at the opening, fees paid at the close)
+        let cases = [
+            (FeeBasis::Entry, 1.0, 1.0),
+            (FeeBasis::Exit, 1.0, 1.2),
+            (FeeBasis::BothLegs, 1.0, 1.1),
+        ];
+
+        for (fee_basis, opening, closing) in cases {
+            let data = get_data();
+            let mut bt = Backtest::new(data, 1000.0, Some((1.0, 1.0)))
+                .unwrap()
+                .with_fee_basis(fee_basis);
+            assert_eq!(bt.fee_basis(), fee_basis);
+            let candle = bt.next().unwrap();
+
+            let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+            bt.place_order(&candle, order).unwrap();
+            bt.execute_orders(&candle).unwrap();
+            assert!((bt.fees_paid() - opening).abs() < 1e-9);
+
+            let position = *bt.positions().next().unwrap();
+            bt.close_position(&candle, &position, 110.0, true).unwrap();
+            assert!((bt.fees_paid() - opening - closing).abs() < 1e-9);
+            assert!((bt.balance() - (1010.0 - opening - closing)).abs() < 1e-9);
+        }
+
+        let bt = BacktestBuilder::builder()
+            .data(get_data())
+            .initial_balance(1000.0)
+            .fee_basis(FeeBasis::Entry)
             .build()
             .unwrap();
-        let candle2 = CandleBuilder::builder()
+        assert_eq!(bt.fee_basis(), FeeBasis::Entry);
+    }
+
+    #[test]
+    fn scenario_borrow_rates() {
+        let candle = CandleBuilder::builder()
             .open(100.0)
-            .high(110.0)
+            .high(111.0)
             .low(99.0)
-            .close(108.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
-            .unwrap();
-        let candle3 = CandleBuilder::builder()
-            .open(108.0)
-            .high(140.0)
-            .low(108.0)
-            .close(135.0)
+            .close(110.0)
             .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+            .open_time(DateTime::from_timestamp_secs(0).unwrap())
+            .close_time(DateTime::from_timestamp_secs(86400).unwrap())
             .build()
             .unwrap();
-        let candle4 = CandleBuilder::builder()
-            .open(135.0)
-            .high(139.9)
-            .low(126.0)
-            .close(130.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
+        let data = Arc::from_iter(vec![candle; 2]);
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_borrow_rates(BorrowRates::new(36.5, 0.0));
+
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.close_candle(&candle).unwrap();
+        // 0.1% a day of the position value at the close
+        assert!((bt.fees_paid() - 0.11).abs() < 1e-9);
+
+        let position = *bt.positions.front().unwrap();
+        assert!((position.fees() - 0.11).abs() < 1e-9);
+        bt.close_position(&candle, &position, 100.0, true).unwrap();
+        assert!((bt.balance() - 999.89).abs() < 1e-9);
+
+        // the long positions are not financed
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.close_candle(&candle).unwrap();
+        assert!((bt.fees_paid() - 0.11).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scenario_modify_exit_rule() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+        let candle = bt.next().unwrap();
+        let exit_rule = OrderType::TakeProfitAndStopLoss(130.0, 75.0);
+        let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+
+        // the second candle closes at 110
+        let candle = bt.next().unwrap();
+        let position = *bt.positions.front().unwrap();
+        let invalid = OrderType::TakeProfitAndStopLoss(130.0, 115.0);
+        assert!(bt.modify_exit_rule(&candle, &position, invalid).is_err());
+        let invalid = OrderType::TakeProfitAndStopLoss(105.0, 95.0);
+        assert!(bt.modify_exit_rule(&candle, &position, invalid).is_err());
+        assert!(
+            bt.modify_exit_rule(&candle, &position, OrderType::Market(100.0))
+                .is_err()
+        );
+
+        bt.move_stop_to_entry(&candle, &position).unwrap();
+        assert_eq!(
+            bt.positions.front().unwrap().exit_rule(),
+            Some(&OrderType::TakeProfitAndStopLoss(130.0, 100.0))
+        );
+        bt.execute_positions(&candle).unwrap();
+
+        // the third candle (low 100) reaches the stop at the entry price
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1000.0);
+        #[cfg(feature = "metrics")]
+        assert!(
+            bt.events()
+                .any(|e| matches!(e, crate::metrics::Event::ModifyPosition(..)))
+        );
+    }
+
+    #[test]
+    fn scenario_spread() {
+        // the candle goes from 99 to 111, quoted from 99.5 to 111.5 at the ask and 98.5 to 110.5 at the bid
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_spread(Spread::Fixed(1.0));
+
+        let candle = bt.next().unwrap();
+        let exit_rule = OrderType::TakeProfitAndStopLoss(110.6, 0.0);
+        let market = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+        let limit = Order::from((OrderType::Limit(99.2), 1.0, OrderSide::Buy));
+        let short = Order::from((OrderType::Market(105.0), 1.0, OrderSide::Sell));
+        bt.place_order(&candle, market).unwrap();
+        bt.place_order(&candle, limit).unwrap();
+        bt.place_order(&candle, short).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+
+        // the limit is below the ask and the take profit above the bid
+        assert_eq!(bt.orders.len(), 1);
+        assert_eq!(bt.positions.len(), 2);
+        assert_eq!(bt.positions[0].entry_price().unwrap(), 100.5);
+        assert_eq!(bt.positions[1].entry_price().unwrap(), 104.5);
+    }
+
+    #[test]
+    fn scenario_market_depth() {
+        // the candle goes from 99 to 111: 4 levels of 3.0 holding 0.25 each
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_depth(MarketDepth::new(4, 1.0));
+
+        let candle = bt.next().unwrap();
+        let market = Order::from((OrderType::Market(100.0), 0.5, OrderSide::Buy));
+        let limit = Order::from((OrderType::Limit(105.0), 0.5, OrderSide::Buy));
+        bt.place_order(&candle, market).unwrap();
+        bt.place_order(&candle, limit).unwrap();
+        bt.execute_orders(&candle).unwrap();
+
+        // 0.25 @ 100 and 0.25 @ 103, the limit is filled at its price
+        assert_eq!(bt.positions.len(), 2);
+        assert_eq!(bt.positions[0].entry_price().unwrap(), 101.5);
+        assert_eq!(bt.positions[1].entry_price().unwrap(), 105.0);
+    }
+
+    #[test]
+    fn scenario_time_exit() {
+        // the candles open at 90, 100 and 110
+        let run = |period: HoldingPeriod| {
+            let mut bt = Backtest::new(get_long_data(), 1000.0, None).unwrap();
+            bt.run(|bt, candle| {
+                if bt.candle_index == 0 {
+                    let exit_rule = OrderType::TakeProfitAndStopLoss(200.0, 10.0);
+                    let order = Order::from((OrderType::Market(90.0), exit_rule, 1.0, OrderSide::Buy));
+                    bt.place_order(candle, order.with_max_holding(period))?;
+                }
+                Ok(())
+            })
             .unwrap();
+            bt
+        };
 
-        let iter = vec![candle1, candle2, candle3, candle4];
-        Arc::from_iter(iter)
+        // held for two candles, closed at the open of the third one
+        let bt = run(HoldingPeriod::Candles(2));
+        assert_eq!(bt.positions.len(), 0);
+        assert_eq!(bt.balance(), 1020.0);
+        // the candles open at the same time, so the position is closed at the next open
+        let bt = run(HoldingPeriod::Duration(TimeDelta::zero()));
+        assert_eq!(bt.balance(), 1010.0);
+        // not held long enough
+        let bt = run(HoldingPeriod::Candles(3));
+        assert_eq!(bt.positions.len(), 1);
+        assert_eq!(bt.positions[0].filled_at(), Some(get_long_data()[0].open_time()));
     }
 
-    fn get_long_data_trailing_stop_loss() -> Arc<[Candle]> {
-        let candle1 = CandleBuilder::builder()
-            .open(99.0)
-            .high(100.0)
-            .low(98.0)
-            .close(100.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
+    #[test]
+    fn scenario_breakeven_stop() {
+        // the candles open at 90, 100 and 110, the second one falls to 90
+        let run = |breakeven: Option<BreakevenStop>| {
+            let mut bt = Backtest::new(get_long_data(), 1000.0, None).unwrap();
+            bt.run(|bt, candle| {
+                if bt.candle_index == 0 {
+                    let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 50.0);
+                    let order = Order::from((OrderType::Market(90.0), exit_rule, 1.0, OrderSide::Buy));
+                    bt.place_order(candle, breakeven.map_or(order, |b| order.with_breakeven(b)))?;
+                }
+                Ok(())
+            })
             .unwrap();
-        let candle2 = CandleBuilder::builder()
-            .open(100.0)
-            .high(100.0)
-            .low(90.0)
-            .close(100.0)
-            .volume(1.0)
-            .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
-            .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
-            .build()
+            bt
+        };
+
+        // armed by the rise to 110 on the first candle, stopped at 91 on the second one
+        let bt = run(Some(BreakevenStop::new(10.0, 1.0)));
+        assert_eq!(bt.positions.len(), 0);
+        assert_eq!(bt.balance(), 1001.0);
+        // never armed
+        let bt = run(Some(BreakevenStop::new(50.0, 1.0)));
+        assert_eq!(bt.positions.len(), 1);
+        let bt = run(None);
+        assert_eq!(bt.positions.len(), 1);
+    }
+
+    #[test]
+    fn scenario_scale_out() {
+        // the candles open at 90, 100 and 110 and reach 110, 119 and 129
+        let run = |rungs: &[(f64, f64)]| {
+            let ladder = ScaleOut::new(rungs).unwrap();
+            let mut bt = Backtest::new(get_long_data(), 1000.0, None).unwrap();
+            bt.run(|bt, candle| {
+                if bt.candle_index == 0 {
+                    let order = Order::from((OrderType::Market(90.0), 4.0, OrderSide::Buy));
+                    bt.place_order(candle, order.with_scale_out(ladder))?;
+                }
+                Ok(())
+            })
             .unwrap();
+            bt
+        };
+
+        // 2 @ 99 on the first candle, then half of the rest @ 112.5
+        let bt = run(&[(10.0, 0.5), (25.0, 0.25)]);
+        assert_eq!(bt.positions.len(), 1);
+        assert_eq!(bt.positions[0].quantity(), 1.0);
+        assert_eq!(bt.positions[0].rungs_filled(), 2);
+        // the last unit is still open, bought at 90
+        assert_eq!(bt.balance(), 1000.0 - 90.0 + 2.0 * 9.0 + 22.5);
+        #[cfg(feature = "metrics")]
+        {
+            let trades = crate::metrics::Metrics::from(&bt).trades();
+            assert_eq!(trades.len(), 2);
+            assert_eq!(trades[0].net_pnl(), 18.0);
+            assert_eq!(trades[1].net_pnl(), 22.5);
+        }
 
-        let iter = vec![candle1, candle2];
-        Arc::from_iter(iter)
+        // the last rung closes the whole position
+        let bt = run(&[(10.0, 0.5), (20.0, 0.5)]);
+        assert_eq!(bt.positions.len(), 0);
+        assert_eq!(bt.balance(), 1000.0 + 2.0 * 9.0 + 2.0 * 18.0);
     }
 
     #[test]
-    fn scenario_place_and_delete_order_with_market_fees() {
-        let data = get_data();
-        let balance = 1000.0;
-        let market_fee = 0.1; // 0.1%
-        let mut bt = Backtest::new(data, balance, Some((market_fee, 0.01))).unwrap();
+    fn scenario_conversion_rates() {
+        // the candles open at 90, 100 and 110, the JPY is worth 0.01 then 0.005 USD
+        let data = get_long_data();
+        let rates = ConversionRates::new("USD").with_series(
+            "USD",
+            "JPY",
+            [(data[0].open_time(), 100.0), (data[1].open_time(), 200.0)],
+        );
+        let mut bt = Backtest::new(data, 1000.0, Some((0.1, 0.1)))
+            .unwrap()
+            .with_instrument(Instrument::new("NKD").with_quote_currency("JPY"))
+            .with_conversion_rates(rates);
+
         let candle = bt.next().unwrap();
-        let price = candle.close(); // 110
+        let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        let candle = bt.next().unwrap();
+        let position = *bt.positions().next().unwrap();
+        bt.close_position(&candle, &position, 110.0, true).unwrap();
 
-        let expected_fee = price * 1.0 * market_fee; // 110 * 1.0 * 0.001 = 0.11
-        let _expected_total_cost = price + expected_fee; // 110 + 0.11 = 110.11
+        // 20 JPY of profit, minus 0.2 + 0.22 JPY of fees
+        assert!((bt.account_pnl() - 19.58 / 200.0).abs() < 1e-12);
 
-        let order = Order::from((OrderType::Market(price), 1.0, OrderSide::Buy));
+        // without conversion rates, the account currency is the quote currency
+        let mut bt = Backtest::new(get_long_data(), 1000.0, None).unwrap();
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        let position = *bt.positions().next().unwrap();
+        bt.close_position(&candle, &position, 110.0, true).unwrap();
+        assert_eq!(bt.account_pnl(), 20.0);
+    }
 
-        assert!(!bt.orders.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 890.0); // 890 with fees \ 900 without fees
+    #[test]
+    fn scenario_latency() {
+        // the candles open at 90, 100 and 110
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_latency(Latency::Candles(2));
 
-        bt.delete_order(&candle, &order, true).unwrap();
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        let candle = bt.next().unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.orders.len(), 1);
 
+        // the market order reaches the third candle and is filled at its open
+        let candle = bt.next().unwrap();
+        bt.execute_orders(&candle).unwrap();
         assert!(bt.orders.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
+        assert_eq!(bt.positions.front().unwrap().entry_price().unwrap(), 110.0);
+        assert_eq!(bt.balance(), 890.0);
+        assert_eq!(bt.free_balance().unwrap(), 890.0);
+        assert!(bt.placed.is_empty());
+    }
+
+    #[test]
+    fn scenario_order_timestamps() {
+        let start = DateTime::default();
+        let data = [100.0, 95.0, 90.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, low)| {
+                let open_time = start + TimeDelta::minutes(i as i64);
+                CandleBuilder::builder()
+                    .open(100.0)
+                    .high(110.0)
+                    .low(low)
+                    .close(105.0)
+                    .volume(1.0)
+                    .open_time(open_time)
+                    .close_time(open_time + TimeDelta::minutes(1))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Arc<[_]>>();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+        bt.run(|bt, candle| {
+            if candle.open_time() == start {
+                bt.place_order(candle, Order::from((OrderType::Limit(92.0), 1.0, OrderSide::Buy)))?;
+            }
+            if let Some(order) = bt.orders().next() {
+                assert_eq!(order.placed_at(), Some(start));
+                assert_eq!(order.filled_at(), None);
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        // reached by the third candle
+        let position = bt.positions().next().unwrap();
+        assert_eq!(position.placed_at(), Some(start));
+        assert_eq!(position.filled_at(), Some(start + TimeDelta::minutes(2)));
+        assert_eq!(position.time_in_queue(), Some(TimeDelta::minutes(2)));
+        assert_eq!(
+            Order::from((OrderType::Limit(92.0), 1.0, OrderSide::Buy)).time_in_queue(),
+            None
+        );
+    }
+
+    #[test]
+    fn scenario_warmup() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap().with_warmup(2);
+
+        // the orders are rejected on the first two candles
+        for _ in 0..2 {
+            let candle = bt.next().unwrap();
+            assert!(bt.is_warming_up());
+            let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+            assert!(matches!(
+                bt.place_order(&candle, order),
+                Err(crate::errors::Error::WarmUp(2))
+            ));
+            bt.execute_orders(&candle).unwrap();
+            bt.execute_positions(&candle).unwrap();
+        }
         assert_eq!(bt.free_balance().unwrap(), 1000.0);
 
-        // Open long, take-profit
+        let candle = bt.next().unwrap();
+        assert!(!bt.is_warming_up());
+        let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        assert_eq!(bt.positions.len(), 1);
+
+        bt.reset();
+        assert!(bt.is_warming_up());
+    }
+
+    #[test]
+    fn scenario_position_mode() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_position_mode(PositionMode::Netting);
+
+        // long 2 at 100
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert_eq!(bt.balance(), 800.0);
+
+        // selling 1 at 110 closes half of the long
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(110.0), 1.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert_eq!(bt.positions.len(), 1);
+        assert_eq!(bt.positions.front().unwrap().quantity(), 1.0);
+        assert_eq!(bt.balance(), 910.0);
+        assert_eq!(bt.free_balance().unwrap(), 910.0);
+
+        // selling 3 at 120 closes the rest of the long and opens a short of 2
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(120.0), 3.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        let position = bt.positions.front().unwrap();
+        assert_eq!(bt.positions.len(), 1);
+        assert_eq!(position.side(), &PositionSide::Short);
+        assert_eq!(position.quantity(), 2.0);
+        assert_eq!(bt.balance(), 790.0);
+        assert_eq!(bt.free_balance().unwrap(), 790.0);
+
+        #[cfg(feature = "metrics")]
         {
-            let data = get_long_data();
-            let balance = 1000.0;
-            let market_fee = 1.0; // 1%
-            let mut bt = Backtest::new(data, balance, Some((market_fee, 1.0))).unwrap();
+            let trades = crate::metrics::Metrics::from(&bt).trades();
+            assert_eq!(trades.len(), 2);
+            assert!(trades.iter().all(|t| t.entry_time() == bt.data[0].open_time()));
+            assert_eq!(trades.iter().map(|t| t.gross_pnl()).sum::<f64>(), 30.0);
+        }
+    }
 
-            let candle = bt.next().unwrap();
-            let price = candle.close(); // 100
-            let take_profit = OrderType::TakeProfitAndStopLoss(price.addpercent(20.0), 0.0);
-            let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Buy));
+    #[test]
+    fn scenario_risk_manager() {
+        let data = get_long_data();
+        let risk_manager = RiskManager::default()
+            .with_max_open_positions(2)
+            .with_max_daily_loss(5.0);
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_risk_manager(risk_manager);
+
+        // a pending order and a position count toward the open positions
+        let candle = bt.next().unwrap();
+        let buy = Order::from((OrderType::Market(100.0), 6.0, OrderSide::Buy));
+        bt.place_order(&candle, buy).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        let limit = Order::from((OrderType::Limit(50.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, limit).unwrap();
+        let order = Order::from((OrderType::Limit(60.0), 1.0, OrderSide::Buy));
+        let rejected = bt.place_order(&candle, order);
+        assert!(matches!(
+            rejected,
+            Err(crate::errors::Error::RiskLimit(RiskLimit::MaxOpenPositions(2)))
+        ));
+        #[cfg(feature = "metrics")]
+        assert!(matches!(
+            bt.events().last(),
+            Some(crate::metrics::Event::RiskRejected(_, o, RiskLimit::MaxOpenPositions(2))) if o.id() == order.id()
+        ));
+        bt.delete_order(&candle, &limit, true).unwrap();
+        bt.execute_positions(&candle).unwrap();
 
-            let open_fee = price * 1.0 * (market_fee / 100.0);
-            let expected_total_cost = price + open_fee; // 100 + 1.0% = 101.0
+        // the long of 6 from 100 loses 60 (6%) at the close of 90 on the next day
+        let position = *bt.positions.front().unwrap();
+        let candle = bt.next().unwrap();
+        bt.close_position(&candle, &position, 90.0, true).unwrap();
+        let order = Order::from((OrderType::Market(90.0), 1.0, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(&candle, order),
+            Err(crate::errors::Error::RiskLimit(RiskLimit::MaxDailyLoss(..)))
+        ));
+    }
+
+    #[test]
+    fn scenario_limit_fill() {
+        // the first candle low is 80, so only the order at 95 is traded through by 5
+        let cases = [
+            (LimitFill::Touch, 2),
+            (LimitFill::TradeThrough(5.0), 1),
+            (LimitFill::VolumeProbability(0.0), 0),
+            // the order at 80 is touched, but not filled before the volume ahead of it is traded
+            (LimitFill::Queue(0.5), 1),
+        ];
+
+        for (limit_fill, positions) in cases {
+            let data = get_long_data();
+            let mut bt = Backtest::new(data, 1000.0, None)
+                .unwrap()
+                .with_limit_fill(limit_fill)
+                .unwrap();
 
+            let candle = bt.next().unwrap();
+            for price in [80.0, 95.0] {
+                let order = Order::from((OrderType::Limit(price), 1.0, OrderSide::Buy));
+                bt.place_order(&candle, order).unwrap();
+            }
+            bt.execute_orders(&candle).unwrap();
+            let candle = bt.next().unwrap();
+            let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
             bt.place_order(&candle, order).unwrap();
             bt.execute_orders(&candle).unwrap();
+            // the market order is always filled
+            assert_eq!(bt.positions.len(), positions + 1);
+        }
+    }
+
+    #[test]
+    fn scenario_run_ticks() {
+        // the stop loss is reached before the take profit within the same candle
+        let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+        let ticks = [
+            Tick::new(100.0, 1.0, OrderSide::Buy, time(0)).unwrap(),
+            Tick::new(94.0, 1.0, OrderSide::Sell, time(10)).unwrap(),
+            Tick::new(106.0, 1.0, OrderSide::Buy, time(20)).unwrap(),
+            Tick::new(103.0, 1.0, OrderSide::Sell, time(70)).unwrap(),
+        ];
+        let interval = chrono::TimeDelta::minutes(1);
+        let candles = Tick::candles(&ticks, interval).unwrap();
+        let mut bt = Backtest::new(Arc::from(candles), 1000.0, None)
+            .unwrap()
+            .with_atr_period(2);
+
+        let mut bars = Vec::new();
+        bt.run_ticks(&ticks, interval, |bt, tick, candle| {
+            bars.push((candle.open_time(), candle.close()));
+            if tick.timestamp() == time(0) {
+                let exit_rule = OrderType::TakeProfitAndStopLoss(105.0, 95.0);
+                let order = Order::from((OrderType::Market(tick.price()), exit_rule, 1.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 995.0);
+        assert_eq!(bars[2], (time(0), 106.0));
+        assert_eq!(bars[3], (time(60), 103.0));
+        // the ATR comes from the candles (true ranges of 12 and 3), not from the ticks
+        assert_eq!(bt.atr(), Some(7.5));
+    }
 
-            assert!(!bt.positions.is_empty());
-            assert_eq!(bt.balance(), 899.0);
-            assert_eq!(bt.total_balance(), 899.0);
-            assert_eq!(bt.free_balance().unwrap(), 1000.0 - expected_total_cost);
+    #[test]
+    fn scenario_run_ticks_calendar() {
+        // 2024-01-05 is a friday, the session is 09:00-10:00 UTC
+        let time = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+        let ticks = [
+            Tick::new(100.0, 1.0, OrderSide::Buy, time("2024-01-05T09:00:00Z")).unwrap(),
+            Tick::new(102.0, 1.0, OrderSide::Buy, time("2024-01-05T09:40:00Z")).unwrap(),
+            Tick::new(90.0, 1.0, OrderSide::Sell, time("2024-01-05T10:05:00Z")).unwrap(),
+        ];
+        let interval = TimeDelta::minutes(30);
+        let candles: Arc<[Candle]> = Arc::from(Tick::candles(&ticks, interval).unwrap());
+        let session = |flatten_at_close| {
+            let open = chrono::NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+            let close = chrono::NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+            TradingCalendar::new(open, close).with_flatten_at_close(flatten_at_close)
+        };
+        let strategy = |bt: &mut Backtest, tick: &Tick, candle: &Candle| {
+            if tick.timestamp() == time("2024-01-05T09:00:00Z") {
+                let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 95.0);
+                let order = Order::from((OrderType::Market(tick.price()), exit_rule, 1.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+            }
+            Ok(())
+        };
+
+        // the stop loss is not reached by the tick after the close
+        let mut bt = Backtest::new(Arc::clone(&candles), 1000.0, None)
+            .unwrap()
+            .with_calendar(session(false));
+        bt.run_ticks(&ticks, interval, strategy).unwrap();
+        assert_eq!(bt.positions.len(), 1);
+
+        // the position is closed at the close of the last candle of the session
+        let mut bt = Backtest::new(candles, 1000.0, None)
+            .unwrap()
+            .with_calendar(session(true));
+        bt.run_ticks(&ticks, interval, strategy).unwrap();
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.balance(), 1002.0);
+    }
 
-            let candle = bt.next().unwrap();
-            bt.execute_positions(&candle).unwrap(); // close = 110, p&l brut = +10
-            assert!(!bt.positions.is_empty());
+    #[test]
+    fn scenario_stop_order() {
+        // the first candle high is 110, the second candle high is 119
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
 
-            let candle = bt.next().unwrap();
-            bt.execute_positions(&candle).unwrap(); // close = 120, take profit
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Stop(115.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.orders.len(), 1);
+        assert_eq!(bt.free_balance().unwrap(), 885.0);
 
-            assert!(bt.positions.is_empty());
-            assert_eq!(bt.balance(), 1018.0); // balance = 1020 - (1 * 2) (fees)
-            assert_eq!(bt.total_balance(), 1018.0);
-            assert_eq!(bt.free_balance().unwrap(), 1018.0);
-        }
+        // next tick, the buy-stop triggers and becomes a market order
+        let candle = bt.next().unwrap();
+        bt.execute_orders(&candle).unwrap();
+        assert!(bt.orders.is_empty());
+        let position = bt.positions.front().unwrap();
+        assert_eq!(position.entry_type(), &OrderType::Market(115.0));
+        assert_eq!(bt.balance(), 885.0);
+
+        // the sell-stop is below the open, so the gap fills it at the open
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+        let candle = bt.next().unwrap();
+        let order = Order::from((OrderType::Stop(95.0), 1.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        let position = bt.positions.front().unwrap();
+        assert_eq!(position.entry_price().unwrap(), 90.0);
+        assert_eq!(bt.balance(), 910.0);
+        assert_eq!(bt.free_balance().unwrap(), 910.0);
     }
 
     #[test]
-    fn scenario_open_position_with_market_fees() {
-        let data = get_long_data();
+    fn scenario_open_long_position_with_trailing_stop_loss() {
+        // enter at 100
+        // the high is 100 and the trailing stop is set to 10%
+        // exit at 90
+        let data = get_long_data_trailing_stop_loss();
         let balance = 1000.0;
-        let market_fee = 1.0; // 1%
-        let mut bt = Backtest::new(data, balance, Some((market_fee, 1.0))).unwrap();
+        let mut bt = Backtest::new(data, balance, None).unwrap();
 
         let candle = bt.next().unwrap();
-        let price = candle.close(); // 100
-        let take_profit = OrderType::TakeProfitAndStopLoss(price.addpercent(20.0), 0.0);
-        let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Buy));
-
-        let open_fee = price * 1.0 * (market_fee / 100.0);
-        let expected_total_cost = price + open_fee; // 100 + 1.0% = 101.0
+        let price = candle.close();
 
+        let trailing_stop = OrderType::TrailingStop(price, 10.0);
+        let order = Order::from((OrderType::Market(price), trailing_stop, 1.0, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
         bt.execute_orders(&candle).unwrap();
 
         assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 899.0);
-        assert_eq!(bt.total_balance(), 899.0);
-        assert_eq!(bt.free_balance().unwrap(), 1000.0 - expected_total_cost);
+        assert_eq!(bt.balance(), 900.0);
+        assert_eq!(bt.total_balance(), 900.0);
+        assert_eq!(bt.free_balance().unwrap(), 900.0);
 
-        let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 110, p&l brut = +10
+        bt.execute_positions(&candle).unwrap();
         assert!(!bt.positions.is_empty());
 
+        // next tick
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 120, take profit
+        bt.execute_positions(&candle).unwrap();
 
         assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1018.0); // balance = 1020 - (1 * 2) (fees)
-        assert_eq!(bt.total_balance(), 1018.0);
-        assert_eq!(bt.free_balance().unwrap(), 1018.0);
+        assert_eq!(bt.balance(), 990.0);
+        assert_eq!(bt.total_balance(), 990.0);
+        assert_eq!(bt.free_balance().unwrap(), 990.0);
     }
 
     #[test]
-    fn scenario_place_and_delete_auto_a_market_order() {
-        let data = get_data();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
-
+    fn scenario_order_time_in_force() {
+        let data = get_short_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
         let candle = bt.next().unwrap();
-        let price = candle.close(); // 110
-
-        let order = Order::from((OrderType::Market(price * 3.0), 1.0, OrderSide::Buy));
-        bt.place_order(&candle, order).unwrap(); // lock amount 110
 
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 670.0);
+        // good-till-cancelled limit rests
+        let gtc = Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, gtc).unwrap();
+        // good-till-date limit rests until the date
+        let expiration = candle.open_time() + chrono::Duration::days(1);
+        let gtd = Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy))
+            .with_time_in_force(TimeInForce::Gtd(expiration));
+        bt.place_order(&candle, gtd).unwrap();
+        // immediate-or-cancel limit is cancelled when it is not filled
+        let ioc = Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy)).with_time_in_force(TimeInForce::Ioc);
+        bt.place_order(&candle, ioc).unwrap();
+        // fill-or-kill is cancelled when the volume can't absorb the quantity
+        let fok = Order::from((OrderType::Limit(140.0), 2.0, OrderSide::Buy)).with_time_in_force(TimeInForce::Fok);
+        bt.place_order(&candle, fok).unwrap();
+        assert_eq!(bt.free_balance().unwrap(), 420.0);
 
         bt.execute_orders(&candle).unwrap();
+        assert_eq!(bt.orders.len(), 2);
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.free_balance().unwrap(), 800.0);
 
-        assert!(bt.orders.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+        let expired = Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy))
+            .with_time_in_force(TimeInForce::Gtd(candle.open_time() - chrono::Duration::days(1)));
+        bt.place_order(&candle, expired).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        assert_eq!(bt.orders.len(), 2);
+        assert_eq!(bt.free_balance().unwrap(), 800.0);
     }
 
     #[test]
-    fn scenario_place_and_delete_order() {
-        let data = get_data();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
-
-        let candle = bt.next().unwrap();
-        let price = candle.close(); // 110
+    fn scenario_fill_model() {
+        let cases = [
+            (FillModel::Optimistic, 1011.0),
+            (FillModel::OpenHighLowClose, 1011.0),
+            (FillModel::OpenLowHighClose, 999.0),
+            (FillModel::Pessimistic, 999.0),
+        ];
+
+        for (fill_model, balance) in cases {
+            let data = get_data();
+            let mut bt = Backtest::new(data, 1000.0, None).unwrap().with_fill_model(fill_model);
+            let candle = bt.next().unwrap();
 
-        let order = Order::from((OrderType::Market(price), 1.0, OrderSide::Buy));
-        bt.place_order(&candle, order).unwrap(); // lock amount 110
+            // take profit and stop loss are both inside the candle
+            let order = Order::from((
+                OrderType::Market(100.0),
+                OrderType::TakeProfitAndStopLoss(111.0, 99.0),
+                1.0,
+                OrderSide::Buy,
+            ));
+            bt.place_order(&candle, order).unwrap();
+            bt.execute_orders(&candle).unwrap();
+            bt.execute_positions(&candle).unwrap();
 
-        assert!(!bt.orders.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 890.0);
+            assert!(bt.positions.is_empty());
+            assert_eq!(bt.balance(), balance);
+        }
+    }
 
-        bt.delete_order(&candle, &order, true).unwrap(); // unlock amount 110
+    #[test]
+    fn scenario_base_inventory_and_fee_currency() {
+        let cases = [
+            (FeeCurrency::Quote, 1.0, 1007.9),
+            (FeeCurrency::Received, 0.99, 1007.811),
+        ];
+
+        for (fee_currency, base, balance) in cases {
+            let data = get_data();
+            let mut bt = Backtest::new(data, 1000.0, Some((1.0, 1.0)))
+                .unwrap()
+                .with_base_balance(0.5)
+                .unwrap()
+                .with_fee_currency(fee_currency);
+            let candle = bt.next().unwrap();
 
-        assert!(bt.orders.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+            let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+            bt.place_order(&candle, order).unwrap();
+            bt.execute_orders(&candle).unwrap();
+            // the quote is converted to base
+            assert!((bt.base_balance() - 0.5 - base).abs() < 1e-9);
+            assert_eq!(bt.fees_paid(), 1.0);
+
+            let position = *bt.positions().next().unwrap();
+            bt.close_position(&candle, &position, 110.0, true).unwrap();
+            // the base is converted back to quote
+            assert!((bt.base_balance() - 0.5).abs() < 1e-9);
+            assert!((bt.balance() - balance).abs() < 1e-9);
+
+            bt.reset();
+            assert_eq!(bt.base_balance(), 0.5);
+        }
     }
 
     #[test]
-    fn scenario_open_long_position_and_take_profit() {
-        let data = get_long_data();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
-
+    fn scenario_exchange_profile() {
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_exchange_profile(ExchangeProfile::binance_usdm_futures());
+        assert_eq!(bt.fee_currency(), &FeeCurrency::Quote);
         let candle = bt.next().unwrap();
-        let price = candle.close();
 
-        let take_profit = OrderType::TakeProfitAndStopLoss(price.addpercent(20.0), 0.0);
-        let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Buy));
+        // 0.5 × 100 is below the 100 USDT minimum notional
+        let order = Order::from((OrderType::Market(100.0), 0.5, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(&candle, order),
+            Err(crate::errors::Error::MinNotional(50.0, 100.0))
+        ));
+        assert_eq!(bt.orders().count(), 0);
+        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        // taker fee of 0.05%
+        assert!((bt.fees_paid() - 0.05).abs() < 1e-9);
+    }
 
-        assert!(!bt.orders.is_empty());
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+    #[test]
+    fn scenario_exchange_profile_rules() {
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_exchange_profile(ExchangeProfile::binance_spot());
+        let candle = bt.next().unwrap();
 
-        bt.execute_orders(&candle).unwrap();
+        // rounded to the 0.01 tick and down to the 0.00001 lot
+        let order = Order::from((OrderType::Limit(100.016), 0.123456789, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        let placed = bt.orders().next().unwrap();
+        assert_eq!(placed.entry_price().unwrap(), 100.02);
+        assert_eq!(placed.quantity(), 0.12345);
+
+        // 0.04 × 100 is below the 5 USDT minimum notional
+        let order = Order::from((OrderType::Limit(100.0), 0.04, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(&candle, order),
+            Err(crate::errors::Error::MinNotional(4.0, 5.0))
+        ));
+        // below the lot size
+        let order = Order::from((OrderType::Limit(100.0), 0.000001, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(&candle, order),
+            Err(crate::errors::Error::LotSize(_, _))
+        ));
+        assert_eq!(bt.orders().count(), 1);
+    }
 
-        assert!(bt.orders.is_empty());
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 900.0);
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+    #[test]
+    fn scenario_exchange_profile_session() {
+        let candle = |open_time: &str| {
+            let open_time = DateTime::parse_from_rfc3339(open_time).unwrap().to_utc();
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(111.0)
+                .low(99.0)
+                .close(110.0)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + TimeDelta::minutes(30))
+                .build()
+                .unwrap()
+        };
+        // 2024-01-05 is a friday, the session opens at 14:30 UTC
+        let data = Arc::from_iter(vec![candle("2024-01-05T13:00:00Z"), candle("2024-01-05T15:00:00Z")]);
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_exchange_profile(ExchangeProfile::equities());
+        assert!(bt.calendar().is_some());
+
+        let before = bt.next().unwrap();
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&before, order).unwrap();
+        bt.execute_orders(&before).unwrap();
+        // queued to the open
+        assert_eq!(bt.positions().count(), 0);
+
+        let open = bt.next().unwrap();
+        bt.execute_orders(&open).unwrap();
+        assert_eq!(bt.positions().count(), 1);
+    }
 
-        bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
+    #[test]
+    fn scenario_exchange_profile_funding() {
+        let candle = CandleBuilder::builder()
+            .open(100.0)
+            .high(111.0)
+            .low(99.0)
+            .close(110.0)
+            .volume(1.0)
+            .open_time(DateTime::from_timestamp_secs(0).unwrap())
+            .close_time(DateTime::from_timestamp_secs(86400).unwrap())
+            .build()
+            .unwrap();
+        let data = Arc::from_iter(vec![candle; 2]);
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_exchange_profile(ExchangeProfile::binance_usdm_futures());
 
-        // next tick
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 110, p&l = +10
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.close_candle(&candle).unwrap();
+        // taker fee of 0.05, then 3 payments of 0.01% of the 110 notional at 08:00, 16:00 and 24:00
+        assert!((bt.fees_paid() - 0.083).abs() < 1e-9);
+        let position = *bt.positions.front().unwrap();
+        assert!((position.fees() - 0.083).abs() < 1e-9);
+        bt.close_position(&candle, &position, 110.0, true).unwrap();
+
+        // the short positions receive the funding
+        let fees_paid = bt.fees_paid();
+        let order = Order::from((OrderType::Market(110.0), 1.0, OrderSide::Sell));
+        bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        let taker = bt.fees_paid() - fees_paid;
+        bt.close_candle(&candle).unwrap();
+        assert!((bt.fees_paid() - fees_paid - taker + 0.033).abs() < 1e-9);
+    }
 
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 910.0); // balance + p&l
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn scenario_equity_sampling() {
+        use crate::metrics::{EquitySampling, Event};
+
+        let candle = get_data()[0];
+        let day = chrono::TimeDelta::days(1);
+        let data = (0..6)
+            .map(|i| {
+                CandleBuilder::builder()
+                    .open(candle.open())
+                    .high(candle.high())
+                    .low(candle.low())
+                    .close(candle.close())
+                    .volume(candle.volume())
+                    .open_time(candle.open_time() + day * (i / 2))
+                    .close_time(candle.close_time() + day * (i / 2))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Arc<[Candle]>>();
+        let cases = [
+            // placement, opening and closing
+            (EquitySampling::EveryTrade, 18),
+            (EquitySampling::EveryCandle, 6),
+            // after the 4th candle, then the last one
+            (EquitySampling::EveryNCandles(4), 2),
+            // the first candle of each day, then the last one
+            (EquitySampling::Daily, 4),
+        ];
+
+        for (equity_sampling, marks) in cases {
+            let mut bt = Backtest::new(Arc::clone(&data), 1000.0, None)
+                .unwrap()
+                .with_equity_sampling(equity_sampling);
+            // open and close a position on each candle
+            bt.run(|bt, candle| {
+                let exit = OrderType::TakeProfitAndStopLoss(candle.high(), 0.0);
+                bt.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())
+            })
+            .unwrap();
 
-        // next tick
-        let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 120, take profit matched
+            let updates = bt.events().filter(|e| matches!(e, Event::WalletUpdate { .. }));
+            assert_eq!(updates.count(), marks);
+            let last = bt.events().rev().find(|e| matches!(e, Event::WalletUpdate { .. }));
+            assert!(matches!(last, Some(Event::WalletUpdate { balance, .. }) if *balance == 1066.0));
+        }
+    }
 
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1020.0);
-        assert_eq!(bt.total_balance(), 1020.0);
-        assert_eq!(bt.free_balance().unwrap(), 1020.0);
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn scenario_on_event() {
+        use std::sync::Mutex;
+
+        use crate::metrics::Event;
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let mut bt = Backtest::new(get_data(), 1000.0, None).unwrap().on_event({
+            let observed = Arc::clone(&observed);
+            move |event: &Event| observed.lock().unwrap().push(*event)
+        });
+        bt.run(|bt, candle| {
+            let exit = OrderType::TakeProfitAndStopLoss(111.0, 0.0);
+            bt.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())
+        })
+        .unwrap();
+
+        let observed = observed.lock().unwrap();
+        assert_eq!(observed.as_slice(), bt.events().copied().collect::<Vec<_>>().as_slice());
+        assert!(observed.iter().any(|e| matches!(e, Event::DelPosition(..))));
     }
 
     #[test]
-    fn scenario_open_long_position_and_stop_loss() {
-        let data = get_short_data();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
+    #[cfg(feature = "metrics")]
+    fn scenario_lifecycle_statuses() {
+        use crate::metrics::Event;
 
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
         let candle = bt.next().unwrap();
-        let price = candle.close();
 
-        let stop_loss = OrderType::TakeProfitAndStopLoss(0.0, price - 20.0);
-        let order = Order::from((OrderType::Market(price), stop_loss, 1.0, OrderSide::Buy));
-        bt.place_order(&candle, order).unwrap();
-
-        assert!(!bt.orders.is_empty());
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 860.0);
+        let filled = Order::from((
+            OrderType::Market(100.0),
+            OrderType::TakeProfitAndStopLoss(111.0, 0.0),
+            1.0,
+            OrderSide::Buy,
+        ));
+        bt.place_order(&candle, filled).unwrap();
+        let cancelled = Order::from((OrderType::Limit(90.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, cancelled).unwrap();
+        bt.delete_order(&candle, &cancelled, true).unwrap();
+        let rejected = Order::from((OrderType::Limit(90.0), 100.0, OrderSide::Buy));
+        assert!(bt.place_order(&candle, rejected).is_err());
+        assert_eq!(bt.orders().next().unwrap().status(), &OrderStatus::Pending);
 
         bt.execute_orders(&candle).unwrap();
+        let position = bt.positions().next().unwrap();
+        assert_eq!(position.status(), &PositionStatus::Open);
+        assert_eq!(position.order().status(), &OrderStatus::Filled);
+        bt.execute_positions(&candle).unwrap();
 
-        assert!(bt.orders.is_empty());
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 860.0);
-        assert_eq!(bt.total_balance(), 860.0);
-        assert_eq!(bt.free_balance().unwrap(), 860.0);
+        let statuses = bt
+            .events()
+            .filter_map(|event| match event {
+                Event::DelOrder(_, order) | Event::OrderRejected(_, order, _) => Some(format!("{:?}", order.status())),
+                Event::AddPosition(_, position) => Some(format!("{:?}", position.order().status())),
+                Event::DelPosition(_, position) => Some(format!("{:?}", position.status())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, ["Cancelled", "Rejected", "Filled", "Closed"]);
+    }
 
-        bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
+    struct TestAggregator;
 
-        // next tick
-        let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 130, p&l = -10
+    impl Aggregation for TestAggregator {
+        fn factors(&self) -> &[usize] {
+            &[1, 2]
+        }
+    }
 
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 860.0);
-        assert_eq!(bt.total_balance(), 850.0); // balance + p&l
-        assert_eq!(bt.free_balance().unwrap(), 860.0);
+    #[test]
+    fn scenario_with_aggregator() {
+        let data = get_short_data();
+        let mut bt = Backtest::new(data, 1.0, None).unwrap();
 
-        // next tick
-        let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 120, stop loss matched
+        let mut ic = 0;
+        let aggregator = TestAggregator;
+        bt.run_with_aggregator(&aggregator, |_, candles| {
+            let candle_one = candles.first();
+            let candle_two = candles.get(1);
 
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 980.0);
-        assert_eq!(bt.total_balance(), 980.0);
-        assert_eq!(bt.free_balance().unwrap(), 980.0);
+            // candle_two is none at ic = 0
+            assert!(candle_one.is_some());
+
+            if ic > 0 {
+                assert!(candle_two.is_some());
+                assert_ne!(candle_one, candle_two);
+            }
+
+            ic += 1;
+
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn scenario_run_from_source() {
+        let strategy = |bt: &mut Backtest, candle: &Candle| {
+            if bt.orders().len() == 0 && bt.positions().len() == 0 {
+                let exit = OrderType::TakeProfitAndStopLoss(candle.close() + 5.0, 0.0);
+                let order = Order::from((OrderType::Market(candle.close()), exit, 1.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+            }
+            Ok(())
+        };
+
+        let data = get_long_data();
+        let mut bt = Backtest::new(Arc::clone(&data), 1000.0, None).unwrap();
+        bt.run(strategy).unwrap();
+        let mut streamed = Backtest::streaming(1000.0, None).unwrap();
+        streamed.run_from_source(data.iter().copied(), strategy).unwrap();
+
+        assert_eq!(streamed.candles().len(), 0);
+        assert_eq!(streamed.total_balance(), bt.total_balance());
+        assert_eq!(streamed.positions().len(), bt.positions().len());
+        assert_eq!(streamed.orders().len(), bt.orders().len());
     }
 
     #[test]
-    fn scenario_open_short_position_and_take_profit() {
-        let data = get_short_data();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
-
+    fn scenario_instrument() {
+        let data = get_long_data();
+        let instrument = Instrument::new("MINI")
+            .with_tick_size(0.5)
+            .with_lot_size(1.0)
+            .with_multiplier(10.0);
+        let mut bt = Backtest::new(data, 10_000.0, None).unwrap().with_instrument(instrument);
         let candle = bt.next().unwrap();
-        let price = candle.close();
 
-        let take_profit = OrderType::TakeProfitAndStopLoss(price - 20.0, 0.0);
-        let order = Order::from((OrderType::Market(price), take_profit, 1.0, OrderSide::Sell));
+        let order = Order::from((OrderType::Market(100.2), 2.5, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
-
-        assert!(!bt.orders.is_empty());
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 860.0);
+        // 2 contracts at 100.0, worth 10 times the price
+        assert_eq!(bt.free_balance().unwrap(), 8000.0);
+        assert!(
+            bt.place_order(&candle, Order::from((OrderType::Market(100.0), 0.5, OrderSide::Buy)))
+                .is_err()
+        );
 
         bt.execute_orders(&candle).unwrap();
+        let position = *bt.positions().next().unwrap();
+        assert_eq!(position.estimate_pnl(110.0).unwrap(), 200.0);
+        // the deleted order is the rounded one
+        let limit = Order::from((OrderType::Limit(90.2), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, limit).unwrap();
+        bt.delete_order(&candle, &limit, true).unwrap();
 
-        assert!(bt.orders.is_empty());
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 860.0);
-        assert_eq!(bt.total_balance(), 860.0);
-        assert_eq!(bt.free_balance().unwrap(), 860.0);
-
-        bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
+        let candle = bt.next().unwrap();
+        let pnl = bt.close_position(&candle, &position, 110.0, true).unwrap();
+        assert_eq!(pnl, 200.0);
+        assert_eq!(bt.balance(), 10_200.0);
+        assert_eq!(bt.free_balance().unwrap(), 10_200.0);
+    }
 
-        // next tick
+    #[test]
+    fn scenario_amend_order() {
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 130, p&l = +10
 
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 860.0);
-        assert_eq!(bt.total_balance(), 870.0); // balance + p&l
-        assert_eq!(bt.free_balance().unwrap(), 860.0);
+        let first = Order::from((OrderType::Limit(90.0), 2.0, OrderSide::Buy));
+        let second = Order::from((OrderType::Limit(90.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, first).unwrap();
+        bt.place_order(&candle, second).unwrap();
+        assert_eq!(bt.free_balance().unwrap(), 730.0);
+
+        // reducing the quantity keeps the time priority
+        bt.amend_order(&candle, &first, 90.0, 1.0).unwrap();
+        let ids = bt.orders_at_price(90.0).map(Order::id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![first.id(), second.id()]);
+        assert_eq!(bt.free_balance().unwrap(), 820.0);
+
+        // increasing it loses the priority
+        bt.amend_order(&candle, &first, 90.0, 3.0).unwrap();
+        let ids = bt.orders_at_price(90.0).map(Order::id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![second.id(), first.id()]);
+        assert_eq!(bt.free_balance().unwrap(), 640.0);
+
+        // a rejected amendment leaves the order unchanged
+        assert!(matches!(
+            bt.amend_order(&candle, &first, 95.0, 100.0),
+            Err(crate::errors::Error::InsufficientFunds(..))
+        ));
+        assert!(bt.amend_order(&candle, &first, 0.0, 1.0).is_err());
+        assert_eq!(bt.orders_at_price(90.0).count(), 2);
+        assert_eq!(bt.free_balance().unwrap(), 640.0);
+
+        let market = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        bt.place_order(&candle, market).unwrap();
+        assert!(bt.amend_order(&candle, &market, 101.0, 1.0).is_err());
+        assert_eq!(bt.orders().len(), 3);
+    }
 
-        // next tick
+    #[test]
+    fn scenario_trading_calendar() {
+        // hourly candles of Wednesday 2024-07-10, from 08:30 to 17:30 in New York
+        let start = DateTime::parse_from_rfc3339("2024-07-10T12:30:00Z").unwrap().to_utc();
+        let data = (0..10)
+            .map(|i| {
+                let price = 100.0 + i as f64;
+                CandleBuilder::builder()
+                    .open(price)
+                    .high(price + 5.0)
+                    .low(price - 5.0)
+                    .close(price + 1.0)
+                    .volume(1.0)
+                    .open_time(start + TimeDelta::hours(i))
+                    .close_time(start + TimeDelta::hours(i + 1))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Arc<[_]>>();
+
+        // the market order placed before the open is filled at the opening price
+        let calendar = TradingCalendar::new_york().with_flatten_at_close(true);
+        let mut bt = Backtest::new(data.clone(), 1000.0, None)
+            .unwrap()
+            .with_calendar(calendar);
+        bt.run(|bt, candle| {
+            if candle.open_time() == start {
+                let exit = OrderType::TakeProfitAndStopLoss(0.0, 90.0);
+                bt.place_order(
+                    candle,
+                    (OrderType::Market(candle.close()), exit, 1.0, OrderSide::Buy).into(),
+                )?;
+            }
+            if candle.open_time() == start + TimeDelta::hours(2) {
+                assert_eq!(bt.positions().next().unwrap().entry_price().unwrap(), 101.0);
+            }
+            Ok(())
+        })
+        .unwrap();
+        // flattened at the close of the 15:30 candle
+        assert!(bt.positions().next().is_none());
+        assert_eq!(bt.balance(), 1007.0);
+
+        // the orders placed outside the session are rejected
+        let calendar = TradingCalendar::new_york().with_out_of_session(OutOfSession::Reject);
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap().with_calendar(calendar);
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 120, take profit matched
+        let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(&candle, order),
+            Err(crate::errors::Error::SessionClosed(_))
+        ));
+        let candle = bt.next().unwrap();
+        bt.place_order(&candle, order).unwrap();
+    }
 
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1020.0);
-        assert_eq!(bt.total_balance(), 1020.0);
-        assert_eq!(bt.free_balance().unwrap(), 1020.0);
+    #[test]
+    fn scenario_reset_queued_orders() {
+        // hourly candles of Wednesday 2024-07-10, from 08:30 to 17:30 in New York
+        let start = DateTime::parse_from_rfc3339("2024-07-10T12:30:00Z").unwrap().to_utc();
+        let data = (0..10)
+            .map(|i| {
+                let price = 100.0 + i as f64;
+                CandleBuilder::builder()
+                    .open(price)
+                    .high(price + 5.0)
+                    .low(price - 5.0)
+                    .close(price + 1.0)
+                    .volume(1.0)
+                    .open_time(start + TimeDelta::hours(i))
+                    .close_time(start + TimeDelta::hours(i + 1))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Arc<[_]>>();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_calendar(TradingCalendar::new_york())
+            .with_deterministic_ids();
+
+        // the order placed after the close stays queued at the end of the run
+        bt.run(|bt, candle| {
+            if candle.open_time() == start + TimeDelta::hours(9) {
+                let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(bt.orders().len(), 1);
+        bt.reset();
+
+        // the order of the next run has the same id but is placed in session
+        bt.run(|bt, candle| {
+            if candle.open_time() == start + TimeDelta::hours(2) {
+                let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(bt.positions().next().unwrap().entry_price().unwrap(), 103.0);
     }
 
     #[test]
-    fn scenario_open_short_position_and_stop_loss() {
+    fn scenario_excursions() {
         let data = get_long_data();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
 
         let candle = bt.next().unwrap();
-        let price = candle.close();
-
-        let stop_loss = OrderType::TakeProfitAndStopLoss(0.0, price.addpercent(20.0));
-        let order = Order::from((OrderType::Market(price), stop_loss, 1.0, OrderSide::Sell));
+        let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
+        bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        let position = *bt.positions().next().unwrap();
+        assert_eq!(position.mae_distance(), 20.0);
+        assert_eq!(position.mfe_distance(), 10.0);
 
-        assert!(!bt.orders.is_empty());
-        assert!(bt.positions.is_empty());
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap();
+        let position = *bt.positions().next().unwrap();
+        assert_eq!(position.mae(), 40.0);
+        assert_eq!(position.mfe(), 38.0);
+    }
+
+    #[test]
+    fn backtest_builder() {
+        let data = get_data();
+        assert!(matches!(
+            BacktestBuilder::builder().data(data.clone()).build(),
+            Err(crate::errors::Error::MissingField("initial balance"))
+        ));
+        assert!(
+            BacktestBuilder::builder()
+                .initial_balance(1000.0)
+                .fees(0.0, 0.1)
+                .build()
+                .is_err()
+        );
+
+        let bt = BacktestBuilder::builder()
+            .data(data)
+            .initial_balance(1000.0)
+            .exchange_profile(ExchangeProfile::kraken())
+            .fees(0.1, 0.05)
+            .fill_model(FillModel::Pessimistic)
+            .warmup(2)
+            .position_mode(PositionMode::Netting)
+            .build()
+            .unwrap();
         assert_eq!(bt.balance(), 1000.0);
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+        assert_eq!(bt.fee_model().unwrap().fee(&FeeLeg::new(100.0, 1.0, true, 0.0)), 0.1);
+        assert_eq!(bt.exchange_profile().unwrap().name(), "Kraken");
+        assert_eq!(bt.warmup(), 2);
+        assert_eq!(bt.position_mode(), &PositionMode::Netting);
+        assert_eq!(bt.fill_model, FillModel::Pessimistic);
+
+        // without data, the backtest streams its candles
+        let bt = BacktestBuilder::builder().initial_balance(1000.0).build().unwrap();
+        assert!(bt.data.is_empty());
+    }
 
+    #[test]
+    fn scenario_relative_exit_rules() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None)
+            .unwrap()
+            .with_slippage(Slippage::Percent(1.0));
+
+        // the ATR is unknown before the first candle is closed
+        let candle = bt.next().unwrap();
+        let atr_exit = OrderType::TakeProfitAndStopLossAtr(2.0, 1.0);
+        let order = Order::from((OrderType::Market(100.0), atr_exit, 1.0, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(&candle, order),
+            Err(crate::errors::Error::AtrUnavailable)
+        ));
+
+        // the percentages apply to the fill price after slippage (101.0)
+        let pct_exit = OrderType::TakeProfitAndStopLossPct(10.0, 5.0);
+        let order = Order::from((OrderType::Market(100.0), pct_exit, 1.0, OrderSide::Buy));
+        bt.place_order(&candle, order).unwrap();
         bt.execute_orders(&candle).unwrap();
+        let position = *bt.positions().next().unwrap();
+        assert_eq!(position.entry_price().unwrap(), 101.0);
+        let Some(&OrderType::TakeProfitAndStopLoss(take_profit, stop_loss)) = position.exit_rule() else {
+            panic!("the exit rule should be resolved");
+        };
+        assert!((take_profit - 111.1).abs() < 1e-9);
+        assert!((stop_loss - 95.95).abs() < 1e-9);
+
+        // the ATR supplied for the candle
+        bt.set_atr(30.0);
+        bt.modify_exit_rule(&candle, &position, OrderType::TakeProfitAndStopLossAtr(1.0, 0.0))
+            .unwrap();
+        let position = bt.positions().next().unwrap();
+        assert_eq!(
+            position.exit_rule(),
+            Some(&OrderType::TakeProfitAndStopLoss(131.0, 0.0))
+        );
+    }
 
-        assert!(bt.orders.is_empty());
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 900.0);
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+    #[test]
+    fn scenario_add_to_position() {
+        let data = get_long_data();
+        let risk_manager = RiskManager::default().with_max_open_positions(1);
+        let mut bt = Backtest::new(data, 1000.0, Some((1.0, 1.0)))
+            .unwrap()
+            .with_risk_manager(risk_manager);
 
-        bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
+        let candle = bt.next().unwrap();
+        let exit = OrderType::TakeProfitAndStopLoss(0.0, 50.0);
+        bt.place_order(&candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())
+            .unwrap();
+        bt.execute_orders(&candle).unwrap();
+        let position = *bt.positions().next().unwrap();
+        assert_eq!(bt.balance(), 899.0);
 
-        // next tick
+        // the scale-in does not count as a new position for the risk manager
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 110, p&l = -10
+        bt.add_to_position(&candle, &position, 3.0, 120.0).unwrap();
+        let increased = *bt.positions().next().unwrap();
+        assert_eq!(increased.id(), position.id());
+        assert_eq!(increased.quantity(), 4.0);
+        assert_eq!(increased.entry_price().unwrap(), 115.0);
+        assert_eq!(increased.exit_rule(), Some(&exit));
+        // 360.0 added and 3.6 of fees on the added notional
+        assert_eq!(increased.fees(), 4.6);
+        assert_eq!(bt.balance(), 535.4);
+        #[cfg(feature = "metrics")]
+        assert!(
+            bt.events()
+                .any(|e| matches!(e, crate::metrics::Event::IncreasePosition(_, p) if p.quantity() == 4.0))
+        );
+
+        assert!(bt.add_to_position(&candle, &position, 0.0, 120.0).is_err());
+        assert!(bt.add_to_position(&candle, &position, 100.0, 120.0).is_err());
+        bt.close_position(&candle, &increased, 125.0, true).unwrap();
+        assert!(bt.add_to_position(&candle, &position, 1.0, 120.0).is_err());
+    }
 
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 890.0); // balance + p&l
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+    #[test]
+    fn scenario_timeframe() {
+        let candle = |hours: i64| {
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(105.0)
+                .volume(1.0)
+                .open_time(DateTime::default() + TimeDelta::hours(hours))
+                .close_time(DateTime::default() + TimeDelta::hours(hours + 1))
+                .build()
+                .unwrap()
+        };
+        let regular = [candle(0), candle(1), candle(2), candle(3)];
+        let irregular = [candle(0), candle(1), candle(3), candle(4), candle(5)];
+
+        // the most frequent interval, even with a gap
+        let bt = Backtest::new(Arc::from_iter(irregular), 1000.0, None).unwrap();
+        assert_eq!(bt.timeframe(), Some(TimeDelta::hours(1)));
+        assert!(matches!(
+            bt.with_strict_timeframe(),
+            Err(crate::errors::Error::IrregularSpacing(_, elapsed, _)) if elapsed == TimeDelta::hours(2)
+        ));
+        let bt = Backtest::new(Arc::from_iter(regular), 1000.0, None).unwrap();
+        assert!(bt.with_strict_timeframe().unwrap().strict_timeframe());
+
+        // inferred from the first candles of a source
+        let mut bt = Backtest::streaming(1000.0, None).unwrap();
+        assert_eq!(bt.timeframe(), None);
+        bt.run_from_source(regular.iter().copied(), |_, _| Ok(())).unwrap();
+        assert_eq!(bt.timeframe(), Some(TimeDelta::hours(1)));
+
+        let mut bt = Backtest::streaming(1000.0, None)
+            .unwrap()
+            .with_strict_timeframe()
+            .unwrap();
+        let mut seen = 0;
+        let result = bt.run_from_source(irregular.iter().copied(), |_, _| {
+            seen += 1;
+            Ok(())
+        });
+        assert!(matches!(result, Err(crate::errors::Error::IrregularSpacing(..))));
+        // the strategy never sees the candle after the gap
+        assert_eq!(seen, 2);
+        bt.reset();
+        bt.run_from_source(regular.iter().copied(), |_, _| Ok(())).unwrap();
+    }
 
-        // next tick
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn scenario_order_rejected() {
+        use crate::metrics::{Event, RejectReason};
+
+        let data = get_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap(); // close = 120, stop loss matched
 
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 980.0);
-        assert_eq!(bt.total_balance(), 980.0);
-        assert_eq!(bt.free_balance().unwrap(), 980.0);
+        let too_large = Order::from((OrderType::Limit(90.0), 100.0, OrderSide::Buy));
+        assert!(bt.place_order(&candle, too_large).is_err());
+        let bad_exit = Order::from((
+            OrderType::Market(100.0),
+            OrderType::TakeProfitAndStopLossAtr(2.0, 1.0),
+            1.0,
+            OrderSide::Buy,
+        ));
+        assert!(bt.place_order(&candle, bad_exit).is_err());
+
+        let rejections = bt
+            .events()
+            .filter_map(|event| match event {
+                Event::OrderRejected(_, order, reason) => Some((order.id(), *order.status(), *reason)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rejections,
+            [
+                (
+                    too_large.id(),
+                    OrderStatus::Rejected,
+                    RejectReason::InsufficientFunds(9000.0, 1000.0)
+                ),
+                (bad_exit.id(), OrderStatus::Rejected, RejectReason::InvalidExitRule),
+            ]
+        );
+        assert_eq!(
+            rejections[0].2.to_string(),
+            "insufficient funds: required 9000, available 1000"
+        );
+        assert_eq!(bt.orders().len(), 0);
     }
 
     #[test]
-    fn scenario_open_long_position_with_trailing_stop_profit() {
-        // enter at 100
-        // the high is 140 and the trailing stop is set to 10%
-        // exit at 126
-        let data = get_long_data_trailing_stop();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
+    fn scenario_equity() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
 
         let candle = bt.next().unwrap();
-        let price = candle.close();
-
-        let trailing_stop = OrderType::TrailingStop(price, 10.0);
-        let order = Order::from((OrderType::Market(price), trailing_stop, 1.0, OrderSide::Buy));
+        let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
-        bt.execute_orders(&candle).unwrap();
-
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 900.0);
+        assert_eq!(bt.locked(), 100.0);
         assert_eq!(bt.free_balance().unwrap(), 900.0);
+        assert_eq!(bt.equity(), 1000.0);
 
+        bt.execute_orders(&candle).unwrap();
         bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
+        assert_eq!(bt.locked(), 0.0);
+        assert_eq!(bt.equity(), 1000.0);
 
-        // next tick
+        // close = 110, p&l = +10
         let candle = bt.next().unwrap();
         bt.execute_positions(&candle).unwrap();
-
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 908.0);
+        assert_eq!(bt.unrealized_pnl(), 10.0);
         assert_eq!(bt.free_balance().unwrap(), 900.0);
+        assert_eq!(bt.equity(), 1010.0);
+    }
+
+    #[test]
+    fn scenario_close_position_by_id() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
 
-        // next tick
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 935.0);
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+        for _ in 0..2 {
+            let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+            bt.place_order(&candle, order).unwrap();
+        }
+        bt.execute_orders(&candle).unwrap();
+        let ids = bt.positions().map(|p| p.id()).collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(bt.position_by_id(ids[1]).map(|p| p.id()), Some(ids[1]));
+        assert!(bt.position_by_id(u32::MAX).is_none());
 
-        // next tick
         let candle = bt.next().unwrap();
-        bt.execute_positions(&candle).unwrap();
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1026.0);
-        assert_eq!(bt.total_balance(), 1026.0);
-        assert_eq!(bt.free_balance().unwrap(), 1026.0);
+        let pnl = bt.close_position_by_id(&candle, ids[1], 110.0).unwrap();
+        assert_eq!(pnl, 10.0);
+        assert_eq!(bt.positions().count(), 1);
+        assert!(bt.position_by_id(ids[1]).is_none());
+        assert_eq!(bt.balance(), 910.0);
+
+        assert!(matches!(
+            bt.close_position_by_id(&candle, ids[1], 110.0),
+            Err(crate::errors::Error::PositionNotFound)
+        ));
+        assert!(matches!(
+            bt.close_position_by_id(&candle, ids[0], 0.0),
+            Err(crate::errors::Error::ExitPrice(_))
+        ));
+        assert_eq!(bt.positions().count(), 1);
     }
 
     #[test]
-    fn scenario_open_long_position_with_trailing_stop_loss() {
-        // enter at 100
-        // the high is 100 and the trailing stop is set to 10%
-        // exit at 90
-        let data = get_long_data_trailing_stop_loss();
-        let balance = 1000.0;
-        let mut bt = Backtest::new(data, balance, None).unwrap();
+    fn scenario_duration_aggregation() {
+        assert!(matches!(
+            DurationAggregation::new(&[]),
+            Err(crate::errors::Error::InvalidFactor)
+        ));
+        assert!(matches!(
+            DurationAggregation::new(&[TimeDelta::zero()]),
+            Err(crate::errors::Error::InvalidFactor)
+        ));
+
+        // 15-minute candles, the one at 01:45 is missing
+        let start = DateTime::from_timestamp_secs(1704067200).unwrap();
+        let data = [0, 15, 30, 45, 60, 75, 90, 120, 135, 150, 165]
+            .into_iter()
+            .map(|minutes| {
+                let open_time = start + TimeDelta::minutes(minutes);
+                CandleBuilder::builder()
+                    .open(100.0)
+                    .high(110.0)
+                    .low(90.0)
+                    .close(105.0)
+                    .volume(1.0)
+                    .open_time(open_time)
+                    .close_time(open_time + TimeDelta::minutes(15))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Arc<[_]>>();
+
+        let aggregator = DurationAggregation::new(&[TimeDelta::minutes(15), TimeDelta::hours(1)]).unwrap();
+        assert_eq!(aggregator.factors(), &[0, 1]);
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+        let mut hours = Vec::new();
+        bt.run_with_aggregator(&aggregator, |_, candles| {
+            assert_eq!(candles[0].close_time() - candles[0].open_time(), TimeDelta::minutes(15));
+            hours.push(
+                candles
+                    .get(1)
+                    .map(|c| ((c.open_time() - start).num_minutes(), c.volume())),
+            );
+            Ok(())
+        })
+        .unwrap();
 
-        let candle = bt.next().unwrap();
-        let price = candle.close();
+        let first = Some((0, 4.0));
+        let second = Some((60, 3.0));
+        assert_eq!(
+            hours,
+            [
+                None,
+                None,
+                None,
+                first,
+                first,
+                first,
+                first,
+                second,
+                second,
+                second,
+                Some((120, 4.0))
+            ]
+        );
+    }
 
-        let trailing_stop = OrderType::TrailingStop(price, 10.0);
-        let order = Order::from((OrderType::Market(price), trailing_stop, 1.0, OrderSide::Buy));
+    #[test]
+    fn scenario_gap_fill() {
+        let candle = |day: i64, open: f64, close: f64| {
+            let open_time = DateTime::from_timestamp_secs(1515151515).unwrap() + TimeDelta::days(day);
+            CandleBuilder::builder()
+                .open(open)
+                .high(open.max(close) + 1.0)
+                .low(open.min(close) - 1.0)
+                .close(close)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + TimeDelta::days(1))
+                .build()
+                .unwrap()
+        };
+        // the second candle opens at 110, over the exit levels
+        let data = Arc::from_iter([candle(0, 100.0, 100.0), candle(1, 110.0, 112.0)]);
+
+        // (side, exit rule, gap fill, p&l)
+        let cases = [
+            (
+                OrderSide::Buy,
+                OrderType::TakeProfitAndStopLoss(105.0, 0.0),
+                GapFill::Open,
+                10.0,
+            ),
+            (
+                OrderSide::Buy,
+                OrderType::TakeProfitAndStopLoss(105.0, 0.0),
+                GapFill::GuaranteedStop,
+                10.0,
+            ),
+            (
+                OrderSide::Sell,
+                OrderType::TakeProfitAndStopLoss(0.0, 105.0),
+                GapFill::Open,
+                -10.0,
+            ),
+            (
+                OrderSide::Sell,
+                OrderType::TakeProfitAndStopLoss(0.0, 105.0),
+                GapFill::GuaranteedStop,
+                -5.0,
+            ),
+            // the stop trails the low of the first candle, at 99 + 3
+            (
+                OrderSide::Sell,
+                OrderType::TrailingStopDistance(100.0, 3.0),
+                GapFill::Open,
+                -10.0,
+            ),
+            (
+                OrderSide::Sell,
+                OrderType::TrailingStopDistance(100.0, 3.0),
+                GapFill::GuaranteedStop,
+                -2.0,
+            ),
+        ];
+
+        for (side, exit, gap_fill, pnl) in cases {
+            let mut bt = Backtest::new(Arc::clone(&data), 1000.0, None)
+                .unwrap()
+                .with_gap_fill(gap_fill);
+            assert_eq!(bt.gap_fill(), gap_fill);
+
+            let candle = bt.next().unwrap();
+            let order = Order::from((OrderType::Market(100.0), exit, 1.0, side));
+            bt.place_order(&candle, order).unwrap();
+            bt.execute_orders(&candle).unwrap();
+            bt.execute_positions(&candle).unwrap();
+            assert_eq!(bt.positions().count(), 1);
+
+            let candle = bt.next().unwrap();
+            bt.execute_positions(&candle).unwrap();
+            assert_eq!(bt.positions().count(), 0);
+            assert!((bt.balance() - (1000.0 + pnl)).abs() < 1e-9);
+        }
+
+        // a position opened during the candle did not exist at its open
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+        bt.next().unwrap();
+        let candle = bt.next().unwrap();
+        let exit = OrderType::TakeProfitAndStopLoss(0.0, 110.5);
+        let order = Order::from((OrderType::Market(112.0), exit, 1.0, OrderSide::Buy));
         bt.place_order(&candle, order).unwrap();
         bt.execute_orders(&candle).unwrap();
+        bt.execute_positions(&candle).unwrap();
+        assert_eq!(bt.positions().count(), 0);
+        assert_eq!(bt.balance(), 998.5);
+    }
 
-        assert!(!bt.positions.is_empty());
-        assert_eq!(bt.balance(), 900.0);
-        assert_eq!(bt.total_balance(), 900.0);
-        assert_eq!(bt.free_balance().unwrap(), 900.0);
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn scenario_exit_reasons() {
+        let data = get_long_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap();
 
+        let candle = bt.next().unwrap();
+        let exits = [
+            OrderType::TakeProfitAndStopLoss(115.0, 0.0),
+            OrderType::TakeProfitAndStopLoss(0.0, 95.0),
+            OrderType::TrailingStopDistance(100.0, 50.0),
+            OrderType::TrailingStopDistance(100.0, 50.0),
+        ];
+        for exit in exits {
+            let order = Order::from((OrderType::Market(100.0), exit, 1.0, OrderSide::Buy));
+            bt.place_order(&candle, order).unwrap();
+        }
+        // the stop at 95 is reached by the low of the first candle
+        bt.execute_orders(&candle).unwrap();
         bt.execute_positions(&candle).unwrap();
-        assert!(!bt.positions.is_empty());
 
-        // next tick
+        // the take profit at 115 is reached by the high of the second candle
         let candle = bt.next().unwrap();
         bt.execute_positions(&candle).unwrap();
+        let id = bt.positions().next().unwrap().id();
+        bt.close_position_by_id(&candle, id, 110.0).unwrap();
 
-        assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 990.0);
-        assert_eq!(bt.total_balance(), 990.0);
-        assert_eq!(bt.free_balance().unwrap(), 990.0);
+        let candle = bt.next().unwrap();
+        bt.execute_positions(&candle).unwrap();
+        bt.flatten_at_end(&candle).unwrap();
+        assert_eq!(bt.positions().len(), 0);
+
+        let trades = crate::metrics::Metrics::from(&bt).trades();
+        let reasons = trades.iter().map(|t| t.exit_reason()).collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            [
+                ExitReason::StopLoss,
+                ExitReason::TakeProfit,
+                ExitReason::Manual,
+                ExitReason::EndOfData
+            ]
+        );
+        let groups = trades.by_exit_reason();
+        assert_eq!(groups[&ExitReason::TakeProfit].win_rate(), 100.0);
+        assert_eq!(groups[&ExitReason::StopLoss].win_rate(), 0.0);
+        assert_eq!(groups[&ExitReason::EndOfData].net_pnl(), 20.0);
+        assert_eq!(trades.win_rate(), 75.0);
     }
 
-    struct TestAggregator;
-
-    impl Aggregation for TestAggregator {
-        fn factors(&self) -> &[usize] {
-            &[1, 2]
-        }
+    #[test]
+    fn fingerprint_and_provenance() {
+        let provenance = crate::data::Provenance::new("data/btc.csv")
+            .with_symbol("BTCUSDT")
+            .with_timeframe(TimeDelta::hours(1));
+        let bt = Backtest::new(get_long_data(), 1000.0, None)
+            .unwrap()
+            .with_provenance(provenance.clone());
+        assert_eq!(bt.provenance(), Some(&provenance));
+        assert_eq!(bt.fingerprint(), crate::data::fingerprint(&get_long_data()));
+        assert_ne!(
+            bt.fingerprint(),
+            Backtest::new(get_data(), 1000.0, None).unwrap().fingerprint()
+        );
+
+        let bt = BacktestBuilder::builder()
+            .data(get_long_data())
+            .initial_balance(1000.0)
+            .provenance(provenance.clone())
+            .build()
+            .unwrap();
+        assert_eq!(bt.provenance().and_then(|p| p.symbol()), Some("BTCUSDT"));
     }
 
     #[test]
-    fn scenario_with_aggregator() {
-        let data = get_short_data();
-        let mut bt = Backtest::new(data, 1.0, None).unwrap();
+    fn scenario_reject_invalid_order() {
+        let data = get_data();
+        let candle = data.first().unwrap();
+        let mut bt = Backtest::new(data.clone(), 1000.0, None)
+            .unwrap()
+            .with_min_quantity(0.1);
+
+        let nan = Order::from((OrderType::Market(f64::NAN), 1.0, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(candle, nan),
+            Err(crate::errors::Error::InvalidPrice(_))
+        ));
+        let negative = Order::from((OrderType::Limit(100.0), -1.0, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(candle, negative),
+            Err(crate::errors::Error::InvalidQuantity(-1.0))
+        ));
+        let small = Order::from((OrderType::Limit(100.0), 0.05, OrderSide::Buy));
+        assert!(matches!(
+            bt.place_order(candle, small),
+            Err(crate::errors::Error::MinQuantity(0.05, 0.1))
+        ));
+        // nothing is locked by the rejected orders
+        assert_eq!(bt.orders().len(), 0);
+        assert_eq!(bt.free_balance().unwrap(), 1000.0);
 
-        let mut ic = 0;
-        let aggregator = TestAggregator;
-        bt.run_with_aggregator(&aggregator, |_, candles| {
-            let candle_one = candles.first();
-            let candle_two = candles.get(1);
+        let order = Order::from((OrderType::Limit(100.0), 0.1, OrderSide::Buy));
+        assert!(bt.place_order(candle, order).is_ok());
+        assert_eq!(bt.min_quantity(), 0.1);
+    }
 
-            // candle_two is none at ic = 0
-            assert!(candle_one.is_some());
+    #[test]
+    fn scenario_rebalance() {
+        let rebalancer = Rebalancer::new(&[("BTC", 0.5)]).unwrap().with_tolerance(0.1);
+        let mut bt = Backtest::new(get_long_data(), 1000.0, None)
+            .unwrap()
+            .with_position_mode(PositionMode::Netting);
+        let mut placed = Vec::new();
+        bt.run(|bt, candle| {
+            placed.push(bt.rebalance(candle, &rebalancer, "BTC")?);
+            Ok(())
+        })
+        .unwrap();
 
-            if ic > 0 {
-                assert!(candle_two.is_some());
-                assert_ne!(candle_one, candle_two);
-            }
+        // 5 bought at 100, then 550 of 1050 and 600 of 1100 in BTC, within the band
+        let order = placed[0].unwrap();
+        assert_eq!((order.side(), order.quantity()), (&OrderSide::Buy, 5.0));
+        assert!(placed[1..].iter().all(Option::is_none));
+        assert_eq!(bt.positions().next().unwrap().quantity(), 5.0);
 
-            ic += 1;
+        let data = get_data();
+        let mut bt = Backtest::new(data.clone(), 1000.0, None).unwrap();
+        assert!(bt.rebalance(&data[0], &rebalancer, "BTC").is_err());
+    }
+
+    #[test]
+    fn scenario_limit_fill_queue() {
+        let candles = (0..7)
+            .map(|i| {
+                let open_time = DateTime::default() + TimeDelta::hours(i);
+                CandleBuilder::builder()
+                    .open(105.0)
+                    .high(110.0)
+                    .low(100.0)
+                    .close(105.0)
+                    .volume(10.0)
+                    .open_time(open_time)
+                    .close_time(open_time + TimeDelta::hours(1))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Arc<[_]>>();
+        let mut bt = Backtest::new(candles, 1000.0, None)
+            .unwrap()
+            .with_limit_fill(LimitFill::Queue(0.1))
+            .unwrap();
 
+        // a queue of 1 ahead, then the quantity of 2, traded by 1 per touch from the next candle; the
+        // second order also waits for the quantity of the first one
+        let mut filled_on = Vec::new();
+        bt.run(|bt, candle| {
+            if bt.candle_index == 0 {
+                let order = Order::from((OrderType::Limit(100.0), 2.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+                let order = Order::from((OrderType::Limit(100.0), 3.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+                // reducing the quantity keeps the place in the queue
+                bt.amend_order(candle, &order, 100.0, 2.0)?;
+            }
+            if bt.positions().len() > filled_on.len() {
+                filled_on.push(bt.candle_index);
+            }
             Ok(())
         })
         .unwrap();
+
+        // filled by the third and the fifth touches after the placement, seen by the strategy on the
+        // next candle
+        assert_eq!(filled_on, vec![4, 6]);
+        assert_eq!(bt.positions().len(), 2);
+        assert!(bt.queue_volumes.is_empty());
+
+        for share in [-0.1, 0.0, 1.5, f64::NAN] {
+            let bt = Backtest::new(get_data(), 1000.0, None).unwrap();
+            assert!(matches!(
+                bt.with_limit_fill(LimitFill::Queue(share)),
+                Err(crate::errors::Error::InvalidQueueShare(_))
+            ));
+        }
     }
 }
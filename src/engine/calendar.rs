@@ -0,0 +1,248 @@
+use super::Candle;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc, Weekday};
+
+/// Daylight saving time rule of a trading calendar.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DstRule {
+    /// One hour ahead from the second Sunday of March to the first Sunday of November, at 02:00 local time.
+    UnitedStates,
+
+    /// One hour ahead from the last Sunday of March to the last Sunday of October, at 01:00 UTC.
+    Europe,
+}
+
+impl DstRule {
+    /// Returns `true` if the daylight saving time is in effect.
+    ///
+    /// ### Arguments
+    /// * `utc` - The UTC date.
+    /// * `standard` - The same date in the standard local time.
+    fn is_active(&self, utc: NaiveDateTime, standard: NaiveDateTime) -> bool {
+        let sunday = |year, month, n| NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Sun, n);
+        let at = |date: Option<NaiveDate>, hour| date.and_then(|date| date.and_hms_opt(hour, 0, 0));
+        let (start, end, now) = match self {
+            //? the clocks go back at 02:00 daylight time, which is 01:00 standard time
+            Self::UnitedStates => {
+                let year = standard.year();
+                (at(sunday(year, 3, 2), 2), at(sunday(year, 11, 1), 1), standard)
+            }
+            Self::Europe => {
+                let year = utc.year();
+                let last = |month| sunday(year, month, 5).or_else(|| sunday(year, month, 4));
+                (at(last(3), 1), at(last(10), 1), utc)
+            }
+        };
+        matches!((start, end), (Some(start), Some(end)) if start <= now && now < end)
+    }
+}
+
+/// Policy for the orders placed while the session is closed.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutOfSession {
+    /// The order waits for the next open; a market order is then filled at the opening price.
+    #[default]
+    Queue,
+
+    /// The order is rejected with `Error::SessionClosed`.
+    Reject,
+}
+
+/// Trading calendar enforced by the engine: session hours in local time, weekends and holidays.
+///
+/// Attach it with `Backtest::with_calendar`. No order is filled and no exit rule is triggered while
+/// the session is closed, the orders placed outside the session are queued to the next open or
+/// rejected (see [`OutOfSession`]), and the positions can be closed automatically on the last candle
+/// of each session.
+///
+/// The session hours are in the local time of the exchange, given by its standard UTC offset and
+/// its daylight saving time rule. The trading days are the weekdays that are not holidays. The candles
+/// must be shorter than a session (e.g., intraday candles).
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, NaiveDate};
+///
+/// let calendar = TradingCalendar::new_york()
+///     .with_holiday(NaiveDate::from_ymd_opt(2024, 7, 4).unwrap())
+///     .with_flatten_at_close(true);
+/// // 09:30 in New York is 13:30 UTC in summer
+/// let open = DateTime::parse_from_rfc3339("2024-07-03T13:30:00Z").unwrap().to_utc();
+/// assert!(calendar.is_open(open));
+/// let holiday = DateTime::parse_from_rfc3339("2024-07-04T15:00:00Z").unwrap().to_utc();
+/// assert!(!calendar.is_open(holiday));
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingCalendar {
+    open: NaiveTime,
+    close: NaiveTime,
+    utc_offset: TimeDelta,
+    dst: Option<DstRule>,
+    holidays: Vec<NaiveDate>,
+    out_of_session: OutOfSession,
+    flatten_at_close: bool,
+}
+
+impl TradingCalendar {
+    /// Creates a calendar trading on weekdays between two times (UTC).
+    ///
+    /// ### Arguments
+    /// * `open` - The opening time.
+    /// * `close` - The closing time (before the opening time for a session overnight).
+    pub fn new(open: NaiveTime, close: NaiveTime) -> Self {
+        Self {
+            open,
+            close,
+            utc_offset: TimeDelta::zero(),
+            dst: None,
+            holidays: Vec::new(),
+            out_of_session: OutOfSession::default(),
+            flatten_at_close: false,
+        }
+    }
+
+    /// US equities regular session: 09:30-16:00 New York time.
+    pub fn new_york() -> Self {
+        let open = NaiveTime::from_hms_opt(9, 30, 0).unwrap_or_default();
+        let close = NaiveTime::from_hms_opt(16, 0, 0).unwrap_or_default();
+        Self::new(open, close).with_timezone(TimeDelta::hours(-5), Some(DstRule::UnitedStates))
+    }
+
+    /// London Stock Exchange regular session: 08:00-16:30 London time.
+    pub fn london() -> Self {
+        let open = NaiveTime::from_hms_opt(8, 0, 0).unwrap_or_default();
+        let close = NaiveTime::from_hms_opt(16, 30, 0).unwrap_or_default();
+        Self::new(open, close).with_timezone(TimeDelta::zero(), Some(DstRule::Europe))
+    }
+
+    /// Sets the local time of the session hours.
+    ///
+    /// ### Arguments
+    /// * `utc_offset` - The standard offset from UTC (e.g., -5 hours for New York).
+    /// * `dst` - The daylight saving time rule, if any.
+    pub fn with_timezone(mut self, utc_offset: TimeDelta, dst: Option<DstRule>) -> Self {
+        self.utc_offset = utc_offset;
+        self.dst = dst;
+        self
+    }
+
+    /// Adds a holiday, a weekday without session.
+    pub fn with_holiday(mut self, date: NaiveDate) -> Self {
+        self.holidays.push(date);
+        self
+    }
+
+    /// Adds holidays, weekdays without session.
+    pub fn with_holidays(mut self, dates: impl IntoIterator<Item = NaiveDate>) -> Self {
+        self.holidays.extend(dates);
+        self
+    }
+
+    /// Sets the policy for the orders placed while the session is closed.
+    pub fn with_out_of_session(mut self, out_of_session: OutOfSession) -> Self {
+        self.out_of_session = out_of_session;
+        self
+    }
+
+    /// Closes all the positions at the close of the last candle of each session.
+    pub fn with_flatten_at_close(mut self, flatten_at_close: bool) -> Self {
+        self.flatten_at_close = flatten_at_close;
+        self
+    }
+
+    /// Returns the opening time, in local time.
+    pub fn open(&self) -> NaiveTime {
+        self.open
+    }
+
+    /// Returns the closing time, in local time.
+    pub fn close(&self) -> NaiveTime {
+        self.close
+    }
+
+    /// Returns the holidays.
+    pub fn holidays(&self) -> &[NaiveDate] {
+        &self.holidays
+    }
+
+    /// Returns the policy for the orders placed while the session is closed.
+    pub fn out_of_session(&self) -> OutOfSession {
+        self.out_of_session
+    }
+
+    /// Returns `true` if the positions are closed at the end of each session.
+    pub fn flatten_at_close(&self) -> bool {
+        self.flatten_at_close
+    }
+
+    /// Converts a UTC date to the local time of the calendar.
+    pub fn local_time(&self, datetime: DateTime<Utc>) -> NaiveDateTime {
+        let utc = datetime.naive_utc();
+        let standard = utc + self.utc_offset;
+        match self.dst {
+            Some(dst) if dst.is_active(utc, standard) => standard + TimeDelta::hours(1),
+            _ => standard,
+        }
+    }
+
+    /// Returns `true` if the session is open at the given date.
+    pub fn is_open(&self, datetime: DateTime<Utc>) -> bool {
+        let local = self.local_time(datetime);
+        let trading_day =
+            !matches!(local.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains(&local.date());
+        let time = local.time();
+        let in_hours = if self.open <= self.close {
+            self.open <= time && time < self.close
+        } else {
+            time >= self.open || time < self.close
+        };
+        trading_day && in_hours
+    }
+
+    /// Returns `true` if the candle is the last one of a session: it opens within the session and
+    /// closes when the session is closed.
+    pub fn closes_session(&self, candle: &Candle) -> bool {
+        self.is_open(candle.open_time()) && !self.is_open(candle.close_time())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn calendar_sessions() {
+    let utc = |date: &str| DateTime::parse_from_rfc3339(date).unwrap().to_utc();
+    let calendar = TradingCalendar::new_york().with_holiday(NaiveDate::from_ymd_opt(2024, 12, 25).unwrap());
+
+    // winter (UTC-5) and summer (UTC-4) opens
+    assert!(!calendar.is_open(utc("2024-01-10T14:29:00Z")));
+    assert!(calendar.is_open(utc("2024-01-10T14:30:00Z")));
+    assert!(calendar.is_open(utc("2024-07-10T13:30:00Z")));
+    assert!(!calendar.is_open(utc("2024-07-10T20:00:00Z")));
+    // the daylight saving time starts on 2024-03-10 and ends on 2024-11-03
+    assert!(!calendar.is_open(utc("2024-03-08T13:30:00Z")));
+    assert!(calendar.is_open(utc("2024-03-11T13:30:00Z")));
+    assert!(calendar.is_open(utc("2024-11-01T13:30:00Z")));
+    assert!(!calendar.is_open(utc("2024-11-04T13:30:00Z")));
+    // weekend and holiday
+    assert!(!calendar.is_open(utc("2024-07-13T15:00:00Z")));
+    assert!(!calendar.is_open(utc("2024-12-25T15:00:00Z")));
+
+    let london = TradingCalendar::london();
+    assert!(!london.is_open(utc("2024-03-29T07:30:00Z")));
+    assert!(london.is_open(utc("2024-04-02T07:30:00Z")));
+    assert_eq!(
+        london.local_time(utc("2024-10-28T12:00:00Z")).time(),
+        NaiveTime::from_hms_opt(12, 0, 0).unwrap()
+    );
+
+    // overnight session, e.g., futures from 18:00 to 17:00
+    let overnight = TradingCalendar::new(
+        NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+    );
+    assert!(overnight.is_open(utc("2024-07-10T02:00:00Z")));
+    assert!(!overnight.is_open(utc("2024-07-10T17:30:00Z")));
+}
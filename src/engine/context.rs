@@ -0,0 +1,123 @@
+use super::Candle;
+
+use chrono::TimeDelta;
+
+/// History of the run given to the strategy by `Backtest::run_with_context`.
+///
+/// It exposes the index of the current candle and the candles before it, so the strategy does not
+/// need to keep its own history buffer.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candles = (0..3)
+///     .map(|i| {
+///         CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(100.0 + i as f64)
+///             .volume(1.0)
+///             .open_time(DateTime::default() + Duration::days(i))
+///             .close_time(DateTime::default() + Duration::days(i + 1))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Vec<_>>();
+///
+/// let mut bts = Backtest::new(Arc::from(candles), 1000.0, None).unwrap();
+/// bts.run_with_context(|_bts, ctx| {
+///     if ctx.index() == 2 {
+///         assert_eq!(ctx.back(1).unwrap().close(), 101.0);
+///         assert_eq!(ctx.history().len(), 2);
+///         assert_eq!(ctx.elapsed(), Duration::days(2));
+///     }
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    candles: &'a [Candle],
+}
+
+impl<'a> Context<'a> {
+    /// Creates the context of the last candle of `candles`.
+    pub(crate) fn new(candles: &'a [Candle]) -> Self {
+        Self { candles }
+    }
+
+    /// Returns the index of the current candle (0 for the first one).
+    pub fn index(&self) -> usize {
+        self.candles.len().saturating_sub(1)
+    }
+
+    /// Returns the current candle.
+    pub fn candle(&self) -> &'a Candle {
+        &self.candles[self.index()]
+    }
+
+    /// Returns the candles before the current one, in chronological order.
+    pub fn history(&self) -> &'a [Candle] {
+        &self.candles[..self.index()]
+    }
+
+    /// Returns the candle `n` candles before the current one (0 for the current one).
+    pub fn back(&self, n: usize) -> Option<&'a Candle> {
+        self.index().checked_sub(n).map(|index| &self.candles[index])
+    }
+
+    /// Returns the last `n` candles up to the current one, in chronological order (fewer at the
+    /// start of the run).
+    pub fn candles_back(&self, n: usize) -> &'a [Candle] {
+        &self.candles[self.candles.len().saturating_sub(n)..]
+    }
+
+    /// Returns the time elapsed since the open of the first candle.
+    pub fn elapsed(&self) -> TimeDelta {
+        self.candle().open_time() - self.candles[0].open_time()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn context_lookbacks() {
+    use super::CandleBuilder;
+    use chrono::DateTime;
+
+    let candles = (0..5)
+        .map(|i| {
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(100.0 + i as f64)
+                .volume(1.0)
+                .open_time(DateTime::default() + TimeDelta::hours(i))
+                .close_time(DateTime::default() + TimeDelta::hours(i + 1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let ctx = Context::new(&candles[..1]);
+    assert_eq!(ctx.index(), 0);
+    assert!(ctx.history().is_empty());
+    assert!(ctx.back(1).is_none());
+    assert_eq!(ctx.candles_back(3).len(), 1);
+    assert_eq!(ctx.elapsed(), TimeDelta::zero());
+
+    let ctx = Context::new(&candles[..4]);
+    assert_eq!(ctx.index(), 3);
+    assert_eq!(ctx.candle().close(), 103.0);
+    assert_eq!(ctx.history().len(), 3);
+    assert_eq!(ctx.back(0).unwrap().close(), 103.0);
+    assert_eq!(ctx.back(3).unwrap().close(), 100.0);
+    let closes = ctx.candles_back(2).iter().map(Candle::close).collect::<Vec<_>>();
+    assert_eq!(closes, vec![102.0, 103.0]);
+    assert_eq!(ctx.elapsed(), TimeDelta::hours(3));
+}
@@ -0,0 +1,172 @@
+use super::Candle;
+use crate::errors::{Error, Result};
+
+use chrono::{DateTime, Utc};
+
+/// Rates of a currency pair: the price of one unit of the base currency in the quote currency.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+enum Rates {
+    Fixed(f64),
+    Series(Vec<(DateTime<Utc>, f64)>),
+}
+
+impl Rates {
+    /// Returns the last known rate at the given date, or the first rate before the series starts.
+    fn at(&self, datetime: DateTime<Utc>) -> Option<f64> {
+        match self {
+            Self::Fixed(rate) => Some(*rate),
+            Self::Series(series) => {
+                let known = series.partition_point(|(time, _)| *time <= datetime);
+                series.get(known.saturating_sub(1)).map(|(_, rate)| *rate)
+            }
+        }
+    }
+}
+
+/// Conversion rates of the quote currencies into the account currency.
+///
+/// Attach them with `Backtest::with_conversion_rates` to backtest an instrument quoted in another
+/// currency than the account (e.g., USD/JPY with an account in USD): the net P&L of each closed
+/// position is converted at the rate known when it is closed and accumulated in
+/// `Wallet::account_pnl`, so the results of several backtests quoted in different currencies can be
+/// summed.
+///
+/// The rate of a pair is either fixed or a series of rates over time (e.g., the candles of the pair),
+/// in which case the last rate known at the conversion date is used. A pair converts in both
+/// directions: the rates of EUR/USD also convert USD into EUR.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let usdjpy = CandleBuilder::builder()
+///     .open(150.0)
+///     .high(161.0)
+///     .low(149.0)
+///     .close(160.0)
+///     .volume(1.0)
+///     .open_time(DateTime::default())
+///     .close_time(DateTime::default() + Duration::days(1))
+///     .build()
+///     .unwrap();
+///
+/// let rates = ConversionRates::new("USD")
+///     .with_rate("EUR", "USD", 1.1)
+///     .with_candles("USD", "JPY", &[usdjpy]);
+/// assert_eq!(rates.rate("EUR", DateTime::default()).unwrap(), 1.1);
+/// // the open is known at the open time and the close at the close time
+/// assert_eq!(rates.rate("JPY", DateTime::default()).unwrap(), 1.0 / 150.0);
+/// assert_eq!(rates.rate("JPY", DateTime::default() + Duration::days(1)).unwrap(), 1.0 / 160.0);
+/// assert!(rates.rate("GBP", DateTime::default()).is_err());
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionRates {
+    account_currency: String,
+    pairs: Vec<(String, String, Rates)>,
+}
+
+impl ConversionRates {
+    /// Creates conversion rates into the account currency, without any pair.
+    pub fn new(account_currency: &str) -> Self {
+        Self {
+            account_currency: account_currency.to_string(),
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Adds a fixed rate: the price of one `base` in `quote` (e.g., 1.1 for EUR/USD).
+    pub fn with_rate(mut self, base: &str, quote: &str, rate: f64) -> Self {
+        self.pairs
+            .push((base.to_string(), quote.to_string(), Rates::Fixed(rate)));
+        self
+    }
+
+    /// Adds the rates of a pair over time, sorted by date.
+    pub fn with_series(
+        mut self,
+        base: &str,
+        quote: &str,
+        series: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+    ) -> Self {
+        let mut series = series.into_iter().collect::<Vec<_>>();
+        series.sort_by_key(|(time, _)| *time);
+        self.pairs
+            .push((base.to_string(), quote.to_string(), Rates::Series(series)));
+        self
+    }
+
+    /// Adds the rates of a pair from its candles: the open is known from the open time of a candle
+    /// and the close from its close time, so there is no look-ahead.
+    pub fn with_candles(self, base: &str, quote: &str, candles: &[Candle]) -> Self {
+        let series = candles
+            .iter()
+            .flat_map(|c| [(c.open_time(), c.open()), (c.close_time(), c.close())]);
+        self.with_series(base, quote, series)
+    }
+
+    /// Returns the account currency.
+    pub fn account_currency(&self) -> &str {
+        &self.account_currency
+    }
+
+    /// Returns the rate converting one unit of `currency` into the account currency at a date.
+    ///
+    /// ### Returns
+    /// The rate (1.0 for the account currency itself), or an error if no pair converts the currency.
+    pub fn rate(&self, currency: &str, datetime: DateTime<Utc>) -> Result<f64> {
+        let account = self.account_currency.as_str();
+        if currency == account {
+            return Ok(1.0);
+        }
+        self.pairs
+            .iter()
+            .find_map(|(base, quote, rates)| match (base.as_str(), quote.as_str()) {
+                (base, quote) if base == currency && quote == account => rates.at(datetime),
+                (base, quote) if base == account && quote == currency => rates.at(datetime).map(|rate| 1.0 / rate),
+                _ => None,
+            })
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .ok_or_else(|| Error::MissingConversionRate(currency.to_string(), account.to_string()))
+    }
+
+    /// Converts an amount of `currency` into the account currency at a date.
+    pub fn convert(&self, amount: f64, currency: &str, datetime: DateTime<Utc>) -> Result<f64> {
+        Ok(amount * self.rate(currency, datetime)?)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn conversion_rates() {
+    use chrono::Duration;
+
+    let start = DateTime::default();
+    let rates = ConversionRates::new("USD").with_rate("EUR", "USD", 1.25).with_series(
+        "USD",
+        "JPY",
+        [(start + Duration::days(1), 200.0), (start, 100.0)],
+    );
+
+    assert_eq!(rates.account_currency(), "USD");
+    assert_eq!(rates.rate("USD", start).unwrap(), 1.0);
+    assert_eq!(rates.convert(100.0, "EUR", start).unwrap(), 125.0);
+    // the series is sorted, and the last known rate is used
+    assert_eq!(rates.convert(1000.0, "JPY", start + Duration::hours(12)).unwrap(), 10.0);
+    assert_eq!(rates.convert(1000.0, "JPY", start + Duration::days(2)).unwrap(), 5.0);
+    // the first rate before the series starts
+    assert_eq!(rates.convert(1000.0, "JPY", start - Duration::days(1)).unwrap(), 10.0);
+    assert!(matches!(
+        rates.rate("GBP", start),
+        Err(Error::MissingConversionRate(from, to)) if from == "GBP" && to == "USD"
+    ));
+    // a rate of zero cannot convert
+    assert!(
+        ConversionRates::new("USD")
+            .with_rate("EUR", "USD", 0.0)
+            .rate("EUR", start)
+            .is_err()
+    );
+}
@@ -0,0 +1,146 @@
+use crate::engine::{Candle, OrderSide};
+
+/// Synthetic order book depth built from the volume profile of the candle.
+///
+/// A share of the candle volume is taken as the liquidity resting in the book and spread evenly over
+/// price levels: the range of the candle (high - low) is split into `levels` steps, each holding
+/// `volume × share / levels`. A market order walks the levels from its price against the trader,
+/// upwards for a buy and downwards for a sell, and is filled at the average price of the levels it
+/// consumes. The book continues beyond the candle range with the same depth, so an order larger than
+/// the liquidity of the range still moves its price further.
+///
+/// A candle without range uses steps of one basis point of the price, and a candle without volume
+/// leaves the price unchanged.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candle = CandleBuilder::builder()
+///     .open(100.0)
+///     .high(110.0)
+///     .low(90.0)
+///     .close(105.0)
+///     .volume(100.0)
+///     .open_time(DateTime::default())
+///     .close_time(DateTime::default() + Duration::days(1))
+///     .build()
+///     .unwrap();
+///
+/// // 10 levels of 2.0 holding 5 units each
+/// let depth = MarketDepth::new(10, 0.5);
+/// assert_eq!(depth.fill_price(100.0, 5.0, &candle, &OrderSide::Buy), 100.0);
+/// // the second half is filled one level above
+/// assert_eq!(depth.fill_price(100.0, 10.0, &candle, &OrderSide::Buy), 101.0);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketDepth {
+    levels: usize,
+    share: f64,
+}
+
+impl MarketDepth {
+    /// Creates a depth model.
+    ///
+    /// ### Arguments
+    /// * `levels` - The number of price levels in the candle range (at least 1).
+    /// * `share` - The share of the candle volume resting in the book (e.g., 0.1 for 10%).
+    pub fn new(levels: usize, share: f64) -> Self {
+        Self {
+            levels: levels.max(1),
+            share: share.max(0.0),
+        }
+    }
+
+    /// Returns the number of price levels in the candle range.
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    /// Returns the share of the candle volume resting in the book.
+    pub fn share(&self) -> f64 {
+        self.share
+    }
+
+    /// Returns the liquidity of each price level during the candle.
+    pub fn level_depth(&self, candle: &Candle) -> f64 {
+        candle.volume() * self.share / self.levels as f64
+    }
+
+    /// Returns the price step between two levels during the candle.
+    pub fn step(&self, price: f64, candle: &Candle) -> f64 {
+        let range = candle.high() - candle.low();
+        if range > 0.0 {
+            range / self.levels as f64
+        } else {
+            price.abs() / 10_000.0
+        }
+    }
+
+    /// Returns the average fill price of a market order of `quantity` at `price`, adjusted against
+    /// the trader.
+    pub fn fill_price(&self, price: f64, quantity: f64, candle: &Candle, side: &OrderSide) -> f64 {
+        let depth = self.level_depth(candle);
+        if depth <= 0.0 || quantity <= 0.0 {
+            return price;
+        }
+
+        //? `full` levels are consumed entirely, level `k` being `k` steps away, and the rest is taken
+        //? from the next one
+        let full = (quantity / depth).floor();
+        let rest = quantity - full * depth;
+        let steps = (depth * full * (full - 1.0) / 2.0 + rest * full) / quantity;
+        let impact = steps * self.step(price, candle);
+        match side {
+            OrderSide::Buy => price + impact,
+            OrderSide::Sell => price - impact,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn depth_fill_price() {
+    use chrono::DateTime;
+
+    let candle = |high: f64, low: f64, volume: f64| {
+        crate::engine::CandleBuilder::builder()
+            .open(100.0)
+            .high(high)
+            .low(low)
+            .close(100.0)
+            .volume(volume)
+            .open_time(DateTime::default())
+            .close_time(DateTime::default() + chrono::Duration::days(1))
+            .build()
+            .unwrap()
+    };
+
+    // 10 levels of 1.0 holding 10 units each
+    let depth = MarketDepth::new(10, 1.0);
+    let bar = candle(105.0, 95.0, 100.0);
+    assert_eq!(depth.level_depth(&bar), 10.0);
+    assert_eq!(depth.fill_price(100.0, 10.0, &bar, &OrderSide::Buy), 100.0);
+    // 10 @ 100 and 5 @ 101
+    assert!((depth.fill_price(100.0, 15.0, &bar, &OrderSide::Buy) - 1505.0 / 15.0).abs() < 1e-9);
+    // 10 @ 100, 10 @ 99 and 10 @ 98
+    assert_eq!(depth.fill_price(100.0, 30.0, &bar, &OrderSide::Sell), 99.0);
+    // beyond the range: 200 units walk 20 levels, 9.5 steps on average
+    assert_eq!(depth.fill_price(100.0, 200.0, &bar, &OrderSide::Buy), 109.5);
+
+    // a larger order gets a worse average price
+    let small = depth.fill_price(100.0, 50.0, &bar, &OrderSide::Buy);
+    let large = depth.fill_price(100.0, 500.0, &bar, &OrderSide::Buy);
+    assert!(100.0 < small && small < large);
+
+    // without range, one basis point per level
+    let flat = depth.fill_price(100.0, 20.0, &candle(100.0, 100.0, 100.0), &OrderSide::Buy);
+    assert!((flat - 100.005).abs() < 1e-9);
+    // without volume, no impact
+    assert_eq!(
+        depth.fill_price(100.0, 20.0, &candle(105.0, 95.0, 0.0), &OrderSide::Buy),
+        100.0
+    );
+}
@@ -0,0 +1,296 @@
+use crate::engine::{FeeCurrency, Instrument, TradingCalendar};
+use crate::utils::round_to;
+
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, Utc, Weekday};
+
+/// Trading session of an exchange.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Session {
+    /// The market trades around the clock, every day (e.g., crypto).
+    #[default]
+    AlwaysOpen,
+
+    /// The market trades on weekdays between two times (UTC).
+    ///
+    /// ### Arguments
+    /// * `0` - The opening time.
+    /// * `1` - The closing time.
+    Weekdays(NaiveTime, NaiveTime),
+}
+
+impl Session {
+    /// Returns `true` if the market is open at the given date.
+    pub fn is_open(&self, datetime: DateTime<Utc>) -> bool {
+        match self {
+            Self::AlwaysOpen => true,
+            Self::Weekdays(open, close) => {
+                let weekday = !matches!(datetime.weekday(), Weekday::Sat | Weekday::Sun);
+                let time = datetime.time();
+                weekday && *open <= time && time < *close
+            }
+        }
+    }
+}
+
+/// Configuration preset of an exchange: fee schedule, tick/lot rules, funding convention and session.
+///
+/// Apply it with `Backtest::with_exchange_profile`. The fees are the base tier of the exchange,
+/// the tick/lot rules are the ones of its main market (e.g., BTC/USDT) and the funding rate is the
+/// base rate of its perpetual futures; adjust them with the `with_*` methods for other tiers or
+/// instruments.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+///
+/// let profile = ExchangeProfile::binance_spot().with_fees(0.075, 0.075);
+/// assert_eq!(profile.round_quantity(0.123456789), 0.12345);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeProfile {
+    name: String,
+    maker_fee: f64,
+    taker_fee: f64,
+    fee_currency: FeeCurrency,
+    tick_size: f64,
+    lot_size: f64,
+    min_notional: f64,
+    funding_interval: Option<TimeDelta>,
+    funding_rate: f64,
+    session: Session,
+}
+
+impl ExchangeProfile {
+    /// Creates a custom profile.
+    ///
+    /// ### Arguments
+    /// * `name` - The name of the exchange.
+    /// * `maker_fee` - The fee percentage of the limit orders (e.g., 0.1 for 0.1%).
+    /// * `taker_fee` - The fee percentage of the market orders (e.g., 0.1 for 0.1%).
+    pub fn new(name: &str, maker_fee: f64, taker_fee: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            maker_fee,
+            taker_fee,
+            fee_currency: FeeCurrency::Quote,
+            tick_size: 0.0,
+            lot_size: 0.0,
+            min_notional: 0.0,
+            funding_interval: None,
+            funding_rate: 0.0,
+            session: Session::AlwaysOpen,
+        }
+    }
+
+    /// Binance spot (BTC/USDT): 0.1% fees charged in the received asset, 5 USDT minimum notional.
+    pub fn binance_spot() -> Self {
+        Self::new("Binance Spot", 0.1, 0.1)
+            .with_fee_currency(FeeCurrency::Received)
+            .with_rules(0.01, 0.00001, 5.0)
+    }
+
+    /// Binance USD-M futures (BTCUSDT perpetual): 0.02% maker, 0.05% taker, 0.01% funding every 8 hours.
+    pub fn binance_usdm_futures() -> Self {
+        Self::new("Binance USD-M Futures", 0.02, 0.05)
+            .with_rules(0.1, 0.001, 100.0)
+            .with_funding_interval(TimeDelta::hours(8))
+            .with_funding_rate(0.01)
+    }
+
+    /// Bybit USDT perpetual (BTCUSDT): 0.02% maker, 0.055% taker, 0.01% funding every 8 hours.
+    pub fn bybit() -> Self {
+        Self::new("Bybit", 0.02, 0.055)
+            .with_rules(0.1, 0.001, 5.0)
+            .with_funding_interval(TimeDelta::hours(8))
+            .with_funding_rate(0.01)
+    }
+
+    /// Kraken spot (BTC/USD): 0.25% maker, 0.40% taker.
+    pub fn kraken() -> Self {
+        Self::new("Kraken", 0.25, 0.4).with_rules(0.1, 0.0001, 0.5)
+    }
+
+    /// Generic equities broker: 0.05% fees, whole shares, cent ticks, US regular session (14:30-21:00 UTC).
+    pub fn equities() -> Self {
+        let open = NaiveTime::from_hms_opt(14, 30, 0).unwrap_or_default();
+        let close = NaiveTime::from_hms_opt(21, 0, 0).unwrap_or_default();
+        Self::new("Equities", 0.05, 0.05)
+            .with_rules(0.01, 1.0, 0.0)
+            .with_session(Session::Weekdays(open, close))
+    }
+
+    /// Sets the maker and taker fee percentages.
+    pub fn with_fees(mut self, maker_fee: f64, taker_fee: f64) -> Self {
+        self.maker_fee = maker_fee;
+        self.taker_fee = taker_fee;
+        self
+    }
+
+    /// Sets the currency in which the fees are charged.
+    pub fn with_fee_currency(mut self, fee_currency: FeeCurrency) -> Self {
+        self.fee_currency = fee_currency;
+        self
+    }
+
+    /// Sets the tick size, the lot size and the minimum notional (0.0 to disable a rule).
+    pub fn with_rules(mut self, tick_size: f64, lot_size: f64, min_notional: f64) -> Self {
+        self.tick_size = tick_size;
+        self.lot_size = lot_size;
+        self.min_notional = min_notional;
+        self
+    }
+
+    /// Sets the interval between two funding payments (perpetual futures).
+    ///
+    /// The payments are due at the multiples of the interval since 1970-01-01 00:00 UTC (e.g.,
+    /// 00:00, 08:00 and 16:00 for 8 hours).
+    pub fn with_funding_interval(mut self, funding_interval: TimeDelta) -> Self {
+        self.funding_interval = Some(funding_interval);
+        self
+    }
+
+    /// Sets the funding rate percentage paid by the long positions to the short ones at each
+    /// payment (e.g., 0.01 for 0.01%, negative if the shorts pay).
+    pub fn with_funding_rate(mut self, funding_rate: f64) -> Self {
+        self.funding_rate = funding_rate;
+        self
+    }
+
+    /// Sets the trading session.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Returns the name of the exchange.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the fee percentage of the limit orders.
+    pub fn maker_fee(&self) -> f64 {
+        self.maker_fee
+    }
+
+    /// Returns the fee percentage of the market orders.
+    pub fn taker_fee(&self) -> f64 {
+        self.taker_fee
+    }
+
+    /// Returns the currency in which the fees are charged.
+    pub fn fee_currency(&self) -> &FeeCurrency {
+        &self.fee_currency
+    }
+
+    /// Returns the minimum price increment.
+    pub fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    /// Returns the minimum quantity increment.
+    pub fn lot_size(&self) -> f64 {
+        self.lot_size
+    }
+
+    /// Returns the minimum value (price × quantity) of an order.
+    pub fn min_notional(&self) -> f64 {
+        self.min_notional
+    }
+
+    /// Returns the interval between two funding payments, if any.
+    pub fn funding_interval(&self) -> Option<TimeDelta> {
+        self.funding_interval
+    }
+
+    /// Returns the funding rate percentage paid by the long positions at each payment.
+    pub fn funding_rate(&self) -> f64 {
+        self.funding_rate
+    }
+
+    /// Returns the number of funding payments due between two dates (the start excluded).
+    pub fn funding_payments(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+        let Some(interval) = self.funding_interval.map(|i| i.num_milliseconds()).filter(|i| *i > 0) else {
+            return 0;
+        };
+        let slot = |datetime: DateTime<Utc>| datetime.timestamp_millis().div_euclid(interval);
+        (slot(to) - slot(from)).max(0)
+    }
+
+    /// Returns the trading session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the instrument enforcing the tick/lot rules and the minimum notional of the profile.
+    pub fn instrument(&self) -> Instrument {
+        Instrument::new(&self.name)
+            .with_tick_size(self.tick_size)
+            .with_lot_size(self.lot_size)
+            .with_min_notional(self.min_notional)
+    }
+
+    /// Returns the trading calendar of the session, or `None` if the market is always open.
+    pub fn calendar(&self) -> Option<TradingCalendar> {
+        match self.session {
+            Session::AlwaysOpen => None,
+            Session::Weekdays(open, close) => Some(TradingCalendar::new(open, close)),
+        }
+    }
+
+    /// Rounds a price to the nearest tick.
+    pub fn round_price(&self, price: f64) -> f64 {
+        round_to(price, self.tick_size, f64::round)
+    }
+
+    /// Rounds a quantity down to the lot size.
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        round_to(quantity, self.lot_size, f64::floor)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn exchange_profile_rounding() {
+    let profile = ExchangeProfile::binance_spot();
+    assert_eq!(profile.round_price(100.016), 100.02);
+    assert_eq!(profile.round_quantity(0.123456789), 0.12345);
+    assert_eq!(profile.round_quantity(0.3), 0.3);
+    assert_eq!(ExchangeProfile::new("Custom", 0.1, 0.1).round_price(1.2345), 1.2345);
+    assert_eq!(ExchangeProfile::equities().round_quantity(10.9), 10.0);
+}
+
+#[cfg(test)]
+#[test]
+fn exchange_session() {
+    let session = ExchangeProfile::equities().session().to_owned();
+    // 2024-01-05 is a friday
+    let friday = DateTime::parse_from_rfc3339("2024-01-05T15:00:00Z").unwrap().to_utc();
+    assert!(session.is_open(friday));
+    assert!(!session.is_open(friday + TimeDelta::hours(7)));
+    assert!(!session.is_open(friday + TimeDelta::days(1)));
+    assert!(Session::AlwaysOpen.is_open(friday + TimeDelta::days(1)));
+
+    let calendar = ExchangeProfile::equities().calendar().unwrap();
+    assert!(calendar.is_open(friday));
+    assert!(!calendar.is_open(friday + TimeDelta::hours(7)));
+    assert!(ExchangeProfile::binance_spot().calendar().is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn exchange_funding_payments() {
+    let profile = ExchangeProfile::binance_usdm_futures();
+    let midnight = DateTime::parse_from_rfc3339("2024-01-05T00:00:00Z").unwrap().to_utc();
+    // 00:00 is excluded, 08:00 and 16:00 are due
+    assert_eq!(profile.funding_payments(midnight, midnight + TimeDelta::hours(16)), 2);
+    assert_eq!(
+        profile.funding_payments(midnight + TimeDelta::hours(1), midnight + TimeDelta::hours(7)),
+        0
+    );
+    assert_eq!(
+        ExchangeProfile::binance_spot().funding_payments(midnight, midnight + TimeDelta::days(1)),
+        0
+    );
+}
@@ -0,0 +1,290 @@
+use super::PositionSide;
+
+use chrono::TimeDelta;
+
+/// Notional on which the commission of each leg of a position is computed.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeBasis {
+    /// Both legs are charged on the entry notional, so the commission of a position is known as
+    /// soon as it is opened.
+    Entry,
+
+    /// Both legs are charged on the exit notional (exit price × quantity): the opening leg is
+    /// charged on the entry notional and adjusted to the exit notional at the close.
+    Exit,
+
+    /// Each leg is charged on its own notional when it is traded: the opening leg on the entry
+    /// notional and the closing leg on the exit notional, like on exchanges.
+    #[default]
+    BothLegs,
+}
+
+/// One leg (opening or closing) of a position on which a commission is charged.
+///
+/// The price of the leg depends on the `FeeBasis` of the backtest: the entry price for the
+/// opening leg, and the exit price for the closing leg by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeLeg {
+    price: f64,
+    quantity: f64,
+    taker: bool,
+    traded_volume: f64,
+}
+
+impl FeeLeg {
+    /// Creates a new `FeeLeg`.
+    ///
+    /// ### Arguments
+    /// * `price` - The price of the leg.
+    /// * `quantity` - The quantity of the leg.
+    /// * `taker` - Whether the leg comes from a market order (taker) or a limit order (maker).
+    /// * `traded_volume` - The value traded during the run before this leg.
+    pub fn new(price: f64, quantity: f64, taker: bool, traded_volume: f64) -> Self {
+        Self {
+            price,
+            quantity,
+            taker,
+            traded_volume,
+        }
+    }
+
+    /// Returns the price of the leg.
+    pub fn price(&self) -> f64 {
+        self.price
+    }
+
+    /// Returns the quantity of the leg.
+    pub fn quantity(&self) -> f64 {
+        self.quantity
+    }
+
+    /// Returns the value of the leg (price × quantity).
+    pub fn notional(&self) -> f64 {
+        self.price * self.quantity
+    }
+
+    /// Returns true if the leg takes liquidity (market order).
+    pub fn is_taker(&self) -> bool {
+        self.taker
+    }
+
+    /// Returns the value traded during the run before this leg, used by volume-tiered schedules.
+    pub fn traded_volume(&self) -> f64 {
+        self.traded_volume
+    }
+}
+
+/// Commission model charged on each leg of a position.
+///
+/// Implement it for the schedule of a broker not covered by the built-in models:
+/// - `PercentFees`: Maker/taker percentage of the value (the default of `Backtest::new`).
+/// - `PerShareFees`: Amount per unit traded with a minimum per trade.
+/// - `FixedFees`: Fixed commission per trade.
+/// - `TieredFees`: Percentage depending on the value already traded.
+pub trait FeeModel: Send + Sync {
+    /// Returns the commission charged on the leg, in quote currency.
+    fn fee(&self, leg: &FeeLeg) -> f64;
+}
+
+/// Maker/taker percentage of the traded value, with an optional minimum per trade.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PercentFees {
+    taker: f64,
+    maker: f64,
+    minimum: f64,
+}
+
+impl PercentFees {
+    /// Creates a new `PercentFees`.
+    ///
+    /// ### Arguments
+    /// * `taker` - The market order fee percentage (e.g., 0.1 for 0.1%).
+    /// * `maker` - The limit order fee percentage (e.g., 0.05 for 0.05%).
+    pub fn new(taker: f64, maker: f64) -> Self {
+        Self {
+            taker,
+            maker,
+            minimum: 0.0,
+        }
+    }
+
+    /// Sets the minimum commission per trade.
+    pub fn with_minimum(mut self, minimum: f64) -> Self {
+        self.minimum = minimum;
+        self
+    }
+
+    /// Returns the market order fee percentage.
+    pub fn taker(&self) -> f64 {
+        self.taker
+    }
+
+    /// Returns the limit order fee percentage.
+    pub fn maker(&self) -> f64 {
+        self.maker
+    }
+}
+
+impl FeeModel for PercentFees {
+    fn fee(&self, leg: &FeeLeg) -> f64 {
+        let percent = if leg.is_taker() { self.taker } else { self.maker };
+        (leg.notional() * percent / 100.0).max(self.minimum)
+    }
+}
+
+/// Commission per unit traded (e.g., per share), with a minimum per trade.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerShareFees {
+    per_share: f64,
+    minimum: f64,
+}
+
+impl PerShareFees {
+    /// Creates a new `PerShareFees`.
+    ///
+    /// ### Arguments
+    /// * `per_share` - The commission per unit traded (e.g., 0.005).
+    /// * `minimum` - The minimum commission per trade (e.g., 1.0).
+    pub fn new(per_share: f64, minimum: f64) -> Self {
+        Self { per_share, minimum }
+    }
+}
+
+impl FeeModel for PerShareFees {
+    fn fee(&self, leg: &FeeLeg) -> f64 {
+        (leg.quantity() * self.per_share).max(self.minimum)
+    }
+}
+
+/// Fixed commission per trade, whatever its size.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedFees(pub f64);
+
+impl FeeModel for FixedFees {
+    fn fee(&self, _leg: &FeeLeg) -> f64 {
+        self.0
+    }
+}
+
+/// Percentage schedule whose tier depends on the value already traded during the run.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TieredFees {
+    tiers: Vec<(f64, PercentFees)>,
+}
+
+impl TieredFees {
+    /// Creates a new `TieredFees`.
+    ///
+    /// ### Arguments
+    /// * `tiers` - The `(traded volume, fees)` tiers. A leg uses the tier with the highest volume
+    ///   reached, or no fees before the first tier.
+    pub fn new(tiers: Vec<(f64, PercentFees)>) -> Self {
+        let mut tiers = tiers;
+        tiers.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { tiers }
+    }
+}
+
+impl FeeModel for TieredFees {
+    fn fee(&self, leg: &FeeLeg) -> f64 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|(volume, _)| leg.traded_volume() >= *volume)
+            .map_or(0.0, |(_, fees)| fees.fee(leg))
+    }
+}
+
+/// Annualized interest rates charged on the open positions while they are held.
+///
+/// The short rate is the borrow cost of the sold asset and the long rate the margin interest of a
+/// financed long (0.0 for a cash account). The interest accrues on the value of the position at the
+/// close of each candle, pro rata of the candle duration over a year of 365 days.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BorrowRates {
+    short: f64,
+    long: f64,
+}
+
+impl BorrowRates {
+    /// Creates a new `BorrowRates`.
+    ///
+    /// ### Arguments
+    /// * `short` - The annual borrow rate percentage of the short positions (e.g., 5.0 for 5%).
+    /// * `long` - The annual interest rate percentage of the long positions (e.g., 8.0 for 8%).
+    pub fn new(short: f64, long: f64) -> Self {
+        Self { short, long }
+    }
+
+    /// Returns the annual borrow rate percentage of the short positions.
+    pub fn short(&self) -> f64 {
+        self.short
+    }
+
+    /// Returns the annual interest rate percentage of the long positions.
+    pub fn long(&self) -> f64 {
+        self.long
+    }
+
+    /// Returns the interest accrued on a position of `notional` value held during `elapsed`.
+    pub fn interest(&self, side: &PositionSide, notional: f64, elapsed: TimeDelta) -> f64 {
+        let rate = match side {
+            PositionSide::Long => self.long,
+            PositionSide::Short => self.short,
+        };
+        let years = elapsed.num_milliseconds() as f64 / TimeDelta::days(365).num_milliseconds() as f64;
+        notional * rate / 100.0 * years.max(0.0)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn percent_fees() {
+    let fees = PercentFees::new(0.1, 0.05);
+    assert_eq!(fees.fee(&FeeLeg::new(100.0, 10.0, true, 0.0)), 1.0);
+    assert_eq!(fees.fee(&FeeLeg::new(100.0, 10.0, false, 0.0)), 0.5);
+
+    let fees = fees.with_minimum(2.0);
+    assert_eq!(fees.fee(&FeeLeg::new(100.0, 10.0, true, 0.0)), 2.0);
+}
+
+#[cfg(test)]
+#[test]
+fn per_share_and_fixed_fees() {
+    let fees = PerShareFees::new(0.005, 1.0);
+    assert_eq!(fees.fee(&FeeLeg::new(50.0, 100.0, true, 0.0)), 1.0);
+    assert_eq!(fees.fee(&FeeLeg::new(50.0, 1000.0, true, 0.0)), 5.0);
+
+    let fees = FixedFees(4.95);
+    assert_eq!(fees.fee(&FeeLeg::new(50.0, 1000.0, false, 0.0)), 4.95);
+}
+
+#[cfg(test)]
+#[test]
+fn tiered_fees() {
+    let fees = TieredFees::new(vec![
+        (10_000.0, PercentFees::new(0.05, 0.02)),
+        (0.0, PercentFees::new(0.1, 0.05)),
+    ]);
+    assert_eq!(fees.fee(&FeeLeg::new(100.0, 10.0, true, 0.0)), 1.0);
+    assert_eq!(fees.fee(&FeeLeg::new(100.0, 10.0, true, 9_999.0)), 1.0);
+    assert_eq!(fees.fee(&FeeLeg::new(100.0, 10.0, true, 10_000.0)), 0.5);
+}
+
+#[cfg(test)]
+#[test]
+fn borrow_rates() {
+    let rates = BorrowRates::new(36.5, 7.3);
+    assert_eq!(rates.interest(&PositionSide::Short, 1000.0, TimeDelta::days(1)), 1.0);
+    assert!((rates.interest(&PositionSide::Long, 1000.0, TimeDelta::hours(12)) - 0.1).abs() < 1e-12);
+    assert_eq!(
+        BorrowRates::default().interest(&PositionSide::Short, 1000.0, TimeDelta::days(1)),
+        0.0
+    );
+}
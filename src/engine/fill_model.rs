@@ -0,0 +1,181 @@
+use crate::engine::{Candle, OrderSide, PositionSide};
+
+/// Intrabar path assumption used when a take profit and a stop loss are both reached within the same candle.
+///
+/// A candle only gives its open, high, low and close prices, so the order in which the high and the low
+/// were reached is unknown. The fill model decides which exit is executed first.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillModel {
+    /// The take profit is always executed first.
+    #[default]
+    Optimistic,
+    /// The price goes open → high → low → close.
+    OpenHighLowClose,
+    /// The price goes open → low → high → close.
+    OpenLowHighClose,
+    /// The stop loss is always executed first (worst case).
+    Pessimistic,
+}
+
+impl FillModel {
+    /// Returns `true` if the take profit is reached before the stop loss for a position on `side`.
+    pub fn take_profit_first(&self, side: &PositionSide) -> bool {
+        match (self, side) {
+            (Self::Optimistic, _) => true,
+            (Self::Pessimistic, _) => false,
+            (Self::OpenHighLowClose, PositionSide::Long) | (Self::OpenLowHighClose, PositionSide::Short) => true,
+            (Self::OpenHighLowClose, PositionSide::Short) | (Self::OpenLowHighClose, PositionSide::Long) => false,
+        }
+    }
+}
+
+/// Exit price of a position whose exit level is gapped over by the open of a candle.
+///
+/// When a candle opens beyond the exit level of a position held through the open (e.g., a long
+/// position with a stop at 95 and a candle opening at 90), the exit cannot be filled at its level.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GapFill {
+    /// The exit is filled at the open: worse than the level for a stop, better for a take profit.
+    #[default]
+    Open,
+    /// The stops are guaranteed and filled at their level whatever the gap, for comparison with
+    /// the guaranteed stop-loss orders of some brokers. The take profits are still filled at the open.
+    GuaranteedStop,
+}
+
+/// Condition for a resting limit order to be filled by a candle whose range contains its price.
+///
+/// Trading at the limit price does not mean the order was filled: the orders queued before it at the
+/// same level are filled first. The policy makes the limit fills less optimistic than a touch.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LimitFill {
+    /// The order is filled as soon as the price touches it.
+    #[default]
+    Touch,
+    /// The price must trade through the limit price by a distance.
+    ///
+    /// ### Arguments
+    /// * `0` - The distance in price units (e.g., 2 ticks of 0.01 gives 0.02).
+    TradeThrough(f64),
+    /// The order is filled with a probability depending on the candle volume.
+    ///
+    /// The probability is `volume × participation / quantity` capped at 1, so a small order is likely filled
+    /// while a big one rarely is. The draw is derived from the order ID and the candle time, so the runs
+    /// are reproducible with `Backtest::with_deterministic_ids`.
+    ///
+    /// ### Arguments
+    /// * `0` - The share of the candle volume traded at the limit price available to the order (e.g., 0.1 for 10%).
+    VolumeProbability(f64),
+    /// The order waits for the volume queued ahead of it at the limit price to be traded.
+    ///
+    /// The volume traded at a price is estimated as a share of the candle volume. The volume queued ahead
+    /// of the order is the volume traded at a price on the candle it is placed on, plus the quantity of
+    /// the own limit orders resting at the same price before it; each following candle
+    /// touching the limit price then trades its share of volume against the queue, and the order is filled once the
+    /// queue and its own quantity are traded. A candle trading through the limit price fills it at once.
+    /// Amending the price or increasing the quantity of the order sends it to the back of the queue.
+    ///
+    /// ### Arguments
+    /// * `0` - The share of the candle volume traded at a price, in (0, 1] (e.g., 0.1 for 10%).
+    Queue(f64),
+}
+
+impl LimitFill {
+    /// Returns `true` if a limit order touched by a candle is filled.
+    ///
+    /// ### Arguments
+    /// * `price` - The limit price.
+    /// * `quantity` - The quantity of the order.
+    /// * `side` - The side of the order.
+    /// * `candle` - The candle reaching the limit price.
+    /// * `draw` - A uniform sample in `[0, 1)` used by `VolumeProbability`.
+    ///
+    /// With `Queue`, only a candle trading through the limit price is known to fill the order; the
+    /// touches trading the queue are counted by the backtest.
+    pub fn is_filled(&self, price: f64, quantity: f64, side: &OrderSide, candle: &Candle, draw: f64) -> bool {
+        match self {
+            Self::Touch => true,
+            Self::TradeThrough(distance) => match side {
+                OrderSide::Buy => candle.low() <= price - distance,
+                OrderSide::Sell => candle.high() >= price + distance,
+            },
+            Self::Queue(_) => match side {
+                OrderSide::Buy => candle.low() < price,
+                OrderSide::Sell => candle.high() > price,
+            },
+            Self::VolumeProbability(participation) => {
+                let probability = if quantity > 0.0 {
+                    (candle.volume() * participation / quantity).min(1.0)
+                } else {
+                    1.0
+                };
+                draw < probability
+            }
+        }
+    }
+
+    /// Returns the volume to trade at the limit price before an order placed on a candle is filled with
+    /// the `Queue` policy: the volume queued ahead of it, then its own quantity.
+    pub(crate) fn queue_volume(&self, quantity: f64, candle: &Candle) -> Option<f64> {
+        match self {
+            Self::Queue(share) => Some(share * candle.volume() + quantity),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn fill_model_take_profit_first() {
+    let long = PositionSide::Long;
+    let short = PositionSide::Short;
+
+    assert!(FillModel::Optimistic.take_profit_first(&long));
+    assert!(FillModel::Optimistic.take_profit_first(&short));
+    assert!(!FillModel::Pessimistic.take_profit_first(&long));
+    assert!(!FillModel::Pessimistic.take_profit_first(&short));
+    assert!(FillModel::OpenHighLowClose.take_profit_first(&long));
+    assert!(!FillModel::OpenHighLowClose.take_profit_first(&short));
+    assert!(!FillModel::OpenLowHighClose.take_profit_first(&long));
+    assert!(FillModel::OpenLowHighClose.take_profit_first(&short));
+}
+
+#[cfg(test)]
+#[test]
+fn limit_fill_is_filled() {
+    use chrono::DateTime;
+
+    let buy = OrderSide::Buy;
+    let sell = OrderSide::Sell;
+    let candle = crate::engine::CandleBuilder::builder()
+        .open(102.0)
+        .high(105.0)
+        .low(100.0)
+        .close(103.0)
+        .volume(10.0)
+        .open_time(DateTime::default())
+        .close_time(DateTime::default())
+        .build()
+        .unwrap();
+
+    assert!(LimitFill::Touch.is_filled(100.0, 1.0, &buy, &candle, 0.9));
+    assert!(!LimitFill::TradeThrough(0.5).is_filled(100.0, 1.0, &buy, &candle, 0.0));
+    assert!(LimitFill::TradeThrough(0.5).is_filled(100.5, 1.0, &buy, &candle, 0.0));
+    assert!(!LimitFill::TradeThrough(0.5).is_filled(105.0, 1.0, &sell, &candle, 0.0));
+    assert!(LimitFill::TradeThrough(0.5).is_filled(104.5, 1.0, &sell, &candle, 0.0));
+
+    // 10% of a volume of 10 against a quantity of 2 gives a probability of 0.5
+    assert!(LimitFill::VolumeProbability(0.1).is_filled(100.0, 2.0, &buy, &candle, 0.4));
+    assert!(!LimitFill::VolumeProbability(0.1).is_filled(100.0, 2.0, &buy, &candle, 0.6));
+    assert!(LimitFill::VolumeProbability(0.1).is_filled(100.0, 0.5, &buy, &candle, 0.99));
+
+    // the queue is only known to be traded through below the low or above the high
+    assert!(!LimitFill::Queue(0.1).is_filled(100.0, 1.0, &buy, &candle, 0.0));
+    assert!(LimitFill::Queue(0.1).is_filled(100.5, 1.0, &buy, &candle, 0.0));
+    assert!(!LimitFill::Queue(0.1).is_filled(105.0, 1.0, &sell, &candle, 0.0));
+    assert_eq!(LimitFill::Queue(0.1).queue_volume(2.0, &candle), Some(3.0));
+    assert_eq!(LimitFill::Touch.queue_volume(2.0, &candle), None);
+}
@@ -0,0 +1,167 @@
+use super::Order;
+use crate::errors::{Error, Result};
+use crate::utils::round_to;
+
+/// Metadata of the traded instrument: tick size, lot size, minimum notional, contract multiplier and
+/// quote currency.
+///
+/// Attach it with `Backtest::with_instrument`: the placed orders get their prices rounded to the
+/// nearest tick and their quantity rounded down to the lot size, the orders below the lot size or the
+/// minimum notional are rejected, and the cost and the P&L of the positions are scaled by the
+/// contract multiplier.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+///
+/// // E-mini S&P 500 futures: 0.25 index points tick, $50 per point
+/// let es = Instrument::new("ES")
+///     .with_tick_size(0.25)
+///     .with_lot_size(1.0)
+///     .with_multiplier(50.0);
+/// assert_eq!(es.round_price(5001.13), 5001.25);
+/// assert_eq!(es.round_quantity(2.7), 2.0);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    symbol: String,
+    tick_size: f64,
+    lot_size: f64,
+    min_notional: f64,
+    multiplier: f64,
+    quote_currency: String,
+}
+
+impl Instrument {
+    /// Creates an instrument without tick/lot rules, with a multiplier of 1.0 and quoted in USD.
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            tick_size: 0.0,
+            lot_size: 0.0,
+            min_notional: 0.0,
+            multiplier: 1.0,
+            quote_currency: "USD".to_string(),
+        }
+    }
+
+    /// Sets the minimum price increment (0.0 to disable the rule).
+    pub fn with_tick_size(mut self, tick_size: f64) -> Self {
+        self.tick_size = tick_size;
+        self
+    }
+
+    /// Sets the minimum quantity increment (0.0 to disable the rule).
+    pub fn with_lot_size(mut self, lot_size: f64) -> Self {
+        self.lot_size = lot_size;
+        self
+    }
+
+    /// Sets the minimum value (price × quantity × multiplier) of an order (0.0 to disable the rule).
+    pub fn with_min_notional(mut self, min_notional: f64) -> Self {
+        self.min_notional = min_notional;
+        self
+    }
+
+    /// Sets the contract multiplier, the value of one unit of price movement for one contract.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the currency in which the instrument is quoted.
+    pub fn with_quote_currency(mut self, quote_currency: &str) -> Self {
+        self.quote_currency = quote_currency.to_string();
+        self
+    }
+
+    /// Returns the symbol of the instrument.
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Returns the minimum price increment.
+    pub fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    /// Returns the minimum quantity increment.
+    pub fn lot_size(&self) -> f64 {
+        self.lot_size
+    }
+
+    /// Returns the minimum value of an order.
+    pub fn min_notional(&self) -> f64 {
+        self.min_notional
+    }
+
+    /// Returns the contract multiplier.
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    /// Returns the currency in which the instrument is quoted.
+    pub fn quote_currency(&self) -> &str {
+        &self.quote_currency
+    }
+
+    /// Rounds a price to the nearest tick.
+    pub fn round_price(&self, price: f64) -> f64 {
+        round_to(price, self.tick_size, f64::round)
+    }
+
+    /// Rounds a quantity down to the lot size.
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        round_to(quantity, self.lot_size, f64::floor)
+    }
+
+    /// Rounds the prices and the quantity of an order to the valid increments and sets its multiplier.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error if the quantity is below the lot size or the value below the
+    /// minimum notional.
+    pub(crate) fn conform(&self, order: &mut Order) -> Result<()> {
+        let quantity = self.round_quantity(order.quantity());
+        if quantity <= 0.0 {
+            return Err(Error::LotSize(order.quantity(), self.lot_size));
+        }
+        order.set_quantity(quantity);
+        order.round_prices(|price| self.round_price(price));
+        order.set_multiplier(self.multiplier);
+        let value = order.cost()?;
+        if value < self.min_notional {
+            return Err(Error::MinNotional(value, self.min_notional));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn instrument_conform() {
+    use super::{OrderSide, OrderType};
+
+    let instrument = Instrument::new("ES")
+        .with_tick_size(0.25)
+        .with_lot_size(1.0)
+        .with_min_notional(100_000.0)
+        .with_multiplier(50.0);
+    assert_eq!(instrument.quote_currency(), "USD");
+
+    let exit = OrderType::TakeProfitAndStopLoss(5010.1, 0.0);
+    let mut order = Order::from((OrderType::Limit(5000.1), exit, 1.6, OrderSide::Buy));
+    instrument.conform(&mut order).unwrap();
+    assert_eq!(order.entry_price().unwrap(), 5000.0);
+    assert_eq!(order.exit_rule(), Some(&OrderType::TakeProfitAndStopLoss(5010.0, 0.0)));
+    assert_eq!(order.quantity(), 1.0);
+    assert_eq!(order.cost().unwrap(), 250_000.0);
+
+    let mut order = Order::from((OrderType::Limit(5000.0), 0.5, OrderSide::Buy));
+    assert!(matches!(instrument.conform(&mut order), Err(Error::LotSize(0.5, 1.0))));
+    let mut order = Order::from((OrderType::Limit(1000.0), 1.0, OrderSide::Buy));
+    assert!(matches!(
+        instrument.conform(&mut order),
+        Err(Error::MinNotional(50_000.0, 100_000.0))
+    ));
+}
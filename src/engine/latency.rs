@@ -0,0 +1,62 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Delay between the moment an order is placed and the moment it can be filled.
+///
+/// Without latency, an order placed by the strategy on a candle can be filled on that same candle,
+/// although the strategy may have used its close to decide. With latency, the order only reaches
+/// the market on a later candle, and a market order is then filled at the open of that candle.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Latency {
+    /// An order placed on candle `N` can be filled from candle `N + k` onwards.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of candles `k` (0 for no latency).
+    Candles(usize),
+
+    /// An order is sent at the close of the candle it was placed on and reaches the market after
+    /// the duration; it can be filled from the first candle still open at that time.
+    ///
+    /// ### Arguments
+    /// * `0` - The delay (e.g., 200 milliseconds).
+    Duration(TimeDelta),
+}
+
+impl Default for Latency {
+    fn default() -> Self {
+        Self::Candles(0)
+    }
+}
+
+impl Latency {
+    /// Returns true if an order placed on the candle at `placed_index` (closing at `placed_close`)
+    /// has reached the market on the candle at `index` (closing at `close`).
+    pub(crate) fn has_arrived(
+        &self,
+        (placed_index, placed_close): (usize, DateTime<Utc>),
+        (index, close): (usize, DateTime<Utc>),
+    ) -> bool {
+        match self {
+            Self::Candles(k) => index >= placed_index + k,
+            Self::Duration(delay) => close > placed_close + *delay,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn latency_has_arrived() {
+    let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+    let placed = (3, time(60));
+
+    assert!(Latency::default().has_arrived(placed, placed));
+    assert!(!Latency::Candles(2).has_arrived(placed, (4, time(120))));
+    assert!(Latency::Candles(2).has_arrived(placed, (5, time(180))));
+
+    let latency = Latency::Duration(TimeDelta::seconds(90));
+    assert!(!latency.has_arrived(placed, placed));
+    assert!(!latency.has_arrived(placed, (4, time(120))));
+    assert!(latency.has_arrived(placed, (5, time(180))));
+    // even without delay, the order is sent once the candle is closed
+    assert!(!Latency::Duration(TimeDelta::zero()).has_arrived(placed, placed));
+}
@@ -3,18 +3,75 @@
 //! This module provides the fundamental types for backtesting:
 //! - `Order`: Market, limit, and conditional orders.
 //! - `Position`: Open trades with exit rules.
-//! - `Wallet`: Tracks balance, fees, and P&L.
+//! - `PositionMode`: Hedging (independent positions) or netting (fills offset the opposite positions).
+//! - `Wallet`: Tracks balance, base asset inventory, fees, and P&L.
 //! - `Candle`: OHLCV data for backtesting.
+//! - `CandleFrame`: Columnar storage of candles for fast scans of large datasets.
+//! - `Context`: Index and history of the run given to the strategy.
+//! - `Tick`: Single trade for tick-level backtesting.
+//! - `CandleSource`: Candles streamed one at a time (e.g., `CsvCandles` from a file).
+//! - `Anonymizer`: Rescales and time-shifts candle data to share it publicly.
+//! - `Slippage`: Price adjustment applied when orders are filled.
+//! - `Spread`: Bid/ask spread around the candle prices.
+//! - `MarketDepth`: Synthetic order book depth giving the market impact of large orders.
+//! - `FeeModel`: Commission charged on each leg of a position.
+//! - `BorrowRates`: Interest charged on the open short and financed long positions.
+//! - `FillModel`: Intrabar path assumption for exits reached within the same candle.
+//! - `GapFill`: Exit price of the positions whose exit level is gapped over by the open.
+//! - `LimitFill`: Condition for the limit orders to be filled when the price reaches them.
+//! - `Latency`: Delay before the placed orders can be filled.
+//! - `RiskManager`: Portfolio-level limits checked before an order is placed.
+//! - `Rebalancer`: Orders moving the holdings to target weights of the equity.
+//! - `Instrument`: Tick size, lot size, minimum notional and contract multiplier of the traded instrument.
+//! - `ConversionRates`: Rates converting the realized P&L into the account currency.
+//! - `TradingCalendar`: Session hours, weekends and holidays enforced by the engine.
+//! - `ExchangeProfile`: Fee schedule, tick/lot rules, funding convention and session of an exchange.
 //! - `Backtest`: The engine to run the backtest.
 
+mod anonymizer;
+mod book;
 mod bts;
+mod calendar;
 mod candle;
+mod context;
+mod conversion;
+mod depth;
+mod exchange;
+mod fees;
+mod fill_model;
+mod instrument;
+mod latency;
 mod order;
 mod position;
+mod rebalance;
+mod risk;
+mod slippage;
+mod source;
+mod spread;
+mod tick;
 mod wallet;
 
+pub use anonymizer::*;
+pub(crate) use book::OrderBook;
 pub use bts::*;
+pub use calendar::{DstRule, OutOfSession, TradingCalendar};
 pub use candle::*;
+pub use context::Context;
+pub use conversion::ConversionRates;
+pub use depth::MarketDepth;
+pub use exchange::*;
+pub use fees::*;
+pub use fill_model::*;
+pub use instrument::Instrument;
+pub use latency::Latency;
 pub use order::*;
 pub use position::*;
+pub use rebalance::Rebalancer;
+pub use risk::{RiskLimit, RiskManager};
+pub use slippage::*;
+pub use source::{CandleSource, CsvCandles};
+pub use spread::Spread;
+pub use tick::Tick;
+pub(crate) use tick::TickBars;
+pub use wallet::FeeCurrency;
 pub(crate) use wallet::*;
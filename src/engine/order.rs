@@ -1,8 +1,10 @@
-use crate::{errors::*, utils::random_id};
+use crate::{errors::*, utils::new_id};
+
+use chrono::{DateTime, TimeDelta, Utc};
 
 /// Represents the side of an order (buy or sell).
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OrderSide {
     /// A buy order, where the trader wants to purchase an asset.
     Buy,
@@ -14,7 +16,7 @@ pub enum OrderSide {
 /// Enum representing the type of an order.
 ///
 /// This enum is divided into two categories:
-/// 1. **Order types for opening positions** (Market, Limit)
+/// 1. **Order types for opening positions** (Market, Limit, Stop)
 /// 2. **Exit rules for closing positions** (TakeProfit, StopLoss, TrailingStop)
 ///
 /// This separation ensures clarity between order types used to open positions
@@ -34,6 +36,16 @@ pub enum OrderType {
     /// * `0` - The limit price for the order.
     Limit(f64),
 
+    /// Stop order to open a position once the price crosses a trigger price.
+    ///
+    /// A buy-stop triggers when the price rises to the trigger, a sell-stop when it falls to it,
+    /// which expresses breakout entries. Once triggered, it becomes a market order filled at the
+    /// trigger price, or at the candle open when the price gaps through it.
+    ///
+    /// ### Arguments
+    /// * `0` - The trigger price.
+    Stop(f64),
+
     /// Combined take-profit and stop-loss **exit rule** for a position.
     ///
     /// When either the take-profit or stop-loss price is reached, the position will be closed.
@@ -43,6 +55,26 @@ pub enum OrderType {
     /// * `1` - The stop-loss price (0.0 to disable)
     TakeProfitAndStopLoss(f64, f64),
 
+    /// Take-profit and stop-loss **exit rule** at percentages of the fill price.
+    ///
+    /// It is resolved into `TakeProfitAndStopLoss` when the position opens, from its actual entry
+    /// price (after slippage or a gap).
+    ///
+    /// ### Arguments
+    /// * `0` - The take-profit distance percentage (e.g., 6.0 for 6%, 0.0 to disable)
+    /// * `1` - The stop-loss distance percentage (e.g., 2.0 for 2%, 0.0 to disable)
+    TakeProfitAndStopLossPct(f64, f64),
+
+    /// Take-profit and stop-loss **exit rule** at multiples of the Average True Range from the fill price.
+    ///
+    /// It is resolved into `TakeProfitAndStopLoss` when the position opens, with the ATR of the
+    /// backtest at that time (see `Backtest::atr`).
+    ///
+    /// ### Arguments
+    /// * `0` - The take-profit ATR multiple (e.g., 3.0 for 3 × ATR, 0.0 to disable)
+    /// * `1` - The stop-loss ATR multiple (e.g., 1.5 for 1.5 × ATR, 0.0 to disable)
+    TakeProfitAndStopLossAtr(f64, f64),
+
     /// Trailing stop **exit rule** for a position.
     ///
     /// The stop price trails the market price by a specified percentage.
@@ -53,16 +85,291 @@ pub enum OrderType {
     /// * `0` - The initial stop price
     /// * `1` - The trailing percentage (e.g., 10.0 for 10%)
     TrailingStop(f64, f64),
+
+    /// Trailing stop **exit rule** for a position, trailing by a fixed price distance.
+    ///
+    /// ### Arguments
+    /// * `0` - The initial reference price
+    /// * `1` - The trailing distance in price units (e.g., 0.05 for 5 cents)
+    TrailingStopDistance(f64, f64),
+
+    /// Trailing stop **exit rule** for a position, trailing by a multiple of the Average True Range.
+    ///
+    /// This is the Chandelier exit: with the entry price as the initial reference, the stop trails
+    /// the highest high since the entry (the lowest low for a short position) by the ATR multiple.
+    /// The ATR is computed by the backtest (see `Backtest::with_atr_period`) or supplied
+    /// per candle with `Backtest::set_atr`.
+    ///
+    /// ### Arguments
+    /// * `0` - The initial reference price
+    /// * `1` - The ATR multiple (e.g., 3.0 for 3 × ATR)
+    TrailingStopAtr(f64, f64),
 }
 
 impl OrderType {
-    /// Returns the price associated with the order type (for Market and Limit orders).
+    /// Returns the price associated with the order type (for Market, Limit and Stop orders).
     pub fn inner(&self) -> Result<f64> {
         match self {
-            Self::Market(price) | Self::Limit(price) => Ok(*price),
+            Self::Market(price) | Self::Limit(price) | Self::Stop(price) => Ok(*price),
             _ => Err(Error::MismatchedOrderType),
         }
     }
+
+    /// Resolves an exit rule relative to the entry into absolute prices.
+    ///
+    /// ### Arguments
+    /// * `side` - The side of the order that opens the position.
+    /// * `entry_price` - The fill price of the position.
+    /// * `atr` - The Average True Range, required by `TakeProfitAndStopLossAtr`.
+    ///
+    /// ### Returns
+    /// The `TakeProfitAndStopLoss` rule for the relative rules, the rule itself otherwise, or an error
+    /// if an offset is negative or the ATR is unknown.
+    pub(crate) fn resolve(&self, side: &OrderSide, entry_price: f64, atr: Option<f64>) -> Result<Self> {
+        let (take_profit, stop_loss) = match *self {
+            Self::TakeProfitAndStopLossPct(take_profit, stop_loss) => {
+                (take_profit * entry_price / 100.0, stop_loss * entry_price / 100.0)
+            }
+            Self::TakeProfitAndStopLossAtr(take_profit, stop_loss) => {
+                let atr = atr.ok_or(Error::AtrUnavailable)?;
+                (take_profit * atr, stop_loss * atr)
+            }
+            rule => return Ok(rule),
+        };
+        if take_profit < 0.0 || stop_loss < 0.0 {
+            return Err(Error::NegTakeProfitAndStopLoss);
+        }
+
+        //? a disabled take-profit or stop-loss (0.0) stays disabled
+        let price = |offset: f64, up: bool| match (offset > 0.0, up) {
+            (false, _) => 0.0,
+            (true, true) => entry_price + offset,
+            (true, false) => (entry_price - offset).max(0.0),
+        };
+        let long = matches!(side, OrderSide::Buy);
+        Ok(Self::TakeProfitAndStopLoss(
+            price(take_profit, long),
+            price(stop_loss, !long),
+        ))
+    }
+}
+
+/// Time-in-force of an order, defining how long it stays in the pool before being cancelled.
+///
+/// The engine does not split orders, so an order is always filled entirely or not at all.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimeInForce {
+    /// Good-till-cancelled: the order rests until it is filled or deleted.
+    #[default]
+    Gtc,
+
+    /// Good-till-date: the order expires once a candle opens after the given date.
+    ///
+    /// ### Arguments
+    /// * `0` - The expiration date.
+    Gtd(DateTime<Utc>),
+
+    /// Immediate-or-cancel: the order expires if it is not filled on the candle it was placed.
+    Ioc,
+
+    /// Fill-or-kill: the order expires if it is not filled on the candle it was placed,
+    /// or if the candle volume cannot absorb the whole quantity.
+    Fok,
+}
+
+/// Maximum holding period of a position, after which it is closed at market.
+///
+/// The period is checked at the open of each candle: once it has elapsed since the candle the
+/// position was opened on, the position is closed at the open with `ExitReason::TimeExit`, before its
+/// other exit rules can be reached.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoldingPeriod {
+    /// A position opened on candle `N` is closed at the open of candle `N + k`.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of candles `k`.
+    Candles(usize),
+
+    /// A position is closed at the open of the first candle opening at least the duration after the
+    /// open of the candle it was opened on.
+    ///
+    /// ### Arguments
+    /// * `0` - The duration (e.g., 5 days).
+    Duration(TimeDelta),
+}
+
+impl HoldingPeriod {
+    /// Returns true if a position opened on the candle at `entry_index` (opening at `entry_time`)
+    /// has been held for the period at the open of the candle at `index` (opening at `open_time`).
+    pub(crate) fn has_elapsed(
+        &self,
+        (entry_index, entry_time): (usize, DateTime<Utc>),
+        (index, open_time): (usize, DateTime<Utc>),
+    ) -> bool {
+        match self {
+            Self::Candles(k) => index >= entry_index + k,
+            Self::Duration(period) => open_time - entry_time >= *period,
+        }
+    }
+}
+
+/// Breakeven stop: moves the stop-loss of a position to its entry price once the price has moved in
+/// its favor.
+///
+/// Once the favorable excursion of the position reaches `trigger` percent of the entry price, the
+/// stop-loss of its `TakeProfitAndStopLoss` exit rule is moved to the entry price plus `offset` in its
+/// favor (e.g., to cover the fees), from the next candle on. A position without exit rule gets one
+/// with only this stop-loss, and a stop-loss already beyond the breakeven price is kept. The trailing
+/// stops are not changed.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+///
+/// // stop-loss at 95, moved to 100.5 once the price reaches 103
+/// let exit_rule = OrderType::TakeProfitAndStopLoss(110.0, 95.0);
+/// let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy))
+///     .with_breakeven(BreakevenStop::new(3.0, 0.5));
+/// assert_eq!(order.breakeven().unwrap().trigger(), 3.0);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakevenStop {
+    trigger: f64,
+    offset: f64,
+}
+
+impl BreakevenStop {
+    /// Creates a breakeven stop.
+    ///
+    /// ### Arguments
+    /// * `trigger` - The favorable move arming the stop, as a percentage of the entry price (e.g., 2.0 for 2%).
+    /// * `offset` - The distance of the stop from the entry price in price units, in favor of the
+    ///   position (0.0 for the entry price itself).
+    pub fn new(trigger: f64, offset: f64) -> Self {
+        Self {
+            trigger: trigger.max(0.0),
+            offset,
+        }
+    }
+
+    /// Returns the favorable move arming the stop, as a percentage of the entry price.
+    pub fn trigger(&self) -> f64 {
+        self.trigger
+    }
+
+    /// Returns the distance of the stop from the entry price, in favor of the position.
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+}
+
+/// Scale-out ladder: partial take-profits closing fractions of a position at increasing distances
+/// from its entry price.
+///
+/// Each rung closes a fraction of the opened quantity once the price reaches its distance in favor of
+/// the position, at the rung price (or at the open when the price gaps over it). The rest of the
+/// position is handled by the exit rule of the order (e.g., a trailing stop). The rungs are checked
+/// after the exit rule, so they are not filled on a candle where the exit rule closes the position.
+///
+/// The closed parts are recorded as `Event::DelPosition` of the position, with their share of the
+/// fees and `ExitReason::TakeProfit`.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+///
+/// // close 50% at +2%, 25% at +4%, and trail the rest by 3%
+/// let ladder = ScaleOut::new(&[(2.0, 0.5), (4.0, 0.25)]).unwrap();
+/// let exit_rule = OrderType::TrailingStop(100.0, 3.0);
+/// let order = Order::from((OrderType::Market(100.0), exit_rule, 4.0, OrderSide::Buy))
+///     .with_scale_out(ladder);
+/// assert_eq!(order.scale_out().unwrap().rungs(), &[(2.0, 0.5), (4.0, 0.25)]);
+/// assert!(ScaleOut::new(&[(2.0, 0.75), (4.0, 0.5)]).is_err());
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleOut {
+    rungs: [(f64, f64); ScaleOut::MAX_RUNGS],
+    len: usize,
+}
+
+impl ScaleOut {
+    /// Maximum number of rungs of a ladder.
+    pub const MAX_RUNGS: usize = 4;
+
+    /// Creates a scale-out ladder.
+    ///
+    /// ### Arguments
+    /// * `rungs` - The rungs, as the distance from the entry price in percentage (e.g., 2.0 for 2%) and
+    ///   the fraction of the opened quantity to close (e.g., 0.5 for 50%), in ascending distances.
+    ///
+    /// ### Returns
+    /// The ladder, or an error if there are more than `MAX_RUNGS` rungs, a distance or a fraction is
+    /// not positive, the distances are not ascending or the fractions sum to more than 1.
+    pub fn new(rungs: &[(f64, f64)]) -> Result<Self> {
+        let ascending = rungs.windows(2).all(|w| w[0].0 < w[1].0);
+        let positive = rungs
+            .iter()
+            .all(|(distance, fraction)| *distance > 0.0 && *fraction > 0.0);
+        let total = rungs.iter().map(|(_, fraction)| fraction).sum::<f64>();
+        if rungs.len() > Self::MAX_RUNGS || !ascending || !positive || total > 1.0 + f64::EPSILON {
+            return Err(Error::InvalidScaleOut);
+        }
+
+        let mut ladder = Self {
+            rungs: [(0.0, 0.0); Self::MAX_RUNGS],
+            len: rungs.len(),
+        };
+        ladder.rungs[..rungs.len()].copy_from_slice(rungs);
+        Ok(ladder)
+    }
+
+    /// Returns the rungs, as the distance from the entry price in percentage and the fraction of the
+    /// opened quantity.
+    pub fn rungs(&self) -> &[(f64, f64)] {
+        &self.rungs[..self.len]
+    }
+
+    /// Returns the distance of a rung and its share of the quantity left open once the previous rungs
+    /// are filled.
+    pub(crate) fn rung(&self, index: usize) -> Option<(f64, f64)> {
+        let (distance, fraction) = *self.rungs().get(index)?;
+        let left = 1.0 - self.rungs[..index].iter().map(|(_, fraction)| fraction).sum::<f64>();
+        //? the last rung of a ladder summing to 1 closes the whole position
+        let share = if left - fraction <= f64::EPSILON {
+            1.0
+        } else {
+            fraction / left
+        };
+        Some((distance, share))
+    }
+}
+
+/// Lifecycle status of an order.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderStatus {
+    /// The order is waiting in the pool (or has not been placed yet).
+    #[default]
+    Pending,
+    /// The order has been filled and a position has been opened.
+    Filled,
+    /// The order has been deleted before being filled.
+    Cancelled,
+    /// The time-in-force of the order has elapsed before it was filled.
+    Expired,
+    /// The order has been refused when it was placed (e.g., insufficient funds).
+    Rejected,
+}
+
+impl OrderStatus {
+    /// Returns `true` if the order can't change anymore.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Filled | Self::Cancelled | Self::Expired | Self::Rejected)
+    }
 }
 
 /// Represents an order with entry and exit rules.
@@ -75,6 +382,10 @@ impl OrderType {
 /// let order = Order::from((OrderType::Market(101.15), 1.0, OrderSide::Sell));
 /// // (OrderType (entry rule type), OrderType (exit rule type), quantity, OrderSide)
 /// let order = Order::from((OrderType::Market(101.15), OrderType::TrailingStop(101.15, 2.0), 1.0, OrderSide::Sell));
+/// // with a time-in-force
+/// let order = Order::from((OrderType::Limit(99.5), 1.0, OrderSide::Buy)).with_time_in_force(TimeInForce::Ioc);
+/// // tagged with the signal that placed it
+/// let order = Order::from((OrderType::Market(101.15), 1.0, OrderSide::Buy)).with_tag(1);
 /// ```
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy)]
@@ -84,6 +395,28 @@ pub struct Order {
     side: OrderSide,
     entry_type: OrderType,
     exit_type: Option<OrderType>,
+    time_in_force: TimeInForce,
+    status: OrderStatus,
+    #[cfg_attr(feature = "serde", serde(default = "unit_multiplier"))]
+    multiplier: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    tag: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    max_holding: Option<HoldingPeriod>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    breakeven: Option<BreakevenStop>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    scale_out: Option<ScaleOut>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    placed_at: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    filled_at: Option<DateTime<Utc>>,
+}
+
+/// Default contract multiplier of the deserialized orders.
+#[cfg(feature = "serde")]
+fn unit_multiplier() -> f64 {
+    1.0
 }
 
 impl PartialEq for Order {
@@ -96,11 +429,20 @@ type O1 = (OrderType, f64, OrderSide);
 impl From<O1> for Order {
     fn from((entry_type, quantity, side): O1) -> Self {
         Self {
-            id: random_id(),
+            id: new_id(),
             entry_type,
             quantity,
             side,
             exit_type: None,
+            time_in_force: TimeInForce::default(),
+            status: OrderStatus::default(),
+            multiplier: 1.0,
+            tag: None,
+            max_holding: None,
+            breakeven: None,
+            scale_out: None,
+            placed_at: None,
+            filled_at: None,
         }
     }
 }
@@ -109,23 +451,46 @@ type O2 = (OrderType, OrderType, f64, OrderSide);
 impl From<O2> for Order {
     fn from((entry_type, exit_type, quantity, side): O2) -> Self {
         Self {
-            id: random_id(),
+            id: new_id(),
             entry_type,
             quantity,
             side,
             exit_type: Some(exit_type),
+            time_in_force: TimeInForce::default(),
+            status: OrderStatus::default(),
+            multiplier: 1.0,
+            tag: None,
+            max_holding: None,
+            breakeven: None,
+            scale_out: None,
+            placed_at: None,
+            filled_at: None,
         }
     }
 }
 
 impl Order {
+    /// Returns the order identifier.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the lifecycle status of the order.
+    pub fn status(&self) -> &OrderStatus {
+        &self.status
+    }
+
+    /// Updates the lifecycle status of the order.
+    pub(crate) fn set_status(&mut self, status: OrderStatus) {
+        self.status = status;
+    }
+
     /// Returns the quantity of the order.
     pub fn quantity(&self) -> f64 {
         self.quantity
     }
 
     /// Updates the quantity.
-    #[cfg(test)]
     pub(crate) fn set_quantity(&mut self, new_quantity: f64) {
         self.quantity = new_quantity;
     }
@@ -140,10 +505,83 @@ impl Order {
         self.entry_type.inner()
     }
 
-    /// Returns the total cost of the order (price * quantity).
+    /// Returns the total cost of the order (price * quantity * contract multiplier).
     pub fn cost(&self) -> Result<f64> {
         let inner = self.entry_type.inner()?;
-        Ok(inner * self.quantity)
+        Ok(inner * self.quantity * self.multiplier)
+    }
+
+    /// Checks the price and the quantity of the order.
+    ///
+    /// `Backtest::place_order` calls it before anything else, so an order built from a NaN or negative
+    /// price fails when it is placed rather than later when it is filled.
+    ///
+    /// ### Arguments
+    /// * `min_quantity` - The minimum quantity (0.0 for none).
+    ///
+    /// ### Returns
+    /// Ok, or `InvalidPrice` if the entry price or a price of the exit rule is not finite (or the entry
+    /// price is not positive), `InvalidQuantity` if the quantity is not finite and positive, or
+    /// `MinQuantity` if it is below the minimum.
+    pub fn validate(&self, min_quantity: f64) -> Result<()> {
+        let price = self.entry_price()?;
+        if !price.is_finite() || price <= 0.0 {
+            return Err(Error::InvalidPrice(price));
+        }
+        let exit_values = match self.exit_type {
+            Some(
+                OrderType::TakeProfitAndStopLoss(a, b)
+                | OrderType::TakeProfitAndStopLossPct(a, b)
+                | OrderType::TakeProfitAndStopLossAtr(a, b)
+                | OrderType::TrailingStop(a, b)
+                | OrderType::TrailingStopDistance(a, b)
+                | OrderType::TrailingStopAtr(a, b),
+            ) => [a, b],
+            _ => [0.0; 2],
+        };
+        if let Some(value) = exit_values.into_iter().find(|value| !value.is_finite()) {
+            return Err(Error::InvalidPrice(value));
+        }
+        if !self.quantity.is_finite() || self.quantity <= 0.0 {
+            return Err(Error::InvalidQuantity(self.quantity));
+        }
+        if self.quantity < min_quantity {
+            return Err(Error::MinQuantity(self.quantity, min_quantity));
+        }
+        Ok(())
+    }
+
+    /// Returns the contract multiplier of the order: 1.0 unless it is placed on a backtest with an
+    /// `Instrument` (e.g., 50.0 for a futures contract worth 50 times the index).
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    /// Updates the contract multiplier.
+    pub(crate) fn set_multiplier(&mut self, multiplier: f64) {
+        self.multiplier = multiplier;
+    }
+
+    /// Rounds the prices of the entry type and of the exit rule with `round`.
+    pub(crate) fn round_prices(&mut self, round: impl Fn(f64) -> f64) {
+        //? a disabled take-profit or stop-loss (0.0) stays disabled
+        let round = |price: &mut f64| *price = round(*price);
+        match &mut self.entry_type {
+            OrderType::Market(price) | OrderType::Limit(price) | OrderType::Stop(price) => round(price),
+            _ => {}
+        }
+        match &mut self.exit_type {
+            Some(OrderType::TakeProfitAndStopLoss(take_profit, stop_loss)) => {
+                round(take_profit);
+                round(stop_loss);
+            }
+            Some(OrderType::TrailingStopDistance(price, distance)) => {
+                round(price);
+                round(distance);
+            }
+            Some(OrderType::TrailingStop(price, _) | OrderType::TrailingStopAtr(price, _)) => round(price),
+            _ => {}
+        }
     }
 
     /// Returns the entry type of the order.
@@ -156,14 +594,160 @@ impl Order {
         self.exit_type.as_ref()
     }
 
+    /// Returns the time-in-force of the order.
+    pub fn time_in_force(&self) -> &TimeInForce {
+        &self.time_in_force
+    }
+
+    /// Sets the time-in-force of the order.
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    /// Returns the user tag of the order, if any.
+    pub fn tag(&self) -> Option<u64> {
+        self.tag
+    }
+
+    /// Tags the order, e.g., with the signal that placed it.
+    ///
+    /// The tag is kept by the resulting position and by the events, so the closed trades can be
+    /// grouped by signal with `Trades::by_tag`.
+    pub fn with_tag(mut self, tag: u64) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Returns the maximum holding period of the resulting position, if any.
+    pub fn max_holding(&self) -> Option<&HoldingPeriod> {
+        self.max_holding.as_ref()
+    }
+
+    /// Closes the resulting position at market once it has been held for the period, whatever its
+    /// exit rule (e.g., the time stop of a mean-reversion system).
+    pub fn with_max_holding(mut self, period: HoldingPeriod) -> Self {
+        self.max_holding = Some(period);
+        self
+    }
+
+    /// Returns the breakeven stop of the resulting position, if any.
+    pub fn breakeven(&self) -> Option<&BreakevenStop> {
+        self.breakeven.as_ref()
+    }
+
+    /// Moves the stop-loss of the resulting position to its entry price once the price has moved in
+    /// its favor (see `BreakevenStop`).
+    pub fn with_breakeven(mut self, breakeven: BreakevenStop) -> Self {
+        self.breakeven = Some(breakeven);
+        self
+    }
+
+    /// Returns the open time of the candle the order was placed on, once it is placed.
+    pub fn placed_at(&self) -> Option<DateTime<Utc>> {
+        self.placed_at
+    }
+
+    /// Records the placement time of the order.
+    pub(crate) fn set_placed_at(&mut self, datetime: DateTime<Utc>) {
+        self.placed_at = Some(datetime);
+    }
+
+    /// Returns the open time of the candle the order was filled on, once it is filled.
+    pub fn filled_at(&self) -> Option<DateTime<Utc>> {
+        self.filled_at
+    }
+
+    /// Records the fill time of the order.
+    pub(crate) fn set_filled_at(&mut self, datetime: DateTime<Utc>) {
+        self.filled_at = Some(datetime);
+    }
+
+    /// Returns the time the order waited in the pool before being filled (zero when it is filled on
+    /// the candle it was placed on), once it is filled.
+    pub fn time_in_queue(&self) -> Option<TimeDelta> {
+        Some(self.filled_at? - self.placed_at?)
+    }
+
+    /// Returns the scale-out ladder of the resulting position, if any.
+    pub fn scale_out(&self) -> Option<&ScaleOut> {
+        self.scale_out.as_ref()
+    }
+
+    /// Closes fractions of the resulting position at increasing distances from its entry price (see
+    /// `ScaleOut`).
+    pub fn with_scale_out(mut self, scale_out: ScaleOut) -> Self {
+        self.scale_out = Some(scale_out);
+        self
+    }
+
+    /// Returns true if a good-till-date order has expired at the given candle open time.
+    pub(crate) fn is_expired(&self, candle_time: DateTime<Utc>) -> bool {
+        matches!(self.time_in_force, TimeInForce::Gtd(expiration) if candle_time > expiration)
+    }
+
+    /// Returns true if the order must be cancelled when it is not filled on the first candle.
+    pub(crate) fn is_immediate(&self) -> bool {
+        matches!(self.time_in_force, TimeInForce::Ioc | TimeInForce::Fok)
+    }
+
     /// Returns true if it is a market order, and false if not.
     pub fn is_market_type(&self) -> bool {
         matches!(self.entry_type, OrderType::Market(_))
     }
 
+    /// Updates the entry price of the order (e.g., the fill price after slippage).
+    pub(crate) fn set_entry_price(&mut self, price: f64) {
+        match &mut self.entry_type {
+            OrderType::Market(p) | OrderType::Limit(p) | OrderType::Stop(p) => *p = price,
+            _ => {}
+        }
+    }
+
+    /// Returns the fill price of a stop order if the candle crosses its trigger price.
+    pub(crate) fn stop_fill_price(&self, open: f64, high: f64, low: f64) -> Option<f64> {
+        match (self.entry_type, self.side) {
+            //? a gap through the trigger fills at the open
+            (OrderType::Stop(trigger), OrderSide::Buy) if high >= trigger => Some(trigger.max(open)),
+            (OrderType::Stop(trigger), OrderSide::Sell) if low <= trigger => Some(trigger.min(open)),
+            _ => None,
+        }
+    }
+
+    /// Turns a triggered stop order into a market order at the fill price.
+    pub(crate) fn trigger_stop(&mut self, fill_price: f64) {
+        self.entry_type = OrderType::Market(fill_price);
+    }
+
+    /// Replaces the exit rule of the order.
+    pub(crate) fn set_exit_rule(&mut self, exit_rule: OrderType) {
+        self.exit_type = Some(exit_rule);
+    }
+
+    /// Moves the stop-loss of the exit rule to `stop` if it tightens it, adding a stop-loss rule to an
+    /// order without exit rule.
+    pub(crate) fn tighten_stop_loss(&mut self, stop: f64) {
+        let long = matches!(self.side, OrderSide::Buy);
+        match &mut self.exit_type {
+            //? a disabled stop-loss (0.0) is always tightened
+            Some(OrderType::TakeProfitAndStopLoss(_, stop_loss))
+                if *stop_loss <= 0.0 || (long && stop > *stop_loss) || (!long && stop < *stop_loss) =>
+            {
+                *stop_loss = stop;
+            }
+            None => self.exit_type = Some(OrderType::TakeProfitAndStopLoss(0.0, stop)),
+            _ => {}
+        }
+    }
+
     /// Updates the trailing stop price for the order.
     pub(crate) fn set_trailingstop(&mut self, new_price: f64) {
-        if let Some(OrderType::TrailingStop(current_price, _)) = &mut self.exit_type {
+        if let Some(
+            OrderType::TrailingStop(current_price, _)
+            | OrderType::TrailingStopDistance(current_price, _)
+            | OrderType::TrailingStopAtr(current_price, _),
+        ) = &mut self.exit_type
+        {
             match self.side {
                 OrderSide::Buy => {
                     if new_price > *current_price {
@@ -311,6 +895,27 @@ fn order_type_inner() {
 
     let limit_order = OrderType::Limit(150.0);
     assert_eq!(limit_order.inner().unwrap(), 150.0);
+
+    let stop_order = OrderType::Stop(120.0);
+    assert_eq!(stop_order.inner().unwrap(), 120.0);
+}
+
+#[cfg(test)]
+#[test]
+fn stop_order_fill_price() {
+    let mut buy_stop: Order = (OrderType::Stop(105.0), 1.0, OrderSide::Buy).into();
+    assert_eq!(buy_stop.stop_fill_price(100.0, 104.0, 95.0), None);
+    assert_eq!(buy_stop.stop_fill_price(100.0, 106.0, 95.0), Some(105.0));
+    assert_eq!(buy_stop.stop_fill_price(108.0, 110.0, 107.0), Some(108.0));
+
+    let sell_stop: Order = (OrderType::Stop(95.0), 1.0, OrderSide::Sell).into();
+    assert_eq!(sell_stop.stop_fill_price(100.0, 104.0, 96.0), None);
+    assert_eq!(sell_stop.stop_fill_price(100.0, 104.0, 94.0), Some(95.0));
+    assert_eq!(sell_stop.stop_fill_price(92.0, 93.0, 90.0), Some(92.0));
+
+    buy_stop.trigger_stop(105.0);
+    assert!(buy_stop.is_market_type());
+    assert_eq!(buy_stop.entry_price().unwrap(), 105.0);
 }
 
 #[cfg(test)]
@@ -320,3 +925,99 @@ fn order_type_inner_panics() {
     let take_profit_order = OrderType::TakeProfitAndStopLoss(120.0, 90.0);
     take_profit_order.inner().unwrap();
 }
+
+#[cfg(test)]
+#[test]
+fn order_time_in_force() {
+    let order: Order = (OrderType::Limit(100.0), 1.0, OrderSide::Buy).into();
+    assert_eq!(order.time_in_force(), &TimeInForce::Gtc);
+    assert!(!order.is_expired(DateTime::default()));
+    assert!(!order.is_immediate());
+
+    let expiration = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let order = order.with_time_in_force(TimeInForce::Gtd(expiration));
+    assert!(!order.is_expired(expiration));
+    assert!(order.is_expired(expiration + chrono::Duration::seconds(1)));
+
+    let order = order.with_time_in_force(TimeInForce::Ioc);
+    assert!(!order.is_expired(expiration + chrono::Duration::seconds(1)));
+    assert!(order.is_immediate());
+}
+
+#[cfg(test)]
+#[test]
+fn order_status() {
+    let mut order = Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy));
+    assert_eq!(order.status(), &OrderStatus::Pending);
+    assert!(!order.status().is_final());
+
+    order.set_status(OrderStatus::Filled);
+    assert!(order.status().is_final());
+}
+
+#[cfg(test)]
+#[test]
+fn order_tighten_stop_loss() {
+    let exit_rule = OrderType::TakeProfitAndStopLoss(90.0, 105.0);
+    let mut order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Sell));
+    order.tighten_stop_loss(99.0);
+    assert_eq!(order.exit_rule(), Some(&OrderType::TakeProfitAndStopLoss(90.0, 99.0)));
+    // a short stop-loss is never raised
+    order.tighten_stop_loss(101.0);
+    assert_eq!(order.exit_rule(), Some(&OrderType::TakeProfitAndStopLoss(90.0, 99.0)));
+
+    let mut order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+    order.tighten_stop_loss(100.5);
+    assert_eq!(order.exit_rule(), Some(&OrderType::TakeProfitAndStopLoss(0.0, 100.5)));
+
+    let mut order = Order::from((
+        OrderType::Market(100.0),
+        OrderType::TrailingStop(95.0, 5.0),
+        1.0,
+        OrderSide::Buy,
+    ));
+    order.tighten_stop_loss(100.5);
+    assert_eq!(order.exit_rule(), Some(&OrderType::TrailingStop(95.0, 5.0)));
+}
+
+#[cfg(test)]
+#[test]
+fn scale_out_rungs() {
+    let ladder = ScaleOut::new(&[(2.0, 0.5), (4.0, 0.25), (6.0, 0.25)]).unwrap();
+    assert_eq!(ladder.rung(0), Some((2.0, 0.5)));
+    // a quarter of the opened quantity is half of what is left
+    assert_eq!(ladder.rung(1), Some((4.0, 0.5)));
+    assert_eq!(ladder.rung(2), Some((6.0, 1.0)));
+    assert_eq!(ladder.rung(3), None);
+
+    assert!(ScaleOut::new(&[(4.0, 0.5), (2.0, 0.25)]).is_err());
+    assert!(ScaleOut::new(&[(2.0, 0.0)]).is_err());
+    assert!(ScaleOut::new(&[(1.0, 0.1), (2.0, 0.1), (3.0, 0.1), (4.0, 0.1), (5.0, 0.1)]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn order_validate() {
+    let order = |price: f64, quantity: f64| Order::from((OrderType::Limit(price), quantity, OrderSide::Buy));
+    assert!(order(100.0, 1.0).validate(0.0).is_ok());
+    assert!(matches!(order(f64::NAN, 1.0).validate(0.0), Err(Error::InvalidPrice(price)) if price.is_nan()));
+    assert!(matches!(order(-5.0, 1.0).validate(0.0), Err(Error::InvalidPrice(-5.0))));
+    assert!(matches!(
+        order(100.0, 0.0).validate(0.0),
+        Err(Error::InvalidQuantity(0.0))
+    ));
+    assert!(matches!(
+        order(100.0, f64::INFINITY).validate(0.0),
+        Err(Error::InvalidQuantity(_))
+    ));
+    assert!(matches!(
+        order(100.0, 0.5).validate(1.0),
+        Err(Error::MinQuantity(0.5, 1.0))
+    ));
+
+    let exit_rule = OrderType::TakeProfitAndStopLoss(f64::NAN, 90.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    assert!(matches!(order.validate(0.0), Err(Error::InvalidPrice(_))));
+    let order = Order::from((OrderType::TrailingStop(100.0, 1.0), 1.0, OrderSide::Buy));
+    assert!(matches!(order.validate(0.0), Err(Error::MismatchedOrderType)));
+}
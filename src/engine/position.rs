@@ -1,9 +1,11 @@
 use super::order::{Order, OrderSide};
-use crate::{errors::*, utils::random_id};
+use crate::{PercentCalculus, errors::*, utils::new_id};
+
+use chrono::{DateTime, Utc};
 
 /// Represents the side of a position (long or short).
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PositionSide {
     /// A long position, where the trader buys an asset with the expectation that its price will increase.
     Long,
@@ -11,6 +13,67 @@ pub enum PositionSide {
     Short,
 }
 
+/// How the fills are turned into positions.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionMode {
+    /// Every fill opens an independent position, so long and short positions can be held together.
+    #[default]
+    Hedging,
+    /// A fill first offsets the opposite positions, oldest first, realizing their P&L. Only the
+    /// residual quantity opens a new position.
+    Netting,
+}
+
+/// Lifecycle status of a position.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionStatus {
+    /// The position is open.
+    #[default]
+    Open,
+    /// The position has been closed.
+    Closed,
+}
+
+/// Reason why a position was closed.
+///
+/// It is stored on the position when it is closed, so it is included in `Event::DelPosition`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExitReason {
+    /// Closed by the strategy (e.g., `Backtest::close_position`).
+    #[default]
+    Manual,
+    /// The take profit was reached.
+    TakeProfit,
+    /// The stop loss was reached.
+    StopLoss,
+    /// The trailing stop was reached.
+    TrailingStop,
+    /// Closed at the end of the trading session.
+    SessionClose,
+    /// Closed at the end of the data (see `Backtest::flatten_at_end`).
+    EndOfData,
+    /// The maximum holding period was reached (see `Order::with_max_holding`).
+    TimeExit,
+}
+
+impl std::fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Self::Manual => "manual",
+            Self::TakeProfit => "take_profit",
+            Self::StopLoss => "stop_loss",
+            Self::TrailingStop => "trailing_stop",
+            Self::SessionClose => "session_close",
+            Self::EndOfData => "end_of_data",
+            Self::TimeExit => "time_exit",
+        };
+        f.write_str(reason)
+    }
+}
+
 /// Represents a trading position with an associated order.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy)]
@@ -18,6 +81,18 @@ pub struct Position {
     id: u32,
     order: Order,
     side: PositionSide,
+    status: PositionStatus,
+    fees: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    adverse: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    favorable: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    exit_reason: ExitReason,
+    #[cfg_attr(feature = "serde", serde(default))]
+    opened_at: Option<(usize, DateTime<Utc>)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    rungs_filled: usize,
     #[cfg(feature = "metrics")]
     exit_price: Option<f64>,
 }
@@ -31,7 +106,14 @@ impl PartialEq for Position {
 impl From<Order> for Position {
     fn from(value: Order) -> Self {
         Self {
-            id: random_id(),
+            id: new_id(),
+            fees: 0.0,
+            adverse: 0.0,
+            favorable: 0.0,
+            exit_reason: ExitReason::default(),
+            status: PositionStatus::default(),
+            opened_at: None,
+            rungs_filled: 0,
             #[cfg(feature = "metrics")]
             exit_price: None,
             order: value,
@@ -57,11 +139,154 @@ impl std::ops::DerefMut for Position {
 }
 
 impl Position {
+    /// Returns the position identifier.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
     /// Returns the position side.
     pub fn side(&self) -> &PositionSide {
         &self.side
     }
 
+    /// Returns the lifecycle status of the position.
+    ///
+    /// The status of the opening order is available with `Order::status` through `Position::order`.
+    pub fn status(&self) -> &PositionStatus {
+        &self.status
+    }
+
+    /// Updates the lifecycle status of the position.
+    pub(crate) fn set_status(&mut self, status: PositionStatus) {
+        self.status = status;
+    }
+
+    /// Returns the reason why the position was closed (`ExitReason::Manual` while it is open).
+    pub fn exit_reason(&self) -> ExitReason {
+        self.exit_reason
+    }
+
+    /// Updates the reason why the position is closed.
+    pub(crate) fn set_exit_reason(&mut self, exit_reason: ExitReason) {
+        self.exit_reason = exit_reason;
+    }
+
+    /// Records the candle the position is opened on.
+    pub(crate) fn set_opened_at(&mut self, index: usize, open_time: DateTime<Utc>) {
+        self.opened_at = Some((index, open_time));
+    }
+
+    /// Returns true if the maximum holding period of the position has elapsed at the open of the
+    /// candle at `index`.
+    pub(crate) fn is_expired(&self, index: usize, open_time: DateTime<Utc>) -> bool {
+        self.max_holding()
+            .zip(self.opened_at)
+            .is_some_and(|(period, opened_at)| period.has_elapsed(opened_at, (index, open_time)))
+    }
+
+    /// Returns the order that opened the position.
+    pub fn order(&self) -> &Order {
+        &self.order
+    }
+
+    /// Returns the market fees paid for this position (opening and closing).
+    pub fn fees(&self) -> f64 {
+        self.fees
+    }
+
+    /// Adds market fees paid for this position.
+    pub(crate) fn add_fees(&mut self, amount: f64) {
+        self.fees += amount;
+    }
+
+    /// Returns the maximum adverse excursion: the worst unrealized loss reached while the position was
+    /// open, as a positive amount.
+    pub fn mae(&self) -> f64 {
+        self.adverse * self.quantity() * self.multiplier()
+    }
+
+    /// Returns the maximum favorable excursion: the best unrealized profit reached while the position
+    /// was open.
+    pub fn mfe(&self) -> f64 {
+        self.favorable * self.quantity() * self.multiplier()
+    }
+
+    /// Returns the maximum adverse excursion as a price distance from the entry price.
+    pub fn mae_distance(&self) -> f64 {
+        self.adverse
+    }
+
+    /// Returns the maximum favorable excursion as a price distance from the entry price.
+    pub fn mfe_distance(&self) -> f64 {
+        self.favorable
+    }
+
+    /// Updates the excursions with the price range reached while the position is open.
+    pub(crate) fn track_excursion(&mut self, low: f64, high: f64) -> Result<()> {
+        let entry_price = self.entry_price()?;
+        let (adverse, favorable) = match self.side {
+            PositionSide::Long => (entry_price - low, high - entry_price),
+            PositionSide::Short => (high - entry_price, entry_price - low),
+        };
+        self.adverse = self.adverse.max(adverse);
+        self.favorable = self.favorable.max(favorable);
+        Ok(())
+    }
+
+    /// Moves the stop-loss to the breakeven price once the favorable excursion reaches the trigger of
+    /// the breakeven stop.
+    pub(crate) fn arm_breakeven(&mut self) -> Result<()> {
+        let Some(breakeven) = self.breakeven().copied() else {
+            return Ok(());
+        };
+        let entry_price = self.entry_price()?;
+        if self.favorable < entry_price * breakeven.trigger() / 100.0 {
+            return Ok(());
+        }
+        let stop = match self.side {
+            PositionSide::Long => entry_price + breakeven.offset(),
+            PositionSide::Short => entry_price - breakeven.offset(),
+        };
+        if stop > 0.0 {
+            self.tighten_stop_loss(stop);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of rungs of the scale-out ladder already filled.
+    pub fn rungs_filled(&self) -> usize {
+        self.rungs_filled
+    }
+
+    /// Returns the price of the next rung of the scale-out ladder and its share of the open quantity.
+    pub(crate) fn next_rung(&self) -> Result<Option<(f64, f64)>> {
+        let Some((distance, share)) = self.scale_out().and_then(|ladder| ladder.rung(self.rungs_filled)) else {
+            return Ok(None);
+        };
+        let entry_price = self.entry_price()?;
+        let price = match self.side {
+            PositionSide::Long => entry_price.addpercent(distance),
+            PositionSide::Short => entry_price.subpercent(distance),
+        };
+        Ok(Some((price, share)))
+    }
+
+    /// Marks the next rung of the scale-out ladder as filled.
+    pub(crate) fn fill_rung(&mut self) {
+        self.rungs_filled += 1;
+    }
+
+    /// Splits off a part of the position with its share of the fees, keeping the rest in this position.
+    pub(crate) fn split_off(&mut self, quantity: f64) -> Position {
+        let total = self.quantity();
+        let mut part = *self;
+        part.set_quantity(quantity);
+        part.fees = self.fees * quantity / total;
+        self.set_quantity(total - quantity);
+        self.fees -= part.fees;
+        part
+    }
+
     /// Returns the current exit price.
     #[cfg(feature = "metrics")]
     pub fn exit_price(&self) -> Option<&f64> {
@@ -91,7 +316,7 @@ impl Position {
             PositionSide::Long => (exit_price - self.entry_price()?) * self.quantity(),
             PositionSide::Short => (self.entry_price()? - exit_price) * self.quantity(),
         };
-        Ok(pnl)
+        Ok(pnl * self.multiplier())
     }
 }
 
@@ -166,6 +391,21 @@ fn position_deref_mut() {
     assert_eq!(position.quantity(), 3.0);
 }
 
+#[cfg(test)]
+#[test]
+fn position_split_off() {
+    let order: Order = (OrderType::Market(100.0), 4.0, OrderSide::Buy).into();
+    let mut position = Position::from(order);
+    position.add_fees(2.0);
+
+    let part = position.split_off(1.0);
+    assert_eq!(part, position);
+    assert_eq!(part.quantity(), 1.0);
+    assert_eq!(part.fees(), 0.5);
+    assert_eq!(position.quantity(), 3.0);
+    assert_eq!(position.fees(), 1.5);
+}
+
 #[cfg(test)]
 #[test]
 fn estimate_pnl_long_position() {
@@ -228,3 +468,21 @@ fn position_set_trailingstop() {
         panic!("Expected TrailingStop order type");
     }
 }
+
+#[cfg(test)]
+#[test]
+fn position_excursions() {
+    let order: Order = (OrderType::Market(100.0), 2.0, OrderSide::Sell).into();
+    let mut position = Position::from(order);
+    position.track_excursion(95.0, 104.0).unwrap();
+    position.track_excursion(90.0, 102.0).unwrap();
+    assert_eq!(position.mae_distance(), 4.0);
+    assert_eq!(position.mfe_distance(), 10.0);
+    assert_eq!(position.mae(), 8.0);
+    assert_eq!(position.mfe(), 20.0);
+
+    // the split parts keep the price excursions
+    let part = position.split_off(0.5);
+    assert_eq!(part.mae(), 2.0);
+    assert_eq!(position.mfe(), 15.0);
+}
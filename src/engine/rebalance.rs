@@ -0,0 +1,183 @@
+use super::{Order, OrderSide, OrderType};
+use crate::errors::{Error, Result};
+
+/// Rebalancing of a portfolio to target weights.
+///
+/// The weights are shares of the equity (the cash plus the value of the holdings) held in each
+/// symbol; the rest of the equity stays in cash. A symbol is traded only once its weight drifts from
+/// the target by more than the tolerance band, and then back to the target, unless the trade is worth
+/// less than the minimum trade value. A held symbol without a target is sold.
+///
+/// `orders` computes the market orders of any set of symbols, and `Backtest::rebalance` places the
+/// order of the symbol of a backtest, from its own equity, e.g., on the first candle of each month.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::engine::{OrderSide, Rebalancer};
+///
+/// let rebalancer = Rebalancer::new(&[("BTC", 0.6), ("ETH", 0.3)])
+///     .unwrap()
+///     .with_tolerance(0.05)
+///     .with_min_trade(10.0);
+/// // an equity of 1000, of which 700 in BTC and 200 in ETH
+/// let orders = rebalancer.orders(1000.0, &[("BTC", 7.0, 100.0), ("ETH", 4.0, 50.0)]).unwrap();
+/// // BTC is 10% above its target and ETH 10% below
+/// assert_eq!(orders.len(), 2);
+/// assert_eq!(orders[0].0, "BTC");
+/// assert_eq!(orders[0].1.side(), &OrderSide::Sell);
+/// assert_eq!(orders[0].1.quantity(), 1.0);
+/// assert_eq!(orders[1].1.quantity(), 2.0);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rebalancer {
+    targets: Vec<(String, f64)>,
+    tolerance: f64,
+    min_trade: f64,
+}
+
+impl Rebalancer {
+    /// Creates a rebalancer to the target weights, without tolerance band nor minimum trade.
+    ///
+    /// ### Arguments
+    /// * `targets` - The symbols and their weights (e.g., 0.6 for 60% of the equity).
+    ///
+    /// ### Returns
+    /// The rebalancer, or an error if a weight is negative or the weights sum to more than 1.
+    pub fn new(targets: &[(&str, f64)]) -> Result<Self> {
+        let total = targets.iter().map(|(_, weight)| weight).sum::<f64>();
+        if targets.iter().any(|(_, weight)| !weight.is_finite() || *weight < 0.0) || total > 1.0 + 1e-9 {
+            return Err(Error::InvalidWeights(total));
+        }
+        Ok(Self {
+            targets: targets
+                .iter()
+                .map(|(symbol, weight)| (symbol.to_string(), *weight))
+                .collect(),
+            tolerance: 0.0,
+            min_trade: 0.0,
+        })
+    }
+
+    /// Sets the tolerance band: the drift of a weight from its target before it is traded
+    /// (e.g., 0.05 to trade a 60% target below 55% or above 65%).
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the minimum value of a trade (price × quantity): a smaller trade is skipped.
+    pub fn with_min_trade(mut self, min_trade: f64) -> Self {
+        self.min_trade = min_trade;
+        self
+    }
+
+    /// Returns the symbols and their target weights.
+    pub fn targets(&self) -> &[(String, f64)] {
+        &self.targets
+    }
+
+    /// Returns the target weight of a symbol (0.0 without target).
+    pub fn target(&self, symbol: &str) -> f64 {
+        self.targets
+            .iter()
+            .find_map(|(target, weight)| (target == symbol).then_some(*weight))
+            .unwrap_or_default()
+    }
+
+    /// Returns the tolerance band.
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Returns the minimum value of a trade.
+    pub fn min_trade(&self) -> f64 {
+        self.min_trade
+    }
+
+    /// Computes the market orders moving the holdings to the target weights.
+    ///
+    /// ### Arguments
+    /// * `equity` - The equity of the portfolio: the cash plus the value of the holdings.
+    /// * `holdings` - The symbols with their held quantity (negative for a short) and current price.
+    ///   Every target must be listed, with a quantity of 0.0 if it is not held.
+    ///
+    /// ### Returns
+    /// The symbols and their orders, the sells first so they free the cash of the buys, or an error
+    /// if a target has no price.
+    pub fn orders(&self, equity: f64, holdings: &[(&str, f64, f64)]) -> Result<Vec<(String, Order)>> {
+        if let Some((symbol, _)) = self
+            .targets
+            .iter()
+            .find(|(symbol, _)| !holdings.iter().any(|(held, _, price)| held == symbol && *price > 0.0))
+        {
+            return Err(Error::MissingPrice(symbol.clone()));
+        }
+        let mut orders = holdings
+            .iter()
+            .filter(|(_, _, price)| *price > 0.0)
+            .filter_map(|&(symbol, quantity, price)| {
+                let order = self.order(symbol, equity, quantity, price)?;
+                Some((symbol.to_string(), order))
+            })
+            .collect::<Vec<_>>();
+        //? stable, so the symbols keep their order within each side
+        orders.sort_by_key(|(_, order)| matches!(order.side(), OrderSide::Buy));
+        Ok(orders)
+    }
+
+    /// Returns the market order moving the holding of a symbol to its target weight, if it drifted
+    /// beyond the tolerance band and the trade is worth the minimum trade value.
+    pub(crate) fn order(&self, symbol: &str, equity: f64, quantity: f64, price: f64) -> Option<Order> {
+        let target = self.target(symbol);
+        let held = quantity * price;
+        let value = target * equity - held;
+        if (target - held / equity).abs() <= self.tolerance || value.abs() < self.min_trade.max(f64::EPSILON) {
+            return None;
+        }
+        let side = if value > 0.0 { OrderSide::Buy } else { OrderSide::Sell };
+        Some(Order::from((OrderType::Market(price), value.abs() / price, side)))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn rebalancer_orders() {
+    assert!(Rebalancer::new(&[("A", 0.7), ("B", 0.4)]).is_err());
+    assert!(Rebalancer::new(&[("A", -0.1)]).is_err());
+
+    let rebalancer = Rebalancer::new(&[("A", 0.5), ("B", 0.5)]).unwrap();
+    assert_eq!(rebalancer.target("C"), 0.0);
+    // from cash only
+    let orders = rebalancer
+        .orders(1000.0, &[("A", 0.0, 10.0), ("B", 0.0, 50.0)])
+        .unwrap();
+    assert_eq!(orders.len(), 2);
+    assert_eq!((orders[0].1.side(), orders[0].1.quantity()), (&OrderSide::Buy, 50.0));
+    assert_eq!((orders[1].1.side(), orders[1].1.quantity()), (&OrderSide::Buy, 10.0));
+    // the symbol without target is sold first
+    let orders = rebalancer
+        .orders(1000.0, &[("A", 50.0, 10.0), ("B", 8.0, 50.0), ("C", 1.0, 100.0)])
+        .unwrap();
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].0, "C");
+    assert_eq!((orders[0].1.side(), orders[0].1.quantity()), (&OrderSide::Sell, 1.0));
+    assert_eq!((orders[1].0.as_str(), orders[1].1.quantity()), ("B", 2.0));
+    assert!(matches!(orders[1].1.entry_type(), OrderType::Market(50.0)));
+
+    // A and B drifted by 4%, within the band
+    let rebalancer = rebalancer.with_tolerance(0.05);
+    let orders = rebalancer
+        .orders(1000.0, &[("A", 54.0, 10.0), ("B", 9.2, 50.0)])
+        .unwrap();
+    assert!(orders.is_empty());
+    // A and B drifted by 6%, but the trades are below the minimum
+    let rebalancer = rebalancer.with_min_trade(100.0);
+    assert!(
+        rebalancer
+            .orders(1000.0, &[("A", 56.0, 10.0), ("B", 8.8, 50.0)])
+            .unwrap()
+            .is_empty()
+    );
+    assert!(rebalancer.orders(1000.0, &[("A", 56.0, 10.0)]).is_err());
+}
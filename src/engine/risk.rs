@@ -0,0 +1,231 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// A risk limit violated by an order.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RiskLimit {
+    /// The maximum number of open positions (and pending orders) is reached.
+    ///
+    /// ### Arguments
+    /// * `0` - The maximum number of open positions.
+    MaxOpenPositions(usize),
+
+    /// The notional of the positions and orders would exceed the maximum.
+    ///
+    /// ### Arguments
+    /// * `0` - The notional with the order.
+    /// * `1` - The maximum notional.
+    MaxNotional(f64, f64),
+
+    /// The exposure would exceed the maximum share of the equity.
+    ///
+    /// ### Arguments
+    /// * `0` - The exposure with the order, as a percentage of the equity.
+    /// * `1` - The maximum exposure percentage.
+    MaxExposure(f64, f64),
+
+    /// The loss of the day reached the maximum, so the trading is halted until the next day.
+    ///
+    /// ### Arguments
+    /// * `0` - The loss of the day, as a percentage of the equity at the start of the day.
+    /// * `1` - The maximum daily loss percentage.
+    MaxDailyLoss(f64, f64),
+}
+
+impl std::fmt::Display for RiskLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MaxOpenPositions(max) => write!(f, "the maximum of {max} open positions is reached"),
+            Self::MaxNotional(notional, max) => write!(f, "notional {notional} exceeds the maximum {max}"),
+            Self::MaxExposure(exposure, max) => write!(f, "exposure {exposure:.2}% exceeds the maximum {max}%"),
+            Self::MaxDailyLoss(loss, max) => write!(f, "daily loss {loss:.2}% reached the maximum {max}%"),
+        }
+    }
+}
+
+/// Portfolio-level limits checked before an order is placed.
+///
+/// The open positions and the pending orders both count toward the limits, since the pending
+/// orders become positions once filled. An order violating a limit is rejected with
+/// `Error::RiskLimit`.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::engine::RiskManager;
+///
+/// let risk_manager = RiskManager::default()
+///     .with_max_open_positions(3)
+///     .with_max_exposure(50.0)
+///     .with_max_daily_loss(2.0);
+/// assert_eq!(risk_manager.max_open_positions(), Some(3));
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskManager {
+    max_open_positions: Option<usize>,
+    max_notional: Option<f64>,
+    max_exposure: Option<f64>,
+    max_daily_loss: Option<f64>,
+    /// The current day and the equity at its start.
+    day: Option<(NaiveDate, f64)>,
+    /// The equity at the close of the last candle.
+    last_equity: f64,
+    /// The day the trading is halted.
+    halted: Option<NaiveDate>,
+}
+
+impl RiskManager {
+    /// Sets the maximum number of open positions and pending orders.
+    pub fn with_max_open_positions(mut self, max: usize) -> Self {
+        self.max_open_positions = Some(max);
+        self
+    }
+
+    /// Sets the maximum notional (price × quantity) of the open positions and pending orders.
+    pub fn with_max_notional(mut self, max: f64) -> Self {
+        self.max_notional = Some(max);
+        self
+    }
+
+    /// Sets the maximum notional of the open positions and pending orders, as a percentage of the equity.
+    pub fn with_max_exposure(mut self, percent: f64) -> Self {
+        self.max_exposure = Some(percent);
+        self
+    }
+
+    /// Sets the maximum loss of a day, as a percentage of the equity at the start of the day.
+    pub fn with_max_daily_loss(mut self, percent: f64) -> Self {
+        self.max_daily_loss = Some(percent);
+        self
+    }
+
+    /// Returns the maximum number of open positions and pending orders.
+    pub fn max_open_positions(&self) -> Option<usize> {
+        self.max_open_positions
+    }
+
+    /// Returns the maximum notional of the open positions and pending orders.
+    pub fn max_notional(&self) -> Option<f64> {
+        self.max_notional
+    }
+
+    /// Returns the maximum exposure percentage.
+    pub fn max_exposure(&self) -> Option<f64> {
+        self.max_exposure
+    }
+
+    /// Returns the maximum daily loss percentage.
+    pub fn max_daily_loss(&self) -> Option<f64> {
+        self.max_daily_loss
+    }
+
+    /// Clears the daily state, starting from the given equity.
+    pub(crate) fn reset(&mut self, equity: f64) {
+        self.day = None;
+        self.halted = None;
+        self.last_equity = equity;
+    }
+
+    /// Starts the day of the candle opening at `time`, from the equity at the close of the previous candle.
+    fn roll_day(&mut self, time: DateTime<Utc>) {
+        let day = time.date_naive();
+        if self.day.is_none_or(|(current, _)| current != day) {
+            self.day = Some((day, self.last_equity));
+        }
+    }
+
+    /// Records the equity at the close of the candle opening at `time`.
+    pub(crate) fn mark(&mut self, time: DateTime<Utc>, equity: f64) {
+        self.roll_day(time);
+        self.last_equity = equity;
+    }
+
+    /// Checks an order placed on the candle opening at `time`.
+    ///
+    /// ### Arguments
+    /// * `time` - The open time of the candle.
+    /// * `open` - The number of open positions and pending orders, without the order.
+    /// * `notional` - The notional of the open positions and pending orders, with the order.
+    /// * `equity` - The current equity.
+    pub(crate) fn check(
+        &mut self,
+        time: DateTime<Utc>,
+        open: usize,
+        notional: f64,
+        equity: f64,
+    ) -> std::result::Result<(), RiskLimit> {
+        self.roll_day(time);
+        if let Some(max) = self.max_daily_loss
+            && let Some((day, start)) = self.day
+        {
+            let loss = if start > 0.0 {
+                (start - equity) / start * 100.0
+            } else {
+                0.0
+            };
+            if loss >= max {
+                self.halted = Some(day);
+            }
+            if self.halted == Some(day) {
+                return Err(RiskLimit::MaxDailyLoss(loss, max));
+            }
+        }
+        if let Some(max) = self.max_open_positions
+            && open >= max
+        {
+            return Err(RiskLimit::MaxOpenPositions(max));
+        }
+        if let Some(max) = self.max_notional
+            && notional > max
+        {
+            return Err(RiskLimit::MaxNotional(notional, max));
+        }
+        if let Some(max) = self.max_exposure {
+            let exposure = if equity > 0.0 {
+                notional / equity * 100.0
+            } else {
+                f64::INFINITY
+            };
+            if exposure > max {
+                return Err(RiskLimit::MaxExposure(exposure, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn risk_manager_check() {
+    let time = |hours| DateTime::default() + chrono::TimeDelta::hours(hours);
+    let mut risk_manager = RiskManager::default()
+        .with_max_open_positions(2)
+        .with_max_notional(1000.0)
+        .with_max_exposure(50.0)
+        .with_max_daily_loss(5.0);
+    risk_manager.reset(1000.0);
+
+    assert!(risk_manager.check(time(0), 1, 400.0, 1000.0).is_ok());
+    assert_eq!(
+        risk_manager.check(time(0), 2, 400.0, 1000.0),
+        Err(RiskLimit::MaxOpenPositions(2))
+    );
+    assert_eq!(
+        risk_manager.check(time(0), 0, 600.0, 1000.0),
+        Err(RiskLimit::MaxExposure(60.0, 50.0))
+    );
+    assert_eq!(
+        risk_manager.check(time(0), 0, 1200.0, 3000.0),
+        Err(RiskLimit::MaxNotional(1200.0, 1000.0))
+    );
+
+    // the loss of 6% halts the trading for the rest of the day, even if the equity recovers
+    risk_manager.mark(time(0), 940.0);
+    assert_eq!(
+        risk_manager.check(time(1), 0, 100.0, 940.0),
+        Err(RiskLimit::MaxDailyLoss(6.0, 5.0))
+    );
+    assert!(risk_manager.check(time(2), 0, 100.0, 1000.0).is_err());
+    risk_manager.mark(time(2), 950.0);
+    assert!(risk_manager.check(time(24), 0, 100.0, 950.0).is_ok());
+}
@@ -0,0 +1,66 @@
+use crate::{PercentCalculus, engine::OrderSide};
+
+/// Slippage model applied to the entry price when an order is filled.
+///
+/// Buy orders are filled above their price and sell orders below it.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slippage {
+    /// Constant slippage.
+    ///
+    /// ### Arguments
+    /// * `0` - The slippage percentage (e.g., 0.1 for 0.1%).
+    Percent(f64),
+
+    /// Slippage proportional to the share of the candle volume taken by the order.
+    ///
+    /// The slippage percentage is `impact × participation`, where the participation is
+    /// `quantity / volume × 100` capped at 100%. A candle without volume is considered fully consumed.
+    ///
+    /// ### Arguments
+    /// * `0` - The impact coefficient (e.g., 0.1 gives 1% of slippage when the order is 10% of the volume).
+    VolumeParticipation(f64),
+}
+
+impl Slippage {
+    /// Returns the slippage percentage for an order of `quantity` on a candle of `volume`.
+    pub fn percent(&self, quantity: f64, volume: f64) -> f64 {
+        match self {
+            Self::Percent(percent) => *percent,
+            Self::VolumeParticipation(impact) => {
+                let participation = if volume > 0.0 {
+                    (quantity / volume * 100.0).min(100.0)
+                } else {
+                    100.0
+                };
+                impact * participation
+            }
+        }
+    }
+
+    /// Returns the fill price of an order at `price`, adjusted against the trader.
+    pub fn fill_price(&self, price: f64, quantity: f64, volume: f64, side: &OrderSide) -> f64 {
+        let percent = self.percent(quantity, volume);
+        match side {
+            OrderSide::Buy => price.addpercent(percent),
+            OrderSide::Sell => price.subpercent(percent),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn slippage_percent() {
+    assert_eq!(Slippage::Percent(0.5).percent(10.0, 100.0), 0.5);
+    assert_eq!(Slippage::VolumeParticipation(0.1).percent(10.0, 100.0), 1.0);
+    assert_eq!(Slippage::VolumeParticipation(0.1).percent(500.0, 100.0), 10.0);
+    assert_eq!(Slippage::VolumeParticipation(0.1).percent(1.0, 0.0), 10.0);
+}
+
+#[cfg(test)]
+#[test]
+fn slippage_fill_price() {
+    let slippage = Slippage::Percent(1.0);
+    assert_eq!(slippage.fill_price(100.0, 1.0, 1.0, &OrderSide::Buy), 101.0);
+    assert_eq!(slippage.fill_price(100.0, 1.0, 1.0, &OrderSide::Sell), 99.0);
+}
@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::{Candle, CandleBuilder};
+use crate::errors::{Error, Result};
+
+use chrono::{DateTime, Utc};
+
+/// Source of candles read one at a time, in chronological order.
+///
+/// Unlike `Backtest::new`, which keeps the whole series in memory, a source is consumed by
+/// `Backtest::run_from_source` one candle at a time, so large datasets can be streamed from disk.
+/// Any iterator of candles is a source.
+pub trait CandleSource {
+    /// Returns the next candle, or None once the source is exhausted.
+    fn next_candle(&mut self) -> Result<Option<Candle>>;
+}
+
+impl<I: Iterator<Item = Candle>> CandleSource for I {
+    fn next_candle(&mut self) -> Result<Option<Candle>> {
+        Ok(self.next())
+    }
+}
+
+/// Candles streamed from CSV lines.
+///
+/// Each line is `open_time,close_time,open,high,low,close,volume`, the times being either RFC 3339
+/// dates or Unix timestamps in milliseconds. A first line that is not a candle (a header) is skipped,
+/// as well as the empty lines.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::engine::{CandleSource, CsvCandles};
+///
+/// let csv = "open_time,close_time,open,high,low,close,volume\n\
+///            0,60000,100,110,90,105,1.5\n\
+///            1970-01-01T00:01:00Z,1970-01-01T00:02:00Z,105,112,101,110,2\n";
+/// let mut source = CsvCandles::new(csv.as_bytes());
+/// assert_eq!(source.next_candle().unwrap().unwrap().close(), 105.0);
+/// assert_eq!(source.next_candle().unwrap().unwrap().close(), 110.0);
+/// assert!(source.next_candle().unwrap().is_none());
+/// ```
+pub struct CsvCandles<R> {
+    reader: R,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> CsvCandles<R> {
+    /// Creates a source reading the CSV lines of `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+}
+
+impl CsvCandles<BufReader<File>> {
+    /// Creates a source reading the CSV file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> CandleSource for CsvCandles<R> {
+    fn next_candle(&mut self) -> Result<Option<Candle>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match parse_line(line) {
+                Ok(candle) => return candle.build().map(Some),
+                Err(_) if self.line_number == 1 => continue,
+                Err(e) => return Err(Error::Msg(format!("Invalid candle at line {}: {e}", self.line_number))),
+            }
+        }
+    }
+}
+
+/// Parses a CSV line into a candle builder.
+fn parse_line(line: &str) -> std::result::Result<CandleBuilder, String> {
+    let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+    let [open_time, close_time, open, high, low, close, volume] = fields[..] else {
+        return Err(format!("expected 7 fields, found {}", fields.len()));
+    };
+    let price = |field: &str| field.parse::<f64>().map_err(|e| format!("{field}: {e}"));
+    Ok(CandleBuilder::builder()
+        .open_time(parse_time(open_time)?)
+        .close_time(parse_time(close_time)?)
+        .open(price(open)?)
+        .high(price(high)?)
+        .low(price(low)?)
+        .close(price(close)?)
+        .volume(price(volume)?))
+}
+
+/// Parses an RFC 3339 date or a Unix timestamp in milliseconds.
+fn parse_time(field: &str) -> std::result::Result<DateTime<Utc>, String> {
+    match field.parse::<i64>() {
+        Ok(millis) => DateTime::from_timestamp_millis(millis).ok_or_else(|| format!("{field}: out of range")),
+        Err(_) => DateTime::parse_from_rfc3339(field)
+            .map(|datetime| datetime.to_utc())
+            .map_err(|e| format!("{field}: {e}")),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn csv_candles() {
+    let csv = "0,60000,100,110,90,105,1\n\n60000,120000,105,112,101,110,2\n";
+    let mut source = CsvCandles::new(csv.as_bytes());
+    let first = source.next_candle().unwrap().unwrap();
+    assert_eq!(first.open_time(), DateTime::<Utc>::default());
+    assert_eq!(first.volume(), 1.0);
+    assert_eq!(source.next_candle().unwrap().unwrap().high(), 112.0);
+    assert!(source.next_candle().unwrap().is_none());
+
+    // the invalid lines after the header are errors
+    let mut source = CsvCandles::new("0,60000,100,110,90,105\n".as_bytes());
+    assert!(source.next_candle().unwrap().is_none());
+    let mut source = CsvCandles::new("header\n0,60000,100,x,90,105,1\n".as_bytes());
+    assert!(source.next_candle().is_err());
+    let mut source = CsvCandles::new("header\n0,60000,100,80,90,105,1\n".as_bytes());
+    assert!(source.next_candle().is_err());
+}
@@ -0,0 +1,72 @@
+use crate::engine::{Candle, OrderSide};
+
+/// Bid/ask spread model applied around the candle prices.
+///
+/// The candle prices are taken as mid prices: the ask is half a spread above them and the bid half a
+/// spread below. Buy orders execute against the ask and sell orders against the bid, so a market
+/// order pays half the spread and a limit or an exit is reached only when the quote of its side is.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spread {
+    /// Constant spread.
+    ///
+    /// ### Arguments
+    /// * `0` - The spread in price units (e.g., 0.0002 for 2 pips on EUR/USD).
+    Fixed(f64),
+
+    /// Spread proportional to the price.
+    ///
+    /// ### Arguments
+    /// * `0` - The spread percentage of the price (e.g., 0.05 for 0.05%).
+    Percent(f64),
+
+    /// Spread derived from the candle, as a share of its range (high - low).
+    ///
+    /// ### Arguments
+    /// * `0` - The share of the range (e.g., 0.1 for a spread of 10% of the range).
+    Range(f64),
+}
+
+impl Spread {
+    /// Returns the spread around the mid `price` during the candle.
+    pub fn width(&self, price: f64, candle: &Candle) -> f64 {
+        match self {
+            Self::Fixed(spread) => *spread,
+            Self::Percent(percent) => price * percent / 100.0,
+            Self::Range(share) => (candle.high() - candle.low()) * share,
+        }
+    }
+
+    /// Returns the price at which an order on `side` executes when the mid price is `price`: the ask
+    /// for a buy and the bid for a sell.
+    pub fn quote(&self, price: f64, candle: &Candle, side: &OrderSide) -> f64 {
+        let half = self.width(price, candle) / 2.0;
+        match side {
+            OrderSide::Buy => price + half,
+            OrderSide::Sell => price - half,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn spread_quote() {
+    use chrono::DateTime;
+
+    let candle = crate::engine::CandleBuilder::builder()
+        .open(100.0)
+        .high(110.0)
+        .low(90.0)
+        .close(105.0)
+        .volume(1.0)
+        .open_time(DateTime::default())
+        .close_time(DateTime::default() + chrono::Duration::days(1))
+        .build()
+        .unwrap();
+
+    assert_eq!(Spread::Fixed(1.0).quote(100.0, &candle, &OrderSide::Buy), 100.5);
+    assert_eq!(Spread::Fixed(1.0).quote(100.0, &candle, &OrderSide::Sell), 99.5);
+    assert_eq!(Spread::Percent(1.0).quote(200.0, &candle, &OrderSide::Buy), 201.0);
+    assert_eq!(Spread::Range(0.1).width(100.0, &candle), 2.0);
+    assert_eq!(Spread::Range(0.1).quote(100.0, &candle, &OrderSide::Sell), 99.0);
+}
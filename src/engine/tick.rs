@@ -0,0 +1,183 @@
+use super::{Candle, CandleBuilder, OrderSide};
+use crate::errors::{Error, Result};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Represents a single trade of the market (tick).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    price: f64,
+    size: f64,
+    side: OrderSide,
+    timestamp: DateTime<Utc>,
+}
+
+impl Tick {
+    /// Creates a new tick.
+    ///
+    /// ### Arguments
+    /// * `price` - The traded price.
+    /// * `size` - The traded quantity.
+    /// * `side` - The side of the aggressor (`Buy` when the trade lifted the ask).
+    /// * `timestamp` - The time of the trade.
+    ///
+    /// ### Returns
+    /// The tick, or an error if the price is not positive or the size is negative.
+    pub fn new(price: f64, size: f64, side: OrderSide, timestamp: DateTime<Utc>) -> Result<Self> {
+        if price <= 0.0 || !price.is_finite() {
+            return Err(Error::InvalidPriceOrder(price, price, price, price));
+        }
+        if size < 0.0 {
+            return Err(Error::NegativeVolume(size));
+        }
+        Ok(Self {
+            price,
+            size,
+            side,
+            timestamp,
+        })
+    }
+
+    /// Returns the traded price.
+    pub fn price(&self) -> f64 {
+        self.price
+    }
+
+    /// Returns the traded quantity.
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// Returns the side of the aggressor.
+    pub fn side(&self) -> &OrderSide {
+        &self.side
+    }
+
+    /// Returns the time of the trade.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Builds the candles of `interval` from the ticks (sorted by time).
+    ///
+    /// The candles are aligned on the interval (e.g., a minute starts at second 0) and the intervals
+    /// without ticks are skipped.
+    pub fn candles(ticks: &[Tick], interval: TimeDelta) -> Result<Vec<Candle>> {
+        let mut bars = TickBars::new(interval)?;
+        let mut candles = Vec::new();
+        for tick in ticks {
+            candles.extend(bars.push(tick)?);
+        }
+        candles.extend(bars.finish());
+        Ok(candles)
+    }
+}
+
+/// Builds candles on the fly from ticks.
+pub(crate) struct TickBars {
+    interval: TimeDelta,
+    current: Option<Candle>,
+}
+
+impl TickBars {
+    /// Creates a new builder of candles of `interval`.
+    pub(crate) fn new(interval: TimeDelta) -> Result<Self> {
+        if interval <= TimeDelta::zero() {
+            return Err(Error::Msg(format!("Invalid candle interval: {interval}")));
+        }
+        Ok(Self {
+            interval,
+            current: None,
+        })
+    }
+
+    /// Returns the candle being built.
+    pub(crate) fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    /// Adds a tick to the current candle, returning the previous candle if the tick opens a new one.
+    pub(crate) fn push(&mut self, tick: &Tick) -> Result<Option<Candle>> {
+        let interval = self.interval.num_milliseconds();
+        let millis = tick.timestamp.timestamp_millis();
+        let open_time = DateTime::from_timestamp_millis(millis - millis.rem_euclid(interval))
+            .ok_or(Error::Msg(format!("Invalid tick time: {}", tick.timestamp)))?;
+
+        let price = tick.price;
+        let (candle, closed) = match self.current {
+            Some(current) if current.open_time() == open_time => (
+                CandleBuilder::builder()
+                    .open(current.open())
+                    .high(current.high().max(price))
+                    .low(current.low().min(price))
+                    .close(price)
+                    .volume(current.volume() + tick.size)
+                    .open_time(open_time)
+                    .close_time(current.close_time()),
+                None,
+            ),
+            Some(current) if current.open_time() > open_time => {
+                return Err(Error::InvalideTimes(current.open_time(), tick.timestamp));
+            }
+            current => (
+                CandleBuilder::builder()
+                    .open(price)
+                    .high(price)
+                    .low(price)
+                    .close(price)
+                    .volume(tick.size)
+                    .open_time(open_time)
+                    .close_time(open_time + self.interval),
+                current,
+            ),
+        };
+        self.current = Some(candle.build()?);
+        Ok(closed)
+    }
+
+    /// Returns the last candle.
+    pub(crate) fn finish(&mut self) -> Option<Candle> {
+        self.current.take()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tick_validation() {
+    assert!(Tick::new(100.0, 1.0, OrderSide::Buy, DateTime::default()).is_ok());
+    assert!(Tick::new(0.0, 1.0, OrderSide::Buy, DateTime::default()).is_err());
+    assert!(Tick::new(100.0, -1.0, OrderSide::Sell, DateTime::default()).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn tick_candles() {
+    let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+    let ticks = [
+        Tick::new(100.0, 1.0, OrderSide::Buy, time(60)).unwrap(),
+        Tick::new(102.0, 2.0, OrderSide::Buy, time(70)).unwrap(),
+        Tick::new(99.0, 1.0, OrderSide::Sell, time(119)).unwrap(),
+        Tick::new(101.0, 3.0, OrderSide::Buy, time(250)).unwrap(),
+    ];
+
+    let candles = Tick::candles(&ticks, TimeDelta::minutes(1)).unwrap();
+    assert_eq!(candles.len(), 2);
+    assert_eq!(
+        (
+            candles[0].open(),
+            candles[0].high(),
+            candles[0].low(),
+            candles[0].close()
+        ),
+        (100.0, 102.0, 99.0, 99.0)
+    );
+    assert_eq!(candles[0].volume(), 4.0);
+    assert_eq!((candles[0].open_time(), candles[0].close_time()), (time(60), time(120)));
+    assert_eq!(candles[1].open_time(), time(240));
+    assert_eq!(candles[1].close(), 101.0);
+
+    let unsorted = [ticks[3], ticks[0]];
+    assert!(Tick::candles(&unsorted, TimeDelta::minutes(1)).is_err());
+    assert!(Tick::candles(&ticks, TimeDelta::zero()).is_err());
+}
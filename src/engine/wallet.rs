@@ -1,6 +1,22 @@
 use crate::errors::{Error, Result};
 
+/// Currency in which the market fees are charged.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeCurrency {
+    /// Fees are charged in the quote asset (e.g., USDT).
+    #[default]
+    Quote,
+    /// Fees are charged in the received asset, like on most spot exchanges:
+    /// in the base asset on buys and in the quote asset on sells.
+    Received,
+}
+
 /// Represents a trading wallet with balance and locked funds management.
+///
+/// The balance is held in the quote asset (e.g., USDT). The wallet also holds an inventory of the
+/// base asset (e.g., BTC): a long position converts quote to base when it is opened and converts it
+/// back when it is closed. Short positions are notional and don't touch the base inventory.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy)]
 pub struct Wallet {
@@ -9,6 +25,10 @@ pub struct Wallet {
     balance: f64,
     unrealized_pnl: f64,
     initial_balance: f64,
+    base: f64,
+    initial_base: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    account_pnl: f64,
 }
 
 impl Wallet {
@@ -25,9 +45,22 @@ impl Wallet {
             locked: 0.0,
             unrealized_pnl: 0.0,
             initial_balance: balance,
+            base: 0.0,
+            initial_base: 0.0,
+            account_pnl: 0.0,
         })
     }
 
+    /// Sets the initial inventory of the base asset.
+    pub(crate) fn with_base(mut self, base: f64) -> Result<Self> {
+        if base < 0.0 {
+            return Err(Error::NegZeroBalance(base));
+        }
+        self.base = base;
+        self.initial_base = base;
+        Ok(self)
+    }
+
     /// Returns the initial balance.
     pub fn initial_balance(&self) -> f64 {
         self.initial_balance
@@ -53,6 +86,22 @@ impl Wallet {
         self.balance
     }
 
+    /// Returns the inventory of the base asset.
+    pub fn base_balance(&self) -> f64 {
+        self.base
+    }
+
+    /// Returns the initial inventory of the base asset.
+    pub fn initial_base_balance(&self) -> f64 {
+        self.initial_base
+    }
+
+    /// Returns the realized P&L, net of the fees, converted into the account currency (see
+    /// `ConversionRates`). Without conversion rates, the account currency is the quote currency.
+    pub fn account_pnl(&self) -> f64 {
+        self.account_pnl
+    }
+
     /// Returns the total balance.
     pub fn total_balance(&self) -> f64 {
         self.balance + self.unrealized_pnl
@@ -88,6 +137,26 @@ impl Wallet {
         self.free_balance()
     }
 
+    /// Adds base asset to the inventory (after a buy).
+    pub(crate) fn add_base(&mut self, quantity: f64) {
+        self.base += quantity;
+    }
+
+    /// Subtracts base asset from the inventory (after a sell).
+    pub(crate) fn sub_base(&mut self, quantity: f64) -> Result<()> {
+        // tolerates the rounding errors accumulated by the additions
+        if self.base - quantity < -f64::EPSILON * quantity.max(1.0) {
+            return Err(Error::InsufficientFunds(quantity, self.base));
+        }
+        self.base = (self.base - quantity).max(0.0);
+        Ok(())
+    }
+
+    /// Records market fees paid in another asset than the quote (valued in quote).
+    pub(crate) fn add_fees(&mut self, amount: f64) {
+        self.fees += amount;
+    }
+
     /// Locks additional funds for a position.
     pub(crate) fn lock(&mut self, amount: f64) -> Result<()> {
         if amount <= 0.0 {
@@ -113,6 +182,11 @@ impl Wallet {
         Ok(())
     }
 
+    /// Replaces an amount of locked funds by another one (e.g., when the fill price differs from the order price).
+    pub(crate) fn relock(&mut self, from: f64, to: f64) {
+        self.locked += to - from;
+    }
+
     /// Updates the unrealized P&L.
     pub(crate) fn set_unrealized_pnl(&mut self, pnl: f64) {
         self.unrealized_pnl = pnl;
@@ -126,12 +200,19 @@ impl Wallet {
         self.unrealized_pnl -= amount;
     }
 
+    /// Adds the realized P&L of a closed position, converted into the account currency.
+    pub(crate) fn add_account_pnl(&mut self, amount: f64) {
+        self.account_pnl += amount;
+    }
+
     /// Resets the wallet to its initial balance.
     pub(crate) fn reset(&mut self) {
         self.fees = 0.0;
         self.locked = 0.0;
         self.unrealized_pnl = 0.0;
         self.balance = self.initial_balance;
+        self.base = self.initial_base;
+        self.account_pnl = 0.0;
     }
 }
 
@@ -298,3 +379,33 @@ fn unrealized_pnl() {
     assert_eq!(wallet.total_balance(), 95.0);
     assert_eq!(wallet.free_balance().unwrap(), 100.0);
 }
+
+#[cfg(test)]
+#[test]
+fn relock_funds() {
+    let mut wallet = Wallet::new(100.0).unwrap();
+    wallet.lock(20.0).unwrap();
+    wallet.relock(20.0, 21.0);
+    assert_eq!(wallet.locked, 21.0);
+
+    wallet.sub(21.0).unwrap();
+    assert_eq!(wallet.balance, 79.0);
+    assert_eq!(wallet.locked, 0.0);
+}
+
+#[cfg(test)]
+#[test]
+fn base_inventory() {
+    let mut wallet = Wallet::new(100.0).unwrap().with_base(1.0).unwrap();
+    assert_eq!(wallet.base_balance(), 1.0);
+
+    wallet.add_base(0.5);
+    assert_eq!(wallet.base_balance(), 1.5);
+    wallet.sub_base(1.5).unwrap();
+    assert_eq!(wallet.base_balance(), 0.0);
+    assert!(matches!(wallet.sub_base(0.1), Err(Error::InsufficientFunds(_, _))));
+
+    wallet.reset();
+    assert_eq!(wallet.base_balance(), 1.0);
+    assert!(Wallet::new(100.0).unwrap().with_base(-1.0).is_err());
+}
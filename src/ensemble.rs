@@ -0,0 +1,377 @@
+//! Ensemble of strategies trading one shared wallet.
+//!
+//! An `Ensemble` runs several strategies in a single backtest: each one trades a sleeve of the
+//! account, a share of the equity given by its weight, and its orders are tagged so its trades can be
+//! told apart (see `Trades::by_tag`). The strategies share the capital and, in
+//! `PositionMode::Netting`, their opposing signals offset each other, so the combined behavior of the
+//! portfolio is simulated rather than summed from separate backtests.
+
+use std::ops::Deref;
+
+use crate::engine::{Backtest, Candle, Order, OrderSide, Position, PositionMode, PositionSide};
+use crate::errors::{Error, Result};
+
+/// A strategy of an ensemble, called with its sleeve and the current candle.
+type Strategy<'a> = Box<dyn FnMut(&mut Sleeve<'_>, &Candle) -> Result<()> + 'a>;
+
+/// Several strategies trading one shared wallet in a single backtest.
+///
+/// The strategies are called in the order they were added, on each candle, before the orders are
+/// filled.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::ensemble::Ensemble;
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candles = (0..3)
+///     .map(|i| {
+///         let open_time = DateTime::default() + Duration::days(i);
+///         CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(100.0)
+///             .volume(1.0)
+///             .open_time(open_time)
+///             .close_time(open_time + Duration::days(1))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Arc<[_]>>();
+///
+/// let mut ensemble = Ensemble::new()
+///     .with_strategy(1, 0.6, |sleeve, candle| {
+///         if sleeve.positions().count() == 0 {
+///             let order = Order::from((OrderType::Market(candle.close()), 5.0, OrderSide::Buy));
+///             sleeve.place_order(candle, order)?;
+///         }
+///         Ok(())
+///     })
+///     .with_strategy(2, 0.4, |sleeve, candle| {
+///         // 5 × 100 is above the 40% of the equity of the sleeve
+///         let order = Order::from((OrderType::Market(candle.close()), 5.0, OrderSide::Buy));
+///         assert!(sleeve.place_order(candle, order).is_err());
+///         Ok(())
+///     });
+/// let mut bts = Backtest::new(candles, 1000.0, None).unwrap();
+/// ensemble.run(&mut bts).unwrap();
+/// assert_eq!(bts.positions().next().unwrap().tag(), Some(1));
+/// ```
+#[derive(Default)]
+pub struct Ensemble<'a> {
+    members: Vec<(u64, f64, Strategy<'a>)>,
+}
+
+impl<'a> Ensemble<'a> {
+    /// Creates an ensemble without strategies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a strategy.
+    ///
+    /// ### Arguments
+    /// * `tag` - The tag of the orders of the strategy, unique in the ensemble.
+    /// * `weight` - The share of the equity allocated to the strategy (e.g., 0.5 for 50%).
+    /// * `strategy` - A closure that takes the sleeve of the strategy and the current candle.
+    pub fn with_strategy<S>(mut self, tag: u64, weight: f64, strategy: S) -> Self
+    where
+        S: FnMut(&mut Sleeve<'_>, &Candle) -> Result<()> + 'a,
+    {
+        self.members.push((tag, weight, Box::new(strategy)));
+        self
+    }
+
+    /// Returns the tags and the weights of the strategies.
+    pub fn weights(&self) -> Vec<(u64, f64)> {
+        self.members.iter().map(|(tag, weight, _)| (*tag, *weight)).collect()
+    }
+
+    /// Returns the number of strategies.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns `true` if there is no strategy.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Runs the strategies on the backtest.
+    ///
+    /// ### Arguments
+    /// * `backtest` - The backtest, with the shared wallet and the configuration of the engine.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error if a weight is negative, the weights sum to more than 1, two
+    /// strategies have the same tag, or a strategy fails.
+    pub fn run(&mut self, backtest: &mut Backtest) -> Result<()> {
+        let total = self.members.iter().map(|(_, weight, _)| weight).sum::<f64>();
+        if self
+            .members
+            .iter()
+            .any(|(_, weight, _)| !weight.is_finite() || *weight < 0.0)
+            || total > 1.0 + 1e-9
+        {
+            return Err(Error::InvalidWeights(total));
+        }
+        let tags = self.members.iter().map(|(tag, _, _)| *tag).collect::<Vec<_>>();
+        if let Some(tag) = tags
+            .iter()
+            .enumerate()
+            .find_map(|(i, tag)| tags[..i].contains(tag).then_some(*tag))
+        {
+            return Err(Error::DuplicateTag(tag));
+        }
+
+        backtest.run(|backtest, candle| {
+            for (tag, weight, strategy) in self.members.iter_mut() {
+                let mut sleeve = Sleeve {
+                    backtest: &mut *backtest,
+                    tag: *tag,
+                    weight: *weight,
+                };
+                strategy(&mut sleeve, candle)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The share of the account traded by a strategy of an `Ensemble`.
+///
+/// It gives a read access to the whole backtest (e.g., the equity of the account), and places the
+/// orders of the strategy, tagged with its tag and checked against its allocation.
+pub struct Sleeve<'b> {
+    backtest: &'b mut Backtest,
+    tag: u64,
+    weight: f64,
+}
+
+impl Deref for Sleeve<'_> {
+    type Target = Backtest;
+
+    fn deref(&self) -> &Self::Target {
+        self.backtest
+    }
+}
+
+impl Sleeve<'_> {
+    /// Returns the tag of the orders of the strategy.
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Returns the share of the equity allocated to the strategy.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Returns the capital allocated to the strategy: its share of the equity of the account.
+    pub fn allocation(&self) -> f64 {
+        self.weight * self.backtest.equity()
+    }
+
+    /// Returns the capital committed by the strategy: the cost of its open positions and pending orders.
+    pub fn committed(&self) -> f64 {
+        let positions = self.positions().filter_map(|p| p.cost().ok());
+        let orders = self.orders().filter_map(|o| o.cost().ok());
+        positions.chain(orders).sum()
+    }
+
+    /// Returns the capital the strategy can still commit.
+    pub fn available(&self) -> f64 {
+        (self.allocation() - self.committed()).max(0.0)
+    }
+
+    /// Returns the open positions of the strategy.
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.backtest.positions().filter(|p| p.tag() == Some(self.tag))
+    }
+
+    /// Returns the pending orders of the strategy.
+    pub fn orders(&self) -> impl Iterator<Item = &Order> {
+        self.backtest.orders().filter(|o| o.tag() == Some(self.tag))
+    }
+
+    /// Returns the net quantity of the strategy: its open positions and pending orders, negative when short.
+    fn net_quantity(&self) -> f64 {
+        let positions = self.positions().map(|p| match p.side() {
+            PositionSide::Long => p.quantity(),
+            PositionSide::Short => -p.quantity(),
+        });
+        let orders = self.orders().map(signed_quantity);
+        positions.chain(orders).sum()
+    }
+
+    /// Places an order of the strategy, tagged with its tag (replacing any tag of the order).
+    ///
+    /// In `PositionMode::Netting`, only the part of the order increasing the exposure of the strategy
+    /// is checked against its allocation, so a fully invested strategy can still reduce or exit its
+    /// position.
+    ///
+    /// ### Arguments
+    /// * `candle` - The current candle.
+    /// * `order` - The order to place.
+    ///
+    /// ### Returns
+    /// Ok if successful, `InsufficientFunds` if the order costs more than the available capital of the
+    /// strategy, or an error of `Backtest::place_order`.
+    pub fn place_order(&mut self, candle: &Candle, order: Order) -> Result<()> {
+        let order = order.with_tag(self.tag);
+        let mut cost = order.cost()?;
+        if self.position_mode() == &PositionMode::Netting && order.quantity() > 0.0 {
+            let net = self.net_quantity();
+            let increase = (net + signed_quantity(&order)).abs() - net.abs();
+            cost *= increase.clamp(0.0, order.quantity()) / order.quantity();
+        }
+        let available = self.available();
+        if cost > available {
+            return Err(Error::InsufficientFunds(cost, available));
+        }
+        self.backtest.place_order(candle, order)
+    }
+
+    /// Returns the backtest, e.g., to close a position or cancel an order of the strategy.
+    pub fn backtest_mut(&mut self) -> &mut Backtest {
+        self.backtest
+    }
+}
+
+/// Returns the quantity of an order, negative for a sell.
+fn signed_quantity(order: &Order) -> f64 {
+    match order.side() {
+        OrderSide::Buy => order.quantity(),
+        OrderSide::Sell => -order.quantity(),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn ensemble_shared_wallet() {
+    use std::sync::Arc;
+
+    use crate::engine::{CandleBuilder, OrderSide, OrderType, PositionMode};
+    use chrono::{DateTime, Duration};
+
+    let candles = (0..4)
+        .map(|i| {
+            let open_time = DateTime::default() + Duration::days(i);
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(100.0)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::days(1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+    let market = |candle: &Candle, quantity: f64, side: OrderSide| {
+        Order::from((OrderType::Market(candle.close()), quantity, side)).with_tag(9)
+    };
+
+    // the trend follower buys on the first candle, the contrarian sells on the third
+    let third = candles[2].open_time();
+    let mut allocations = Vec::new();
+    let mut ensemble = Ensemble::new()
+        .with_strategy(1, 0.5, |sleeve, candle| {
+            allocations.push((sleeve.allocation(), sleeve.available()));
+            if sleeve.positions().count() == 0 && allocations.len() == 1 {
+                sleeve.place_order(candle, market(candle, 4.0, OrderSide::Buy))?;
+                assert!(sleeve.place_order(candle, market(candle, 2.0, OrderSide::Buy)).is_err());
+            }
+            Ok(())
+        })
+        .with_strategy(2, 0.3, |sleeve, candle| {
+            if candle.open_time() == third {
+                assert_eq!(sleeve.committed(), 0.0);
+                sleeve.place_order(candle, market(candle, 1.0, OrderSide::Sell))?;
+            }
+            Ok(())
+        });
+    assert_eq!(ensemble.weights(), vec![(1, 0.5), (2, 0.3)]);
+
+    let mut bt = Backtest::new(candles.clone(), 1000.0, None)
+        .unwrap()
+        .with_position_mode(PositionMode::Netting);
+    ensemble.run(&mut bt).unwrap();
+    drop(ensemble);
+
+    assert_eq!(allocations[0], (500.0, 500.0));
+    assert_eq!(allocations[1], (500.0, 100.0));
+    // the sell of the contrarian nets the long of the trend follower
+    let position = bt.positions().next().unwrap();
+    assert_eq!(bt.positions().len(), 1);
+    assert_eq!((position.tag(), position.quantity()), (Some(1), 3.0));
+
+    let mut bt = Backtest::new(candles.clone(), 1000.0, None).unwrap();
+    let mut ensemble = Ensemble::new()
+        .with_strategy(1, 0.6, |_, _| Ok(()))
+        .with_strategy(2, 0.6, |_, _| Ok(()));
+    assert!(matches!(ensemble.run(&mut bt), Err(Error::InvalidWeights(_))));
+    let mut ensemble = Ensemble::new()
+        .with_strategy(1, 0.5, |_, _| Ok(()))
+        .with_strategy(1, 0.5, |_, _| Ok(()));
+    assert!(matches!(ensemble.run(&mut bt), Err(Error::DuplicateTag(1))));
+}
+
+#[cfg(test)]
+#[test]
+fn ensemble_netting_exit() {
+    use std::sync::Arc;
+
+    use crate::engine::{CandleBuilder, OrderType};
+    use chrono::{DateTime, Duration};
+
+    let candles = (0..3)
+        .map(|i| {
+            let open_time = DateTime::default() + Duration::days(i);
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(100.0)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::days(1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+    let market = |candle: &Candle, quantity: f64, side: OrderSide| {
+        Order::from((OrderType::Market(candle.close()), quantity, side))
+    };
+
+    // a fully invested sleeve exits its long, but can't flip to a larger short
+    let mut candle_count = 0;
+    let mut ensemble = Ensemble::new().with_strategy(1, 0.5, |sleeve, candle| {
+        candle_count += 1;
+        match candle_count {
+            1 => sleeve.place_order(candle, market(candle, 4.0, OrderSide::Buy))?,
+            2 => {
+                assert_eq!(sleeve.available(), 100.0);
+                assert!(matches!(
+                    sleeve.place_order(candle, market(candle, 10.0, OrderSide::Sell)),
+                    Err(Error::InsufficientFunds(..))
+                ));
+                sleeve.place_order(candle, market(candle, 4.0, OrderSide::Sell))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    });
+    let mut bt = Backtest::new(candles, 1000.0, None)
+        .unwrap()
+        .with_position_mode(PositionMode::Netting);
+    ensemble.run(&mut bt).unwrap();
+    drop(ensemble);
+    assert_eq!(bt.positions().len(), 0);
+    assert_eq!(bt.balance(), 1000.0);
+}
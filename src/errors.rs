@@ -1,6 +1,8 @@
 //! Error types for the BTS library.
 
-use chrono::{DateTime, Utc};
+use crate::engine::RiskLimit;
+
+use chrono::{DateTime, TimeDelta, Utc};
 
 /// Enum representing possible errors in the crate.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -93,6 +95,145 @@ pub enum Error {
     #[error("Invalid exit price {0}")]
     ExitPrice(f64),
 
+    /// The exit price of a rule is on the wrong side of the market price for the position.
+    ///
+    /// ### Arguments
+    /// * `0` - The take-profit or stop-loss price.
+    /// * `1` - The market price.
+    #[error("Exit price {0} is on the wrong side of the market price {1}")]
+    InvalidExitRule(f64, f64),
+
+    /// A price of the order is not a finite positive number (e.g., NaN).
+    ///
+    /// ### Arguments
+    /// * `0` - The invalid price.
+    #[error("Invalid order price {0}: prices must be finite and positive")]
+    InvalidPrice(f64),
+
+    /// The quantity of the order is not a finite positive number.
+    ///
+    /// ### Arguments
+    /// * `0` - The invalid quantity.
+    #[error("Invalid order quantity {0}: quantities must be finite and positive")]
+    InvalidQuantity(f64),
+
+    /// The quantity of the order is below the minimum quantity of the backtest.
+    ///
+    /// ### Arguments
+    /// * `0` - The order quantity.
+    /// * `1` - The minimum quantity.
+    #[error("Order quantity {0} is below the minimum quantity {1}")]
+    MinQuantity(f64, f64),
+
+    /// The order value is below the minimum notional of the exchange.
+    ///
+    /// ### Arguments
+    /// * `0` - The order value (price × quantity).
+    /// * `1` - The minimum notional.
+    #[error("Order value {0} is below the minimum notional {1}")]
+    MinNotional(f64, f64),
+
+    /// The order quantity is below the lot size of the instrument.
+    ///
+    /// ### Arguments
+    /// * `0` - The order quantity.
+    /// * `1` - The lot size.
+    #[error("Order quantity {0} is below the lot size {1}")]
+    LotSize(f64, f64),
+
+    /// The new price or quantity of an amended order is invalid.
+    ///
+    /// ### Arguments
+    /// * `0` - The new price.
+    /// * `1` - The new quantity.
+    #[error("Invalid order amendment: price={0}, quantity={1}")]
+    InvalidAmend(f64, f64),
+
+    /// The quantity or the price added to a position is invalid.
+    ///
+    /// ### Arguments
+    /// * `0` - The added quantity.
+    /// * `1` - The price.
+    #[error("Invalid scale-in: quantity={0}, price={1}")]
+    InvalidScaleIn(f64, f64),
+
+    /// An order was placed while the trading session is closed.
+    ///
+    /// ### Arguments
+    /// * `0` - The date of the order.
+    #[error("The trading session is closed at {0}")]
+    SessionClosed(DateTime<Utc>),
+
+    /// The candles are not regularly spaced by the timeframe.
+    ///
+    /// ### Arguments
+    /// * `0` - The open time of the candle.
+    /// * `1` - The time since the previous candle.
+    /// * `2` - The timeframe.
+    #[error("Irregular candle spacing at {0}: {1} since the previous candle, expected {2}")]
+    IrregularSpacing(DateTime<Utc>, TimeDelta, TimeDelta),
+
+    /// No conversion rate converts a currency into the account currency.
+    ///
+    /// ### Arguments
+    /// * `0` - The currency to convert.
+    /// * `1` - The account currency.
+    #[error("No conversion rate from {0} to {1}")]
+    MissingConversionRate(String, String),
+
+    /// An order was placed during the warm-up period.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of warm-up candles.
+    #[error("Orders cannot be placed during the warm-up of {0} candles")]
+    WarmUp(usize),
+
+    /// An order violates a limit of the risk manager.
+    ///
+    /// ### Arguments
+    /// * `0` - The violated limit.
+    #[error("Order rejected by the risk manager: {0}")]
+    RiskLimit(RiskLimit),
+
+    /// The operation needs the netting position mode (e.g., rebalancing).
+    #[error("The operation requires the netting position mode")]
+    NettingRequired,
+
+    /// Portfolio weights are negative or sum to more than 1.
+    ///
+    /// ### Arguments
+    /// * `0` - The sum of the weights.
+    #[error("Invalid weights: they must be positive and sum to at most 1, got {0}")]
+    InvalidWeights(f64),
+
+    /// The share of the candle volume of `LimitFill::Queue` is not in (0, 1].
+    ///
+    /// ### Arguments
+    /// * `0` - The invalid share.
+    #[error("The queue share of volume must be in (0, 1] (got: {0})")]
+    InvalidQueueShare(f64),
+
+    /// Two strategies of an ensemble have the same tag.
+    ///
+    /// ### Arguments
+    /// * `0` - The duplicated tag.
+    #[error("The strategies of an ensemble must have distinct tags, {0} is duplicated")]
+    DuplicateTag(u64),
+
+    /// A symbol has no price to be valued.
+    ///
+    /// ### Arguments
+    /// * `0` - The symbol.
+    #[error("No price for {0}")]
+    MissingPrice(String),
+
+    /// An I/O error (e.g., while exporting results).
+    ///
+    /// ### Arguments
+    /// * `0` - The underlying I/O error.
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
     /// A generic error with a custom message.
     ///
     /// ### Arguments
@@ -108,12 +249,36 @@ pub enum Error {
     #[error("TrailingStop must be positive and greater than 0")]
     NegZeroTrailingStop,
 
+    /// The rungs of a scale-out ladder are invalid.
+    #[error("Scale-out rungs must be at most 4, at ascending positive distances, with fractions summing to at most 1")]
+    InvalidScaleOut,
+
+    /// An exit rule relative to the Average True Range was used before the ATR is known.
+    #[error("The Average True Range is not available yet")]
+    AtrUnavailable,
+
     /// The order type is not compatible with the operation.
     ///
     /// Use market or limit orders to open a position, and take profit, stop loss, or trailing stop to close a position.
     #[error("Try another order type")]
     MismatchedOrderType,
 
+    /// An event sink failed to record an event.
+    ///
+    /// ### Arguments
+    /// * `0` - The underlying sink error.
+    #[cfg(feature = "metrics")]
+    #[error("Event sink: {0}")]
+    EventSink(String),
+
+    /// The Binance API failed or returned an unexpected response.
+    ///
+    /// ### Arguments
+    /// * `0` - The underlying error.
+    #[cfg(feature = "data-binance")]
+    #[error("Binance: {0}")]
+    Binance(String),
+
     /// An error with plotters crate.
     ///
     /// ### Arguments
@@ -193,12 +358,86 @@ impl<'de> serde::Deserialize<'de> for Error {
             ExitPrice {
                 price: f64,
             },
+            InvalidExitRule {
+                price: f64,
+                market: f64,
+            },
+            InvalidPrice {
+                price: f64,
+            },
+            InvalidQuantity {
+                quantity: f64,
+            },
+            MinQuantity {
+                quantity: f64,
+                min_quantity: f64,
+            },
+            MinNotional {
+                value: f64,
+                min_notional: f64,
+            },
+            LotSize {
+                quantity: f64,
+                lot_size: f64,
+            },
+            InvalidAmend {
+                price: f64,
+                quantity: f64,
+            },
+            InvalidScaleIn {
+                quantity: f64,
+                price: f64,
+            },
+            SessionClosed {
+                datetime: i64,
+            },
+            IrregularSpacing {
+                datetime: i64,
+                elapsed: i64,
+                timeframe: i64,
+            },
+            MissingConversionRate {
+                from: String,
+                to: String,
+            },
+            WarmUp {
+                candles: usize,
+            },
+            RiskLimit {
+                limit: RiskLimit,
+            },
+            NettingRequired,
+            InvalidWeights {
+                total: f64,
+            },
+            InvalidQueueShare {
+                share: f64,
+            },
+            DuplicateTag {
+                tag: u64,
+            },
+            MissingPrice {
+                symbol: String,
+            },
+            Io {
+                error: String,
+            },
             Msg {
                 message: String,
             },
             NegTakeProfitAndStopLoss,
             NegZeroTrailingStop,
+            InvalidScaleOut,
+            AtrUnavailable,
             MismatchedOrderType,
+            #[cfg(feature = "metrics")]
+            EventSink {
+                error: String,
+            },
+            #[cfg(feature = "data-binance")]
+            Binance {
+                error: String,
+            },
             #[cfg(feature = "draws")]
             Plotters {
                 error: String,
@@ -237,10 +476,45 @@ impl<'de> serde::Deserialize<'de> for Error {
             ErrorWrapper::PositionNotFound => Error::PositionNotFound,
             ErrorWrapper::RemovePosition => Error::RemovePosition,
             ErrorWrapper::ExitPrice { price } => Error::ExitPrice(price),
+            ErrorWrapper::InvalidExitRule { price, market } => Error::InvalidExitRule(price, market),
+            ErrorWrapper::InvalidPrice { price } => Error::InvalidPrice(price),
+            ErrorWrapper::InvalidQuantity { quantity } => Error::InvalidQuantity(quantity),
+            ErrorWrapper::MinQuantity { quantity, min_quantity } => Error::MinQuantity(quantity, min_quantity),
+            ErrorWrapper::MinNotional { value, min_notional } => Error::MinNotional(value, min_notional),
+            ErrorWrapper::LotSize { quantity, lot_size } => Error::LotSize(quantity, lot_size),
+            ErrorWrapper::InvalidAmend { price, quantity } => Error::InvalidAmend(price, quantity),
+            ErrorWrapper::InvalidScaleIn { quantity, price } => Error::InvalidScaleIn(quantity, price),
+            ErrorWrapper::SessionClosed { datetime } => {
+                Error::SessionClosed(DateTime::from_timestamp_millis(datetime).unwrap_or(Utc::now()))
+            }
+            ErrorWrapper::IrregularSpacing {
+                datetime,
+                elapsed,
+                timeframe,
+            } => Error::IrregularSpacing(
+                DateTime::from_timestamp_millis(datetime).unwrap_or(Utc::now()),
+                TimeDelta::milliseconds(elapsed),
+                TimeDelta::milliseconds(timeframe),
+            ),
+            ErrorWrapper::MissingConversionRate { from, to } => Error::MissingConversionRate(from, to),
+            ErrorWrapper::WarmUp { candles } => Error::WarmUp(candles),
+            ErrorWrapper::RiskLimit { limit } => Error::RiskLimit(limit),
+            ErrorWrapper::NettingRequired => Error::NettingRequired,
+            ErrorWrapper::InvalidWeights { total } => Error::InvalidWeights(total),
+            ErrorWrapper::InvalidQueueShare { share } => Error::InvalidQueueShare(share),
+            ErrorWrapper::DuplicateTag { tag } => Error::DuplicateTag(tag),
+            ErrorWrapper::MissingPrice { symbol } => Error::MissingPrice(symbol),
+            ErrorWrapper::Io { error } => Error::Io(std::io::Error::other(error)),
             ErrorWrapper::Msg { message } => Error::Msg(message),
             ErrorWrapper::NegTakeProfitAndStopLoss => Error::NegTakeProfitAndStopLoss,
+            ErrorWrapper::AtrUnavailable => Error::AtrUnavailable,
             ErrorWrapper::NegZeroTrailingStop => Error::NegZeroTrailingStop,
+            ErrorWrapper::InvalidScaleOut => Error::InvalidScaleOut,
             ErrorWrapper::MismatchedOrderType => Error::MismatchedOrderType,
+            #[cfg(feature = "metrics")]
+            ErrorWrapper::EventSink { error } => Error::EventSink(error),
+            #[cfg(feature = "data-binance")]
+            ErrorWrapper::Binance { error } => Error::Binance(error),
             #[cfg(feature = "draws")]
             ErrorWrapper::Plotters { error } => Error::Plotters(error),
             #[cfg(feature = "draws")]
@@ -0,0 +1,181 @@
+use crate::engine::{Aggregation, Candle};
+
+use chrono::{DateTime, Utc};
+use ta::Next;
+
+/// One indicator per timeframe of an aggregation, fed with the aggregated candles.
+///
+/// Pass the candles given to the strategy by `Backtest::run_with_aggregator` to `next`: each new
+/// candle of a timeframe feeds its close to the indicator of that timeframe, and the latest value of
+/// each indicator is kept. An aggregated candle repeated on the following candles (while the next one
+/// is not complete) is not fed twice.
+///
+/// The i-th candle feeds the i-th indicator. The timeframes whose candle is not complete yet are
+/// missing from the candles, so the factors of the aggregation should be in ascending order.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+/// use ta::indicators::SimpleMovingAverage;
+///
+/// let candles = (0..8)
+///     .map(|i| {
+///         let open_time = DateTime::default() + Duration::minutes(15 * i);
+///         CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(100.0 + i as f64)
+///             .volume(1.0)
+///             .open_time(open_time)
+///             .close_time(open_time + Duration::minutes(15))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Arc<[_]>>();
+///
+/// let aggregator = DurationAggregation::new(&[Duration::minutes(15), Duration::hours(1)]).unwrap();
+/// let mut averages = MultiTimeframe::new(&aggregator, |_| SimpleMovingAverage::new(2).unwrap());
+/// let mut bts = Backtest::new(candles, 1000.0, None).unwrap();
+/// bts.run_with_aggregator(&aggregator, |_bts, candles| {
+///     averages.next(&candles);
+///     if let Some(hourly) = averages.value(1) {
+///         assert!(*hourly > 100.0);
+///     }
+///     Ok(())
+/// })
+/// .unwrap();
+/// // the two hourly candles closed at 103 and 107
+/// assert_eq!(averages.value(1), Some(&105.0));
+/// ```
+pub struct MultiTimeframe<T: Next<f64>> {
+    indicators: Vec<T>,
+    values: Vec<Option<T::Output>>,
+    last: Vec<Option<(DateTime<Utc>, DateTime<Utc>)>>,
+}
+
+impl<T: Next<f64>> MultiTimeframe<T> {
+    /// Creates one indicator per factor of the aggregation.
+    ///
+    /// ### Arguments
+    /// * `aggregator` - The aggregation given to `Backtest::run_with_aggregator`.
+    /// * `indicator` - A closure creating the indicator of a factor.
+    pub fn new<A: Aggregation>(aggregator: &A, indicator: impl FnMut(usize) -> T) -> Self {
+        Self::from_indicators(aggregator.factors().iter().copied().map(indicator))
+    }
+
+    /// Creates the indicators of the timeframes, in the order of the factors.
+    pub fn from_indicators(indicators: impl IntoIterator<Item = T>) -> Self {
+        let indicators = indicators.into_iter().collect::<Vec<_>>();
+        Self {
+            values: indicators.iter().map(|_| None).collect(),
+            last: vec![None; indicators.len()],
+            indicators,
+        }
+    }
+
+    /// Feeds the new aggregated candles to their indicators.
+    ///
+    /// ### Arguments
+    /// * `candles` - The candles given to the strategy, one per timeframe.
+    ///
+    /// ### Returns
+    /// The latest value of each indicator, `None` until its timeframe has a candle.
+    pub fn next(&mut self, candles: &[&Candle]) -> &[Option<T::Output>] {
+        let timeframes = self.indicators.iter_mut().zip(&mut self.values).zip(&mut self.last);
+        for (candle, ((indicator, value), last)) in candles.iter().zip(timeframes) {
+            let period = Some((candle.open_time(), candle.close_time()));
+            if *last != period {
+                *last = period;
+                *value = Some(indicator.next(candle.close()));
+            }
+        }
+        &self.values
+    }
+
+    /// Returns the latest value of the indicator of a timeframe.
+    pub fn value(&self, timeframe: usize) -> Option<&T::Output> {
+        self.values.get(timeframe)?.as_ref()
+    }
+
+    /// Returns the latest value of each indicator.
+    pub fn values(&self) -> &[Option<T::Output>] {
+        &self.values
+    }
+
+    /// Returns the indicator of a timeframe.
+    pub fn indicator(&self, timeframe: usize) -> Option<&T> {
+        self.indicators.get(timeframe)
+    }
+
+    /// Returns the number of timeframes.
+    pub fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    /// Returns `true` if there is no timeframe.
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn multi_timeframe() {
+    use std::sync::Arc;
+
+    use crate::engine::{Backtest, CandleBuilder};
+    use chrono::Duration;
+    use ta::indicators::SimpleMovingAverage;
+
+    struct Aggregator;
+    impl Aggregation for Aggregator {
+        fn factors(&self) -> &[usize] {
+            &[1, 4]
+        }
+
+        fn ready(&self, factor: usize, candles: &[&Candle]) -> Option<(std::ops::Range<usize>, usize)> {
+            //? consecutive buckets of `factor` candles
+            (candles.len() == factor).then_some((0..factor, factor))
+        }
+    }
+
+    let candles = (0..10)
+        .map(|i| {
+            let open_time = DateTime::default() + Duration::hours(i);
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(120.0)
+                .low(90.0)
+                .close(100.0 + i as f64)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::hours(1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+
+    let mut averages = MultiTimeframe::new(&Aggregator, |factor| SimpleMovingAverage::new(factor).unwrap());
+    assert_eq!(averages.len(), 2);
+    assert_eq!(averages.values(), &[None, None]);
+
+    let mut fed = Vec::new();
+    let mut bt = Backtest::new(candles, 1000.0, None).unwrap();
+    bt.run_with_aggregator(&Aggregator, |_, candles| {
+        fed.push(averages.next(&candles).to_vec());
+        Ok(())
+    })
+    .unwrap();
+
+    // the candle itself, then the 4-hour candles closed at 103 and 107, each fed once
+    assert_eq!(fed[0], vec![Some(100.0), None]);
+    assert_eq!(fed[3], vec![Some(103.0), Some(103.0)]);
+    assert_eq!(fed[6], vec![Some(106.0), Some(103.0)]);
+    assert_eq!(averages.value(0), Some(&109.0));
+    assert_eq!(averages.value(1), Some(&105.0));
+    assert_eq!(averages.value(2), None);
+}
@@ -31,6 +31,7 @@
 //! |-----------------------------|---------------------------------------------------------------|
 //! | **Market Order**            | Executes immediately at the current price.                    |
 //! | **Limit Order**             | Executes only at a specified price or better.                 |
+//! | **Stop Order**              | Becomes a market order once the price crosses a trigger.      |
 //! | **Take-Profit**             | Closes the position when a target price is reached.           |
 //! | **Stop-Loss**               | Closes the position to limit losses.                          |
 //! | **Trailing Stop**           | Dynamically adjusts the stop price based on market movements. |
@@ -44,6 +45,7 @@
 //! | **Profit Factor**    | Ratio of gross profits to gross losses.                |
 //! | **Sharpe Ratio**     | Risk-adjusted return (higher = better).                |
 //! | **Win Rate**         | Percentage of winning trades.                          |
+//! | **CAGR**             | Compound annual growth rate of the equity (%).         |
 //!
 //! ### 4. **Optimization Tools**
 //!
@@ -61,7 +63,7 @@
 //! ### 2. Run a Simple Backtest:
 //! ```rust
 //! use std::sync::Arc;
-//! 
+//!
 //! use bts_rs::prelude::*;
 //! use chrono::{DateTime, Duration};
 //!
@@ -109,7 +111,7 @@
 //! Final Balance: 10018.00
 //! Profit & Loss (P&L): 0.00
 //! Fees paid: 0.00
-//! 
+//!
 //! Max Drawdown: 0.20%
 //! Profit Factor: 2.00
 //! Sharpe Ratio: 1.50
@@ -141,7 +143,7 @@
 //! Example:
 //! ```rust
 //! use std::sync::Arc;
-//! 
+//!
 //! use bts_rs::prelude::*;
 //! use chrono::{DateTime, Duration};
 //!
@@ -156,10 +158,10 @@
 //!     .close_time(DateTime::default() + Duration::days(1))
 //!     .build()
 //!     .unwrap();
-//! 
+//!
 //! // Initialize backtest with \$10,000
 //! let mut backtest = Backtest::new(Arc::from_iter(vec![candle]), 10_000.0, None).unwrap();
-//! 
+//!
 //! // Execute a market buy order
 //! backtest
 //!     .run(|bt, candle| {
@@ -188,6 +190,18 @@ pub mod engine;
 /// Error types for the library.
 pub mod errors;
 
+/// Validation and repair of candle data.
+pub mod data;
+
+/// Time-series cross-validation: train/test splits of candle data.
+pub mod split;
+
+/// Paper trading of a strategy on a live candle feed, with the engine of the backtest.
+pub mod paper;
+
+/// Ensemble of strategies trading one shared wallet in a single backtest.
+pub mod ensemble;
+
 /// Utility functions and helpers.
 mod utils;
 
@@ -195,14 +209,69 @@ mod utils;
 #[cfg(feature = "metrics")]
 pub mod metrics;
 
+/// Persistence sinks for the backtest events.
+#[cfg(feature = "metrics")]
+pub mod sinks;
+
+/// Regression testing of the backtest results against a golden result.
+#[cfg(feature = "metrics")]
+pub mod regression;
+
+/// Manifest of a backtest run: strategy parameters, data hash and engine configuration.
+#[cfg(feature = "serde")]
+pub mod manifest;
+
 /// Strategy parameter optimization.
 #[cfg(feature = "optimizer")]
 pub mod optimizer;
 
+/// Server-Sent Events endpoint streaming the run progress and events.
+#[cfg(feature = "server")]
+pub mod server;
+
+/// REST service running backtests for remote clients.
+#[cfg(feature = "service")]
+pub mod service;
+
+/// Binance klines downloader.
+#[cfg(feature = "data-binance")]
+pub mod binance;
+
+/// Python bindings of the engine.
+#[cfg(feature = "python")]
+pub mod python;
+
+/// JavaScript bindings of the engine for the browser.
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(all(
+    target_arch = "wasm32",
+    any(
+        feature = "draws",
+        feature = "sqlite",
+        feature = "server",
+        feature = "service",
+        feature = "data-binance",
+        feature = "python"
+    )
+))]
+compile_error!(
+    "The `draws`, `sqlite`, `server`, `service`, `data-binance` and `python` features need files, sockets or native libraries and are not supported on wasm32 targets."
+);
+
+/// Indicators of the `ta` crate fed with the aggregated candles of several timeframes.
+#[cfg(feature = "indicators")]
+pub mod indicators;
+
 /// Module for visualizing backtest results and candle charts.
 #[cfg(feature = "draws")]
 pub mod draws;
 
+/// Single-file HTML report of a backtest.
+#[cfg(feature = "report")]
+pub mod report;
+
 /// Re-exports of commonly used types and traits for convenience.
 pub mod prelude {
     pub use super::PercentCalculus;
@@ -211,11 +280,32 @@ pub mod prelude {
     #[cfg(feature = "metrics")]
     pub use crate::metrics::*;
 
+    #[cfg(feature = "metrics")]
+    pub use crate::sinks::*;
+
+    #[cfg(feature = "metrics")]
+    pub use crate::regression::*;
+
     #[cfg(feature = "optimizer")]
     pub use crate::optimizer::*;
 
     #[cfg(feature = "draws")]
     pub use crate::draws::*;
+
+    #[cfg(feature = "report")]
+    pub use crate::report::*;
+
+    #[cfg(feature = "indicators")]
+    pub use crate::indicators::*;
+
+    #[cfg(feature = "server")]
+    pub use crate::server::*;
+
+    #[cfg(feature = "service")]
+    pub use crate::service::*;
+
+    #[cfg(feature = "data-binance")]
+    pub use crate::binance::*;
 }
 
 use std::ops::{Add, Div, Mul, Sub};
@@ -0,0 +1,305 @@
+//! Manifest of a backtest run.
+//!
+//! A `RunManifest` records what produced a result: the strategy name, its parameters, a hash of the
+//! candle data and the configuration of the engine. Save it next to the metrics so a result stays
+//! attributable long after the run, and compare the data hashes to check that two results come from
+//! the same candles.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+#[cfg(feature = "metrics")]
+use std::path::PathBuf;
+
+use crate::engine::{Backtest, FeeLeg};
+use crate::errors::{Error, Result};
+#[cfg(feature = "metrics")]
+use crate::regression::BacktestResult;
+use crate::utils::{FNV_OFFSET, fnv1a};
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+/// Manifest of a backtest run: the strategy, its parameters, the data and the engine configuration.
+///
+/// The run ID is a hash of the content of the manifest and of its creation time, like a commit
+/// hash. The fees of a fee model are recorded as the percentages charged on a first taker and maker
+/// leg of 100.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::manifest::RunManifest;
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Parameters {
+///     period: usize,
+///     take_profit: f64,
+/// }
+///
+/// let candle = CandleBuilder::builder()
+///     .open(100.0)
+///     .high(110.0)
+///     .low(95.0)
+///     .close(105.0)
+///     .volume(1.0)
+///     .open_time(DateTime::default())
+///     .close_time(DateTime::default() + Duration::days(1))
+///     .build()
+///     .unwrap();
+///
+/// let mut bts = Backtest::new(Arc::from_iter(vec![candle]), 1000.0, Some((0.1, 0.05))).unwrap();
+/// bts.run(|_bts, _candle| Ok(())).unwrap();
+///
+/// let parameters = Parameters { period: 14, take_profit: 2.0 };
+/// let manifest = RunManifest::capture(&bts, "breakout", &parameters)
+///     .unwrap()
+///     .with_tag("v2");
+/// assert_eq!(manifest.run_id().len(), 16);
+/// assert_eq!(manifest.parameters_as::<Parameters>().unwrap().period, 14);
+/// assert_eq!(manifest.config()["fees"]["taker"], 0.1);
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RunManifest {
+    run_id: String,
+    name: String,
+    tag: Option<String>,
+    parameters: Value,
+    data_hash: String,
+    candles: usize,
+    config: Value,
+    version: String,
+    created_at: DateTime<Utc>,
+}
+
+impl RunManifest {
+    /// Captures the manifest of a run.
+    ///
+    /// ### Arguments
+    /// * `backtest` - The backtest, before or after the run.
+    /// * `name` - The name of the strategy.
+    /// * `parameters` - The parameters of the strategy.
+    ///
+    /// ### Returns
+    /// The manifest, or an error if the parameters cannot be serialized.
+    pub fn capture<P: Serialize>(backtest: &Backtest, name: &str, parameters: &P) -> Result<Self> {
+        let parameters = serde_json::to_value(parameters).map_err(|e| Error::Msg(e.to_string()))?;
+        let mut manifest = Self {
+            run_id: String::new(),
+            name: name.to_string(),
+            tag: None,
+            parameters,
+            data_hash: format!("{:016x}", backtest.fingerprint()),
+            candles: backtest.candles().len(),
+            config: Self::engine_config(backtest),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+        };
+        let content = serde_json::to_vec(&manifest).map_err(|e| Error::Msg(e.to_string()))?;
+        manifest.run_id = format!("{:016x}", fnv1a(FNV_OFFSET, &content));
+        Ok(manifest)
+    }
+
+    /// Returns the configuration of the engine.
+    fn engine_config(backtest: &Backtest) -> Value {
+        let fees = backtest.fee_model().map(|fee_model| {
+            let fee = |taker: bool| fee_model.fee(&FeeLeg::new(100.0, 1.0, taker, 0.0));
+            json!({ "taker": fee(true), "maker": fee(false) })
+        });
+        json!({
+            "initial_balance": backtest.initial_balance(),
+            "fees": fees,
+            "fee_basis": backtest.fee_basis(),
+            "fee_currency": backtest.fee_currency(),
+            "borrow_rates": backtest.borrow_rates(),
+            "slippage": backtest.slippage(),
+            "depth": backtest.depth(),
+            "spread": backtest.spread(),
+            "fill_model": backtest.fill_model(),
+            "gap_fill": backtest.gap_fill(),
+            "limit_fill": backtest.limit_fill(),
+            "latency": backtest.latency(),
+            "warmup": backtest.warmup(),
+            "min_quantity": backtest.min_quantity(),
+            "position_mode": backtest.position_mode(),
+            "risk_manager": backtest.risk_manager(),
+            "instrument": backtest.instrument(),
+            "calendar": backtest.calendar(),
+            "timeframe": backtest.timeframe(),
+            "provenance": backtest.provenance(),
+        })
+    }
+
+    /// Tags the run, e.g., with the version of the strategy.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Returns the ID of the run, as 16 hexadecimal digits.
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Returns the name of the strategy.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the tag of the run, if any.
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Returns the parameters of the strategy, as JSON.
+    pub fn parameters(&self) -> &Value {
+        &self.parameters
+    }
+
+    /// Returns the parameters of the strategy, deserialized.
+    pub fn parameters_as<P: DeserializeOwned>(&self) -> Result<P> {
+        serde_json::from_value(self.parameters.clone()).map_err(|e| Error::Msg(e.to_string()))
+    }
+
+    /// Returns the fingerprint of the candle data, as 16 hexadecimal digits (see `data::fingerprint`).
+    pub fn data_hash(&self) -> &str {
+        &self.data_hash
+    }
+
+    /// Returns the number of candles of the data.
+    pub fn candles(&self) -> usize {
+        self.candles
+    }
+
+    /// Returns the configuration of the engine, as JSON.
+    pub fn config(&self) -> &Value {
+        &self.config
+    }
+
+    /// Returns the version of the crate that ran the backtest.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the creation time of the manifest.
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Writes the manifest as JSON.
+    pub fn to_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self).map_err(|e| Error::Msg(e.to_string()))
+    }
+
+    /// Reads a manifest written by `to_json`.
+    pub fn from_json<R: Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader(reader).map_err(|e| Error::Msg(e.to_string()))
+    }
+
+    /// Saves the manifest to a file.
+    ///
+    /// ### Arguments
+    /// * `path` - The path of the file, created or truncated.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_json(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads a manifest from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the manifest and the result of the run in a directory named after the run ID.
+    ///
+    /// ### Arguments
+    /// * `dir` - The parent directory, e.g., `runs`, created if needed.
+    /// * `result` - The result of the run.
+    ///
+    /// ### Returns
+    /// The directory of the run, holding `manifest.json` and `result.json`, or an error.
+    #[cfg(feature = "metrics")]
+    pub fn save_with_result(&self, dir: impl AsRef<Path>, result: &BacktestResult) -> Result<PathBuf> {
+        let dir = dir.as_ref().join(&self.run_id);
+        std::fs::create_dir_all(&dir)?;
+        self.save(dir.join("manifest.json"))?;
+        result.save(dir.join("result.json"))?;
+        Ok(dir)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn run_manifest() {
+    use std::sync::Arc;
+
+    use crate::engine::{CandleBuilder, Slippage};
+    use chrono::Duration;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Parameters {
+        period: usize,
+        threshold: f64,
+    }
+
+    let candles = (0..3)
+        .map(|i| {
+            let open_time = DateTime::default() + Duration::days(i);
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(100.0 + i as f64)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::days(1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+    let backtest = Backtest::new(candles.clone(), 1000.0, None)
+        .unwrap()
+        .with_slippage(Slippage::Percent(0.1));
+
+    let parameters = Parameters {
+        period: 20,
+        threshold: 1.5,
+    };
+    let manifest = RunManifest::capture(&backtest, "mean-reversion", &parameters).unwrap();
+    assert_eq!(manifest.name(), "mean-reversion");
+    assert_eq!(manifest.tag(), None);
+    assert_eq!(manifest.parameters_as::<Parameters>().unwrap(), parameters);
+    assert_eq!(manifest.candles(), 3);
+    assert_eq!(
+        manifest.data_hash(),
+        format!("{:016x}", crate::data::fingerprint(&candles))
+    );
+    assert_eq!(manifest.config()["initial_balance"], 1000.0);
+    assert_eq!(manifest.config()["fees"], Value::Null);
+    assert_eq!(manifest.config()["slippage"]["Percent"], 0.1);
+    assert!(manifest.run_id().chars().all(|c| c.is_ascii_hexdigit()));
+
+    let manifest = manifest.with_tag("v1");
+    let path = std::env::temp_dir().join("bts_run_manifest.json");
+    manifest.save(&path).unwrap();
+    assert_eq!(RunManifest::load(&path).unwrap(), manifest);
+    std::fs::remove_file(path).unwrap();
+
+    #[cfg(feature = "metrics")]
+    {
+        let dir = std::env::temp_dir().join("bts_runs");
+        let result = BacktestResult::from(&backtest);
+        let run_dir = manifest.save_with_result(&dir, &result).unwrap();
+        assert!(run_dir.ends_with(manifest.run_id()));
+        assert_eq!(RunManifest::load(run_dir.join("manifest.json")).unwrap(), manifest);
+        assert!(BacktestResult::load(run_dir.join("result.json")).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
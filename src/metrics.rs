@@ -5,19 +5,58 @@
 //! - Profit factor
 //! - Sharpe ratio
 //! - Win rate
+//! - CAGR, annualized return, volatility and Sharpe ratio
+//! - Trade list and per-trade statistics (expectancy, System Quality Number, Kelly fraction)
+//! - Trade clustering (runs test and autocorrelation of trade outcomes)
+//! - Winning and losing streaks
+//! - Equity curve and trade list (with CSV and JSON export)
+//! - Benchmark comparison (buy-and-hold return, alpha, beta, correlation, tracking error)
 //!
 //! Events generated during backtesting.
 //!
 //! This module defines the `Event` enum, which represents actions and state changes
 //! during a backtest, such as order execution, position updates, and wallet changes.
-//! 
+//!
 //! It needs to enable `metrics` feature to use it. Take a look at [trailing stop](https://github.com/raonagos/bts-rs/blob/master/examples/trailing_stop.rs#L62) for example.
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
 
+use crate::PercentCalculus;
 use crate::engine::*;
+use crate::errors::{Error, Result};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Duration of a year used to annualize the metrics (365.25 days).
+const YEAR: TimeDelta = TimeDelta::hours(365 * 24 + 6);
+
+/// Returns the number of years between two dates.
+fn years_between(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_milliseconds() as f64 / YEAR.num_milliseconds() as f64
+}
 
-use chrono::{DateTime, Utc};
+/// Policy defining when the wallet updates are recorded as events.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EquitySampling {
+    /// A wallet update is recorded with each order and position event.
+    #[default]
+    EveryTrade,
+    /// A wallet update is recorded once each candle is processed.
+    EveryCandle,
+    /// A wallet update is recorded every N candles.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of candles between two updates.
+    EveryNCandles(usize),
+    /// A wallet update is recorded on the first candle of each UTC day.
+    Daily,
+}
 
 /// Events generated during a backtest.
 ///
@@ -35,9 +74,20 @@ pub enum Event {
 
     /// An order has been removed from the backtest.
     ///
-    /// This event is triggered when an order is canceled or executed.
+    /// This event is triggered when an order is cancelled or rejected, as given by its status.
     DelOrder(DateTime<Utc>, Order),
 
+    /// A pending order has been amended.
+    ///
+    /// This event is triggered when the price or the quantity of a pending order is changed; it
+    /// contains the amended order, which keeps its ID.
+    AmendOrder(DateTime<Utc>, Order),
+
+    /// An order has expired.
+    ///
+    /// This event is triggered when the time-in-force of a pending order elapses before it is filled.
+    OrderExpired(DateTime<Utc>, Order),
+
     /// A position has been opened.
     ///
     /// This event is triggered when an order is executed and a new position is created.
@@ -48,6 +98,29 @@ pub enum Event {
     /// This event is triggered when a position is closed, either manually or by an exit rule.
     DelPosition(DateTime<Utc>, Position),
 
+    /// The exit rule of a position has been modified.
+    ///
+    /// This event is triggered when the strategy moves the stop or the target of an open position.
+    ModifyPosition(DateTime<Utc>, Position),
+
+    /// A position has been increased.
+    ///
+    /// This event is triggered when the strategy adds to an open position (scale-in); it holds the
+    /// position with its new quantity and average entry price.
+    IncreasePosition(DateTime<Utc>, Position),
+
+    /// An order has been rejected by the risk manager.
+    ///
+    /// This event is triggered when an order violates a limit of the risk manager; it is not placed.
+    RiskRejected(DateTime<Utc>, Order, RiskLimit),
+
+    /// An order has been rejected by the engine.
+    ///
+    /// This event is triggered when `Backtest::place_order` refuses an order (e.g., insufficient
+    /// funds or an invalid exit rule); it holds the rejected order and the reason. The orders rejected
+    /// by the risk manager are recorded with `Event::RiskRejected` instead.
+    OrderRejected(DateTime<Utc>, Order, RejectReason),
+
     /// The wallet balance has been updated.
     ///
     /// This event is triggered after each trade or fee deduction.
@@ -68,6 +141,99 @@ pub enum Event {
     },
 }
 
+/// Reason why the engine rejected an order, recorded with `Event::OrderRejected`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RejectReason {
+    /// The order was placed during the warm-up period.
+    WarmUp,
+
+    /// The order was placed while the trading session is closed.
+    SessionClosed,
+
+    /// The free balance does not cover the order.
+    ///
+    /// ### Arguments
+    /// * `0` - The required amount.
+    /// * `1` - The available amount.
+    InsufficientFunds(f64, f64),
+
+    /// The order value is below the minimum notional of the exchange.
+    ///
+    /// ### Arguments
+    /// * `0` - The order value.
+    /// * `1` - The minimum notional.
+    MinNotional(f64, f64),
+
+    /// The order quantity is below the lot size of the instrument.
+    ///
+    /// ### Arguments
+    /// * `0` - The order quantity.
+    /// * `1` - The lot size.
+    LotSize(f64, f64),
+
+    /// The take-profit, stop-loss or trailing stop of the order is invalid.
+    InvalidExitRule,
+
+    /// The price, the quantity or the type of the order is invalid.
+    InvalidOrder,
+}
+
+impl From<&Error> for RejectReason {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::WarmUp(_) => Self::WarmUp,
+            Error::SessionClosed(_) => Self::SessionClosed,
+            Error::InsufficientFunds(required, available) => Self::InsufficientFunds(*required, *available),
+            Error::MinNotional(value, min_notional) => Self::MinNotional(*value, *min_notional),
+            Error::LotSize(quantity, lot_size) => Self::LotSize(*quantity, *lot_size),
+            Error::ExitPrice(_)
+            | Error::InvalidExitRule(..)
+            | Error::NegTakeProfitAndStopLoss
+            | Error::NegZeroTrailingStop
+            | Error::AtrUnavailable => Self::InvalidExitRule,
+            _ => Self::InvalidOrder,
+        }
+    }
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WarmUp => write!(f, "warm-up"),
+            Self::SessionClosed => write!(f, "session closed"),
+            Self::InsufficientFunds(required, available) => {
+                write!(f, "insufficient funds: required {required}, available {available}")
+            }
+            Self::MinNotional(value, min_notional) => {
+                write!(f, "order value {value} below the minimum notional {min_notional}")
+            }
+            Self::LotSize(quantity, lot_size) => write!(f, "quantity {quantity} below the lot size {lot_size}"),
+            Self::InvalidExitRule => write!(f, "invalid exit rule"),
+            Self::InvalidOrder => write!(f, "invalid order"),
+        }
+    }
+}
+
+impl Event {
+    /// Returns the date of the event.
+    pub fn datetime(&self) -> DateTime<Utc> {
+        match self {
+            Self::AddOrder(datetime, _)
+            | Self::DelOrder(datetime, _)
+            | Self::AmendOrder(datetime, _)
+            | Self::OrderExpired(datetime, _)
+            | Self::AddPosition(datetime, _)
+            | Self::DelPosition(datetime, _)
+            | Self::ModifyPosition(datetime, _)
+            | Self::IncreasePosition(datetime, _)
+            | Self::RiskRejected(datetime, ..)
+            | Self::OrderRejected(datetime, ..)
+            | Self::WalletUpdate { datetime, .. } => *datetime,
+        }
+    }
+}
+
 impl From<(DateTime<Utc>, &Wallet)> for Event {
     fn from((datetime, value): (DateTime<Utc>, &Wallet)) -> Self {
         Self::WalletUpdate {
@@ -81,23 +247,566 @@ impl From<(DateTime<Utc>, &Wallet)> for Event {
     }
 }
 
+/// A closed trade reconstructed from the backtest events.
+///
+/// All monetary values are expressed in the quote currency.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trade {
+    entry_time: DateTime<Utc>,
+    exit_time: DateTime<Utc>,
+    entry_price: f64,
+    exit_price: f64,
+    side: PositionSide,
+    quantity: f64,
+    gross_pnl: f64,
+    fees: f64,
+    bars_held: usize,
+    mae: f64,
+    mfe: f64,
+    exit_rule: Option<OrderType>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    tag: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    exit_reason: ExitReason,
+}
+
+impl Trade {
+    /// Builds a trade from a closed position, its entry/exit times and the candles of the backtest.
+    fn new(entry_time: DateTime<Utc>, exit_time: DateTime<Utc>, position: &Position, candles: &CandleFrame) -> Self {
+        let entry_price = position.entry_price().expect("position should have an entry price");
+        let exit_price = *position.exit_price().expect("position should have an exit price");
+        let quantity = position.quantity();
+
+        let held = candles.between(entry_time, exit_time);
+
+        //? the excursions are tracked by the engine, the candles are only used for older events
+        let (mae, mfe) = if position.mae() > 0.0 || position.mfe() > 0.0 {
+            (position.mae(), position.mfe())
+        } else {
+            let highest = candles
+                .max_high(held.clone())
+                .map_or(entry_price.max(exit_price), |high| {
+                    high.max(entry_price.max(exit_price))
+                });
+            let lowest = candles
+                .min_low(held.clone())
+                .map_or(entry_price.min(exit_price), |low| low.min(entry_price.min(exit_price)));
+            let value = quantity * position.multiplier();
+            match position.side() {
+                PositionSide::Long => ((entry_price - lowest) * value, (highest - entry_price) * value),
+                PositionSide::Short => ((highest - entry_price) * value, (entry_price - lowest) * value),
+            }
+        };
+
+        Self {
+            entry_time,
+            exit_time,
+            entry_price,
+            exit_price,
+            side: *position.side(),
+            quantity,
+            gross_pnl: position.pnl().expect("pnl should be set the last exit price"),
+            fees: position.fees(),
+            bars_held: held.len().saturating_sub(1),
+            mae,
+            mfe,
+            exit_rule: position.exit_rule().copied(),
+            tag: position.tag(),
+            exit_reason: position.exit_reason(),
+        }
+    }
+
+    /// Returns the time the position was opened.
+    pub fn entry_time(&self) -> DateTime<Utc> {
+        self.entry_time
+    }
+
+    /// Returns the time the position was closed.
+    pub fn exit_time(&self) -> DateTime<Utc> {
+        self.exit_time
+    }
+
+    /// Returns the entry price.
+    pub fn entry_price(&self) -> f64 {
+        self.entry_price
+    }
+
+    /// Returns the exit price.
+    pub fn exit_price(&self) -> f64 {
+        self.exit_price
+    }
+
+    /// Returns the side of the position.
+    pub fn side(&self) -> &PositionSide {
+        &self.side
+    }
+
+    /// Returns the traded quantity.
+    pub fn quantity(&self) -> f64 {
+        self.quantity
+    }
+
+    /// Returns the profit and loss before fees.
+    pub fn gross_pnl(&self) -> f64 {
+        self.gross_pnl
+    }
+
+    /// Returns the fees paid to open and close the position.
+    pub fn fees(&self) -> f64 {
+        self.fees
+    }
+
+    /// Returns the profit and loss after fees.
+    pub fn net_pnl(&self) -> f64 {
+        self.gross_pnl - self.fees
+    }
+
+    /// Returns the number of candles between the entry candle and the exit candle.
+    pub fn bars_held(&self) -> usize {
+        self.bars_held
+    }
+
+    /// Returns the maximum adverse excursion (worst unrealized loss, as a positive amount).
+    pub fn mae(&self) -> f64 {
+        self.mae
+    }
+
+    /// Returns the maximum favorable excursion (best unrealized profit).
+    pub fn mfe(&self) -> f64 {
+        self.mfe
+    }
+
+    /// Returns true if the trade is a winner after fees.
+    pub fn is_win(&self) -> bool {
+        self.net_pnl() > 0.0
+    }
+
+    /// Returns the exit rule of the position, if any.
+    pub fn exit_rule(&self) -> Option<&OrderType> {
+        self.exit_rule.as_ref()
+    }
+
+    /// Returns the tag of the order that opened the position, if any.
+    pub fn tag(&self) -> Option<u64> {
+        self.tag
+    }
+
+    /// Returns the reason why the position was closed.
+    pub fn exit_reason(&self) -> ExitReason {
+        self.exit_reason
+    }
+}
+
+/// File format of the exported data.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row.
+    Csv,
+    /// An array of JSON objects.
+    Json,
+}
+
+/// Columns of the exported trades.
+const TRADE_COLUMNS: [&str; 14] = [
+    "entry_time",
+    "exit_time",
+    "side",
+    "quantity",
+    "entry_price",
+    "exit_price",
+    "gross_pnl",
+    "fees",
+    "net_pnl",
+    "bars_held",
+    "mae",
+    "mfe",
+    "exit_reason",
+    "tag",
+];
+
+/// The list of closed trades in chronological order of exit, with aggregate statistics.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trades(Vec<Trade>);
+
+impl std::ops::Deref for Trades {
+    type Target = [Trade];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Trades {
+    /// Groups the trades by the tag of their order, to attribute the P&L to each signal.
+    ///
+    /// The untagged trades are grouped under `None`.
+    pub fn by_tag(&self) -> BTreeMap<Option<u64>, Trades> {
+        let mut groups = BTreeMap::<_, Trades>::new();
+        for trade in self.iter() {
+            groups.entry(trade.tag()).or_default().0.push(*trade);
+        }
+        groups
+    }
+
+    /// Groups the trades by the reason why their position was closed, e.g., to compare the win
+    /// rate of the take profits and the trailing stops.
+    pub fn by_exit_reason(&self) -> BTreeMap<ExitReason, Trades> {
+        let mut groups = BTreeMap::<_, Trades>::new();
+        for trade in self.iter() {
+            groups.entry(trade.exit_reason()).or_default().0.push(*trade);
+        }
+        groups
+    }
+
+    /// Returns the total net profit of the trades.
+    pub fn net_pnl(&self) -> f64 {
+        self.iter().map(|t| t.net_pnl()).sum()
+    }
+
+    /// Returns the percentage of winning trades after fees (0.0 if there is none).
+    pub fn win_rate(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.iter().filter(|t| t.is_win()).count() as f64 / self.len() as f64 * 100.0
+    }
+
+    /// Returns the average net profit of the winning trades (0.0 if there is none).
+    pub fn avg_win(&self) -> f64 {
+        let wins = self
+            .iter()
+            .filter(|t| t.is_win())
+            .map(|t| t.net_pnl())
+            .collect::<Vec<_>>();
+        if wins.is_empty() {
+            return 0.0;
+        }
+        wins.iter().sum::<f64>() / wins.len() as f64
+    }
+
+    /// Returns the average net loss of the losing trades, as a negative value (0.0 if there is none).
+    pub fn avg_loss(&self) -> f64 {
+        let losses = self
+            .iter()
+            .filter(|t| !t.is_win())
+            .map(|t| t.net_pnl())
+            .collect::<Vec<_>>();
+        if losses.is_empty() {
+            return 0.0;
+        }
+        losses.iter().sum::<f64>() / losses.len() as f64
+    }
+
+    /// Returns the expectancy, the average net profit expected per trade.
+    pub fn expectancy(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.iter().map(|t| t.net_pnl()).sum::<f64>() / self.len() as f64
+    }
+
+    /// Returns the System Quality Number (Van Tharp): `√N × mean / standard deviation` of the net
+    /// profits of the trades, with N capped at 100 (0.0 with less than two trades or without variance).
+    ///
+    /// Above 2.0 is usually considered a good system, above 3.0 an excellent one.
+    pub fn system_quality_number(&self) -> f64 {
+        let n = self.len() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let mean = self.expectancy();
+        let variance = self.iter().map(|t| (t.net_pnl() - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        n.min(100.0).sqrt() * mean / variance.sqrt()
+    }
+
+    /// Returns the Kelly fraction, the share of the capital to risk per trade that maximizes the
+    /// growth: `W - (1 - W) / R`, where W is the win rate and R the ratio of the average win to the
+    /// average loss.
+    ///
+    /// It is negative when the edge is negative, 1.0 without losing trade, -1.0 without winning trade
+    /// and 0.0 without trade.
+    pub fn kelly_fraction(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let win_rate = self.win_rate() / 100.0;
+        let (avg_win, avg_loss) = (self.avg_win(), self.avg_loss().abs());
+        if avg_loss == 0.0 {
+            return 1.0;
+        }
+        if avg_win == 0.0 {
+            return -1.0;
+        }
+        win_rate - (1.0 - win_rate) / (avg_win / avg_loss)
+    }
+
+    /// Returns the longest sequence of consecutive winning trades.
+    pub fn longest_win_streak(&self) -> usize {
+        self.longest_streak(true)
+    }
+
+    /// Returns the longest sequence of consecutive losing trades.
+    pub fn longest_loss_streak(&self) -> usize {
+        self.longest_streak(false)
+    }
+
+    /// Detects the temporal clustering of wins and losses.
+    ///
+    /// Returns `None` if there are less than two trades or if all trades have the same outcome.
+    pub fn clustering(&self) -> Option<Clustering> {
+        let n = self.len() as f64;
+        let wins = self.iter().filter(|t| t.is_win()).count() as f64;
+        let losses = n - wins;
+        if wins == 0.0 || losses == 0.0 {
+            return None;
+        }
+
+        // Wald-Wolfowitz runs test
+        let runs = 1 + self.windows(2).filter(|w| w[0].is_win() != w[1].is_win()).count();
+        let expected_runs = 2.0 * wins * losses / n + 1.0;
+        let variance = 2.0 * wins * losses * (2.0 * wins * losses - n) / (n.powi(2) * (n - 1.0));
+        let z_score = if variance > 0.0 {
+            (runs as f64 - expected_runs) / variance.sqrt()
+        } else {
+            0.0
+        };
+
+        // lag-1 autocorrelation of the outcomes
+        let mean = self.expectancy();
+        let denominator = self.iter().map(|t| (t.net_pnl() - mean).powi(2)).sum::<f64>();
+        let numerator = self
+            .windows(2)
+            .map(|w| (w[0].net_pnl() - mean) * (w[1].net_pnl() - mean))
+            .sum::<f64>();
+        let autocorrelation = if denominator > 0.0 {
+            numerator / denominator
+        } else {
+            0.0
+        };
+
+        Some(Clustering {
+            runs,
+            expected_runs,
+            z_score,
+            autocorrelation,
+            autocorrelation_bound: Clustering::Z_CRITICAL / n.sqrt(),
+        })
+    }
+
+    fn longest_streak(&self, win: bool) -> usize {
+        let (mut longest, mut current) = (0, 0);
+        for trade in self.iter() {
+            if trade.is_win() == win {
+                current += 1;
+                longest = longest.max(current);
+            } else {
+                current = 0;
+            }
+        }
+        longest
+    }
+}
+
+/// Result of the independence analysis of the trade outcomes.
+///
+/// Kelly sizing and Monte Carlo reshuffling assume that the trade results are independent.
+/// The analysis combines a Wald-Wolfowitz runs test on the win/loss sequence with the lag-1
+/// autocorrelation of the net P&L, both evaluated at a 95% confidence level.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clustering {
+    runs: usize,
+    expected_runs: f64,
+    z_score: f64,
+    autocorrelation: f64,
+    autocorrelation_bound: f64,
+}
+
+impl Clustering {
+    /// Critical value of the standard normal distribution at a 95% confidence level.
+    const Z_CRITICAL: f64 = 1.96;
+
+    /// Returns the number of runs (sequences of consecutive wins or losses).
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Returns the number of runs expected if the outcomes are independent.
+    pub fn expected_runs(&self) -> f64 {
+        self.expected_runs
+    }
+
+    /// Returns the z-score of the runs test.
+    ///
+    /// A negative value means fewer runs than expected (wins and losses are clustered),
+    /// a positive value means more runs than expected (wins and losses alternate).
+    pub fn z_score(&self) -> f64 {
+        self.z_score
+    }
+
+    /// Returns the lag-1 autocorrelation of the net P&L of the trades.
+    pub fn autocorrelation(&self) -> f64 {
+        self.autocorrelation
+    }
+
+    /// Returns true if wins and losses are significantly clustered together.
+    pub fn is_clustered(&self) -> bool {
+        self.z_score < -Self::Z_CRITICAL || self.autocorrelation > self.autocorrelation_bound
+    }
+
+    /// Returns true if neither the runs test nor the autocorrelation reject the independence of the trades.
+    pub fn is_independent(&self) -> bool {
+        self.z_score.abs() <= Self::Z_CRITICAL && self.autocorrelation.abs() <= self.autocorrelation_bound
+    }
+}
+
+/// Streaks of consecutive winning and losing positions.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Streaks {
+    longest_win: usize,
+    longest_loss: usize,
+    average_length: f64,
+    worst_loss: f64,
+}
+
+impl Streaks {
+    /// Returns the longest sequence of consecutive winning positions.
+    pub fn longest_win(&self) -> usize {
+        self.longest_win
+    }
+
+    /// Returns the longest sequence of consecutive losing positions.
+    pub fn longest_loss(&self) -> usize {
+        self.longest_loss
+    }
+
+    /// Returns the average length of the streaks, winning and losing (0.0 without position).
+    pub fn average_length(&self) -> f64 {
+        self.average_length
+    }
+
+    /// Returns the cumulative P&L of the losing streak that lost the most (0.0 without losing position).
+    pub fn worst_loss(&self) -> f64 {
+        self.worst_loss
+    }
+}
+
+/// Comparison of the strategy against a benchmark series.
+///
+/// The strategy equity is sampled at the close of each benchmark candle. Alpha, beta, correlation and
+/// tracking error are computed on these per-candle returns, expressed as percentages.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Benchmark {
+    strategy_return: f64,
+    benchmark_return: f64,
+    alpha: f64,
+    beta: f64,
+    correlation: f64,
+    tracking_error: f64,
+}
+
+impl Benchmark {
+    /// Returns the total return of the strategy (percentage).
+    pub fn strategy_return(&self) -> f64 {
+        self.strategy_return
+    }
+
+    /// Returns the buy-and-hold return of the benchmark (percentage).
+    pub fn benchmark_return(&self) -> f64 {
+        self.benchmark_return
+    }
+
+    /// Returns the excess return of the strategy over the buy-and-hold return (percentage).
+    pub fn excess_return(&self) -> f64 {
+        self.strategy_return - self.benchmark_return
+    }
+
+    /// Returns the per-candle return not explained by the benchmark (percentage).
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Returns the sensitivity of the strategy returns to the benchmark returns.
+    pub fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    /// Returns the correlation between the strategy and the benchmark returns.
+    pub fn correlation(&self) -> f64 {
+        self.correlation
+    }
+
+    /// Returns the standard deviation of the per-candle excess returns (percentage).
+    pub fn tracking_error(&self) -> f64 {
+        self.tracking_error
+    }
+}
+
+/// Rolling metrics, to see how the performance of a strategy evolves over the backtest.
+///
+/// Each point is computed over the `window` last observations and keyed by the datetime of the
+/// last one. There is no point before the window is full.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rolling {
+    window: usize,
+    sharpe_ratio: Vec<(DateTime<Utc>, f64)>,
+    win_rate: Vec<(DateTime<Utc>, f64)>,
+    max_drawdown: Vec<(DateTime<Utc>, f64)>,
+}
+
+impl Rolling {
+    /// Returns the number of observations of the window.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Returns the Sharpe ratio (risk-free rate = 0.0) of the `window` last balance returns.
+    ///
+    /// The ratio is 0.0 when the balance did not move during the window.
+    pub fn sharpe_ratio(&self) -> &[(DateTime<Utc>, f64)] {
+        &self.sharpe_ratio
+    }
+
+    /// Returns the win rate, as a percentage, of the `window` last closed trades.
+    pub fn win_rate(&self) -> &[(DateTime<Utc>, f64)] {
+        &self.win_rate
+    }
+
+    /// Returns the maximum drawdown, as a percentage, within the `window` last balances.
+    pub fn max_drawdown(&self) -> &[(DateTime<Utc>, f64)] {
+        &self.max_drawdown
+    }
+}
+
 /// A collection of trading metrics calculated from a series of events.
 ///
 /// `Metrics` is used to compute and display key performance indicators (KPIs)
 /// for a trading strategy, such as max drawdown, profit factor, Sharpe ratio, and win rate.
 /// It is typically constructed from a `Backtest` or a list of `Event`s.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
 pub struct Metrics {
     pnl: f64,
     fees: f64,
     balance: f64,
     events: Vec<Event>,
+    candles: Arc<CandleFrame>,
     initial_balance: f64,
 }
 
 impl From<&Backtest> for Metrics {
     fn from(value: &Backtest) -> Self {
         Self {
+            candles: value.frame(),
             fees: value.fees_paid(),
             balance: value.balance(),
             pnl: value.unrealized_pnl(),
@@ -116,9 +825,27 @@ impl Metrics {
             events,
             balance,
             initial_balance,
+            candles: Arc::default(),
         }
     }
 
+    /// Sets the candles the events were generated from, used to compute per-trade statistics.
+    pub fn with_candles(mut self, candles: Arc<[Candle]>) -> Self {
+        self.candles = Arc::new(CandleFrame::from(&candles[..]));
+        self
+    }
+
+    /// Sets the candles the events were generated from, already in columnar storage.
+    pub fn with_frame(mut self, candles: Arc<CandleFrame>) -> Self {
+        self.candles = candles;
+        self
+    }
+
+    /// Returns the candles the events were generated from.
+    pub fn candles(&self) -> &CandleFrame {
+        &self.candles
+    }
+
     /// Returns the initial balance.
     pub fn initial_balance(&self) -> f64 {
         self.initial_balance
@@ -144,6 +871,292 @@ impl Metrics {
         self.pnl
     }
 
+    /// Returns the list of closed trades.
+    ///
+    /// Trades are matched from `AddPosition` and `DelPosition` events. The number of bars held
+    /// and the MAE/MFE are computed from the candles, so they are only meaningful when the
+    /// metrics were built from a `Backtest` or with `Metrics::with_candles`.
+    pub fn trades(&self) -> Trades {
+        let mut entries = HashMap::new();
+        let mut trades = Vec::new();
+
+        for event in &self.events {
+            match event {
+                Event::AddPosition(datetime, position) => {
+                    entries.insert(position.id(), *datetime);
+                }
+                Event::DelPosition(datetime, position) => {
+                    //? a netted position can be closed in several parts
+                    let entry_time = entries.get(&position.id()).copied().unwrap_or(*datetime);
+                    trades.push(Trade::new(entry_time, *datetime, position, &self.candles));
+                }
+                _ => {}
+            }
+        }
+
+        Trades(trades)
+    }
+
+    /// Returns the equity curve: the cash balance plus the marked-to-market value of the open positions.
+    ///
+    /// There is one point per candle, valued at the candle close, when the metrics were built from a
+    /// `Backtest` or with `Metrics::with_candles`. Otherwise there is one point per event date and
+    /// the open positions are valued at their entry price.
+    pub fn equity_curve(&self) -> Vec<(DateTime<Utc>, f64)> {
+        let mut balance = self.initial_balance;
+        let mut positions = HashMap::new();
+        let mut events = self.events.iter().peekable();
+        let mut curve = Vec::new();
+
+        if self.candles.is_empty() {
+            while let Some(datetime) = events.peek().map(|e| e.datetime()) {
+                while let Some(event) = events.next_if(|e| e.datetime() == datetime) {
+                    Self::replay(event, &mut balance, &mut positions);
+                }
+                let value = positions.values().map(|p| p.cost().unwrap_or_default()).sum::<f64>();
+                curve.push((datetime, balance + value));
+            }
+        } else {
+            let candles = &self.candles;
+            curve.reserve(candles.len());
+            for ((open_time, close), close_time) in candles
+                .open_time()
+                .iter()
+                .zip(candles.close())
+                .zip(candles.close_time())
+            {
+                while let Some(event) = events.next_if(|e| e.datetime() <= *open_time) {
+                    Self::replay(event, &mut balance, &mut positions);
+                }
+                let value = positions
+                    .values()
+                    .map(|p| p.cost().unwrap_or_default() + p.estimate_pnl(*close).unwrap_or_default())
+                    .sum::<f64>();
+                curve.push((*close_time, balance + value));
+            }
+        }
+
+        curve
+    }
+
+    /// Writes the equity curve as CSV (`datetime,equity` header, RFC 3339 dates).
+    pub fn equity_curve_to_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "datetime,equity")?;
+        for (datetime, equity) in self.equity_curve() {
+            writeln!(writer, "{},{}", datetime.to_rfc3339(), equity)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the equity curve as a JSON array of `{"datetime": ..., "equity": ...}` objects.
+    pub fn equity_curve_to_json<W: Write>(&self, mut writer: W) -> Result<()> {
+        write!(writer, "[")?;
+        for (i, (datetime, equity)) in self.equity_curve().into_iter().enumerate() {
+            let separator = if i > 0 { "," } else { "" };
+            let equity = if equity.is_finite() {
+                equity.to_string()
+            } else {
+                "null".to_string()
+            };
+            write!(
+                writer,
+                r#"{separator}{{"datetime":"{}","equity":{equity}}}"#,
+                datetime.to_rfc3339()
+            )?;
+        }
+        write!(writer, "]")?;
+        Ok(())
+    }
+
+    /// Writes the closed trades as CSV, one trade per row (RFC 3339 dates, empty tag if untagged).
+    pub fn trades_to_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "{}", TRADE_COLUMNS.join(","))?;
+        for trade in self.trades().iter() {
+            let side = match trade.side() {
+                PositionSide::Long => "long",
+                PositionSide::Short => "short",
+            };
+            writeln!(
+                writer,
+                "{},{},{side},{},{},{},{},{},{},{},{},{},{},{}",
+                trade.entry_time().to_rfc3339(),
+                trade.exit_time().to_rfc3339(),
+                trade.quantity(),
+                trade.entry_price(),
+                trade.exit_price(),
+                trade.gross_pnl(),
+                trade.fees(),
+                trade.net_pnl(),
+                trade.bars_held(),
+                trade.mae(),
+                trade.mfe(),
+                trade.exit_reason(),
+                trade.tag().map(|tag| tag.to_string()).unwrap_or_default(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the closed trades as a JSON array of objects, with the same fields as the CSV export.
+    pub fn trades_to_json<W: Write>(&self, mut writer: W) -> Result<()> {
+        let number = |value: f64| {
+            if value.is_finite() {
+                value.to_string()
+            } else {
+                "null".to_string()
+            }
+        };
+        write!(writer, "[")?;
+        for (i, trade) in self.trades().iter().enumerate() {
+            let separator = if i > 0 { "," } else { "" };
+            let side = match trade.side() {
+                PositionSide::Long => "long",
+                PositionSide::Short => "short",
+            };
+            let values = [
+                format!(r#""{}""#, trade.entry_time().to_rfc3339()),
+                format!(r#""{}""#, trade.exit_time().to_rfc3339()),
+                format!(r#""{side}""#),
+                number(trade.quantity()),
+                number(trade.entry_price()),
+                number(trade.exit_price()),
+                number(trade.gross_pnl()),
+                number(trade.fees()),
+                number(trade.net_pnl()),
+                trade.bars_held().to_string(),
+                number(trade.mae()),
+                number(trade.mfe()),
+                format!(r#""{}""#, trade.exit_reason()),
+                trade.tag().map_or("null".to_string(), |tag| tag.to_string()),
+            ];
+            let fields = TRADE_COLUMNS
+                .iter()
+                .zip(values)
+                .map(|(column, value)| format!(r#""{column}":{value}"#))
+                .collect::<Vec<_>>();
+            write!(writer, "{separator}{{{}}}", fields.join(","))?;
+        }
+        write!(writer, "]")?;
+        Ok(())
+    }
+
+    /// Exports the closed trades to a file, to audit them in a spreadsheet or a dataframe.
+    ///
+    /// ### Arguments
+    /// * `path` - The path of the file, created or truncated.
+    /// * `format` - The format of the file.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an I/O error.
+    pub fn export_trades(&self, path: impl AsRef<Path>, format: ExportFormat) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        match format {
+            ExportFormat::Csv => self.trades_to_csv(&mut writer)?,
+            ExportFormat::Json => self.trades_to_json(&mut writer)?,
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Applies an event to the replayed balance and open positions.
+    fn replay(event: &Event, balance: &mut f64, positions: &mut HashMap<u32, Position>) {
+        match event {
+            Event::WalletUpdate { balance: b, .. } => *balance = *b,
+            Event::AddPosition(_, position) | Event::IncreasePosition(_, position) => {
+                positions.insert(position.id(), *position);
+            }
+            Event::DelPosition(_, position) => match positions.get_mut(&position.id()) {
+                //? the part of a netted position left open
+                Some(open) if open.quantity() > position.quantity() => {
+                    let quantity = open.quantity() - position.quantity();
+                    open.set_quantity(quantity);
+                }
+                _ => {
+                    positions.remove(&position.id());
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Compares the strategy against a benchmark series.
+    ///
+    /// ### Arguments
+    /// * `benchmark` - The benchmark candles (e.g., the traded asset for a buy-and-hold comparison).
+    ///
+    /// ### Returns
+    /// The comparison, or `None` if the benchmark is empty.
+    pub fn vs_benchmark(&self, benchmark: &[Candle]) -> Option<Benchmark> {
+        self.compare(&CandleFrame::from(benchmark))
+    }
+
+    /// Compares the strategy against the benchmark candles, in columnar storage.
+    fn compare(&self, benchmark: &CandleFrame) -> Option<Benchmark> {
+        let first_open = *benchmark.open().first()?;
+        let last_close_time = *benchmark.close_time().last()?;
+        let last_close = *benchmark.close().last()?;
+        let curve = self.equity_curve();
+
+        // strategy equity at the close of each benchmark candle
+        let equity_at = |datetime| {
+            let idx = curve.partition_point(|(dt, _)| *dt <= datetime);
+            idx.checked_sub(1).map_or(self.initial_balance, |i| curve[i].1)
+        };
+
+        let mut strategy_returns = Vec::with_capacity(benchmark.len());
+        let mut benchmark_returns = Vec::with_capacity(benchmark.len());
+        let mut previous = (self.initial_balance, first_open);
+        for (close, close_time) in benchmark.close().iter().zip(benchmark.close_time()) {
+            let equity = equity_at(*close_time);
+            strategy_returns.push(previous.0.change(equity));
+            benchmark_returns.push(previous.1.change(*close));
+            previous = (equity, *close);
+        }
+
+        let n = benchmark.len() as f64;
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / n;
+        let (mean_s, mean_b) = (mean(&strategy_returns), mean(&benchmark_returns));
+        let covariance = |a: &[f64], mean_a: f64, b: &[f64], mean_b: f64| {
+            a.iter().zip(b).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum::<f64>() / n
+        };
+        let cov = covariance(&strategy_returns, mean_s, &benchmark_returns, mean_b);
+        let var_s = covariance(&strategy_returns, mean_s, &strategy_returns, mean_s);
+        let var_b = covariance(&benchmark_returns, mean_b, &benchmark_returns, mean_b);
+
+        let beta = cov / var_b;
+        let excess = strategy_returns
+            .iter()
+            .zip(&benchmark_returns)
+            .map(|(s, b)| s - b)
+            .collect::<Vec<_>>();
+        let mean_excess = mean(&excess);
+
+        Some(Benchmark {
+            strategy_return: self.initial_balance.change(equity_at(last_close_time)),
+            benchmark_return: first_open.change(last_close),
+            alpha: mean_s - beta * mean_b,
+            beta,
+            correlation: cov / (var_s * var_b).sqrt(),
+            tracking_error: covariance(&excess, mean_excess, &excess, mean_excess).sqrt(),
+        })
+    }
+
+    /// Returns the underwater curve: the drawdown of each `Event::WalletUpdate` balance from the
+    /// previous peak, as a negative percentage (0.0 at a new high).
+    pub fn drawdown_curve(&self) -> Vec<(DateTime<Utc>, f64)> {
+        let mut max_peak = self.initial_balance;
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::WalletUpdate { datetime, balance, .. } => {
+                    max_peak = max_peak.max(*balance);
+                    Some((*datetime, (balance - max_peak) / max_peak * 100.0))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Computes the maximum drawdown as a percentage.
     pub fn max_drawdown(&self) -> f64 {
         let mut balance_history = Vec::new();
@@ -215,6 +1228,54 @@ impl Metrics {
         (mean_return - risk_free_rate) / std_dev
     }
 
+    /// Computes the expectancy, the average net profit of the closed trades.
+    pub fn expectancy(&self) -> f64 {
+        self.trades().expectancy()
+    }
+
+    /// Computes the System Quality Number of the closed trades (see `Trades::system_quality_number`).
+    pub fn system_quality_number(&self) -> f64 {
+        self.trades().system_quality_number()
+    }
+
+    /// Computes the Kelly fraction of the closed trades (see `Trades::kelly_fraction`).
+    pub fn kelly_fraction(&self) -> f64 {
+        self.trades().kelly_fraction()
+    }
+
+    /// Computes the streaks of consecutive winning and losing positions.
+    ///
+    /// The closed positions are the trades (see `Metrics::trades`) in chronological order of exit,
+    /// and a position is winning when its net P&L is positive, as for `Trade::is_win`.
+    pub fn streaks(&self) -> Streaks {
+        let mut closed = self.trades().0;
+        closed.sort_by_key(|trade| trade.exit_time());
+
+        let mut streaks = Streaks::default();
+        let (mut runs, mut length, mut loss) = (0, 0, 0.0_f64);
+        for (i, trade) in closed.iter().enumerate() {
+            let win = trade.is_win();
+            if i == 0 || win != closed[i - 1].is_win() {
+                runs += 1;
+                length = 0;
+                loss = 0.0;
+            }
+            length += 1;
+            if win {
+                streaks.longest_win = streaks.longest_win.max(length);
+            } else {
+                streaks.longest_loss = streaks.longest_loss.max(length);
+                loss += trade.net_pnl();
+                streaks.worst_loss = streaks.worst_loss.min(loss);
+            }
+        }
+        if runs > 0 {
+            streaks.average_length = closed.len() as f64 / runs as f64;
+        }
+
+        streaks
+    }
+
     /// Computes the win rate as a percentage of winning trades.
     pub fn win_rate(&self) -> f64 {
         let mut winning_trades = 0;
@@ -235,6 +1296,201 @@ impl Metrics {
 
         (winning_trades as f64 / total_trades as f64) * 100.0
     }
+
+    /// Returns the equity values and the number of years they span, used to annualize the metrics.
+    ///
+    /// With candles, the curve starts from the initial balance at the open of the first candle.
+    fn annualization(&self) -> Option<(Vec<f64>, f64)> {
+        let curve = self.annualization_curve();
+        let years = years_between(curve.first()?.0, curve.last()?.0);
+        if years <= 0.0 {
+            return None;
+        }
+        Some((curve.into_iter().map(|(_, equity)| equity).collect(), years))
+    }
+
+    /// Returns the equity curve used to annualize the metrics, starting from the initial balance
+    /// with candles.
+    fn annualization_curve(&self) -> Vec<(DateTime<Utc>, f64)> {
+        let mut curve = self.equity_curve();
+        if let Some(open_time) = self.candles.open_time().first() {
+            curve.insert(0, (*open_time, self.initial_balance));
+        }
+        curve
+    }
+
+    /// Returns the returns of the equity curve and the number of returns per year.
+    fn annualized_returns(&self) -> Option<(Vec<f64>, f64)> {
+        let (equity, years) = self.annualization()?;
+        let returns = equity.windows(2).map(|w| (w[1] - w[0]) / w[0]).collect::<Vec<_>>();
+        let per_year = returns.len() as f64 / years;
+        Some((returns, per_year))
+    }
+
+    /// Computes the compound annual growth rate of the equity as a percentage.
+    ///
+    /// The duration is taken from the candle (or event) timestamps, so it does not depend on the
+    /// timeframe. Returns 0.0 if the equity curve spans no time.
+    pub fn cagr(&self) -> f64 {
+        let Some((equity, years)) = self.annualization() else {
+            return 0.0;
+        };
+        let (first, last) = (equity[0], equity[equity.len() - 1]);
+        ((last / first).powf(1.0 / years) - 1.0) * 100.0
+    }
+
+    /// Computes the annualized arithmetic return of the equity curve as a percentage.
+    ///
+    /// The mean return per observation is scaled by the number of observations per year, given by
+    /// the timestamps of the equity curve.
+    pub fn annualized_return(&self) -> f64 {
+        let Some((returns, per_year)) = self.annualized_returns() else {
+            return 0.0;
+        };
+        returns.iter().sum::<f64>() / returns.len() as f64 * per_year * 100.0
+    }
+
+    /// Computes the annualized volatility (standard deviation) of the equity returns as a percentage.
+    pub fn annualized_volatility(&self) -> f64 {
+        let Some((returns, per_year)) = self.annualized_returns() else {
+            return 0.0;
+        };
+        let n = returns.len() as f64;
+        let mean_return = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean_return).powi(2)).sum::<f64>() / n;
+        (variance * per_year).sqrt() * 100.0
+    }
+
+    /// Computes the annualized Sharpe ratio, comparable across datasets and timeframes.
+    ///
+    /// `risk_free_rate` is the annual risk-free return percentage (e.g., 4.0 for 4%).
+    /// Returns 0.0 if the volatility is zero.
+    pub fn annualized_sharpe_ratio(&self, risk_free_rate: f64) -> f64 {
+        let volatility = self.annualized_volatility();
+        if volatility == 0.0 {
+            return 0.0;
+        }
+        (self.annualized_return() - risk_free_rate) / volatility
+    }
+
+    /// Computes the annualized Sharpe ratio against a time series of risk-free rates.
+    ///
+    /// Each return of the equity curve is reduced by the risk-free return over its own period,
+    /// compounded from the rate in effect at its start, so the backtests spanning several rate
+    /// regimes are measured against the rates of their time.
+    ///
+    /// ### Arguments
+    /// * `rates` - The annual risk-free return percentages (e.g., 4.0 for 4%) with the date from
+    ///   which they apply, in chronological order. The first rate also applies before its date.
+    ///
+    /// ### Returns
+    /// The annualized Sharpe ratio, or 0.0 if the volatility of the excess returns is zero.
+    pub fn annualized_sharpe_ratio_with_rates(&self, rates: &[(DateTime<Utc>, f64)]) -> f64 {
+        let curve = self.annualization_curve();
+        let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+            return 0.0;
+        };
+        let years = years_between(first.0, last.0);
+        if years <= 0.0 {
+            return 0.0;
+        }
+        let excess = curve
+            .windows(2)
+            .map(|w| {
+                let ((start, from), (end, to)) = (w[0], w[1]);
+                let index = rates.partition_point(|(datetime, _)| *datetime <= start);
+                let rate = rates.get(index.saturating_sub(1)).map_or(0.0, |(_, rate)| *rate);
+                let risk_free = (1.0 + rate / 100.0).powf(years_between(start, end)) - 1.0;
+                (to - from) / from - risk_free
+            })
+            .collect::<Vec<_>>();
+
+        let n = excess.len() as f64;
+        let mean = excess.iter().sum::<f64>() / n;
+        let std_dev = (excess.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+        if std_dev == 0.0 {
+            return 0.0;
+        }
+        mean / std_dev * (n / years).sqrt()
+    }
+
+    /// Computes rolling metrics over a window of observations.
+    ///
+    /// ### Arguments
+    /// * `window` - The number of `Event::WalletUpdate` balances (Sharpe ratio, drawdown) or closed
+    ///   trades (win rate) of each point.
+    ///
+    /// ### Returns
+    /// The rolling series, empty if `window` is 0 or larger than the observations.
+    pub fn rolling(&self, window: usize) -> Rolling {
+        let mut rolling = Rolling {
+            window,
+            sharpe_ratio: Vec::new(),
+            win_rate: Vec::new(),
+            max_drawdown: Vec::new(),
+        };
+        if window == 0 {
+            return rolling;
+        }
+
+        let mut balances = Vec::new();
+        let mut trades = Vec::new();
+        for event in &self.events {
+            match event {
+                Event::WalletUpdate { datetime, balance, .. } => balances.push((*datetime, *balance)),
+                Event::DelPosition(datetime, position) => {
+                    let pnl = position.pnl().expect("pnl should be set the last exit price");
+                    trades.push((*datetime, pnl > 0.0));
+                }
+                _ => {}
+            }
+        }
+
+        let mut previous_balance = self.initial_balance;
+        let returns = balances
+            .iter()
+            .map(|(_, balance)| {
+                let return_pct = (balance - previous_balance) / previous_balance;
+                previous_balance = *balance;
+                return_pct
+            })
+            .collect::<Vec<_>>();
+
+        let n = window as f64;
+        rolling.sharpe_ratio = returns
+            .windows(window)
+            .zip(balances.iter().skip(window - 1))
+            .map(|(returns, (datetime, _))| {
+                let mean_return = returns.iter().sum::<f64>() / n;
+                let std_dev = (returns.iter().map(|r| (r - mean_return).powi(2)).sum::<f64>() / n).sqrt();
+                let sharpe = if std_dev > 0.0 { mean_return / std_dev } else { 0.0 };
+                (*datetime, sharpe)
+            })
+            .collect();
+
+        rolling.win_rate = trades
+            .windows(window)
+            .map(|trades| {
+                let wins = trades.iter().filter(|(_, win)| *win).count();
+                (trades[window - 1].0, wins as f64 / n * 100.0)
+            })
+            .collect();
+
+        rolling.max_drawdown = balances
+            .windows(window)
+            .map(|balances| {
+                let mut max_peak = f64::MIN;
+                let mut max_drawdown = 0.0_f64;
+                for (_, balance) in balances {
+                    max_peak = max_peak.max(*balance);
+                    max_drawdown = max_drawdown.max((max_peak - balance) / max_peak);
+                }
+                (balances[window - 1].0, max_drawdown * 100.0)
+            })
+            .collect();
+
+        rolling
+    }
 }
 
 impl fmt::Display for Metrics {
@@ -249,7 +1505,34 @@ impl fmt::Display for Metrics {
         writeln!(f, "Max Drawdown: {:.2}%", self.max_drawdown())?;
         writeln!(f, "Profit Factor: {:.2}", self.profit_factor())?;
         writeln!(f, "Sharpe Ratio (risk-free rate = 0.0): {:.2}", self.sharpe_ratio(0.0))?;
-        writeln!(f, "Win Rate: {:.2}%", self.win_rate())
+        writeln!(f, "Win Rate: {:.2}%", self.win_rate())?;
+        let trades = self.trades();
+        writeln!(f, "Expectancy: {:.2}", trades.expectancy())?;
+        writeln!(f, "System Quality Number: {:.2}", trades.system_quality_number())?;
+        writeln!(f, "Kelly Fraction: {:.2}", trades.kelly_fraction())?;
+        let streaks = self.streaks();
+        writeln!(f, "Longest Winning Streak: {}", streaks.longest_win())?;
+        writeln!(f, "Longest Losing Streak: {}", streaks.longest_loss())?;
+        writeln!(f, "Average Streak Length: {:.2}", streaks.average_length())?;
+        writeln!(f, "Worst Losing Streak: {:.2}", streaks.worst_loss())?;
+        writeln!(f, "CAGR: {:.2}%", self.cagr())?;
+        writeln!(f, "Annualized Volatility: {:.2}%", self.annualized_volatility())?;
+        writeln!(
+            f,
+            "Annualized Sharpe Ratio (risk-free rate = 0.0): {:.2}",
+            self.annualized_sharpe_ratio(0.0)
+        )?;
+        if let Some(benchmark) = self.compare(&self.candles) {
+            #[allow(clippy::writeln_empty_string)]
+            writeln!(f, "")?;
+            writeln!(f, "Buy & Hold Return: {:.2}%", benchmark.benchmark_return())?;
+            writeln!(f, "Excess Return: {:.2}%", benchmark.excess_return())?;
+            writeln!(f, "Alpha: {:.4}%", benchmark.alpha())?;
+            writeln!(f, "Beta: {:.2}", benchmark.beta())?;
+            writeln!(f, "Correlation: {:.2}", benchmark.correlation())?;
+            writeln!(f, "Tracking Error: {:.2}%", benchmark.tracking_error())?;
+        }
+        Ok(())
     }
 }
 
@@ -304,6 +1587,14 @@ fn max_drawdown() {
     ];
     let metrics = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0);
     assert_eq!(metrics.max_drawdown(), 25.0); // (12000 - 9000) / 12000 = 25%
+
+    let underwater = metrics
+        .drawdown_curve()
+        .into_iter()
+        .map(|(_, dd)| dd)
+        .collect::<Vec<_>>();
+    assert_eq!(underwater[..3], [0.0, 0.0, -25.0]);
+    assert!((underwater[3] + 100.0 / 12.0).abs() < 1e-9);
 }
 
 #[cfg(test)]
@@ -422,3 +1713,488 @@ fn win_rate_all_winning() {
     let metrics = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0);
     assert_eq!(metrics.win_rate(), 100.0); // 1 win out of 1 trade
 }
+
+#[cfg(test)]
+#[test]
+fn trades_stats() {
+    let events = vec![
+        Event::DelPosition(DateTime::default(), create_position(20.0)),
+        Event::DelPosition(DateTime::default(), create_position(10.0)),
+        Event::DelPosition(DateTime::default(), create_position(-10.0)),
+        Event::DelPosition(DateTime::default(), create_position(-20.0)),
+        Event::DelPosition(DateTime::default(), create_position(-30.0)),
+    ];
+    let metrics = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0);
+    let trades = metrics.trades();
+
+    assert_eq!(trades.len(), 5);
+    assert_eq!(trades.avg_win(), 15.0);
+    assert_eq!(trades.avg_loss(), -20.0);
+    assert_eq!(trades.expectancy(), -6.0);
+    assert_eq!(metrics.expectancy(), -6.0);
+    // standard deviation of √(1720 / 4)
+    assert!((trades.system_quality_number() - 5f64.sqrt() * -6.0 / 430f64.sqrt()).abs() < 1e-12);
+    assert_eq!(metrics.system_quality_number(), trades.system_quality_number());
+    // 0.4 - 0.6 / (15 / 20)
+    assert!((metrics.kelly_fraction() + 0.4).abs() < 1e-12);
+    assert_eq!(trades.longest_win_streak(), 2);
+    assert_eq!(trades.longest_loss_streak(), 3);
+}
+
+#[cfg(test)]
+#[test]
+fn trades_by_tag() {
+    let tagged = |pnl: f64, tag: Option<u64>| {
+        let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+        let mut position = Position::from(tag.map_or(order, |tag| order.with_tag(tag)));
+        position.set_exit_price(100.0 + pnl).unwrap();
+        Event::DelPosition(DateTime::default(), position)
+    };
+    let events = vec![
+        tagged(20.0, Some(1)),
+        tagged(-5.0, Some(2)),
+        tagged(10.0, Some(1)),
+        tagged(-15.0, None),
+        tagged(-10.0, Some(2)),
+    ];
+    let trades = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0).trades();
+    assert_eq!(trades[0].tag(), Some(1));
+    assert_eq!(trades[3].tag(), None);
+    assert_eq!(trades.net_pnl(), 0.0);
+
+    let groups = trades.by_tag();
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[&None].net_pnl(), -15.0);
+    assert_eq!(groups[&Some(1)].len(), 2);
+    assert_eq!(groups[&Some(1)].net_pnl(), 30.0);
+    assert_eq!(groups[&Some(2)].net_pnl(), -15.0);
+    assert_eq!(groups[&Some(2)].avg_loss(), -7.5);
+}
+
+#[cfg(test)]
+#[test]
+fn trades_from_backtest() {
+    use chrono::Duration;
+
+    let start = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let candles = [
+        (100.0, 105.0, 95.0, 100.0),
+        (100.0, 112.0, 90.0, 110.0),
+        (110.0, 121.0, 108.0, 120.0),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, (open, high, low, close))| {
+        CandleBuilder::builder()
+            .open(open)
+            .high(high)
+            .low(low)
+            .close(close)
+            .volume(1.0)
+            .open_time(start + Duration::days(i as i64))
+            .close_time(start + Duration::days(i as i64 + 1))
+            .build()
+            .unwrap()
+    })
+    .collect::<Arc<[_]>>();
+
+    let mut bt = Backtest::new(candles, 1000.0, Some((1.0, 1.0))).unwrap();
+    bt.run(|bt, candle| {
+        if bt.orders().count() == 0 && bt.positions().count() == 0 && candle.close() == 100.0 {
+            let exit = OrderType::TakeProfitAndStopLoss(120.0, 0.0);
+            bt.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let trades = Metrics::from(&bt).trades();
+    assert_eq!(trades.len(), 1);
+
+    let trade = trades[0];
+    assert_eq!(trade.entry_time(), start);
+    assert_eq!(trade.exit_time(), start + Duration::days(2));
+    assert_eq!(trade.entry_price(), 100.0);
+    assert_eq!(trade.exit_price(), 120.0);
+    assert_eq!(trade.gross_pnl(), 20.0);
+    assert_eq!(trade.fees(), 2.2);
+    assert_eq!(trade.net_pnl(), 17.8);
+    assert_eq!(trade.bars_held(), 2);
+    assert_eq!(trade.mae(), 10.0);
+    assert_eq!(trade.mfe(), 21.0);
+    assert_eq!(trade.exit_reason(), ExitReason::TakeProfit);
+}
+
+#[cfg(test)]
+#[test]
+fn trades_export() {
+    let start = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Sell)).with_tag(7);
+    let mut position = Position::from(order);
+    position.set_exit_price(90.0).unwrap();
+    let events = vec![
+        Event::AddPosition(start, position),
+        Event::DelPosition(start, position),
+        Event::DelPosition(start, create_position(-5.0)),
+    ];
+    let metrics = Metrics::new(events, 1000.0, 0.0, 0.0, 0.0);
+
+    let mut csv = Vec::new();
+    metrics.trades_to_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some(
+            "entry_time,exit_time,side,quantity,entry_price,exit_price,gross_pnl,fees,net_pnl,bars_held,mae,mfe,exit_reason,tag"
+        )
+    );
+    assert_eq!(
+        lines.next(),
+        Some("2018-01-05T11:25:15+00:00,2018-01-05T11:25:15+00:00,short,2,100,90,20,0,20,0,0,20,manual,7")
+    );
+    assert!(lines.next().unwrap().ends_with(",long,1,100,95,-5,0,-5,0,5,0,manual,"));
+    assert_eq!(lines.next(), None);
+
+    let mut json = Vec::new();
+    metrics.trades_to_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(r#"[{"entry_time":"2018-01-05T11:25:15+00:00","#));
+    assert!(json.contains(r#""side":"short","quantity":2,"#));
+    assert!(json.ends_with(r#""exit_reason":"manual","tag":null}]"#));
+
+    let path = std::env::temp_dir().join("bts_trades_export.csv");
+    metrics.export_trades(&path, ExportFormat::Csv).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), csv);
+    metrics.export_trades(&path, ExportFormat::Json).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), json);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn streaks() {
+    let time = |day| DateTime::default() + chrono::Duration::days(day);
+    // closed out of order: +10, -5, -15, +5, +5, +5, -18, then +1 with 4 of fees
+    let events = [
+        (2, -5.0, 0.0),
+        (1, 10.0, 0.0),
+        (3, -15.0, 0.0),
+        (4, 5.0, 0.0),
+        (5, 5.0, 0.0),
+        (6, 5.0, 0.0),
+        (8, 1.0, 4.0),
+        (7, -18.0, 0.0),
+    ]
+    .into_iter()
+    .map(|(day, pnl, fees)| {
+        let mut position = create_position(pnl);
+        position.add_fees(fees);
+        Event::DelPosition(time(day), position)
+    })
+    .collect::<Vec<_>>();
+    let metrics = Metrics::new(events, 1000.0, 0.0, 0.0, 0.0);
+    let streaks = metrics.streaks();
+
+    assert_eq!(streaks.longest_win(), 3);
+    // the last position loses after fees
+    assert_eq!(streaks.longest_loss(), 2);
+    // 8 positions in 4 streaks
+    assert_eq!(streaks.average_length(), 2.0);
+    // -18 and -3
+    assert_eq!(streaks.worst_loss(), -21.0);
+
+    assert_eq!(
+        Metrics::new(vec![], 1000.0, 0.0, 0.0, 0.0).streaks(),
+        Streaks::default()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn trades_clustering() {
+    let clustered = [20.0, 10.0, 15.0, 30.0, 25.0, -10.0, -20.0, -15.0, -5.0, -30.0];
+    let events = clustered
+        .iter()
+        .map(|pnl| Event::DelPosition(DateTime::default(), create_position(*pnl)))
+        .collect();
+    let clustering = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0)
+        .trades()
+        .clustering()
+        .unwrap();
+    assert_eq!(clustering.runs(), 2);
+    assert_eq!(clustering.expected_runs(), 6.0);
+    assert!(clustering.z_score() < -1.96);
+    assert!(clustering.is_clustered());
+    assert!(!clustering.is_independent());
+
+    let alternating = [20.0, -10.0, 15.0, -20.0, 10.0, -15.0, 30.0, -5.0];
+    let events = alternating
+        .iter()
+        .map(|pnl| Event::DelPosition(DateTime::default(), create_position(*pnl)))
+        .collect();
+    let clustering = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0)
+        .trades()
+        .clustering()
+        .unwrap();
+    assert_eq!(clustering.runs(), 8);
+    assert!(!clustering.is_clustered());
+
+    let events = vec![Event::DelPosition(DateTime::default(), create_position(20.0))];
+    assert!(
+        Metrics::new(events, 10000.0, 0.0, 0.0, 0.0)
+            .trades()
+            .clustering()
+            .is_none()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn equity_curve() {
+    use chrono::Duration;
+
+    let start = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let candles = [(100.0, 100.0), (100.0, 110.0), (110.0, 120.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (open, close))| {
+            CandleBuilder::builder()
+                .open(open)
+                .high(close)
+                .low(open)
+                .close(close)
+                .volume(1.0)
+                .open_time(start + Duration::days(i as i64))
+                .close_time(start + Duration::days(i as i64 + 1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+
+    let mut bt = Backtest::new(candles, 1000.0, None).unwrap();
+    bt.run(|bt, candle| {
+        if candle.open_time() == start {
+            let exit = OrderType::TakeProfitAndStopLoss(120.0, 0.0);
+            bt.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let metrics = Metrics::from(&bt);
+    let curve = metrics.equity_curve();
+    assert_eq!(curve.len(), 3);
+    assert_eq!(curve[0], (start + Duration::days(1), 1000.0));
+    assert_eq!(curve[1], (start + Duration::days(2), 1010.0));
+    assert_eq!(curve[2], (start + Duration::days(3), 1020.0));
+
+    let mut csv = Vec::new();
+    metrics.equity_curve_to_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), 4);
+    assert_eq!(csv.lines().nth(2), Some("2018-01-07T11:25:15+00:00,1010"));
+
+    let mut json = Vec::new();
+    metrics.equity_curve_to_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(r#"[{"datetime":"2018-01-06T11:25:15+00:00","equity":1000},"#));
+    assert!(json.ends_with("]"));
+}
+
+#[cfg(test)]
+#[test]
+fn equity_curve_without_candles() {
+    let position = create_position(10.0);
+    let events = vec![
+        Event::WalletUpdate {
+            datetime: DateTime::default(),
+            pnl: 0.0,
+            fees: 0.0,
+            free: 900.0,
+            locked: 0.0,
+            balance: 900.0,
+        },
+        Event::AddPosition(DateTime::default(), position),
+        Event::WalletUpdate {
+            datetime: DateTime::default() + chrono::Duration::days(1),
+            pnl: 0.0,
+            fees: 0.0,
+            free: 1010.0,
+            locked: 0.0,
+            balance: 1010.0,
+        },
+        Event::DelPosition(DateTime::default() + chrono::Duration::days(1), position),
+    ];
+    let metrics = Metrics::new(events, 1000.0, 1010.0, 0.0, 0.0);
+    let curve = metrics.equity_curve();
+    assert_eq!(curve.len(), 2);
+    assert_eq!(curve[0].1, 1000.0);
+    assert_eq!(curve[1].1, 1010.0);
+}
+
+#[cfg(test)]
+#[test]
+fn annualized_metrics() {
+    use chrono::Duration;
+
+    // the equity doubles over two years, whatever the timeframe
+    let start = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let events = |step: Duration, count: i32| {
+        (1..=count)
+            .map(|i| {
+                let balance = 1000.0 * 2f64.powf(i as f64 / count as f64);
+                Event::WalletUpdate {
+                    datetime: start + step * i,
+                    pnl: 0.0,
+                    fees: 0.0,
+                    free: balance,
+                    locked: 0.0,
+                    balance,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let first = Event::WalletUpdate {
+        datetime: start,
+        pnl: 0.0,
+        fees: 0.0,
+        free: 1000.0,
+        locked: 0.0,
+        balance: 1000.0,
+    };
+
+    let two_years = YEAR * 2;
+    for count in [24, 730] {
+        let mut events = events(two_years / count, count);
+        events.insert(0, first);
+        let metrics = Metrics::new(events, 1000.0, 2000.0, 0.0, 0.0);
+        assert!((metrics.cagr() - (2f64.sqrt() - 1.0) * 100.0).abs() < 1e-9);
+        // constant returns: no volatility
+        assert!(metrics.annualized_volatility() < 1e-9);
+        let annualized_return = metrics.annualized_return();
+        assert!(annualized_return > 34.0 && annualized_return < 42.0);
+    }
+
+    let mut events = events(Duration::days(1), 4);
+    events.insert(0, first);
+    events[2] = Event::WalletUpdate {
+        datetime: start + Duration::days(2),
+        pnl: 0.0,
+        fees: 0.0,
+        free: 900.0,
+        locked: 0.0,
+        balance: 900.0,
+    };
+    let metrics = Metrics::new(events, 1000.0, 0.0, 0.0, 0.0);
+    let volatility = metrics.annualized_volatility();
+    assert!(volatility > 0.0);
+    let sharpe = (metrics.annualized_return() - 4.0) / volatility;
+    assert!((metrics.annualized_sharpe_ratio(4.0) - sharpe).abs() < 1e-12);
+    // without risk-free rate, the series gives the same ratio
+    let sharpe = metrics.annualized_sharpe_ratio(0.0);
+    assert!((metrics.annualized_sharpe_ratio_with_rates(&[]) - sharpe).abs() < 1e-9);
+    // a rate rising after the first days lowers the ratio more than the first rate alone
+    let constant = metrics.annualized_sharpe_ratio_with_rates(&[(start, 4.0)]);
+    assert!(constant < sharpe);
+    let rising = [(start, 4.0), (start + Duration::days(2), 400.0)];
+    assert!(metrics.annualized_sharpe_ratio_with_rates(&rising) < constant);
+
+    let metrics = Metrics::new(vec![], 1000.0, 0.0, 0.0, 0.0);
+    assert_eq!(metrics.cagr(), 0.0);
+    assert_eq!(metrics.annualized_return(), 0.0);
+    assert_eq!(metrics.annualized_volatility(), 0.0);
+    assert_eq!(metrics.annualized_sharpe_ratio(0.0), 0.0);
+    assert_eq!(metrics.annualized_sharpe_ratio_with_rates(&[(start, 4.0)]), 0.0);
+}
+
+#[cfg(test)]
+#[test]
+fn vs_benchmark() {
+    use chrono::Duration;
+
+    let start = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let candles = [(100.0, 110.0), (110.0, 99.0), (99.0, 120.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (open, close))| {
+            CandleBuilder::builder()
+                .open(open)
+                .high(f64::max(open, close))
+                .low(f64::min(open, close))
+                .close(close)
+                .volume(1.0)
+                .open_time(start + Duration::days(i as i64))
+                .close_time(start + Duration::days(i as i64 + 1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+
+    // all-in buy and hold tracks the benchmark
+    let mut bt = Backtest::new(Arc::clone(&candles), 1000.0, None).unwrap();
+    bt.run(|bt, candle| {
+        if candle.open_time() == start {
+            bt.place_order(candle, (OrderType::Market(100.0), 10.0, OrderSide::Buy).into())?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let metrics = Metrics::from(&bt);
+    let benchmark = metrics.vs_benchmark(&candles).unwrap();
+    assert!((benchmark.benchmark_return() - 20.0).abs() < 1e-9);
+    assert!((benchmark.strategy_return() - 20.0).abs() < 1e-9);
+    assert!(benchmark.excess_return().abs() < 1e-9);
+    assert!((benchmark.beta() - 1.0).abs() < 1e-9);
+    assert!((benchmark.correlation() - 1.0).abs() < 1e-9);
+    assert!(benchmark.alpha().abs() < 1e-9);
+    assert!(benchmark.tracking_error() < 1e-9);
+    assert!(metrics.to_string().contains("Buy & Hold Return: 20.00%"));
+
+    // doing nothing is uncorrelated with the benchmark
+    let metrics = Metrics::new(Vec::new(), 1000.0, 1000.0, 0.0, 0.0);
+    let benchmark = metrics.vs_benchmark(&candles).unwrap();
+    assert_eq!(benchmark.strategy_return(), 0.0);
+    assert_eq!(benchmark.beta(), 0.0);
+    assert!(metrics.vs_benchmark(&[]).is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn rolling() {
+    let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+    let wallet = |secs, balance| Event::WalletUpdate {
+        datetime: time(secs),
+        pnl: 0.0,
+        fees: 0.0,
+        free: balance,
+        locked: 0.0,
+        balance,
+    };
+    let events = vec![
+        wallet(1, 10000.0),
+        Event::DelPosition(time(1), create_position(20.0)),
+        wallet(2, 12000.0),
+        Event::DelPosition(time(2), create_position(-10.0)),
+        wallet(3, 9000.0),
+        Event::DelPosition(time(3), create_position(-5.0)),
+        wallet(4, 9900.0),
+    ];
+    let metrics = Metrics::new(events, 10000.0, 0.0, 0.0, 0.0);
+
+    let rolling = metrics.rolling(2);
+    assert_eq!(rolling.window(), 2);
+    assert_eq!(
+        rolling.max_drawdown(),
+        [(time(2), 0.0), (time(3), 25.0), (time(4), 0.0)]
+    );
+    assert_eq!(rolling.win_rate(), [(time(2), 50.0), (time(3), 0.0)]);
+    let sharpe = rolling.sharpe_ratio().iter().map(|(_, s)| *s).collect::<Vec<_>>();
+    assert_eq!(sharpe.len(), 3);
+    assert_eq!(sharpe[0], 1.0); // returns 0% and 20%
+    assert!(sharpe[1] < 0.0 && sharpe[2] < 0.0);
+
+    assert!(metrics.rolling(5).sharpe_ratio().is_empty());
+    assert!(metrics.rolling(0).win_rate().is_empty());
+}
@@ -2,16 +2,27 @@
 //!
 //! This module provides tools to optimize trading strategies by testing different parameter combinations.
 //! The `Optimizer` struct handles the execution of backtests for each combination, while the
-//! `ParameterCombination` trait defines how to generate parameter sets.
+//! `ParameterCombination` trait defines how to generate parameter sets and the `Objective` trait
+//! how to score a run.
 //!
 //! It needs to enable `optimizer` feature to use it. Take a look at [parallelize parameters optimization](https://github.com/raonagos/bts-rs/blob/master/examples/par_parameters_optimization.rs) for example.
 
+use std::collections::{BTreeMap, BinaryHeap};
 use std::marker::PhantomData;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::engine::{Backtest, Candle};
-use crate::errors::Result;
+use crate::PercentCalculus;
+use crate::engine::{Backtest, Candle, FeeModel, PercentFees, Slippage};
+use crate::errors::{Error, Result};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::split::{Fold, Split};
 
+use rand::rngs::StdRng;
+use rand::seq::{SliceRandom, index};
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 
 /// Trait defining how to generate parameter combinations for optimization.
@@ -27,8 +38,1062 @@ pub trait ParameterCombination: Sync {
     /// # Returns
     /// A vector containing all parameter combinations.
     fn generate() -> Vec<Self::Item>;
+
+    /// Returns the range of each parameter, for the sampling search modes.
+    ///
+    /// # Returns
+    /// The ranges, or an empty vector (the default) to sample the combinations of `generate`.
+    fn ranges() -> Vec<RangeInclusive<f64>> {
+        Vec::new()
+    }
+
+    /// Builds a combination from one value drawn in each range of `ranges` (e.g., rounding the integer parameters).
+    ///
+    /// # Returns
+    /// The combination, or `None` (the default) if the combinations can't be built from values.
+    fn from_values(_values: &[f64]) -> Option<Self::Item> {
+        None
+    }
+
+    /// Returns `true` if the combination is valid (the default), e.g., a fast period shorter than the
+    /// slow one. The invalid combinations are skipped without running a backtest.
+    ///
+    /// # Arguments
+    /// * `item` - The parameter combination.
+    fn validate(_item: &Self::Item) -> bool {
+        true
+    }
+}
+
+/// How the optimizer chooses the parameter combinations to evaluate.
+///
+/// The sampling modes draw from `ParameterCombination::ranges` without enumerating the cartesian
+/// product. If no ranges are defined, they draw `n` distinct combinations of `generate` instead.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SearchMode {
+    /// Evaluates every combination of `ParameterCombination::generate` (the default).
+    #[default]
+    Grid,
+
+    /// Evaluates `n` combinations drawn uniformly in the ranges.
+    Random {
+        /// The number of combinations.
+        n: usize,
+    },
+
+    /// Evaluates `n` combinations of a Latin hypercube: each range is split into `n` strata and
+    /// every stratum of every parameter is drawn exactly once, which covers the space more evenly
+    /// than random draws.
+    LatinHypercube {
+        /// The number of combinations.
+        n: usize,
+    },
+}
+
+impl SearchMode {
+    /// Returns the combinations to evaluate.
+    fn combinations<PC: ParameterCombination>(&self, rng: &mut StdRng) -> Result<Vec<PC::Item>> {
+        let n = match *self {
+            Self::Grid => return Ok(valid::<PC>(PC::generate())),
+            Self::Random { n } | Self::LatinHypercube { n } => n,
+        };
+
+        let ranges = PC::ranges();
+        if ranges.is_empty() {
+            let combinations = valid::<PC>(PC::generate());
+            let len = combinations.len();
+            let indexes = match self {
+                Self::LatinHypercube { .. } if n < len => {
+                    // one index drawn in each of the n strata of the combinations
+                    (0..n)
+                        .map(|i| rng.random_range(i * len / n..(i + 1) * len / n))
+                        .collect::<Vec<_>>()
+                }
+                _ => index::sample(rng, len, n.min(len)).into_vec(),
+            };
+            return Ok(indexes.into_iter().map(|i| combinations[i].clone()).collect());
+        }
+
+        let latin = matches!(self, Self::LatinHypercube { .. });
+        let combinations = unit_points(ranges.len(), n, latin, rng)
+            .iter()
+            .map(|point| from_unit::<PC>(&ranges, point))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(valid::<PC>(combinations))
+    }
+}
+
+/// Keeps the combinations accepted by `ParameterCombination::validate`.
+fn valid<PC: ParameterCombination>(mut combinations: Vec<PC::Item>) -> Vec<PC::Item> {
+    combinations.retain(PC::validate);
+    combinations
+}
+
+/// Draws `n` points of the unit hypercube, stratified on each dimension if `latin` (Latin hypercube).
+fn unit_points(dims: usize, n: usize, latin: bool, rng: &mut StdRng) -> Vec<Vec<f64>> {
+    let columns = (0..dims)
+        .map(|_| {
+            if latin {
+                let mut strata = (0..n).collect::<Vec<_>>();
+                strata.shuffle(rng);
+                strata
+                    .into_iter()
+                    .map(|stratum| (stratum as f64 + rng.random::<f64>()) / n as f64)
+                    .collect::<Vec<_>>()
+            } else {
+                (0..n).map(|_| rng.random::<f64>()).collect()
+            }
+        })
+        .collect::<Vec<_>>();
+    (0..n)
+        .map(|i| columns.iter().map(|column| column[i]).collect())
+        .collect()
+}
+
+/// Builds the combination of a point of the unit hypercube scaled to the parameter ranges.
+fn from_unit<PC: ParameterCombination>(ranges: &[RangeInclusive<f64>], point: &[f64]) -> Result<PC::Item> {
+    let values = ranges
+        .iter()
+        .zip(point)
+        .map(|(range, unit)| range.start() + unit * (range.end() - range.start()))
+        .collect::<Vec<_>>();
+    PC::from_values(&values).ok_or(Error::Msg(
+        "ParameterCombination::from_values is required to sample the ranges".to_string(),
+    ))
+}
+
+/// Settings of the Bayesian optimization, a Tree-structured Parzen Estimator (TPE).
+///
+/// The first combinations are drawn from a Latin hypercube of the parameter ranges. Then the
+/// evaluated combinations are split between the best ones (the `gamma` quantile of the scores) and
+/// the others; each new combination is the candidate maximizing the ratio of the density of the
+/// best combinations over the density of the others, so the search focuses on the promising regions.
+///
+/// Used by `Optimizer::bayesian`, it requires `ParameterCombination::ranges` and `from_values`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tpe {
+    evaluations: usize,
+    initial: usize,
+    gamma: f64,
+    candidates: usize,
+    batch: usize,
+}
+
+impl Tpe {
+    /// Creates the settings for a budget of `evaluations` backtests.
+    ///
+    /// By default, a fifth of the budget (at least 2) explores the ranges, the best quarter of the
+    /// scores is considered promising, 24 candidates are compared for each new combination and
+    /// 8 combinations are proposed at once.
+    pub fn new(evaluations: usize) -> Self {
+        Self {
+            evaluations,
+            initial: (evaluations / 5).max(2).min(evaluations),
+            gamma: 0.25,
+            candidates: 24,
+            batch: 8,
+        }
+    }
+
+    /// Sets the number of combinations drawn from the Latin hypercube before the model is used.
+    pub fn with_initial(mut self, initial: usize) -> Self {
+        self.initial = initial.clamp(1, self.evaluations.max(1));
+        self
+    }
+
+    /// Sets the quantile of the scores considered promising (e.g., 0.25 for the best quarter).
+    pub fn with_gamma(mut self, gamma: f64) -> Self {
+        self.gamma = gamma.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    /// Sets the number of candidates compared for each new combination.
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+
+    /// Sets the number of combinations proposed at once and evaluated in parallel.
+    ///
+    /// A smaller batch uses more of the previous scores for each proposal, a larger one uses more CPUs.
+    pub fn with_batch(mut self, batch: usize) -> Self {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// Returns the `count` most promising points according to the evaluated `(point, score)` observations.
+    fn propose(&self, observations: &[(Vec<f64>, f64)], count: usize, rng: &mut StdRng) -> Vec<Vec<f64>> {
+        let mut ranked = observations.iter().collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| rank(*b).total_cmp(&rank(*a)));
+        let split = ((ranked.len() as f64 * self.gamma).ceil() as usize).clamp(1, ranked.len());
+        let (good, bad) = ranked.split_at(split);
+        let dims = observations.first().map_or(0, |(point, _)| point.len());
+
+        let parzen = |points: &[&(Vec<f64>, f64)]| {
+            (0..dims)
+                .map(|d| Parzen::new(points.iter().map(|(point, _)| point[d]).collect()))
+                .collect::<Vec<_>>()
+        };
+        let (good, bad) = (parzen(good), parzen(bad));
+
+        //? each point is the best of its own candidates, so a batch does not collapse on a single region
+        let ratio = |point: &[f64]| {
+            point
+                .iter()
+                .zip(good.iter().zip(&bad))
+                .map(|(x, (l, g))| l.density(*x).ln() - g.density(*x).ln())
+                .sum::<f64>()
+        };
+        (0..count)
+            .filter_map(|_| {
+                (0..self.candidates)
+                    .map(|_| good.iter().map(|l| l.sample(rng)).collect::<Vec<_>>())
+                    .map(|point| (ratio(&point), point))
+                    .max_by(|(a, _), (b, _)| a.total_cmp(b))
+                    .map(|(_, point)| point)
+            })
+            .collect()
+    }
+}
+
+/// Ranks the undefined (NaN) scores last.
+fn rank(score: f64) -> f64 {
+    if score.is_nan() { f64::NEG_INFINITY } else { score }
+}
+
+/// A scored combination kept by `Optimizer::top_n`.
+struct Ranked<I> {
+    score: f64,
+    index: usize,
+    item: I,
+}
+
+impl<I> PartialEq for Ranked<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl<I> Eq for Ranked<I> {}
+
+impl<I> PartialOrd for Ranked<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I> Ord for Ranked<I> {
+    //? the greatest is the worst: the lowest score, then the latest combination
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        rank(other.score)
+            .total_cmp(&rank(self.score))
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+/// Pushes a scored combination to a heap holding the `n` best ones, dropping the worst if it is full.
+fn keep_best<I>(heap: &mut BinaryHeap<Ranked<I>>, n: usize, ranked: Ranked<I>) {
+    if heap.len() < n {
+        heap.push(ranked);
+    } else if heap.peek().is_some_and(|worst| ranked < *worst) {
+        heap.pop();
+        heap.push(ranked);
+    }
+}
+
+/// Parzen estimator of the density of a parameter on [0, 1]: a Gaussian kernel on each observation
+/// mixed with a uniform prior.
+struct Parzen {
+    points: Vec<f64>,
+    bandwidth: f64,
+}
+
+impl Parzen {
+    fn new(points: Vec<f64>) -> Self {
+        let n = points.len().max(1) as f64;
+        let mean = points.iter().sum::<f64>() / n;
+        let std_dev = (points.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n).sqrt();
+        //? Scott's rule, bounded so a single observation still explores its neighbourhood
+        let bandwidth = (1.06 * std_dev * n.powf(-0.2)).clamp(0.1, 0.5);
+        Self { points, bandwidth }
+    }
+
+    fn density(&self, x: f64) -> f64 {
+        let kernels = self
+            .points
+            .iter()
+            .map(|p| {
+                (-0.5 * ((x - p) / self.bandwidth).powi(2)).exp()
+                    / (self.bandwidth * (2.0 * std::f64::consts::PI).sqrt())
+            })
+            .sum::<f64>();
+        // the uniform prior weighs as one observation
+        (kernels + 1.0) / (self.points.len() + 1) as f64
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        let i = rng.random_range(0..=self.points.len());
+        let Some(center) = self.points.get(i) else {
+            return rng.random::<f64>();
+        };
+        // Box-Muller transform
+        let (u1, u2) = (1.0 - rng.random::<f64>(), rng.random::<f64>());
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        (center + z * self.bandwidth).clamp(0.0, 1.0)
+    }
+}
+
+/// Trait defining the score of a backtest to maximize.
+///
+/// Built-in objectives:
+/// - `FinalBalance`: The final equity.
+/// - `SharpeRatio`: The Sharpe ratio of the balance returns *(requires the `metrics` feature)*.
+/// - `CalmarRatio`: The annualized return over the maximum drawdown *(requires the `metrics` feature)*.
+/// - `ProfitFactor`: The gross profits over the gross losses *(requires the `metrics` feature)*.
+/// - `Weighted`: A weighted sum of other objectives.
+///
+/// Any `Fn(&Backtest) -> f64` closure is also an objective.
+pub trait Objective: Send + Sync {
+    /// Returns the score of the backtest after the strategy execution, the higher the better.
+    fn score(&self, backtest: &Backtest) -> f64;
+}
+
+impl<F: Fn(&Backtest) -> f64 + Send + Sync> Objective for F {
+    fn score(&self, backtest: &Backtest) -> f64 {
+        self(backtest)
+    }
+}
+
+/// Maximizes the final equity (balance plus the funds committed in the open positions).
+#[derive(Debug, Clone, Copy)]
+pub struct FinalBalance;
+
+impl Objective for FinalBalance {
+    fn score(&self, backtest: &Backtest) -> f64 {
+        backtest.equity()
+    }
+}
+
+/// Maximizes the Sharpe ratio, given the risk-free rate.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy)]
+pub struct SharpeRatio(pub f64);
+
+#[cfg(feature = "metrics")]
+impl Objective for SharpeRatio {
+    fn score(&self, backtest: &Backtest) -> f64 {
+        Metrics::from(backtest).sharpe_ratio(self.0)
+    }
+}
+
+/// Maximizes the Calmar ratio: the annualized return over the maximum drawdown (percentages).
+///
+/// The return is not annualized when the candles span no time.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy)]
+pub struct CalmarRatio;
+
+#[cfg(feature = "metrics")]
+impl Objective for CalmarRatio {
+    fn score(&self, backtest: &Backtest) -> f64 {
+        let initial_balance = backtest.initial_balance();
+        let growth = backtest.equity() / initial_balance;
+        let years = match (backtest.candles().next(), backtest.candles().last()) {
+            (Some(first), Some(last)) => (last.close_time() - first.open_time()).num_seconds() as f64 / 31_557_600.0,
+            _ => 0.0,
+        };
+        let annual_return = if years > 0.0 {
+            (growth.powf(1.0 / years) - 1.0) * 100.0
+        } else {
+            (growth - 1.0) * 100.0
+        };
+
+        let max_drawdown = Metrics::from(backtest).max_drawdown();
+        if max_drawdown == 0.0 {
+            return match annual_return {
+                r if r > 0.0 => f64::INFINITY,
+                r if r < 0.0 => f64::NEG_INFINITY,
+                _ => 0.0,
+            };
+        }
+        annual_return / max_drawdown
+    }
+}
+
+/// Maximizes the profit factor.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy)]
+pub struct ProfitFactor;
+
+#[cfg(feature = "metrics")]
+impl Objective for ProfitFactor {
+    fn score(&self, backtest: &Backtest) -> f64 {
+        Metrics::from(backtest).profit_factor()
+    }
+}
+
+/// Maximizes a weighted sum of objectives.
+///
+/// The objectives are not normalized, so the weights should account for their scales.
+#[derive(Default)]
+pub struct Weighted(Vec<(f64, Box<dyn Objective>)>);
+
+impl Weighted {
+    /// Creates an empty combination.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an objective with its weight.
+    pub fn with(mut self, weight: f64, objective: impl Objective + 'static) -> Self {
+        self.0.push((weight, Box::new(objective)));
+        self
+    }
+}
+
+impl Objective for Weighted {
+    fn score(&self, backtest: &Backtest) -> f64 {
+        self.0
+            .iter()
+            .map(|(weight, objective)| weight * objective.score(backtest))
+            .sum()
+    }
+}
+
+/// Returns of a strategy for different amounts of deployed capital (AUM).
+///
+/// Built by `Optimizer::capacity`, it describes how the volume-participation slippage erodes
+/// the edge of the strategy as the capital grows.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capacity {
+    points: Vec<(f64, f64)>,
+}
+
+impl Capacity {
+    /// Returns the return vs AUM curve as `(aum, return %)` points sorted by AUM.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Returns the largest AUM whose return stays above `min_return` (percentage),
+    /// scanning the curve from the smallest AUM and stopping at the first level below it.
+    pub fn max_aum(&self, min_return: f64) -> Option<f64> {
+        self.points
+            .iter()
+            .take_while(|(_, ret)| *ret > min_return)
+            .last()
+            .map(|(aum, _)| *aum)
+    }
+}
+
+/// Distribution of the returns of a strategy started at different dates.
+///
+/// Built by `Optimizer::start_dates`, it shows how much the outcome of a strategy depends on
+/// the date it was started at.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartDates {
+    returns: Vec<(usize, f64)>,
+}
+
+impl StartDates {
+    /// Returns the `(start offset, return %)` of each run sorted by offset.
+    pub fn returns(&self) -> &[(usize, f64)] {
+        &self.returns
+    }
+
+    /// Returns the average return (percentage).
+    pub fn mean(&self) -> f64 {
+        if self.returns.is_empty() {
+            return 0.0;
+        }
+        self.returns.iter().map(|(_, ret)| ret).sum::<f64>() / self.returns.len() as f64
+    }
+
+    /// Returns the standard deviation of the returns.
+    pub fn std_dev(&self) -> f64 {
+        if self.returns.len() < 2 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        let variance =
+            self.returns.iter().map(|(_, ret)| (ret - mean).powi(2)).sum::<f64>() / (self.returns.len() - 1) as f64;
+        variance.sqrt()
+    }
+
+    /// Returns the return at the given percentile (0 to 100), using the nearest rank.
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        let mut returns = self.returns.iter().map(|(_, ret)| *ret).collect::<Vec<_>>();
+        returns.sort_by(f64::total_cmp);
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * returns.len() as f64).ceil() as usize;
+        returns.get(rank.saturating_sub(1)).copied()
+    }
+
+    /// Returns the worst return.
+    pub fn min(&self) -> Option<f64> {
+        self.percentile(0.0)
+    }
+
+    /// Returns the median return.
+    pub fn median(&self) -> Option<f64> {
+        self.percentile(50.0)
+    }
+
+    /// Returns the best return.
+    pub fn max(&self) -> Option<f64> {
+        self.percentile(100.0)
+    }
+
+    /// Returns the share of the runs ending with a positive return (percentage).
+    pub fn positive_rate(&self) -> f64 {
+        if self.returns.is_empty() {
+            return 0.0;
+        }
+        let positives = self.returns.iter().filter(|(_, ret)| *ret > 0.0).count();
+        positives as f64 / self.returns.len() as f64 * 100.0
+    }
+}
+
+/// Best parameters of a cross-validation fold, scored in-sample and out-of-sample.
+///
+/// Built by `Optimizer::cross_validate`, it compares the score of the parameters on the candles
+/// they were optimized on with their score on the unseen candles.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossValidation<P> {
+    fold: Fold,
+    params: P,
+    in_sample: f64,
+    out_of_sample: f64,
+}
+
+impl<P> CrossValidation<P> {
+    /// Returns the fold.
+    pub fn fold(&self) -> &Fold {
+        &self.fold
+    }
+
+    /// Returns the best parameters on the train candles.
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+
+    /// Returns the score on the train candles.
+    pub fn in_sample(&self) -> f64 {
+        self.in_sample
+    }
+
+    /// Returns the score on the test candles.
+    pub fn out_of_sample(&self) -> f64 {
+        self.out_of_sample
+    }
+}
+
+/// Overfitting statistics of an optimization, computed from the returns of every tested combination.
+///
+/// The best Sharpe ratio out of many combinations is inflated by the selection itself. The deflated
+/// Sharpe ratio (Bailey and López de Prado) corrects it for the number of combinations and the
+/// non-normality of the returns, and the probability of backtest overfitting (PBO) estimates, with
+/// a combinatorially symmetric cross-validation (CSCV), how often the best in-sample combination
+/// ranks below the median out-of-sample.
+///
+/// The Sharpe ratios are computed per period (not annualized) and all the combinations must have
+/// the returns of the same periods.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::optimizer::Overfitting;
+///
+/// // 3 combinations of 8 periods
+/// let returns = vec![
+///     vec![0.01, 0.02, 0.01, 0.015, 0.01, 0.02, 0.01, 0.015],
+///     vec![0.01, -0.01, 0.02, -0.02, 0.01, -0.01, 0.02, -0.02],
+///     vec![-0.01, 0.01, -0.02, 0.02, -0.01, 0.01, -0.02, 0.02],
+/// ];
+/// let overfitting = Overfitting::new(returns).unwrap();
+/// assert_eq!(overfitting.best(), 0);
+/// assert_eq!(overfitting.pbo(4).unwrap(), 0.0);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overfitting {
+    returns: Vec<Vec<f64>>,
+}
+
+/// Euler-Mascheroni constant.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+impl Overfitting {
+    /// Creates the statistics from the returns of each combination.
+    ///
+    /// ### Arguments
+    /// * `returns` - The returns of each combination, over the same periods.
+    ///
+    /// ### Returns
+    /// The statistics, or an error if there are less than 2 combinations, less than 2 periods or
+    /// combinations with a different number of periods.
+    pub fn new(returns: Vec<Vec<f64>>) -> Result<Self> {
+        let periods = returns.first().map_or(0, Vec::len);
+        if returns.len() < 2 || periods < 2 || returns.iter().any(|r| r.len() != periods) {
+            return Err(Error::Msg(
+                "At least 2 combinations with the returns of the same periods are required".to_string(),
+            ));
+        }
+        Ok(Self { returns })
+    }
+
+    /// Creates the statistics from the backtests of an optimization (e.g., `Optimizer::with`), using
+    /// the returns of their equity at the close of each candle.
+    #[cfg(feature = "metrics")]
+    pub fn from_backtests<'a>(backtests: impl IntoIterator<Item = &'a Backtest>) -> Result<Self> {
+        let returns = backtests
+            .into_iter()
+            .map(|backtest| {
+                let metrics = Metrics::from(backtest);
+                let equity = std::iter::once(metrics.initial_balance())
+                    .chain(metrics.equity_curve().into_iter().map(|(_, equity)| equity))
+                    .collect::<Vec<_>>();
+                equity.windows(2).map(|w| (w[1] - w[0]) / w[0]).collect()
+            })
+            .collect();
+        Self::new(returns)
+    }
+
+    /// Returns the Sharpe ratio (per period) of each combination.
+    pub fn sharpe_ratios(&self) -> Vec<f64> {
+        self.returns.iter().map(|returns| sharpe(returns)).collect()
+    }
+
+    /// Returns the index of the combination with the best Sharpe ratio.
+    pub fn best(&self) -> usize {
+        self.sharpe_ratios()
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// Returns the Sharpe ratio expected for the best of the combinations if none of them had an edge,
+    /// from the variance of their Sharpe ratios.
+    pub fn expected_max_sharpe(&self) -> f64 {
+        let sharpe_ratios = self.sharpe_ratios();
+        let n = sharpe_ratios.len() as f64;
+        let mean = sharpe_ratios.iter().sum::<f64>() / n;
+        let variance = sharpe_ratios.iter().map(|sr| (sr - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        variance.sqrt()
+            * ((1.0 - EULER_GAMMA) * inverse_normal_cdf(1.0 - 1.0 / n)
+                + EULER_GAMMA * inverse_normal_cdf(1.0 - 1.0 / (n * std::f64::consts::E)))
+    }
+
+    /// Returns the deflated Sharpe ratio of the best combination: the probability (0 to 1) that its
+    /// Sharpe ratio is above the one expected from the selection alone, given the skewness and the
+    /// kurtosis of its returns.
+    pub fn deflated_sharpe_ratio(&self) -> f64 {
+        let returns = &self.returns[self.best()];
+        let t = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / t;
+        let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / t).sqrt();
+        let (skewness, kurtosis) = if std_dev > 0.0 {
+            let moment = |k| returns.iter().map(|r| ((r - mean) / std_dev).powi(k)).sum::<f64>() / t;
+            (moment(3), moment(4))
+        } else {
+            (0.0, 3.0)
+        };
+
+        let sharpe = sharpe(returns);
+        let variance = (1.0 - skewness * sharpe + (kurtosis - 1.0) / 4.0 * sharpe.powi(2)).max(f64::EPSILON);
+        normal_cdf((sharpe - self.expected_max_sharpe()) * (t - 1.0).sqrt() / variance.sqrt())
+    }
+
+    /// Returns the probability of backtest overfitting (0 to 1).
+    ///
+    /// The periods are split into `splits` contiguous blocks; for each half of the blocks taken as
+    /// the in-sample set (the other half being the out-of-sample set), the best in-sample combination
+    /// is ranked out-of-sample. The probability is the share of the splits where it ranks at or below
+    /// the median.
+    ///
+    /// ### Arguments
+    /// * `splits` - The number of blocks, even and between 2 and 24 (e.g., 16).
+    ///
+    /// ### Returns
+    /// The probability, or an error if the number of blocks is invalid or above the number of periods.
+    pub fn pbo(&self, splits: usize) -> Result<f64> {
+        let periods = self.returns[0].len();
+        if !(2..=24).contains(&splits) || !splits.is_multiple_of(2) || splits > periods {
+            return Err(Error::Msg(format!(
+                "Invalid number of splits: {splits} (even, from 2 to 24, at most {periods} periods)"
+            )));
+        }
+
+        let block = |i: usize| i * periods / splits..(i + 1) * periods / splits;
+        let n = self.returns.len();
+        let mut overfit = 0;
+        let mut total = 0;
+        for mask in 0u32..1 << splits {
+            if mask.count_ones() as usize != splits / 2 {
+                continue;
+            }
+            let sample = |in_sample: bool| {
+                self.returns
+                    .iter()
+                    .map(|returns| {
+                        let returns = (0..splits)
+                            .filter(|i| (mask >> i & 1 == 1) == in_sample)
+                            .flat_map(|i| &returns[block(i)])
+                            .copied()
+                            .collect::<Vec<_>>();
+                        sharpe(&returns)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let (is, oos) = (sample(true), sample(false));
+            let best = (0..n).max_by(|a, b| is[*a].total_cmp(&is[*b])).unwrap_or_default();
+            let rank = 1 + oos.iter().filter(|sr| **sr < oos[best]).count();
+            let omega = rank as f64 / (n + 1) as f64;
+            if (omega / (1.0 - omega)).ln() <= 0.0 {
+                overfit += 1;
+            }
+            total += 1;
+        }
+
+        Ok(overfit as f64 / total as f64)
+    }
+}
+
+/// Correlation and covariance of the combinations of an optimization.
+///
+/// The best combination alone is fragile; an ensemble of good combinations whose equity curves do not
+/// move together is more robust. The correlation of the equity curves tells which combinations trade
+/// alike, and the covariance of their returns (per period) gives the risk of an ensemble of them.
+///
+/// All the combinations must have the equity of the same periods.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::optimizer::Correlations;
+///
+/// // 3 combinations of 5 periods
+/// let equity = vec![
+///     vec![100.0, 102.0, 104.0, 103.0, 106.0],
+///     vec![100.0, 101.0, 103.0, 102.0, 104.0],
+///     vec![100.0, 99.0, 98.0, 101.0, 99.0],
+/// ];
+/// let correlations = Correlations::new(equity).unwrap();
+/// assert!(correlations.equity_correlation(0, 1) > 0.9);
+/// assert!(correlations.equity_correlation(0, 2) < 0.0);
+/// // the best combination, then the least correlated one
+/// assert_eq!(correlations.diversified(2), vec![0, 2]);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correlations {
+    equity: Vec<Vec<f64>>,
+    returns: Vec<Vec<f64>>,
+}
+
+impl Correlations {
+    /// Creates the statistics from the equity curves of each combination.
+    ///
+    /// ### Arguments
+    /// * `equity` - The equity of each combination, over the same periods.
+    ///
+    /// ### Returns
+    /// The statistics, or an error if there are less than 2 combinations, less than 3 periods,
+    /// combinations with a different number of periods or a non-positive equity.
+    pub fn new(equity: Vec<Vec<f64>>) -> Result<Self> {
+        let periods = equity.first().map_or(0, Vec::len);
+        if equity.len() < 2 || periods < 3 || equity.iter().any(|e| e.len() != periods) {
+            return Err(Error::Msg(
+                "At least 2 combinations with the equity of the same periods are required".to_string(),
+            ));
+        }
+        if equity.iter().flatten().any(|e| !e.is_finite() || *e <= 0.0) {
+            return Err(Error::Msg("The equity must be finite and positive".to_string()));
+        }
+        let returns = equity
+            .iter()
+            .map(|equity| equity.windows(2).map(|w| (w[1] - w[0]) / w[0]).collect())
+            .collect();
+        Ok(Self { equity, returns })
+    }
+
+    /// Creates the statistics from the backtests of an optimization (e.g., `Optimizer::with`), using
+    /// their equity at the close of each candle.
+    #[cfg(feature = "metrics")]
+    pub fn from_backtests<'a>(backtests: impl IntoIterator<Item = &'a Backtest>) -> Result<Self> {
+        let equity = backtests
+            .into_iter()
+            .map(|backtest| {
+                let metrics = Metrics::from(backtest);
+                std::iter::once(metrics.initial_balance())
+                    .chain(metrics.equity_curve().into_iter().map(|(_, equity)| equity))
+                    .collect()
+            })
+            .collect();
+        Self::new(equity)
+    }
+
+    /// Returns the number of combinations.
+    pub fn len(&self) -> usize {
+        self.equity.len()
+    }
+
+    /// Returns `true` if there is no combination (never, since at least 2 are required).
+    pub fn is_empty(&self) -> bool {
+        self.equity.is_empty()
+    }
+
+    /// Returns the correlation (-1 to 1) of the equity curves of two combinations, or 0.0 if one of
+    /// them is flat.
+    pub fn equity_correlation(&self, i: usize, j: usize) -> f64 {
+        pearson(&self.equity[i], &self.equity[j])
+    }
+
+    /// Returns the correlation (-1 to 1) of the returns of two combinations, or 0.0 if one of them
+    /// does not vary.
+    pub fn return_correlation(&self, i: usize, j: usize) -> f64 {
+        pearson(&self.returns[i], &self.returns[j])
+    }
+
+    /// Returns the sample covariance of the returns of two combinations.
+    pub fn return_covariance(&self, i: usize, j: usize) -> f64 {
+        covariance(&self.returns[i], &self.returns[j])
+    }
+
+    /// Returns the matrix of the correlations of the equity curves.
+    pub fn equity_correlations(&self) -> Vec<Vec<f64>> {
+        self.matrix(|i, j| self.equity_correlation(i, j))
+    }
+
+    /// Returns the covariance matrix of the returns.
+    pub fn return_covariances(&self) -> Vec<Vec<f64>> {
+        self.matrix(|i, j| self.return_covariance(i, j))
+    }
+
+    /// Returns the matrix of a pairwise statistic.
+    fn matrix(&self, statistic: impl Fn(usize, usize) -> f64) -> Vec<Vec<f64>> {
+        let n = self.len();
+        (0..n).map(|i| (0..n).map(|j| statistic(i, j)).collect()).collect()
+    }
+
+    /// Picks a diversified ensemble of combinations.
+    ///
+    /// The combination with the best Sharpe ratio comes first, then each next one is the combination
+    /// whose returns are the least correlated, on average, with the ones already picked.
+    ///
+    /// ### Arguments
+    /// * `n` - The number of combinations to pick.
+    ///
+    /// ### Returns
+    /// The indexes of the picked combinations, at most `n`.
+    pub fn diversified(&self, n: usize) -> Vec<usize> {
+        let best = self
+            .returns
+            .iter()
+            .map(|returns| sharpe(returns))
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(0, |(i, _)| i);
+        let mut picked = vec![best];
+        while picked.len() < n.min(self.len()) {
+            let correlation =
+                |i: usize| picked.iter().map(|&j| self.return_correlation(i, j)).sum::<f64>() / picked.len() as f64;
+            let next = (0..self.len())
+                .filter(|i| !picked.contains(i))
+                .min_by(|a, b| correlation(*a).total_cmp(&correlation(*b)))
+                .unwrap_or_default();
+            picked.push(next);
+        }
+        picked.truncate(n);
+        picked
+    }
+}
+
+/// Returns the sample covariance of two series of the same length.
+fn covariance(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let (mean_x, mean_y) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+    x.iter().zip(y).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / (n - 1.0)
+}
+
+/// Returns the Pearson correlation of two series of the same length, or 0.0 if one of them is constant.
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let std_dev = (covariance(x, x) * covariance(y, y)).sqrt();
+    if std_dev > 0.0 { covariance(x, y) / std_dev } else { 0.0 }
+}
+
+/// Returns the Sharpe ratio of the returns (per period), or 0.0 if they do not vary.
+fn sharpe(returns: &[f64]) -> f64 {
+    let n = returns.len() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / n;
+    let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    if std_dev > 0.0 { mean / std_dev } else { 0.0 }
+}
+
+/// Returns the cumulative distribution function of the standard normal distribution.
+fn normal_cdf(x: f64) -> f64 {
+    // complementary error function (Numerical Recipes, relative error below 1.2e-7)
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |acc, c| acc * t + c);
+    let erfc = t * (-z * z + poly).exp();
+    if x >= 0.0 { 1.0 - erfc / 2.0 } else { erfc / 2.0 }
+}
+
+/// Returns the quantile function of the standard normal distribution (Acklam's algorithm).
+fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    let poly = |coefficients: &[f64], x: f64| coefficients.iter().fold(0.0, |acc, c| acc * x + c);
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    let tail = |p: f64| {
+        let q = (-2.0 * p.ln()).sqrt();
+        poly(&C, q) / (poly(&D, q) * q + 1.0)
+    };
+    match p {
+        p if p < 0.024_25 => tail(p),
+        p if p > 1.0 - 0.024_25 => -tail(1.0 - p),
+        p => {
+            let q = p - 0.5;
+            let r = q * q;
+            poly(&A, r) * q / (poly(&B, r) * r + 1.0)
+        }
+    }
+}
+
+/// Progress of an optimization saved by `Optimizer::resume`: the evaluated combinations and their score.
+///
+/// Each combination is identified by its index in the combinations of the search mode, so a state can
+/// only be resumed or merged with an optimizer using the same `ParameterCombination`, search mode and
+/// seed. The states of several shards (see `Optimizer::with_shard`), possibly run on several machines,
+/// are merged into the state of the whole search.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizerState<I> {
+    //? an undefined (NaN) score is saved as `None`, the serialization formats may not support NaN
+    evaluated: BTreeMap<usize, (I, Option<f64>)>,
+}
+
+impl<I> Default for OptimizerState<I> {
+    fn default() -> Self {
+        Self {
+            evaluated: BTreeMap::new(),
+        }
+    }
 }
 
+impl<I: Clone> OptimizerState<I> {
+    /// Creates an empty state, to start a search with `Optimizer::resume`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of evaluated combinations.
+    pub fn len(&self) -> usize {
+        self.evaluated.len()
+    }
+
+    /// Returns `true` if no combination has been evaluated.
+    pub fn is_empty(&self) -> bool {
+        self.evaluated.is_empty()
+    }
+
+    /// Returns `true` if the combination at `index` has been evaluated.
+    pub fn contains(&self, index: usize) -> bool {
+        self.evaluated.contains_key(&index)
+    }
+
+    /// Returns the evaluated combinations with their score, in the order of the combinations.
+    pub fn results(&self) -> impl Iterator<Item = (&I, f64)> {
+        self.evaluated
+            .values()
+            .map(|(item, score)| (item, score.unwrap_or(f64::NAN)))
+    }
+
+    /// Returns the evaluated combinations with their score, best score first, ranked as `Optimizer::maximize`.
+    pub fn ranked(&self) -> Vec<(I, f64)> {
+        let mut results = self
+            .results()
+            .map(|(item, score)| (item.clone(), score))
+            .collect::<Vec<_>>();
+        results.sort_by(|(_, a), (_, b)| rank(*b).total_cmp(&rank(*a)));
+        results
+    }
+
+    /// Merges the combinations evaluated in another state (e.g., another shard), keeping the
+    /// combinations of `self` evaluated in both.
+    ///
+    /// # Arguments
+    /// * `other` - The state to merge.
+    pub fn merge(&mut self, other: Self) {
+        for (index, entry) in other.evaluated {
+            self.evaluated.entry(index).or_insert(entry);
+        }
+    }
+
+    /// Records the score of the combination at `index`.
+    fn insert(&mut self, index: usize, item: I, score: f64) {
+        let score = (!score.is_nan()).then_some(score);
+        self.evaluated.insert(index, (item, score));
+    }
+}
+
+/// Callback receiving the number of evaluated combinations and the total number of combinations.
+pub type Progress = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
 /// Optimizer for testing trading strategies with different parameter combinations.
 ///
 /// This struct handles the execution of backtests for each parameter combination,
@@ -38,7 +1103,14 @@ pub struct Optimizer<PC: ParameterCombination> {
     data: Arc<[Candle]>,
     initial_balance: f64,
     _marker: PhantomData<PC>,
-    market_fees: Option<(f64, f64)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    fee_model: Option<Arc<dyn FeeModel>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    progress: Option<Progress>,
+    search_mode: SearchMode,
+    seed: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    shard: Option<(usize, usize)>,
 }
 
 impl<PC: ParameterCombination> From<&Backtest> for Optimizer<PC> {
@@ -47,81 +1119,706 @@ impl<PC: ParameterCombination> From<&Backtest> for Optimizer<PC> {
             _marker: PhantomData,
             data: value.candles().cloned().collect(),
             initial_balance: value.initial_balance(),
-            market_fees: value.market_fees().copied(),
+            fee_model: value.shared_fee_model(),
+            progress: None,
+            search_mode: SearchMode::Grid,
+            seed: None,
+            shard: None,
         }
     }
-}
+}
+
+impl<PC: ParameterCombination> Optimizer<PC> {
+    /// Creates a new `Optimizer` with the given data and initial balance.
+    ///
+    /// # Arguments
+    /// * `data` - Historical candle data for backtesting.
+    /// * `initial_balance` - Starting balance for the backtest.
+    /// * `market_fees` - Optional tuple of (maker fee, taker fee).
+    ///
+    /// # Returns
+    /// A new `Optimizer` instance.
+    pub fn new(data: Arc<[Candle]>, initial_balance: f64, market_fees: Option<(f64, f64)>) -> Self {
+        Self {
+            data,
+            fee_model: market_fees.map(|(taker, maker)| Arc::new(PercentFees::new(taker, maker)) as Arc<dyn FeeModel>),
+            initial_balance,
+            _marker: PhantomData,
+            progress: None,
+            search_mode: SearchMode::Grid,
+            seed: None,
+            shard: None,
+        }
+    }
+
+    /// Sets the commission model of the backtests.
+    ///
+    /// # Arguments
+    /// * `fee_model` - The commission model charged when positions are opened and closed.
+    ///
+    /// # Returns
+    /// The `Optimizer` with the commission model.
+    pub fn with_fee_model(mut self, fee_model: impl FeeModel + 'static) -> Self {
+        self.fee_model = Some(Arc::new(fee_model));
+        self
+    }
+
+    /// Sets how the parameter combinations to evaluate are chosen.
+    ///
+    /// # Arguments
+    /// * `search_mode` - The grid (default) or a sampling of the parameter ranges.
+    ///
+    /// # Returns
+    /// The `Optimizer` with the search mode.
+    pub fn with_search_mode(mut self, search_mode: SearchMode) -> Self {
+        self.search_mode = search_mode;
+        self
+    }
+
+    /// Sets the seed of the sampling search modes, so the same combinations are drawn on each run.
+    ///
+    /// # Arguments
+    /// * `seed` - The seed of the random generator.
+    ///
+    /// # Returns
+    /// The `Optimizer` with the seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Evaluates one shard of the combinations only, to split a search between several processes or
+    /// machines: the shard `index` keeps every `count`-th combination from the `index`-th one.
+    ///
+    /// The sampling search modes need a seed for all the shards to draw the same combinations. The
+    /// Bayesian optimization ignores the shard.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the shard, lower than `count`.
+    /// * `count` - The number of shards.
+    ///
+    /// # Returns
+    /// The `Optimizer` with the shard.
+    ///
+    /// # Errors
+    /// Returns an error if `index` isn't lower than `count`.
+    pub fn with_shard(mut self, index: usize, count: usize) -> Result<Self> {
+        if index >= count {
+            return Err(Error::Msg(format!("the shard {index} is out of {count} shards")));
+        }
+        self.shard = Some((index, count));
+        Ok(self)
+    }
+
+    /// Returns the combinations to evaluate according to the search mode.
+    fn combinations(&self) -> Result<Vec<PC::Item>> {
+        Ok(self.indexed_combinations()?.into_iter().map(|(_, item)| item).collect())
+    }
+
+    /// Returns the combinations to evaluate according to the search mode, with their index, keeping
+    /// the combinations of the shard only.
+    fn indexed_combinations(&self) -> Result<Vec<(usize, PC::Item)>> {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        let (shard, count) = self.shard.unwrap_or((0, 1));
+        Ok(self
+            .search_mode
+            .combinations::<PC>(&mut rng)?
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| index % count == shard)
+            .collect())
+    }
+
+    /// Sets a callback notified each time a parameter combination has been evaluated.
+    ///
+    /// # Arguments
+    /// * `progress` - Function receiving the number of evaluated combinations and the total.
+    ///
+    /// # Returns
+    /// The `Optimizer` with the progress callback.
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Optimizes a trading strategy by testing all parameter combinations and filtering the results.
+    ///
+    /// This function leverages multi-threading to evaluate multiple parameter combinations
+    /// simultaneously, significantly improving performance by utilizing all available CPU cores.
+    ///
+    /// # Arguments
+    /// * `combinator` - A function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - A trading strategy function to test.
+    /// * `filter` - A function that takes a reference to a `Backtest` instance after strategy execution and returns an `Option<R>`. The function returns only the `Some` result.
+    ///
+    /// # Returns
+    /// A vector of tuples where each tuple contains:
+    /// - The original parameter combination.
+    /// - The filtered result, as determined by the `filter` function.
+    ///
+    /// # Errors
+    /// Returns an error if backtest execution fails.
+    pub fn with_filter<T, R, C, S, F>(&self, combinator: C, strategy: S, filter: F) -> Result<Vec<(PC::Item, R)>>
+    where
+        R: Send,
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+        F: Fn(&Backtest) -> Option<R> + Sync,
+    {
+        self.evaluate(|backtest, param_set| {
+            let mut strategy = strategy.clone();
+            let mut output = combinator(param_set)?;
+            backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+            Ok(filter(backtest))
+        })
+    }
+
+    /// Optimizes a trading strategy for the highest return under a maximum drawdown.
+    ///
+    /// The drawdown of the equity is tracked during each run; a combination is dropped
+    /// as soon as it exceeds `max_drawdown`, without running the remaining candles.
+    ///
+    /// # Arguments
+    /// * `max_drawdown` - The maximum drawdown percentage allowed (e.g., 20.0 for 20%).
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
+    ///
+    /// # Returns
+    /// The combinations that respected the drawdown with their return percentage, best return first.
+    ///
+    /// # Errors
+    /// Returns an error if backtest execution fails.
+    pub fn with_max_drawdown<T, C, S>(
+        &self,
+        max_drawdown: f64,
+        combinator: C,
+        strategy: S,
+    ) -> Result<Vec<(PC::Item, f64)>>
+    where
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+    {
+        let mut results = self.evaluate(|backtest, param_set| {
+            let mut strategy = strategy.clone();
+            let mut output = combinator(param_set)?;
+            let mut peak = self.initial_balance;
+            let mut breached = false;
+            let mut breaches = |balance: f64| {
+                peak = peak.max(balance);
+                -peak.change(balance) > max_drawdown
+            };
+
+            let run = backtest.run(|bt, candle| {
+                if breaches(bt.equity()) {
+                    breached = true;
+                    //? aborts the run, the error is discarded below
+                    return Err(Error::Msg("Maximum drawdown exceeded".to_string()));
+                }
+                strategy(bt, &mut output, candle)
+            });
+            match run {
+                Err(_) if breached => Ok(None),
+                Err(e) => Err(e),
+                Ok(()) if breaches(backtest.equity()) => Ok(None),
+                Ok(()) => Ok(Some(self.initial_balance.change(backtest.equity()))),
+            }
+        })?;
+        results.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        Ok(results)
+    }
+
+    /// Optimizes a trading strategy for the highest score of an objective.
+    ///
+    /// # Arguments
+    /// * `objective` - The score to maximize (e.g., `FinalBalance`, a `Weighted` combination or a closure).
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
+    ///
+    /// # Returns
+    /// All the combinations with their score, best score first. Equal scores keep the order of
+    /// `ParameterCombination::generate` and undefined (NaN) scores come last, so the result is deterministic.
+    ///
+    /// # Errors
+    /// Returns an error if backtest execution fails.
+    pub fn maximize<T, O, C, S>(&self, objective: O, combinator: C, strategy: S) -> Result<Vec<(PC::Item, f64)>>
+    where
+        O: Objective,
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+    {
+        let mut results = self.with_filter(combinator, strategy, |backtest| Some(objective.score(backtest)))?;
+        //? the sort is stable and the results are in the order of the combinations
+        results.sort_by(|(_, a), (_, b)| rank(*b).total_cmp(&rank(*a)));
+
+        Ok(results)
+    }
+
+    /// Optimizes a trading strategy for the highest score of an objective, skipping the combinations
+    /// already evaluated in a saved state, so a long search survives a crash or is split between machines.
+    ///
+    /// The remaining combinations are evaluated by batches of `every` combinations; `checkpoint`
+    /// receives the state after each batch (e.g., to save it to a file). An error of `checkpoint`
+    /// stops the search.
+    ///
+    /// # Arguments
+    /// * `state` - The saved state, or `OptimizerState::new()` to start a search.
+    /// * `every` - The number of combinations between two checkpoints.
+    /// * `checkpoint` - Function receiving the state after each batch.
+    /// * `objective` - The score to maximize.
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
+    ///
+    /// # Returns
+    /// The state with all the combinations evaluated; `OptimizerState::ranked` gives the same result
+    /// as `Optimizer::maximize`.
+    ///
+    /// # Errors
+    /// Returns an error if backtest execution or a checkpoint fails.
+    pub fn resume<T, O, C, S, K>(
+        &self,
+        mut state: OptimizerState<PC::Item>,
+        every: usize,
+        mut checkpoint: K,
+        objective: O,
+        combinator: C,
+        strategy: S,
+    ) -> Result<OptimizerState<PC::Item>>
+    where
+        O: Objective,
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+        K: FnMut(&OptimizerState<PC::Item>) -> Result<()>,
+    {
+        let (indexes, remaining): (Vec<_>, Vec<_>) = self
+            .indexed_combinations()?
+            .into_iter()
+            .filter(|(index, _)| !state.contains(*index))
+            .unzip();
+        let done = AtomicUsize::new(0);
+        let score = |backtest: &mut Backtest, param_set: &PC::Item| {
+            let mut strategy = strategy.clone();
+            let mut output = combinator(param_set)?;
+            backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+            Ok(Some(objective.score(backtest)))
+        };
+
+        for (indexes, batch) in indexes.chunks(every.max(1)).zip(remaining.chunks(every.max(1))) {
+            let scores = self.evaluate_combinations(batch, &done, remaining.len(), score)?;
+            for (index, (param_set, score)) in indexes.iter().zip(scores) {
+                state.insert(*index, param_set, score);
+            }
+            checkpoint(&state)?;
+        }
+
+        Ok(state)
+    }
+
+    /// Optimizes a trading strategy for the highest score of an objective, keeping the `n` best
+    /// combinations only.
+    ///
+    /// Unlike `Optimizer::maximize`, each worker keeps its best results in a bounded heap instead of
+    /// collecting the results of every combination, so the memory stays small for searches over
+    /// millions of combinations.
+    ///
+    /// # Arguments
+    /// * `n` - The number of combinations to keep.
+    /// * `objective` - The score to maximize.
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
+    ///
+    /// # Returns
+    /// The `n` best combinations with their score, best score first, ranked as `Optimizer::maximize`.
+    ///
+    /// # Errors
+    /// Returns an error if backtest execution fails.
+    pub fn top_n<T, O, C, S>(&self, n: usize, objective: O, combinator: C, strategy: S) -> Result<Vec<(PC::Item, f64)>>
+    where
+        O: Objective,
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+    {
+        let combinations = self.combinations()?;
+        let heaps = self.fold_combinations(
+            &combinations,
+            &AtomicUsize::new(0),
+            combinations.len(),
+            || BinaryHeap::with_capacity(n + 1),
+            |heap, backtest, index, param_set| {
+                let mut strategy = strategy.clone();
+                let mut output = combinator(param_set)?;
+                backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+                let score = objective.score(backtest);
+                keep_best(
+                    heap,
+                    n,
+                    Ranked {
+                        score,
+                        index,
+                        item: param_set,
+                    },
+                );
+                Ok(())
+            },
+        )?;
+
+        let mut best = BinaryHeap::with_capacity(n + 1);
+        for ranked in heaps.into_iter().flatten() {
+            keep_best(&mut best, n, ranked);
+        }
+        Ok(best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ranked| (ranked.item.clone(), ranked.score))
+            .collect())
+    }
+
+    /// Optimizes a trading strategy with a Bayesian search of the parameter ranges.
+    ///
+    /// Instead of evaluating a fixed set of combinations, each round proposes the most promising
+    /// combinations given the scores of the previous ones (see `Tpe`), and evaluates them in parallel.
+    /// The search mode of the optimizer is ignored, and its seed makes the search reproducible.
+    ///
+    /// # Arguments
+    /// * `tpe` - The settings of the search, including the number of evaluations.
+    /// * `objective` - The score to maximize.
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
+    ///
+    /// # Returns
+    /// The evaluated combinations with their score, best score first (as `Optimizer::maximize`).
+    ///
+    /// # Errors
+    /// Returns an error if `ParameterCombination::ranges` or `from_values` is not implemented,
+    /// or if backtest execution fails.
+    pub fn bayesian<T, O, C, S>(
+        &self,
+        tpe: Tpe,
+        objective: O,
+        combinator: C,
+        strategy: S,
+    ) -> Result<Vec<(PC::Item, f64)>>
+    where
+        O: Objective,
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+    {
+        let ranges = PC::ranges();
+        if ranges.is_empty() {
+            return Err(Error::Msg(
+                "ParameterCombination::ranges is required for the Bayesian optimization".to_string(),
+            ));
+        }
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        let done = AtomicUsize::new(0);
+        let score = |backtest: &mut Backtest, param_set: &PC::Item| {
+            let mut strategy = strategy.clone();
+            let mut output = combinator(param_set)?;
+            backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+            Ok(Some(objective.score(backtest)))
+        };
+
+        let mut observations = Vec::with_capacity(tpe.evaluations);
+        let mut results = Vec::with_capacity(tpe.evaluations);
+        let mut points = unit_points(ranges.len(), tpe.initial, true, &mut rng);
+        while !points.is_empty() {
+            let combinations = points
+                .iter()
+                .map(|point| from_unit::<PC>(&ranges, point))
+                .collect::<Result<Vec<_>>>()?;
+            //? the invalid combinations use up the budget and are observed with an undefined score, so
+            //? the estimator moves away from them
+            let (valid, invalid): (Vec<_>, Vec<_>) = points
+                .into_iter()
+                .zip(combinations)
+                .partition(|(_, param_set)| PC::validate(param_set));
+            let (valid_points, combinations): (Vec<_>, Vec<_>) = valid.into_iter().unzip();
+            let scores = self.evaluate_combinations(&combinations, &done, tpe.evaluations, score)?;
+            for (point, result) in valid_points.into_iter().zip(scores) {
+                observations.push((point, result.1));
+                results.push(result);
+            }
+            observations.extend(invalid.into_iter().map(|(point, _)| (point, f64::NAN)));
+
+            let count = tpe.batch.min(tpe.evaluations.saturating_sub(observations.len()));
+            points = tpe.propose(&observations, count, &mut rng);
+        }
+        results.sort_by(|(_, a), (_, b)| rank(*b).total_cmp(&rank(*a)));
+
+        Ok(results)
+    }
+
+    /// Creates a backtest on `candles` with the commission model of the optimizer.
+    fn backtest(&self, candles: Arc<[Candle]>, initial_balance: f64) -> Result<Backtest> {
+        Ok(Backtest::new(candles, initial_balance, None)?.with_shared_fee_model(self.fee_model.clone()))
+    }
+
+    /// Evaluates all parameter combinations in parallel, keeping the `Some` results.
+    ///
+    /// The backtest given to `evaluate` is reset after each combination.
+    fn evaluate<R, E>(&self, evaluate: E) -> Result<Vec<(PC::Item, R)>>
+    where
+        R: Send,
+        E: Fn(&mut Backtest, &PC::Item) -> Result<Option<R>> + Sync,
+    {
+        let combinations = self.combinations()?;
+        self.evaluate_combinations(&combinations, &AtomicUsize::new(0), combinations.len(), evaluate)
+    }
+
+    /// Evaluates the given combinations in parallel, keeping the `Some` results in the order of the combinations.
+    ///
+    /// `done` counts the evaluated combinations out of `total` for the progress callback.
+    fn evaluate_combinations<R, E>(
+        &self,
+        combinations: &[PC::Item],
+        done: &AtomicUsize,
+        total: usize,
+        evaluate: E,
+    ) -> Result<Vec<(PC::Item, R)>>
+    where
+        R: Send,
+        E: Fn(&mut Backtest, &PC::Item) -> Result<Option<R>> + Sync,
+    {
+        self.fold_combinations(
+            combinations,
+            done,
+            total,
+            Vec::new,
+            |results, backtest, _, param_set| {
+                if let Some(r) = evaluate(backtest, param_set)? {
+                    results.push((param_set.clone(), r));
+                }
+                Ok(())
+            },
+        )
+        .map(|chunks| chunks.into_iter().flatten().collect())
+    }
+
+    /// Evaluates the given combinations in parallel, folding the results of each worker into an
+    /// accumulator created by `init`.
+    ///
+    /// `fold` receives the accumulator, the backtest (reset after each combination), the index of
+    /// the combination and the combination. `done` counts the evaluated combinations out of `total`
+    /// for the progress callback.
+    fn fold_combinations<'a, A, I, F>(
+        &self,
+        combinations: &'a [PC::Item],
+        done: &AtomicUsize,
+        total: usize,
+        init: I,
+        fold: F,
+    ) -> Result<Vec<A>>
+    where
+        A: Send,
+        I: Fn() -> A + Sync,
+        F: Fn(&mut A, &mut Backtest, usize, &'a PC::Item) -> Result<()> + Sync,
+    {
+        let num_cpus = num_cpus::get();
+        let chunk_size = combinations.len().div_ceil(num_cpus).max(1);
+
+        combinations
+            .par_chunks(chunk_size)
+            .enumerate()
+            .map::<_, Result<_>>(|(chunk, par_combinations)| {
+                let candles = Arc::clone(&self.data);
+                let mut backtest = self.backtest(candles, self.initial_balance)?;
+                let mut accumulator = init();
+
+                for (i, param_set) in par_combinations.iter().enumerate() {
+                    fold(&mut accumulator, &mut backtest, chunk * chunk_size + i, param_set)?;
+                    backtest.reset();
+
+                    if let Some(progress) = &self.progress {
+                        progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+                    }
+                }
+
+                Ok(accumulator)
+            })
+            .collect()
+    }
 
-impl<PC: ParameterCombination> Optimizer<PC> {
-    /// Creates a new `Optimizer` with the given data and initial balance.
+    /// Estimates the capacity of a strategy for a given parameter combination.
+    ///
+    /// The strategy is run once per AUM level (in parallel) with the AUM as initial balance and a
+    /// `Slippage::VolumeParticipation` model, so larger accounts pay more slippage on each fill.
+    /// The strategy should size its orders relatively to the balance for the result to be meaningful.
     ///
     /// # Arguments
-    /// * `data` - Historical candle data for backtesting.
-    /// * `initial_balance` - Starting balance for the backtest.
-    /// * `market_fees` - Optional tuple of (maker fee, taker fee).
+    /// * `params` - The parameter combination to evaluate.
+    /// * `aums` - The amounts of capital to test.
+    /// * `impact` - The impact coefficient of the volume-participation slippage.
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
     ///
     /// # Returns
-    /// A new `Optimizer` instance.
-    pub fn new(data: Arc<[Candle]>, initial_balance: f64, market_fees: Option<(f64, f64)>) -> Self {
-        Self {
-            data,
-            market_fees,
-            initial_balance,
-            _marker: PhantomData,
+    /// The return vs AUM curve.
+    ///
+    /// # Errors
+    /// Returns an error if an AUM is not positive or if backtest execution fails.
+    pub fn capacity<T, C, S>(
+        &self,
+        params: &PC::Item,
+        aums: &[f64],
+        impact: f64,
+        combinator: C,
+        strategy: S,
+    ) -> Result<Capacity>
+    where
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+    {
+        if let Some(aum) = aums.iter().find(|aum| **aum <= 0.0) {
+            return Err(Error::NegZeroBalance(*aum));
         }
+
+        let mut points = aums
+            .par_iter()
+            .map(|&aum| {
+                let candles = Arc::clone(&self.data);
+                let mut strategy = strategy.clone();
+                let mut output = combinator(params)?;
+                let mut backtest = self
+                    .backtest(candles, aum)?
+                    .with_slippage(Slippage::VolumeParticipation(impact));
+                backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+                Ok((aum, aum.change(backtest.total_balance())))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        points.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        Ok(Capacity { points })
     }
 
-    /// Optimizes a trading strategy by testing all parameter combinations and filtering the results.
+    /// Runs a strategy from random start dates to measure how path dependent its result is.
     ///
-    /// This function leverages multi-threading to evaluate multiple parameter combinations
-    /// simultaneously, significantly improving performance by utilizing all available CPU cores.
+    /// Each run (in parallel) skips a random number of candles, keeping at least `min_candles`
+    /// candles, and starts with a fresh strategy and backtest.
     ///
     /// # Arguments
-    /// * `combinator` - A function that converts a parameter combination into strategy-specific parameters.
-    /// * `strategy` - A trading strategy function to test.
-    /// * `filter` - A function that takes a reference to a `Backtest` instance after strategy execution and returns an `Option<R>`. The function returns only the `Some` result.
+    /// * `params` - The parameter combination to evaluate.
+    /// * `runs` - The number of start dates to draw.
+    /// * `min_candles` - The minimum number of candles of each run.
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
     ///
     /// # Returns
-    /// A vector of tuples where each tuple contains:
-    /// - The original parameter combination.
-    /// - The filtered result, as determined by the `filter` function.
+    /// The distribution of the returns.
     ///
     /// # Errors
-    /// Returns an error if backtest execution fails.
-    pub fn with_filter<T, R, C, S, F>(&self, combinator: C, strategy: S, filter: F) -> Result<Vec<(PC::Item, R)>>
+    /// Returns an error if there are fewer candles than `min_candles` or if backtest execution fails.
+    pub fn start_dates<T, C, S>(
+        &self,
+        params: &PC::Item,
+        runs: usize,
+        min_candles: usize,
+        combinator: C,
+        strategy: S,
+    ) -> Result<StartDates>
     where
-        R: Send,
         C: Fn(&PC::Item) -> Result<T> + Sync,
         S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
-        F: Fn(&Backtest) -> Option<R> + Sync,
     {
-        let num_cpus = num_cpus::get();
-        let combinations = PC::generate();
-        let chunk_size = combinations.len().div_ceil(num_cpus).max(1);
-
-        combinations
-            .par_chunks(chunk_size)
-            .map::<_, Result<_>>(|par_combinations| {
-                let candles = Arc::clone(&self.data);
+        let min_candles = min_candles.max(1);
+        if self.data.len() < min_candles {
+            return Err(Error::Msg(format!(
+                "Not enough candles: {} available, {min_candles} required",
+                self.data.len()
+            )));
+        }
 
+        let max_offset = self.data.len() - min_candles;
+        let offsets = (0..runs)
+            .map(|_| rand::random_range(0..=max_offset))
+            .collect::<Vec<_>>();
+        let mut returns = offsets
+            .par_iter()
+            .map(|&offset| {
+                let candles = Arc::from(&self.data[offset..]);
                 let mut strategy = strategy.clone();
-                let mut backtest = Backtest::new(candles, self.initial_balance, self.market_fees)?;
-                let mut local_results = Vec::with_capacity(par_combinations.len());
-
-                for param_set in par_combinations {
-                    let mut output = combinator(param_set)?;
-                    backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
-                    let result = filter(&backtest);
-                    if let Some(r) = result {
-                        local_results.push((param_set.clone(), r));
-                    }
-                    backtest.reset();
-                }
+                let mut output = combinator(params)?;
+                let mut backtest = self.backtest(candles, self.initial_balance)?;
+                backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+                Ok((offset, self.initial_balance.change(backtest.equity())))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        returns.sort_by_key(|(offset, _)| *offset);
+
+        Ok(StartDates { returns })
+    }
+
+    /// Optimizes a trading strategy on the train candles of each fold of a split, and scores the
+    /// best parameters on the test candles.
+    ///
+    /// # Arguments
+    /// * `split` - The splitter of the candles of the optimizer.
+    /// * `objective` - The score to maximize.
+    /// * `combinator` - Function that converts a parameter combination into strategy-specific parameters.
+    /// * `strategy` - Trading strategy function to test.
+    ///
+    /// # Returns
+    /// The best parameters of each fold with their in-sample and out-of-sample scores.
+    ///
+    /// # Errors
+    /// Returns an error if the split fails, if there is no combination to evaluate, or if backtest
+    /// execution fails.
+    pub fn cross_validate<T, O, C, S>(
+        &self,
+        split: &Split,
+        objective: O,
+        combinator: C,
+        strategy: S,
+    ) -> Result<Vec<CrossValidation<PC::Item>>>
+    where
+        O: Objective,
+        C: Fn(&PC::Item) -> Result<T> + Sync,
+        S: FnMut(&mut Backtest, &mut T, &Candle) -> Result<()> + Clone + Sync,
+    {
+        split
+            .folds(&self.data)?
+            .into_iter()
+            .map(|fold| {
+                let train = self.with_data(fold.train_data(&self.data));
+                let results = train.maximize(|bt: &Backtest| objective.score(bt), &combinator, strategy.clone())?;
+                let Some((params, in_sample)) = results.into_iter().next() else {
+                    return Err(Error::Msg("No parameter combination to evaluate".to_string()));
+                };
 
-                Ok(local_results)
+                let mut strategy = strategy.clone();
+                let mut output = combinator(&params)?;
+                let mut backtest = self.backtest(fold.test_data(&self.data), self.initial_balance)?;
+                backtest.run(|bt, candle| strategy(bt, &mut output, candle))?;
+                Ok(CrossValidation {
+                    out_of_sample: objective.score(&backtest),
+                    fold,
+                    params,
+                    in_sample,
+                })
             })
-            .collect::<Result<Vec<_>>>()
-            .map(|chunks| chunks.into_iter().flatten().collect())
+            .collect()
+    }
+
+    /// Returns a copy of the optimizer running on other candles.
+    fn with_data(&self, data: Arc<[Candle]>) -> Self {
+        Self {
+            data,
+            initial_balance: self.initial_balance,
+            _marker: PhantomData,
+            fee_model: self.fee_model.clone(),
+            progress: self.progress.clone(),
+            search_mode: self.search_mode,
+            seed: self.seed,
+            shard: self.shard,
+        }
     }
 
     /// Optimizes a trading strategy by testing all possible parameter combinations.
@@ -281,3 +1978,612 @@ fn optimizer_with_ema_macd() {
     )
     .unwrap();
 }
+
+#[cfg(test)]
+#[test]
+fn optimizer_capacity() {
+    use crate::prelude::*;
+
+    let candles = std::sync::Arc::from_iter(get_data());
+    let opt = Optimizer::<Parameters>::new(candles, 1_000.0, None);
+
+    let capacity = opt
+        .capacity(
+            &(0, 0, 0, 0),
+            &[100.0, 10.0, 50.0],
+            0.1,
+            |_| Ok(()),
+            |bt, _, candle| {
+                if bt.positions().count() == 0 && bt.orders().count() == 0 {
+                    let quantity = bt.free_balance()?.how_many(50.0) / candle.close();
+                    let exit = OrderType::TakeProfitAndStopLoss(120.0, 0.0);
+                    bt.place_order(
+                        candle,
+                        (OrderType::Market(candle.close()), exit, quantity, OrderSide::Buy).into(),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    let points = capacity.points();
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].0, 10.0);
+    assert_eq!(points[2].0, 100.0);
+    // more capital, more slippage, less return
+    assert!(points[0].1 > points[1].1);
+    assert!(points[1].1 > points[2].1);
+    assert_eq!(capacity.max_aum(f64::NEG_INFINITY), Some(100.0));
+    assert_eq!(capacity.max_aum(f64::INFINITY), None);
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_start_dates() {
+    use crate::prelude::*;
+
+    let candles = std::sync::Arc::from_iter(get_data());
+    let opt = Optimizer::<Parameters>::new(candles, 1_000.0, None);
+    let buy_and_hold = |bt: &mut Backtest, _: &mut (), candle: &Candle| {
+        if bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(50.0) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    };
+
+    let start_dates = opt.start_dates(&(0, 0, 0, 0), 20, 2, |_| Ok(()), buy_and_hold).unwrap();
+    let returns = start_dates.returns();
+    assert_eq!(returns.len(), 20);
+    // at least two candles are kept
+    assert!(returns.iter().all(|(offset, _)| *offset <= 1));
+    assert!(returns.windows(2).all(|w| w[0].0 <= w[1].0));
+    // the price only rises, so the open position is in profit
+    assert!(start_dates.min() <= start_dates.median());
+    assert!(start_dates.median() <= start_dates.max());
+    assert_eq!(start_dates.positive_rate(), 100.0);
+    assert!(start_dates.mean() > 0.0);
+
+    assert!(opt.start_dates(&(0, 0, 0, 0), 1, 4, |_| Ok(()), buy_and_hold).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_max_drawdown() {
+    use crate::prelude::*;
+
+    struct Sizes;
+    impl ParameterCombination for Sizes {
+        type Item = f64;
+
+        fn generate() -> Vec<Self::Item> {
+            vec![10.0, 50.0, 90.0]
+        }
+    }
+
+    // the price drops by 20% then rises by 50%
+    let mut data = get_data();
+    data[1] = CandleBuilder::builder()
+        .open(100.0)
+        .high(100.0)
+        .low(80.0)
+        .close(80.0)
+        .volume(1.0)
+        .open_time(data[1].open_time())
+        .close_time(data[1].close_time())
+        .build()
+        .unwrap();
+    let opt = Optimizer::<Sizes>::new(std::sync::Arc::from_iter(data), 1_000.0, None);
+
+    let results = opt
+        .with_max_drawdown(
+            15.0,
+            |percent| Ok(*percent),
+            |bt, percent, candle| {
+                if bt.positions().count() == 0 && bt.orders().count() == 0 {
+                    let quantity = bt.free_balance()?.how_many(*percent) / candle.close();
+                    bt.place_order(
+                        candle,
+                        (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    // the largest size draws down by 18%
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0], (50.0, 10.0));
+    assert_eq!(results[1], (10.0, 2.0));
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_maximize() {
+    use crate::prelude::*;
+
+    struct Sizes;
+    impl ParameterCombination for Sizes {
+        type Item = f64;
+
+        fn generate() -> Vec<Self::Item> {
+            vec![10.0, 0.0, 50.0, 30.0, 0.0]
+        }
+    }
+
+    let opt = Optimizer::<Sizes>::new(std::sync::Arc::from_iter(get_data()), 1_000.0, None);
+    let buy = |bt: &mut Backtest, percent: &mut f64, candle: &Candle| {
+        if *percent > 0.0 && bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(*percent) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    };
+
+    // the price rises by 20%: the largest size wins, the empty runs tie in generation order
+    let results = opt.maximize(FinalBalance, |percent| Ok(*percent), buy).unwrap();
+    let sizes = results.iter().map(|(size, _)| *size).collect::<Vec<_>>();
+    assert_eq!(sizes, [50.0, 30.0, 10.0, 0.0, 0.0]);
+    assert_eq!(results[0].1, 1_100.0);
+
+    let smallest = Weighted::new()
+        .with(-1.0, FinalBalance)
+        .with(0.5, |bt: &Backtest| bt.initial_balance());
+    let results = opt.maximize(smallest, |percent| Ok(*percent), buy).unwrap();
+    assert_eq!(results[0], (0.0, -500.0));
+    assert_eq!(results[4].0, 50.0);
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_top_n() {
+    use crate::prelude::*;
+
+    struct Sizes;
+    impl ParameterCombination for Sizes {
+        type Item = f64;
+
+        fn generate() -> Vec<Self::Item> {
+            vec![10.0, 0.0, 50.0, 30.0, 0.0, 20.0, 40.0, 0.0]
+        }
+    }
+
+    let opt = Optimizer::<Sizes>::new(std::sync::Arc::from_iter(get_data()), 1_000.0, None);
+    let buy = |bt: &mut Backtest, percent: &mut f64, candle: &Candle| {
+        if *percent > 0.0 && bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(*percent) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    };
+
+    let all = opt.maximize(FinalBalance, |percent| Ok(*percent), buy).unwrap();
+    let top = opt.top_n(3, FinalBalance, |percent| Ok(*percent), buy).unwrap();
+    assert_eq!(top, all[..3]);
+    assert_eq!(
+        top.iter().map(|(size, _)| *size).collect::<Vec<_>>(),
+        [50.0, 40.0, 30.0]
+    );
+
+    // the ties keep the order of the combinations, as with `maximize`
+    let smallest = |bt: &Backtest| -bt.total_balance();
+    let all = opt.maximize(smallest, |percent| Ok(*percent), buy).unwrap();
+    assert_eq!(opt.top_n(20, smallest, |percent| Ok(*percent), buy).unwrap(), all);
+    assert!(
+        opt.top_n(0, FinalBalance, |percent| Ok(*percent), buy)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_search_mode() {
+    struct Ranges;
+    impl ParameterCombination for Ranges {
+        type Item = (f64, usize);
+
+        fn generate() -> Vec<Self::Item> {
+            unreachable!("the ranges are sampled")
+        }
+
+        fn ranges() -> Vec<RangeInclusive<f64>> {
+            vec![0.0..=100.0, 5.0..=9.0]
+        }
+
+        fn from_values(values: &[f64]) -> Option<Self::Item> {
+            Some((values[0], values[1].round() as usize))
+        }
+    }
+
+    let candles = std::sync::Arc::from_iter(get_data());
+    let opt = Optimizer::<Ranges>::new(Arc::clone(&candles), 1_000.0, None)
+        .with_search_mode(SearchMode::Random { n: 20 })
+        .with_seed(42);
+    let random = opt.combinations().unwrap();
+    assert_eq!(random.len(), 20);
+    assert_eq!(random, opt.combinations().unwrap());
+    assert!(
+        random
+            .iter()
+            .all(|(a, b)| (0.0..=100.0).contains(a) && (5..=9).contains(b))
+    );
+
+    // one combination in each quarter of the range
+    let opt = opt.with_search_mode(SearchMode::LatinHypercube { n: 4 });
+    let mut strata = opt
+        .combinations()
+        .unwrap()
+        .iter()
+        .map(|(a, _)| (a / 25.0) as usize)
+        .collect::<Vec<_>>();
+    strata.sort();
+    assert_eq!(strata, [0, 1, 2, 3]);
+
+    // without ranges, the combinations of the grid are sampled
+    let opt = Optimizer::<Parameters>::new(candles, 1_000.0, None)
+        .with_search_mode(SearchMode::Random { n: 10 })
+        .with_seed(7);
+    let mut sampled = opt.combinations().unwrap();
+    assert_eq!(sampled.len(), 10);
+    assert!(sampled.iter().all(|c| Parameters::generate().contains(c)));
+    sampled.sort();
+    sampled.dedup();
+    assert_eq!(sampled.len(), 10);
+    let results = opt.with_filter(|_| Ok(()), |_, _, _| Ok(()), |_| Some(())).unwrap();
+    assert_eq!(results.len(), 10);
+
+    let opt = opt.with_search_mode(SearchMode::LatinHypercube { n: 4 });
+    assert_eq!(opt.combinations().unwrap().len(), 4);
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_bayesian() {
+    use crate::prelude::*;
+
+    struct Quadratic;
+    impl ParameterCombination for Quadratic {
+        type Item = (f64, f64);
+
+        fn generate() -> Vec<Self::Item> {
+            Vec::new()
+        }
+
+        fn ranges() -> Vec<RangeInclusive<f64>> {
+            vec![-10.0..=10.0, -10.0..=10.0]
+        }
+
+        fn from_values(values: &[f64]) -> Option<Self::Item> {
+            Some((values[0], values[1]))
+        }
+    }
+
+    // the size of the position, so the final balance, peaks at (3, -2)
+    let candles = std::sync::Arc::from_iter(get_data());
+    let opt = Optimizer::<Quadratic>::new(candles, 1_000.0, None).with_seed(1);
+    let strategy = |bt: &mut Backtest, percent: &mut f64, candle: &Candle| {
+        if bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(*percent) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    };
+    let combinator = |&(x, y): &(f64, f64)| Ok(50.0 * (-((x - 3.0).powi(2) + (y + 2.0).powi(2)) / 20.0).exp());
+
+    let results = opt.bayesian(Tpe::new(60), FinalBalance, combinator, strategy).unwrap();
+    assert_eq!(results.len(), 60);
+    assert!(results.windows(2).all(|w| w[0].1 >= w[1].1));
+    let ((x, y), _) = results[0];
+    assert!((x - 3.0).hypot(y + 2.0) < 1.0);
+    assert_eq!(
+        results,
+        opt.bayesian(Tpe::new(60), FinalBalance, combinator, strategy).unwrap()
+    );
+
+    let opt = Optimizer::<Parameters>::new(std::sync::Arc::from_iter(get_data()), 1_000.0, None);
+    assert!(
+        opt.bayesian(Tpe::new(10), FinalBalance, |_| Ok(()), |_, _, _| Ok(()))
+            .is_err()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_progress() {
+    let candles = std::sync::Arc::from_iter(get_data());
+    let done = Arc::new(AtomicUsize::new(0));
+    let total = Arc::new(AtomicUsize::new(0));
+    let opt = Optimizer::<Parameters>::new(candles, 1_000.0, None).with_progress({
+        let done = Arc::clone(&done);
+        let total = Arc::clone(&total);
+        move |d, t| {
+            done.fetch_max(d, Ordering::Relaxed);
+            total.store(t, Ordering::Relaxed);
+        }
+    });
+
+    let results = opt.with_filter(|_| Ok(()), |_, _, _| Ok(()), |_| None::<()>).unwrap();
+    assert!(results.is_empty());
+    assert_eq!(done.load(Ordering::Relaxed), Parameters::generate().len());
+    assert_eq!(total.load(Ordering::Relaxed), Parameters::generate().len());
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_overfitting() {
+    assert!((inverse_normal_cdf(0.975) - 1.959_964).abs() < 1e-6);
+    assert!((normal_cdf(1.959_964) - 0.975).abs() < 1e-6);
+    assert!(Overfitting::new(vec![vec![0.01, 0.02]]).is_err());
+    assert!(Overfitting::new(vec![vec![0.01, 0.02], vec![0.01]]).is_err());
+
+    // a combination better than the others on every period is not overfitted
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut returns = (0..10)
+        .map(|_| (0..64).map(|_| rng.random_range(-0.01..0.01)).collect::<Vec<f64>>())
+        .collect::<Vec<_>>();
+    returns.push(returns[0].iter().map(|r| r.abs() + 0.01).collect());
+    let overfitting = Overfitting::new(returns.clone()).unwrap();
+    assert_eq!(overfitting.best(), 10);
+    assert_eq!(overfitting.pbo(8).unwrap(), 0.0);
+    assert!(overfitting.deflated_sharpe_ratio() > 0.99);
+    assert!(overfitting.pbo(7).is_err());
+    assert!(overfitting.pbo(128).is_err());
+
+    // the best of random combinations has no edge
+    returns.pop();
+    let overfitting = Overfitting::new(returns).unwrap();
+    let pbo = overfitting.pbo(8).unwrap();
+    assert!(pbo > 0.2 && pbo < 0.8, "{pbo}");
+    assert!(overfitting.deflated_sharpe_ratio() < 0.5);
+    assert!(overfitting.expected_max_sharpe() > 0.0);
+}
+
+#[cfg(test)]
+#[test]
+#[allow(clippy::single_range_in_vec_init)]
+fn optimizer_cross_validate() {
+    use crate::prelude::*;
+    use crate::split::Split;
+
+    struct Sizes;
+    impl ParameterCombination for Sizes {
+        type Item = f64;
+
+        fn generate() -> Vec<Self::Item> {
+            vec![10.0, 50.0, 30.0]
+        }
+    }
+
+    let opt = Optimizer::<Sizes>::new(std::sync::Arc::from_iter(get_data()), 1_000.0, None);
+    let buy = |bt: &mut Backtest, percent: &mut f64, candle: &Candle| {
+        if bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(*percent) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    };
+
+    // trains on the first candle, then on the first two
+    let results = opt
+        .cross_validate(&Split::Expanding(2), FinalBalance, |percent| Ok(*percent), buy)
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].fold().train(), &[0..2]);
+    assert_eq!(results[1].fold().test(), &(2..3));
+    // the price rises on the train candles, the largest size wins
+    assert_eq!(*results[1].params(), 50.0);
+    assert_eq!(results[1].in_sample(), 1_050.0);
+    // a single test candle: the order is filled at its close, without profit
+    assert_eq!(results[1].out_of_sample(), 1_000.0);
+
+    assert!(
+        opt.cross_validate(&Split::Expanding(5), FinalBalance, |p| Ok(*p), buy)
+            .is_err()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_validate() {
+    struct Crossover;
+    impl ParameterCombination for Crossover {
+        type Item = (usize, usize);
+
+        fn generate() -> Vec<Self::Item> {
+            (1..=5).flat_map(|fast| (1..=5).map(move |slow| (fast, slow))).collect()
+        }
+
+        fn ranges() -> Vec<RangeInclusive<f64>> {
+            vec![1.0..=5.0, 1.0..=5.0]
+        }
+
+        fn from_values(values: &[f64]) -> Option<Self::Item> {
+            Some((values[0].round() as usize, values[1].round() as usize))
+        }
+
+        fn validate(&(fast, slow): &Self::Item) -> bool {
+            fast < slow
+        }
+    }
+
+    let candles = std::sync::Arc::from_iter(get_data());
+    let done = Arc::new(AtomicUsize::new(0));
+    let opt = Optimizer::<Crossover>::new(candles, 1_000.0, None)
+        .with_seed(7)
+        .with_progress({
+            let done = Arc::clone(&done);
+            move |d, _| {
+                done.fetch_max(d, Ordering::Relaxed);
+            }
+        });
+
+    // the 10 valid combinations of the grid are the only ones run
+    let results = opt.with_filter(|_| Ok(()), |_, _, _| Ok(()), |_| Some(())).unwrap();
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|((fast, slow), _)| fast < slow));
+    assert_eq!(done.load(Ordering::Relaxed), 10);
+
+    let opt = opt.with_search_mode(SearchMode::Random { n: 20 });
+    let results = opt.with_filter(|_| Ok(()), |_, _, _| Ok(()), |_| Some(())).unwrap();
+    assert!(!results.is_empty() && results.len() < 20);
+    assert!(results.iter().all(|((fast, slow), _)| fast < slow));
+
+    let results = opt
+        .bayesian(Tpe::new(20), FinalBalance, |_| Ok(()), |_, _, _| Ok(()))
+        .unwrap();
+    assert!(results.len() <= 20);
+    assert!(results.iter().all(|((fast, slow), _)| fast < slow));
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_resume() {
+    use crate::prelude::*;
+
+    struct Percents;
+    impl ParameterCombination for Percents {
+        type Item = usize;
+
+        fn generate() -> Vec<Self::Item> {
+            (1..=10).collect()
+        }
+    }
+
+    let candles = std::sync::Arc::from_iter(get_data());
+    let opt = Optimizer::<Percents>::new(candles, 1_000.0, None);
+    let strategy = |bt: &mut Backtest, percent: &mut f64, candle: &Candle| {
+        if bt.positions().count() == 0 && bt.orders().count() == 0 {
+            let quantity = bt.free_balance()?.how_many(*percent) / candle.close();
+            bt.place_order(
+                candle,
+                (OrderType::Market(candle.close()), quantity, OrderSide::Buy).into(),
+            )?;
+        }
+        Ok(())
+    };
+    let combinator = |percent: &usize| Ok(*percent as f64);
+    let expected = opt.maximize(FinalBalance, combinator, strategy).unwrap();
+
+    // the search crashes after the first checkpoint, then resumes from the saved state
+    let mut saved = OptimizerState::new();
+    let crash = opt.resume(
+        OptimizerState::new(),
+        4,
+        |state| {
+            saved = state.clone();
+            Err(Error::Msg("crash".to_string()))
+        },
+        FinalBalance,
+        combinator,
+        strategy,
+    );
+    assert!(crash.is_err());
+    assert_eq!(saved.len(), 4);
+    assert!(saved.contains(3) && !saved.contains(4));
+
+    let mut checkpoints = 0;
+    let state = opt
+        .resume(
+            saved,
+            4,
+            |_| {
+                checkpoints += 1;
+                Ok(())
+            },
+            FinalBalance,
+            combinator,
+            strategy,
+        )
+        .unwrap();
+    assert_eq!(checkpoints, 2);
+    assert_eq!(state.len(), 10);
+    assert_eq!(state.ranked(), expected);
+
+    // two shards merged give the whole search
+    let shards = (0..2)
+        .map(|index| {
+            let shard = Optimizer::<Percents>::new(std::sync::Arc::from_iter(get_data()), 1_000.0, None)
+                .with_shard(index, 2)
+                .unwrap();
+            shard
+                .resume(
+                    OptimizerState::new(),
+                    10,
+                    |_| Ok(()),
+                    FinalBalance,
+                    combinator,
+                    strategy,
+                )
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(shards[0].len(), 5);
+    assert!(shards[0].contains(0) && !shards[0].contains(1));
+    let mut merged = shards[0].clone();
+    merged.merge(shards[1].clone());
+    assert_eq!(merged, state);
+    assert!(opt.with_shard(2, 2).is_err());
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<OptimizerState<usize>>(&json).unwrap(), state);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn optimizer_correlations() {
+    assert!(Correlations::new(vec![vec![100.0, 101.0, 102.0]]).is_err());
+    assert!(Correlations::new(vec![vec![100.0, 101.0, 102.0], vec![100.0, 101.0]]).is_err());
+    assert!(Correlations::new(vec![vec![100.0, 0.0, 102.0], vec![100.0, 101.0, 102.0]]).is_err());
+
+    let equity = vec![
+        vec![100.0, 200.0, 400.0, 800.0],
+        // the same curve at twice the size
+        vec![200.0, 400.0, 800.0, 1600.0],
+        vec![100.0, 50.0, 100.0, 50.0],
+        vec![100.0, 100.0, 100.0, 100.0],
+    ];
+    let correlations = Correlations::new(equity).unwrap();
+    assert_eq!(correlations.len(), 4);
+    assert!((correlations.equity_correlation(0, 1) - 1.0).abs() < 1e-12);
+    assert_eq!(correlations.equity_correlation(0, 3), 0.0);
+    // the returns of the first curve do not vary
+    assert_eq!(correlations.return_correlation(0, 2), 0.0);
+    assert_eq!(correlations.return_covariance(0, 1), 0.0);
+    // returns of -50%, +100%, -50%
+    assert_eq!(correlations.return_covariance(2, 2), 0.75);
+
+    let matrix = correlations.equity_correlations();
+    assert_eq!(matrix.len(), 4);
+    assert_eq!(matrix[1][2], matrix[2][1]);
+    assert!((matrix[2][2] - 1.0).abs() < 1e-12);
+    assert_eq!(
+        correlations.return_covariances()[2][2],
+        correlations.return_covariance(2, 2)
+    );
+
+    assert_eq!(correlations.diversified(1).len(), 1);
+    let picked = correlations.diversified(10);
+    assert_eq!(picked.len(), 4);
+    assert!(picked.iter().all(|i| picked.iter().filter(|j| *j == i).count() == 1));
+}
@@ -0,0 +1,204 @@
+//! Paper trading on a live candle feed.
+//!
+//! `PaperTrader` runs a strategy on candles received one at a time (e.g., from a websocket of an
+//! exchange) with the engine of the backtest: the orders, the positions and the wallet are handled
+//! by the same code as `Backtest::run_from_source`, so a strategy behaves identically in the backtest
+//! and in paper trading.
+
+use std::ops::Deref;
+
+use crate::engine::{Backtest, Candle, CandleSource};
+use crate::errors::Result;
+
+/// Paper trading of a strategy on a live candle feed.
+///
+/// The trader wraps a `Backtest`, usually created with `Backtest::streaming` or the
+/// `BacktestBuilder` without data, with its fees, slippage, risk manager and event sink. Each closed
+/// candle of the feed is given to `on_candle`, or the whole feed to `run`. A candle that does not open
+/// after the last one (e.g., replayed after a reconnection) is ignored.
+///
+/// The order and position IDs are random, even with `Backtest::with_deterministic_ids`.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::mpsc;
+/// use std::thread;
+///
+/// use bts_rs::paper::PaperTrader;
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let (sender, receiver) = mpsc::channel();
+/// // the feed of the exchange, sending the candles as they close
+/// let feed = thread::spawn(move || {
+///     for i in 0..3 {
+///         let open_time = DateTime::default() + Duration::minutes(i);
+///         let candle = CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(105.0)
+///             .volume(1.0)
+///             .open_time(open_time)
+///             .close_time(open_time + Duration::minutes(1))
+///             .build()
+///             .unwrap();
+///         sender.send(candle).unwrap();
+///     }
+/// });
+///
+/// let mut trader = PaperTrader::new(Backtest::streaming(1000.0, None).unwrap());
+/// trader
+///     .run(receiver.iter(), |bts, candle| {
+///         if bts.positions().len() == 0 {
+///             let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+///             bts.place_order(candle, order)?;
+///         }
+///         Ok(())
+///     })
+///     .unwrap();
+/// feed.join().unwrap();
+/// assert_eq!(trader.positions().len(), 1);
+/// ```
+pub struct PaperTrader {
+    backtest: Backtest,
+    last: Option<Candle>,
+}
+
+impl Deref for PaperTrader {
+    type Target = Backtest;
+
+    fn deref(&self) -> &Self::Target {
+        &self.backtest
+    }
+}
+
+impl PaperTrader {
+    /// Creates a paper trader.
+    ///
+    /// ### Arguments
+    /// * `backtest` - The engine, with its configuration and initial balance.
+    pub fn new(backtest: Backtest) -> Self {
+        Self { backtest, last: None }
+    }
+
+    /// Processes a closed candle of the feed: runs the strategy, then fills the orders and manages
+    /// the positions.
+    ///
+    /// ### Arguments
+    /// * `candle` - The closed candle.
+    /// * `strategy` - A closure that takes the backtest and the candle.
+    ///
+    /// ### Returns
+    /// `true` if the candle was processed, `false` if it was ignored because it does not open after
+    /// the last one, or an error.
+    pub fn on_candle<S>(&mut self, candle: Candle, mut strategy: S) -> Result<bool>
+    where
+        S: FnMut(&mut Backtest, &Candle) -> Result<()>,
+    {
+        if self
+            .last
+            .as_ref()
+            .is_some_and(|last| candle.open_time() <= last.open_time())
+        {
+            return Ok(false);
+        }
+        self.backtest.step(&candle, &mut strategy)?;
+        self.last = Some(candle);
+        Ok(true)
+    }
+
+    /// Processes the candles of the feed until it ends, then flushes the events.
+    ///
+    /// ### Arguments
+    /// * `feed` - The source of the closed candles (e.g., `receiver.iter()` of a channel).
+    /// * `strategy` - A closure that takes the backtest and the current candle.
+    ///
+    /// ### Returns
+    /// Ok once the feed ends, or an error (including the errors of the feed).
+    pub fn run<C, S>(&mut self, mut feed: C, mut strategy: S) -> Result<()>
+    where
+        C: CandleSource,
+        S: FnMut(&mut Backtest, &Candle) -> Result<()>,
+    {
+        while let Some(candle) = feed.next_candle()? {
+            self.on_candle(candle, &mut strategy)?;
+        }
+        self.flush()
+    }
+
+    /// Records the last equity and flushes the event sink, e.g., before stopping the trader.
+    pub fn flush(&mut self) -> Result<()> {
+        self.backtest.flush_events_at(self.last.as_ref())
+    }
+
+    /// Returns the last processed candle.
+    pub fn last_candle(&self) -> Option<&Candle> {
+        self.last.as_ref()
+    }
+
+    /// Returns the engine, e.g., to cancel an order or close a position manually.
+    pub fn backtest_mut(&mut self) -> &mut Backtest {
+        &mut self.backtest
+    }
+
+    /// Returns the engine, e.g., to compute the metrics of the session.
+    pub fn into_backtest(self) -> Backtest {
+        self.backtest
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn paper_trader_parity() {
+    use std::sync::{Arc, mpsc};
+
+    use crate::engine::{CandleBuilder, Order, OrderSide, OrderType};
+    use chrono::{DateTime, Duration};
+
+    let candles = (0..20)
+        .map(|i| {
+            let open_time = DateTime::default() + Duration::minutes(i);
+            let price = 100.0 + (i % 7) as f64;
+            CandleBuilder::builder()
+                .open(price)
+                .high(price + 3.0)
+                .low(price - 3.0)
+                .close(price + 1.0)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::minutes(1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+    let strategy = |bts: &mut Backtest, candle: &Candle| {
+        if bts.positions().len() == 0 && bts.orders().len() == 0 {
+            let exit_rule = OrderType::TakeProfitAndStopLoss(candle.close() + 2.0, candle.close() - 2.0);
+            let order = Order::from((OrderType::Limit(candle.close() - 1.0), exit_rule, 1.0, OrderSide::Buy));
+            bts.place_order(candle, order)?;
+        }
+        Ok(())
+    };
+
+    let mut backtest = Backtest::new(candles.clone(), 1000.0, Some((0.1, 0.05))).unwrap();
+    backtest.run(strategy).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    for candle in candles.iter() {
+        sender.send(*candle).unwrap();
+    }
+    // a candle replayed by the feed
+    sender.send(candles[5]).unwrap();
+    drop(sender);
+    let mut trader = PaperTrader::new(Backtest::streaming(1000.0, Some((0.1, 0.05))).unwrap());
+    trader.run(receiver.iter(), strategy).unwrap();
+
+    assert!(backtest.fees_paid() > 0.0);
+    assert_eq!(trader.last_candle(), candles.last());
+    assert_eq!(trader.balance(), backtest.balance());
+    assert_eq!(trader.fees_paid(), backtest.fees_paid());
+    assert_eq!(trader.positions().len(), backtest.positions().len());
+    assert_eq!(trader.orders().len(), backtest.orders().len());
+    assert!(!trader.on_candle(candles[0], strategy).unwrap());
+}
@@ -0,0 +1,529 @@
+//! Python bindings.
+//!
+//! This module exposes the engine to Python with [`pyo3`](https://pyo3.rs), so a strategy can be
+//! written in Python (e.g., in a Jupyter notebook) while the orders are filled by the Rust engine:
+//! - `Candle`, `Order` and `Position` mirror the engine types.
+//! - `Backtest.run(strategy)` calls `strategy(ctx, candle)` on each candle. The strategy reads the
+//!   wallet and the open positions from the `Context` and places or closes orders with it; the
+//!   actions are applied once the strategy returns.
+//! - `Backtest.metrics()` returns the `Metrics` of the run.
+//!
+//! Build the extension module with [`maturin`](https://www.maturin.rs) (see `pyproject.toml`):
+//! ```bash
+//! maturin develop --release
+//! ```
+//!
+//! ```python
+//! from datetime import datetime, timedelta, timezone
+//! import bts_rs
+//!
+//! start = datetime(2024, 1, 1, tzinfo=timezone.utc)
+//! candles = [
+//!     bts_rs.Candle(100.0, 110.0, 95.0, 105.0, 1.0, start + timedelta(days=i), start + timedelta(days=i + 1))
+//!     for i in range(10)
+//! ]
+//!
+//! def strategy(ctx, candle):
+//!     if not ctx.positions and not ctx.orders:
+//!         ctx.place_order(bts_rs.Order("buy", 1.0, candle.close, take_profit=candle.close * 1.05))
+//!
+//! bt = bts_rs.Backtest(candles, 1000.0)
+//! bt.run(strategy)
+//! print(bt.metrics())
+//! ```
+//!
+//! It needs to enable `python` feature to use it.
+
+use std::sync::Arc;
+
+use crate::engine::{self, Backtest, CandleBuilder, OrderSide, OrderType, PositionSide};
+use crate::errors::{Error, Result};
+use crate::metrics;
+
+use chrono::{DateTime, Utc};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+impl From<Error> for PyErr {
+    fn from(value: Error) -> Self {
+        PyValueError::new_err(value.to_string())
+    }
+}
+
+/// Returns the name of a side.
+fn side_name(side: &OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+/// A candle of market data.
+#[pyclass(name = "Candle", module = "bts_rs", frozen, from_py_object)]
+#[derive(Clone, Copy)]
+pub struct PyCandle(engine::Candle);
+
+#[pymethods]
+impl PyCandle {
+    /// Creates a candle, raising a `ValueError` if its prices or times are invalid.
+    #[new]
+    #[pyo3(signature = (open, high, low, close, volume, open_time, close_time, bid = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        open: f64,
+        high: f64,
+        low: f64,
+        close: f64,
+        volume: f64,
+        open_time: DateTime<Utc>,
+        close_time: DateTime<Utc>,
+        bid: Option<f64>,
+    ) -> PyResult<Self> {
+        let mut builder = CandleBuilder::builder()
+            .open(open)
+            .high(high)
+            .low(low)
+            .close(close)
+            .volume(volume)
+            .open_time(open_time)
+            .close_time(close_time);
+        if let Some(bid) = bid {
+            builder = builder.bid(bid);
+        }
+        Ok(Self(builder.build()?))
+    }
+
+    #[getter]
+    fn open(&self) -> f64 {
+        self.0.open()
+    }
+
+    #[getter]
+    fn high(&self) -> f64 {
+        self.0.high()
+    }
+
+    #[getter]
+    fn low(&self) -> f64 {
+        self.0.low()
+    }
+
+    #[getter]
+    fn close(&self) -> f64 {
+        self.0.close()
+    }
+
+    #[getter]
+    fn volume(&self) -> f64 {
+        self.0.volume()
+    }
+
+    #[getter]
+    fn open_time(&self) -> DateTime<Utc> {
+        self.0.open_time()
+    }
+
+    #[getter]
+    fn close_time(&self) -> DateTime<Utc> {
+        self.0.close_time()
+    }
+
+    fn __repr__(&self) -> String {
+        let c = &self.0;
+        format!(
+            "Candle(open={}, high={}, low={}, close={}, volume={}, open_time={})",
+            c.open(),
+            c.high(),
+            c.low(),
+            c.close(),
+            c.volume(),
+            c.open_time().to_rfc3339()
+        )
+    }
+}
+
+/// An order, with an optional exit rule.
+#[pyclass(name = "Order", module = "bts_rs", frozen, from_py_object)]
+#[derive(Clone, Copy)]
+pub struct PyOrder(engine::Order);
+
+#[pymethods]
+impl PyOrder {
+    /// Creates an order.
+    ///
+    /// `side` is `"buy"` or `"sell"` and `order_type` is `"market"`, `"limit"` or `"stop"`. The exit
+    /// rule is a take-profit and/or a stop-loss, or a trailing stop percentage (not both).
+    #[new]
+    #[pyo3(signature = (side, quantity, price, order_type = "market", take_profit = None, stop_loss = None, trailing_stop = None))]
+    fn new(
+        side: &str,
+        quantity: f64,
+        price: f64,
+        order_type: &str,
+        take_profit: Option<f64>,
+        stop_loss: Option<f64>,
+        trailing_stop: Option<f64>,
+    ) -> PyResult<Self> {
+        let side = match side.to_lowercase().as_str() {
+            "buy" => OrderSide::Buy,
+            "sell" => OrderSide::Sell,
+            _ => return Err(PyValueError::new_err(format!("Unknown order side: {side}"))),
+        };
+        let entry_type = match order_type.to_lowercase().as_str() {
+            "market" => OrderType::Market(price),
+            "limit" => OrderType::Limit(price),
+            "stop" => OrderType::Stop(price),
+            _ => return Err(PyValueError::new_err(format!("Unknown order type: {order_type}"))),
+        };
+        let order = match (take_profit, stop_loss, trailing_stop) {
+            (None, None, None) => (entry_type, quantity, side).into(),
+            (Some(_), _, Some(_)) | (_, Some(_), Some(_)) => {
+                return Err(PyValueError::new_err(
+                    "A trailing stop can't be combined with a take-profit or a stop-loss",
+                ));
+            }
+            (None, None, Some(percent)) => (entry_type, OrderType::TrailingStop(price, percent), quantity, side).into(),
+            (take_profit, stop_loss, None) => {
+                let exit_rule = OrderType::TakeProfitAndStopLoss(take_profit.unwrap_or(0.0), stop_loss.unwrap_or(0.0));
+                (entry_type, exit_rule, quantity, side).into()
+            }
+        };
+        Ok(Self(order))
+    }
+
+    #[getter]
+    fn id(&self) -> u32 {
+        self.0.id()
+    }
+
+    #[getter]
+    fn side(&self) -> &'static str {
+        side_name(self.0.side())
+    }
+
+    #[getter]
+    fn quantity(&self) -> f64 {
+        self.0.quantity()
+    }
+
+    #[getter]
+    fn price(&self) -> PyResult<f64> {
+        Ok(self.0.entry_price()?)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Order(id={}, {:?}, {:?})",
+            self.0.id(),
+            self.0.entry_type(),
+            self.0.exit_rule()
+        )
+    }
+}
+
+/// An open or closed position.
+#[pyclass(name = "Position", module = "bts_rs", frozen, from_py_object)]
+#[derive(Clone, Copy)]
+pub struct PyPosition(engine::Position);
+
+#[pymethods]
+impl PyPosition {
+    #[getter]
+    fn id(&self) -> u32 {
+        self.0.id()
+    }
+
+    /// Returns `"long"` or `"short"`.
+    #[getter]
+    fn side(&self) -> &'static str {
+        match self.0.side() {
+            PositionSide::Long => "long",
+            PositionSide::Short => "short",
+        }
+    }
+
+    #[getter]
+    fn quantity(&self) -> f64 {
+        self.0.quantity()
+    }
+
+    #[getter]
+    fn entry_price(&self) -> PyResult<f64> {
+        Ok(self.0.entry_price()?)
+    }
+
+    /// Returns the profit and loss if the position is closed at `exit_price`.
+    fn estimate_pnl(&self, exit_price: f64) -> PyResult<f64> {
+        Ok(self.0.estimate_pnl(exit_price)?)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Position(id={}, side={}, quantity={}, {:?})",
+            self.0.id(),
+            self.side(),
+            self.0.quantity(),
+            self.0.entry_type()
+        )
+    }
+}
+
+/// An action requested by the Python strategy.
+enum Action {
+    PlaceOrder(engine::Order),
+    DeleteOrder(engine::Order),
+    ClosePosition(engine::Position, f64),
+    CloseAllPositions(f64),
+}
+
+/// The state of the backtest given to the strategy, which queues its actions.
+#[pyclass(name = "Context", module = "bts_rs")]
+pub struct PyContext {
+    #[pyo3(get)]
+    balance: f64,
+    #[pyo3(get)]
+    free_balance: f64,
+    #[pyo3(get)]
+    fees_paid: f64,
+    #[pyo3(get)]
+    orders: Vec<PyOrder>,
+    #[pyo3(get)]
+    positions: Vec<PyPosition>,
+    actions: Vec<Action>,
+}
+
+impl PyContext {
+    fn new(bts: &Backtest) -> Result<Self> {
+        Ok(Self {
+            balance: bts.balance(),
+            free_balance: bts.free_balance()?,
+            fees_paid: bts.fees_paid(),
+            orders: bts.orders().copied().map(PyOrder).collect(),
+            positions: bts.positions().copied().map(PyPosition).collect(),
+            actions: Vec::new(),
+        })
+    }
+
+    /// Applies the queued actions to the backtest.
+    fn apply(&mut self, bts: &mut Backtest, candle: &engine::Candle) -> Result<()> {
+        for action in self.actions.drain(..) {
+            match action {
+                Action::PlaceOrder(order) => bts.place_order(candle, order)?,
+                Action::DeleteOrder(order) => bts.delete_order(candle, &order, true)?,
+                Action::ClosePosition(position, exit_price) => {
+                    bts.close_position(candle, &position, exit_price, true)?;
+                }
+                Action::CloseAllPositions(exit_price) => bts.close_all_positions(candle, exit_price)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyContext {
+    /// Places an order once the strategy returns.
+    fn place_order(&mut self, order: &PyOrder) {
+        self.actions.push(Action::PlaceOrder(order.0));
+    }
+
+    /// Deletes a pending order once the strategy returns.
+    fn delete_order(&mut self, order: &PyOrder) {
+        self.actions.push(Action::DeleteOrder(order.0));
+    }
+
+    /// Closes a position at `exit_price` once the strategy returns.
+    fn close_position(&mut self, position: &PyPosition, exit_price: f64) {
+        self.actions.push(Action::ClosePosition(position.0, exit_price));
+    }
+
+    /// Closes all the positions at `exit_price` once the strategy returns.
+    fn close_all_positions(&mut self, exit_price: f64) {
+        self.actions.push(Action::CloseAllPositions(exit_price));
+    }
+}
+
+/// The backtest engine.
+#[pyclass(name = "Backtest", module = "bts_rs", unsendable)]
+pub struct PyBacktest(Backtest);
+
+#[pymethods]
+impl PyBacktest {
+    /// Creates a backtest over the candles, with optional `(taker, maker)` fee percentages.
+    #[new]
+    #[pyo3(signature = (candles, initial_balance, market_fees = None))]
+    fn new(candles: Vec<PyCandle>, initial_balance: f64, market_fees: Option<(f64, f64)>) -> PyResult<Self> {
+        let data = candles.into_iter().map(|c| c.0).collect::<Arc<[_]>>();
+        Ok(Self(Backtest::new(data, initial_balance, market_fees)?))
+    }
+
+    /// Runs `strategy(ctx, candle)` on each candle.
+    ///
+    /// An exception raised by the strategy stops the run and is raised again.
+    fn run(&mut self, py: Python<'_>, strategy: Bound<'_, PyAny>) -> PyResult<()> {
+        let mut raised = None;
+        let result = self.0.run(|bts, candle| {
+            let ctx = Bound::new(py, PyContext::new(bts)?).map_err(|e| Error::Msg(e.to_string()))?;
+            if let Err(e) = strategy.call1((ctx.clone(), PyCandle(*candle))) {
+                let message = e.to_string();
+                raised = Some(e);
+                return Err(Error::Msg(message));
+            }
+            ctx.borrow_mut().apply(bts, candle)
+        });
+        match (result, raised) {
+            (_, Some(e)) => Err(e),
+            (result, None) => Ok(result?),
+        }
+    }
+
+    /// Resets the backtest to its initial state.
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    #[getter]
+    fn balance(&self) -> f64 {
+        self.0.balance()
+    }
+
+    #[getter]
+    fn free_balance(&self) -> PyResult<f64> {
+        Ok(self.0.free_balance()?)
+    }
+
+    #[getter]
+    fn fees_paid(&self) -> f64 {
+        self.0.fees_paid()
+    }
+
+    #[getter]
+    fn orders(&self) -> Vec<PyOrder> {
+        self.0.orders().copied().map(PyOrder).collect()
+    }
+
+    #[getter]
+    fn positions(&self) -> Vec<PyPosition> {
+        self.0.positions().copied().map(PyPosition).collect()
+    }
+
+    /// Returns the metrics of the run.
+    fn metrics(&self) -> PyMetrics {
+        PyMetrics(metrics::Metrics::from(&self.0))
+    }
+}
+
+/// The performance metrics of a run.
+#[pyclass(name = "Metrics", module = "bts_rs", frozen)]
+pub struct PyMetrics(metrics::Metrics);
+
+#[pymethods]
+impl PyMetrics {
+    /// Returns the maximum drawdown as a percentage.
+    fn max_drawdown(&self) -> f64 {
+        self.0.max_drawdown()
+    }
+
+    /// Returns the profit factor.
+    fn profit_factor(&self) -> f64 {
+        self.0.profit_factor()
+    }
+
+    /// Returns the Sharpe ratio per observation.
+    #[pyo3(signature = (risk_free_rate = 0.0))]
+    fn sharpe_ratio(&self, risk_free_rate: f64) -> f64 {
+        self.0.sharpe_ratio(risk_free_rate)
+    }
+
+    /// Returns the annualized Sharpe ratio.
+    #[pyo3(signature = (risk_free_rate = 0.0))]
+    fn annualized_sharpe_ratio(&self, risk_free_rate: f64) -> f64 {
+        self.0.annualized_sharpe_ratio(risk_free_rate)
+    }
+
+    /// Returns the win rate as a percentage.
+    fn win_rate(&self) -> f64 {
+        self.0.win_rate()
+    }
+
+    /// Returns the compound annual growth rate as a percentage.
+    fn cagr(&self) -> f64 {
+        self.0.cagr()
+    }
+
+    /// Returns the equity curve as a list of `(datetime, equity)`.
+    fn equity_curve(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.0.equity_curve()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// The `bts_rs` Python module.
+#[pymodule]
+fn bts_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCandle>()?;
+    m.add_class::<PyOrder>()?;
+    m.add_class::<PyPosition>()?;
+    m.add_class::<PyContext>()?;
+    m.add_class::<PyBacktest>()?;
+    m.add_class::<PyMetrics>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[test]
+fn python_strategy() {
+    use std::ffi::CString;
+
+    Python::initialize();
+    Python::attach(|py| {
+        let module = PyModule::new(py, "bts_rs").unwrap();
+        bts_rs(&module).unwrap();
+        let globals = pyo3::types::PyDict::new(py);
+        globals.set_item("bts_rs", module).unwrap();
+        let code = CString::new(
+            r#"
+from datetime import datetime, timedelta, timezone
+
+start = datetime(2024, 1, 1, tzinfo=timezone.utc)
+candles = [
+    bts_rs.Candle(100.0 + i, 110.0 + i, 95.0 + i, 105.0 + i, 1.0, start + timedelta(days=i), start + timedelta(days=i + 1))
+    for i in range(5)
+]
+
+def strategy(ctx, candle):
+    if not ctx.positions and not ctx.orders:
+        ctx.place_order(bts_rs.Order("buy", 1.0, candle.close, take_profit=candle.close + 5.0))
+
+bt = bts_rs.Backtest(candles, 1000.0)
+bt.run(strategy)
+balance = bt.balance
+metrics = bt.metrics()
+
+def failing(ctx, candle):
+    raise RuntimeError("boom")
+
+bt.reset()
+try:
+    bt.run(failing)
+    raised = False
+except RuntimeError:
+    raised = True
+"#,
+        )
+        .unwrap();
+        py.run(&code, Some(&globals), None).unwrap();
+
+        let balance = py.eval(c"balance", Some(&globals), None).unwrap();
+        assert!(balance.extract::<f64>().unwrap() > 1000.0);
+        let win_rate = py.eval(c"metrics.win_rate()", Some(&globals), None).unwrap();
+        assert_eq!(win_rate.extract::<f64>().unwrap(), 100.0);
+        let raised = py.eval(c"raised", Some(&globals), None).unwrap();
+        assert!(raised.extract::<bool>().unwrap());
+        let invalid = py.eval(c"bts_rs.Order('hold', 1.0, 100.0)", Some(&globals), None);
+        assert!(invalid.is_err());
+    });
+}
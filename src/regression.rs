@@ -0,0 +1,497 @@
+//! Regression testing of the backtest results.
+//!
+//! A `BacktestResult` is the outcome of a run: the balances, the main metrics and the closed trades.
+//! Save it once as a golden file, then compare the later runs against it with `BacktestResult::diff`
+//! to catch a strategy or an engine change that alters the outcomes unexpectedly.
+
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(feature = "serde")]
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::engine::Backtest;
+#[cfg(feature = "serde")]
+use crate::errors::{Error, Result};
+use crate::metrics::{Metrics, Trade, Trades};
+
+/// Tolerances of the comparison of two results.
+///
+/// Two values match when their difference is within the absolute tolerance or within the relative
+/// tolerance of the largest one. The tolerances can be overridden for a field (e.g., `sharpe_ratio`,
+/// `balance` or `exit_price`). The times, the sides and the exit reasons of the trades must be equal.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tolerance {
+    absolute: f64,
+    relative: f64,
+    fields: BTreeMap<String, (f64, f64)>,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self::new(1e-9, 1e-9)
+    }
+}
+
+impl Tolerance {
+    /// Creates the tolerances of all the fields.
+    ///
+    /// ### Arguments
+    /// * `absolute` - The absolute tolerance (e.g., 0.01 for one cent).
+    /// * `relative` - The relative tolerance (e.g., 0.001 for 0.1%).
+    pub fn new(absolute: f64, relative: f64) -> Self {
+        Self {
+            absolute: absolute.abs(),
+            relative: relative.abs(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Overrides the tolerances of a field.
+    pub fn with_field(mut self, field: impl Into<String>, absolute: f64, relative: f64) -> Self {
+        self.fields.insert(field.into(), (absolute.abs(), relative.abs()));
+        self
+    }
+
+    /// Returns `true` if the values of a field match.
+    pub fn matches(&self, field: &str, expected: f64, actual: f64) -> bool {
+        if expected == actual || (expected.is_nan() && actual.is_nan()) {
+            return true;
+        }
+        let (absolute, relative) = self
+            .fields
+            .get(field)
+            .copied()
+            .unwrap_or((self.absolute, self.relative));
+        let difference = (expected - actual).abs();
+        difference <= absolute || difference <= relative * expected.abs().max(actual.abs())
+    }
+}
+
+/// A difference between two results.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// A balance or a metric differs beyond its tolerance.
+    Value {
+        /// The name of the field.
+        field: String,
+        /// The value of the expected result, `None` if undefined (e.g., a profit factor without loss).
+        expected: Option<f64>,
+        /// The value of the actual result.
+        actual: Option<f64>,
+    },
+
+    /// The number of trades differs.
+    TradeCount {
+        /// The number of trades of the expected result.
+        expected: usize,
+        /// The number of trades of the actual result.
+        actual: usize,
+    },
+
+    /// A trade differs.
+    Trade {
+        /// The index of the trade.
+        index: usize,
+        /// The name of the field.
+        field: &'static str,
+        /// The value of the expected trade.
+        expected: String,
+        /// The value of the actual trade.
+        actual: String,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |value: &Option<f64>| value.map_or(String::from("undefined"), |v| v.to_string());
+        match self {
+            Self::Value {
+                field,
+                expected,
+                actual,
+            } => {
+                write!(f, "{field}: expected {}, got {}", value(expected), value(actual))
+            }
+            Self::TradeCount { expected, actual } => write!(f, "trades: expected {expected}, got {actual}"),
+            Self::Trade {
+                index,
+                field,
+                expected,
+                actual,
+            } => write!(f, "trade #{index} {field}: expected {expected}, got {actual}"),
+        }
+    }
+}
+
+/// The discrepancies between two results, empty if they match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultDiff(Vec<Discrepancy>);
+
+impl std::ops::Deref for ResultDiff {
+    type Target = [Discrepancy];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for ResultDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return writeln!(f, "The results match");
+        }
+        writeln!(f, "=== {} discrepancies ===", self.0.len())?;
+        for discrepancy in &self.0 {
+            writeln!(f, "{discrepancy}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a backtest, to be pinned and compared with later runs.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candles = (0..4)
+///     .map(|i| {
+///         let open_time = DateTime::default() + Duration::days(i);
+///         CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(100.0 + i as f64)
+///             .volume(1.0)
+///             .open_time(open_time)
+///             .close_time(open_time + Duration::days(1))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Arc<[_]>>();
+///
+/// let run = |quantity: f64| {
+///     let mut bts = Backtest::new(candles.clone(), 1000.0, None).unwrap();
+///     bts.run(|bt, candle| {
+///         if bt.positions().count() == 0 {
+///             let exit_rule = OrderType::TakeProfitAndStopLoss(candle.close() + 5.0, 0.0);
+///             bt.place_order(candle, (OrderType::Market(candle.close()), exit_rule, quantity, OrderSide::Buy).into())?;
+///         }
+///         Ok(())
+///     })
+///     .unwrap();
+///     BacktestResult::from(&bts)
+/// };
+///
+/// let golden = run(1.0);
+/// assert!(golden.diff(&run(1.0)).is_empty());
+/// // a larger size changes the outcome
+/// let diff = golden.diff(&run(2.0));
+/// assert!(!diff.is_empty(), "{diff}");
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestResult {
+    initial_balance: f64,
+    balance: f64,
+    pnl: f64,
+    fees: f64,
+    metrics: BTreeMap<String, Option<f64>>,
+    trades: Trades,
+}
+
+impl From<&Metrics> for BacktestResult {
+    fn from(value: &Metrics) -> Self {
+        let trades = value.trades();
+        //? the undefined metrics (e.g., infinite) are kept as `None`, which is valid JSON
+        let metrics = [
+            ("max_drawdown", value.max_drawdown()),
+            ("profit_factor", value.profit_factor()),
+            ("sharpe_ratio", value.sharpe_ratio(0.0)),
+            ("win_rate", value.win_rate()),
+            ("cagr", value.cagr()),
+            ("annualized_volatility", value.annualized_volatility()),
+            ("expectancy", trades.expectancy()),
+            ("system_quality_number", trades.system_quality_number()),
+            ("kelly_fraction", trades.kelly_fraction()),
+        ]
+        .into_iter()
+        .map(|(name, metric)| (name.to_string(), metric.is_finite().then_some(metric)))
+        .collect();
+
+        Self {
+            initial_balance: value.initial_balance(),
+            balance: value.balance(),
+            pnl: value.pnl(),
+            fees: value.fees(),
+            metrics,
+            trades,
+        }
+    }
+}
+
+impl From<&Backtest> for BacktestResult {
+    fn from(value: &Backtest) -> Self {
+        Self::from(&Metrics::from(value))
+    }
+}
+
+impl BacktestResult {
+    /// Returns the initial balance.
+    pub fn initial_balance(&self) -> f64 {
+        self.initial_balance
+    }
+
+    /// Returns the final balance.
+    pub fn balance(&self) -> f64 {
+        self.balance
+    }
+
+    /// Returns the unrealized profit and loss at the end of the run.
+    pub fn pnl(&self) -> f64 {
+        self.pnl
+    }
+
+    /// Returns the fees paid.
+    pub fn fees(&self) -> f64 {
+        self.fees
+    }
+
+    /// Returns a metric by name (e.g., `max_drawdown`), `None` if unknown or undefined.
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics.get(name).copied().flatten()
+    }
+
+    /// Returns the closed trades.
+    pub fn trades(&self) -> &Trades {
+        &self.trades
+    }
+
+    /// Compares the result with another run, with the default tolerances.
+    ///
+    /// ### Arguments
+    /// * `other` - The actual result, `self` being the expected one.
+    pub fn diff(&self, other: &Self) -> ResultDiff {
+        self.diff_with(other, &Tolerance::default())
+    }
+
+    /// Compares the result with another run.
+    ///
+    /// ### Arguments
+    /// * `other` - The actual result, `self` being the expected one.
+    /// * `tolerance` - The tolerances of the values.
+    ///
+    /// ### Returns
+    /// The discrepancies of the balances, the metrics and the trades.
+    pub fn diff_with(&self, other: &Self, tolerance: &Tolerance) -> ResultDiff {
+        let mut discrepancies = Vec::new();
+        let mut value = |field: &str, expected: Option<f64>, actual: Option<f64>| {
+            let matched = match (expected, actual) {
+                (Some(expected), Some(actual)) => tolerance.matches(field, expected, actual),
+                (expected, actual) => expected == actual,
+            };
+            if !matched {
+                discrepancies.push(Discrepancy::Value {
+                    field: field.to_string(),
+                    expected,
+                    actual,
+                });
+            }
+        };
+
+        value(
+            "initial_balance",
+            Some(self.initial_balance),
+            Some(other.initial_balance),
+        );
+        value("balance", Some(self.balance), Some(other.balance));
+        value("pnl", Some(self.pnl), Some(other.pnl));
+        value("fees", Some(self.fees), Some(other.fees));
+        let names = self
+            .metrics
+            .keys()
+            .chain(other.metrics.keys())
+            .collect::<std::collections::BTreeSet<_>>();
+        for name in names {
+            value(name, self.metric(name), other.metric(name));
+        }
+
+        if self.trades.len() != other.trades.len() {
+            discrepancies.push(Discrepancy::TradeCount {
+                expected: self.trades.len(),
+                actual: other.trades.len(),
+            });
+        }
+        for (index, (expected, actual)) in self.trades.iter().zip(other.trades.iter()).enumerate() {
+            discrepancies.extend(Self::diff_trades(index, expected, actual, tolerance));
+        }
+
+        ResultDiff(discrepancies)
+    }
+
+    /// Compares two trades.
+    fn diff_trades(index: usize, expected: &Trade, actual: &Trade, tolerance: &Tolerance) -> Vec<Discrepancy> {
+        let trade = |field, expected: String, actual: String| Discrepancy::Trade {
+            index,
+            field,
+            expected,
+            actual,
+        };
+
+        let exact = [
+            (
+                "entry_time",
+                expected.entry_time().to_rfc3339(),
+                actual.entry_time().to_rfc3339(),
+            ),
+            (
+                "exit_time",
+                expected.exit_time().to_rfc3339(),
+                actual.exit_time().to_rfc3339(),
+            ),
+            ("side", format!("{:?}", expected.side()), format!("{:?}", actual.side())),
+            (
+                "exit_reason",
+                format!("{:?}", expected.exit_reason()),
+                format!("{:?}", actual.exit_reason()),
+            ),
+        ];
+        let values = [
+            ("quantity", expected.quantity(), actual.quantity()),
+            ("entry_price", expected.entry_price(), actual.entry_price()),
+            ("exit_price", expected.exit_price(), actual.exit_price()),
+            ("fees", expected.fees(), actual.fees()),
+            ("net_pnl", expected.net_pnl(), actual.net_pnl()),
+        ];
+
+        let mut discrepancies = exact
+            .into_iter()
+            .filter(|(_, expected, actual)| expected != actual)
+            .map(|(field, expected, actual)| trade(field, expected, actual))
+            .collect::<Vec<_>>();
+        discrepancies.extend(
+            values
+                .into_iter()
+                .filter(|(field, expected, actual)| !tolerance.matches(field, *expected, *actual))
+                .map(|(field, expected, actual)| trade(field, expected.to_string(), actual.to_string())),
+        );
+        discrepancies
+    }
+
+    /// Writes the result as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self).map_err(|e| Error::Msg(e.to_string()))
+    }
+
+    /// Reads a result written by `to_json`.
+    #[cfg(feature = "serde")]
+    pub fn from_json<R: Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader(reader).map_err(|e| Error::Msg(e.to_string()))
+    }
+
+    /// Saves the result to a golden file.
+    ///
+    /// ### Arguments
+    /// * `path` - The path of the file, created or truncated.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_json(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads a result from a golden file.
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn result_diff() {
+    use std::sync::Arc;
+
+    use crate::engine::{CandleBuilder, OrderSide, OrderType};
+    use chrono::{DateTime, Duration};
+
+    let candles = (0..6)
+        .map(|i| {
+            let open_time = DateTime::default() + Duration::days(i);
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(120.0)
+                .low(90.0)
+                .close(100.0 + i as f64)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + Duration::days(1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Arc<[_]>>();
+    let run = |target: f64| {
+        let mut bt = Backtest::new(candles.clone(), 1000.0, None).unwrap();
+        bt.run(|bt, candle| {
+            if bt.positions().count() == 0 {
+                let exit_rule = OrderType::TakeProfitAndStopLoss(candle.close() + target, 0.0);
+                let order = (OrderType::Market(candle.close()), exit_rule, 1.0, OrderSide::Buy);
+                bt.place_order(candle, order.into())?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        BacktestResult::from(&bt)
+    };
+
+    let golden = run(5.0);
+    assert_eq!(golden.trades().len(), 6);
+    assert!(golden.diff(&run(5.0)).is_empty());
+    assert_eq!(golden.diff(&run(5.0)).to_string(), "The results match\n");
+
+    // the take profits are 5 cents higher
+    let diff = golden.diff(&run(5.05));
+    assert!(diff.contains(&Discrepancy::Trade {
+        index: 0,
+        field: "exit_price",
+        expected: String::from("105"),
+        actual: String::from("105.05"),
+    }));
+    assert!(
+        diff.iter()
+            .any(|d| matches!(d, Discrepancy::Value { field, .. } if field == "balance"))
+    );
+    assert!(
+        diff.to_string()
+            .contains("trade #0 exit_price: expected 105, got 105.05")
+    );
+
+    // within the tolerances, except the balance
+    let tolerance = Tolerance::new(0.1, 0.03);
+    assert!(golden.diff_with(&run(5.05), &tolerance).is_empty());
+    let diff = golden.diff_with(&run(5.05), &tolerance.with_field("balance", 0.01, 0.0));
+    assert_eq!(diff.len(), 1);
+    assert!(matches!(&diff[0], Discrepancy::Value { field, .. } if field == "balance"));
+
+    #[cfg(feature = "serde")]
+    {
+        let mut json = Vec::new();
+        golden.to_json(&mut json).unwrap();
+        //? the JSON floats may lose their last digit, within the default tolerances
+        assert!(
+            golden
+                .diff(&BacktestResult::from_json(json.as_slice()).unwrap())
+                .is_empty()
+        );
+    }
+}
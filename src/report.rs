@@ -0,0 +1,312 @@
+//! Single-file HTML report of a backtest.
+//!
+//! The report combines the price chart, the equity curve, the drawdown, the metrics and the list of
+//! the trades in one HTML file. The charts are rendered with ECharts, loaded from its CDN like the
+//! `DrawOutput::Html` output of [`Draw`].
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::draws::Draw;
+use crate::engine::{Backtest, OrderType};
+use crate::errors::{Error, Result};
+use crate::metrics::Metrics;
+
+use charming::Chart;
+use charming::component::{Axis, DataZoom, DataZoomType, Title};
+use charming::element::{AreaStyle, ItemStyle, Tooltip, Trigger};
+use charming::series::Line;
+
+/// Height of the price chart, in pixels.
+const PRICE_HEIGHT: u32 = 700;
+/// Height of the equity and drawdown charts, in pixels.
+const CURVE_HEIGHT: u32 = 300;
+
+/// HTML report of a backtest: price chart, equity curve, drawdown, metrics and trade list.
+///
+/// The price chart is the HTML chart of a [`Draw`], so the indicators and panels appended to it are
+/// part of the report (see [`Report::with_draw`]).
+///
+/// ### Example
+/// ```no_run
+/// use bts_rs::report::Report;
+/// # use bts_rs::engine::{Backtest, CandleBuilder};
+/// # use chrono::{DateTime, Duration};
+/// # let candle = CandleBuilder::builder()
+/// #     .open(100.0)
+/// #     .high(110.0)
+/// #     .low(95.0)
+/// #     .close(105.0)
+/// #     .volume(1.0)
+/// #     .open_time(DateTime::default())
+/// #     .close_time(DateTime::default() + Duration::days(1))
+/// #     .build()
+/// #     .unwrap();
+/// # let backtest = Backtest::new(std::sync::Arc::from_iter(vec![candle]), 1000.0, None).unwrap();
+///
+/// Report::from(&backtest).with_title("EMA cross").save("report.html").unwrap();
+/// ```
+pub struct Report {
+    title: String,
+    draw: Draw,
+    metrics: Metrics,
+}
+
+impl From<&Backtest> for Report {
+    fn from(backtest: &Backtest) -> Self {
+        Self {
+            title: "BTS Report".to_string(),
+            draw: Draw::from(backtest),
+            metrics: Metrics::from(backtest),
+        }
+    }
+}
+
+impl Report {
+    /// Sets the title of the report.
+    pub fn with_title(mut self, title: impl ToString) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Sets the price chart, e.g., a [`Draw`] of the same backtest with indicators and panels.
+    pub fn with_draw(mut self, draw: Draw) -> Self {
+        self.draw = draw;
+        self
+    }
+
+    /// Renders the report as an HTML page.
+    pub fn render(&self) -> Result<String> {
+        let title = escape(&self.title);
+        let mut html = String::new();
+        let write = |e: std::fmt::Error| Error::Msg(e.to_string());
+
+        writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\" />").map_err(write)?;
+        writeln!(html, "<title>{title}</title>").map_err(write)?;
+        writeln!(
+            html,
+            "<script src=\"https://cdn.jsdelivr.net/npm/echarts@5.5.1/dist/echarts.min.js\"></script>"
+        )
+        .map_err(write)?;
+        writeln!(
+            html,
+            "<style>body {{ font-family: sans-serif; margin: 2em auto; max-width: 1280px; }} \
+             table {{ border-collapse: collapse; margin-bottom: 2em; }} \
+             th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }} \
+             th {{ background: #eee; }} .win {{ color: green; }} .loss {{ color: red; }}</style>"
+        )
+        .map_err(write)?;
+        writeln!(html, "</head>\n<body>\n<h1>{title}</h1>").map_err(write)?;
+
+        let charts = [
+            ("price", PRICE_HEIGHT, self.draw.with_html_chart()),
+            ("equity", CURVE_HEIGHT, self.equity_chart()),
+            ("drawdown", CURVE_HEIGHT, self.drawdown_chart()),
+        ];
+        for (id, height, _) in &charts {
+            writeln!(
+                html,
+                "<div id=\"{id}\" style=\"width: 100%; height: {height}px\"></div>"
+            )
+            .map_err(write)?;
+        }
+
+        writeln!(html, "<h2>Metrics</h2>\n<table>").map_err(write)?;
+        for (name, value) in self.metrics_rows() {
+            writeln!(html, "<tr><th>{name}</th><td>{value}</td></tr>").map_err(write)?;
+        }
+        writeln!(html, "</table>").map_err(write)?;
+
+        let trades = self.metrics.trades();
+        writeln!(html, "<h2>Trades ({})</h2>\n<table>", trades.len()).map_err(write)?;
+        writeln!(
+            html,
+            "<tr><th>#</th><th>Side</th><th>Entry time</th><th>Entry price</th><th>Exit time</th>\
+             <th>Exit price</th><th>Quantity</th><th>Net P&amp;L</th><th>Fees</th><th>MAE</th>\
+             <th>MFE</th><th>Bars</th><th>Exit rule</th></tr>"
+        )
+        .map_err(write)?;
+        for (i, trade) in trades.iter().enumerate() {
+            writeln!(
+                html,
+                "<tr class=\"{}\"><td>{}</td><td>{:?}</td><td>{}</td><td>{:.2}</td><td>{}</td><td>{:.2}</td>\
+                 <td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{}</td><td>{}</td></tr>",
+                if trade.is_win() { "win" } else { "loss" },
+                i + 1,
+                trade.side(),
+                trade.entry_time(),
+                trade.entry_price(),
+                trade.exit_time(),
+                trade.exit_price(),
+                trade.quantity(),
+                trade.net_pnl(),
+                trade.fees(),
+                trade.mae(),
+                trade.mfe(),
+                trade.bars_held(),
+                trade.exit_rule().map_or("-", exit_rule),
+            )
+            .map_err(write)?;
+        }
+        writeln!(html, "</table>\n<script type=\"text/javascript\">").map_err(write)?;
+
+        for (id, _, chart) in &charts {
+            writeln!(
+                html,
+                "echarts.init(document.getElementById('{id}')).setOption({chart});"
+            )
+            .map_err(write)?;
+        }
+        writeln!(html, "</script>\n</body>\n</html>").map_err(write)?;
+
+        Ok(html)
+    }
+
+    /// Renders the report and saves it to an HTML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.render()?)?;
+        Ok(())
+    }
+
+    /// Returns the metrics shown in the metrics table, formatted.
+    fn metrics_rows(&self) -> Vec<(&'static str, String)> {
+        let metrics = &self.metrics;
+        let trades = metrics.trades();
+        let streaks = metrics.streaks();
+        vec![
+            ("Initial Balance", format!("{:.2}", metrics.initial_balance())),
+            ("Final Balance", format!("{:.2}", metrics.balance())),
+            ("Profit &amp; Loss (P&amp;L)", format!("{:.2}", metrics.pnl())),
+            ("Fees paid", format!("{:.2}", metrics.fees())),
+            ("Max Drawdown", format!("{:.2}%", metrics.max_drawdown())),
+            ("Profit Factor", format!("{:.2}", metrics.profit_factor())),
+            ("Sharpe Ratio", format!("{:.2}", metrics.sharpe_ratio(0.0))),
+            ("Win Rate", format!("{:.2}%", metrics.win_rate())),
+            ("CAGR", format!("{:.2}%", metrics.cagr())),
+            (
+                "Annualized Volatility",
+                format!("{:.2}%", metrics.annualized_volatility()),
+            ),
+            (
+                "Annualized Sharpe Ratio",
+                format!("{:.2}", metrics.annualized_sharpe_ratio(0.0)),
+            ),
+            ("Average Win", format!("{:.2}", trades.avg_win())),
+            ("Average Loss", format!("{:.2}", trades.avg_loss())),
+            ("Expectancy", format!("{:.2}", trades.expectancy())),
+            (
+                "System Quality Number",
+                format!("{:.2}", trades.system_quality_number()),
+            ),
+            ("Kelly Fraction", format!("{:.2}", trades.kelly_fraction())),
+            ("Longest Win Streak", trades.longest_win_streak().to_string()),
+            ("Longest Loss Streak", trades.longest_loss_streak().to_string()),
+            ("Average Streak Length", format!("{:.2}", streaks.average_length())),
+            ("Worst Losing Streak", format!("{:.2}", streaks.worst_loss())),
+        ]
+    }
+
+    /// Returns the chart of the equity curve.
+    fn equity_chart(&self) -> Chart {
+        let equity = self.metrics.equity_curve();
+        curve_chart(
+            "Equity",
+            equity.iter().map(|(datetime, _)| datetime.to_string()).collect(),
+            equity.iter().map(|(_, value)| *value).collect(),
+            "BLUE",
+        )
+    }
+
+    /// Returns the chart of the drawdown curve.
+    fn drawdown_chart(&self) -> Chart {
+        let drawdown = self.metrics.drawdown_curve();
+        curve_chart(
+            "Drawdown (%)",
+            drawdown.iter().map(|(datetime, _)| datetime.to_string()).collect(),
+            drawdown.iter().map(|(_, value)| *value).collect(),
+            "RED",
+        )
+    }
+}
+
+/// Returns a line chart of a curve over time, filled down to its axis.
+fn curve_chart(title: &str, dates: Vec<String>, values: Vec<f64>, color: &str) -> Chart {
+    Chart::new()
+        .title(Title::new().text(title).left("center"))
+        .tooltip(Tooltip::new().trigger(Trigger::Axis))
+        .data_zoom(DataZoom::new().type_(DataZoomType::Slider))
+        .x_axis(Axis::new().data(dates))
+        .y_axis(Axis::new().scale(true))
+        .series(
+            Line::new()
+                .name(title)
+                .data(values)
+                .item_style(ItemStyle::new().color(color))
+                .area_style(AreaStyle::new().opacity(0.2)),
+        )
+}
+
+/// Returns the name of an exit rule.
+fn exit_rule(rule: &OrderType) -> &'static str {
+    match rule {
+        OrderType::TakeProfitAndStopLoss(..)
+        | OrderType::TakeProfitAndStopLossPct(..)
+        | OrderType::TakeProfitAndStopLossAtr(..) => "Take-profit / stop-loss",
+        OrderType::TrailingStop(..) | OrderType::TrailingStopDistance(..) | OrderType::TrailingStopAtr(..) => {
+            "Trailing stop"
+        }
+        _ => "Manual",
+    }
+}
+
+/// Escapes the HTML special characters of a text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+#[test]
+fn report_render() {
+    use crate::engine::{CandleBuilder, Order, OrderSide};
+    use chrono::{DateTime, Duration};
+
+    let candles = (0..3)
+        .map(|i| {
+            let price = 100.0 + i as f64 * 10.0;
+            CandleBuilder::builder()
+                .open(price)
+                .high(price + 9.0)
+                .low(price - 10.0)
+                .close(price + 5.0)
+                .volume(1.0)
+                .open_time(DateTime::default() + Duration::days(i))
+                .close_time(DateTime::default() + Duration::days(i + 1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let mut backtest = Backtest::new(std::sync::Arc::from_iter(candles), 1000.0, None).unwrap();
+    backtest
+        .run(|bt, candle| {
+            if bt.positions().len() == 0 && bt.orders().len() == 0 {
+                let exit = OrderType::TakeProfitAndStopLoss(candle.close() + 5.0, 0.0);
+                let order = Order::from((OrderType::Market(candle.close()), exit, 1.0, OrderSide::Buy));
+                bt.place_order(candle, order)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+    let html = Report::from(&backtest).with_title("<EMA> & co").render().unwrap();
+    assert!(html.contains("<title>&lt;EMA&gt; &amp; co</title>"));
+    for id in ["price", "equity", "drawdown"] {
+        assert!(html.contains(&format!("document.getElementById('{id}')")));
+    }
+    assert!(html.contains("<tr><th>Win Rate</th><td>100.00%</td></tr>"));
+    assert!(html.contains("<h2>Trades (1)</h2>"));
+    assert!(html.contains("<td>Long</td>"));
+    assert!(html.contains("<td>Take-profit / stop-loss</td>"));
+}
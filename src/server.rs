@@ -0,0 +1,289 @@
+//! Server-Sent Events (SSE) endpoint streaming a backtest as it executes.
+//!
+//! This module provides a lightweight HTTP server, without any dependency, that pushes to every
+//! connected client:
+//! - The backtest events (`event: event`), through the `SseSink` event sink.
+//! - The run or optimizer progress (`event: progress`).
+//!
+//! A browser can subscribe with `new EventSource("http://127.0.0.1:8080")`.
+//!
+//! Each client is served by its own thread through a bounded queue, so a client that stops
+//! reading is dropped instead of stalling the backtest.
+//!
+//! It needs to enable `server` feature to use it.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::errors::{Error, Result};
+use crate::metrics::Event;
+use crate::sinks::EventSink;
+
+const HEADERS: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: text/event-stream\r\n\
+Cache-Control: no-cache\r\n\
+Connection: keep-alive\r\n\
+Access-Control-Allow-Origin: *\r\n\r\n";
+
+/// Number of messages queued for a client before it is considered stalled and dropped.
+const CLIENT_QUEUE: usize = 1024;
+
+/// Maximum size of the request of a client (request line and headers).
+const MAX_HEADER: u64 = 8 * 1024;
+
+/// Time allowed to read the request of a client or to write a message to it.
+const IO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// SSE server broadcasting messages to its connected clients.
+///
+/// The server accepts the clients in a background thread. It is cheap to clone, every clone
+/// broadcasts to the same clients.
+///
+/// ### Example
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use bts_rs::prelude::*;
+/// use chrono::{DateTime, Duration};
+///
+/// let candle = CandleBuilder::builder()
+///     .open(100.0)
+///     .high(110.0)
+///     .low(95.0)
+///     .close(105.0)
+///     .volume(1.0)
+///     .open_time(DateTime::default())
+///     .close_time(DateTime::default() + Duration::days(1))
+///     .build()
+///     .unwrap();
+/// let candles = Arc::from_iter(vec![candle]);
+/// let total = candles.len();
+///
+/// let server = SseServer::bind("127.0.0.1:0").unwrap();
+/// let mut bts = Backtest::new(candles, 1000.0, None).unwrap().with_event_sink(server.sink());
+/// let mut done = 0;
+/// bts.run(|_bts, _candle| {
+///     done += 1;
+///     server.progress(done, total);
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SseServer {
+    addr: SocketAddr,
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+}
+
+impl SseServer {
+    /// Binds the server to an address and starts accepting clients.
+    ///
+    /// ### Arguments
+    /// * `addr` - The address to listen on (e.g., `127.0.0.1:8080`, or port 0 for any free port).
+    ///
+    /// ### Returns
+    /// The server, or an error if the address can't be bound.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Self::bind_with_queue(addr, CLIENT_QUEUE)
+    }
+
+    /// Binds the server, queuing at most `queue` messages for each client.
+    fn bind_with_queue<A: ToSocketAddrs>(addr: A, queue: usize) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let server = Self {
+            addr: listener.local_addr()?,
+            clients: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let clients = Arc::clone(&server.clients);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = Arc::clone(&clients);
+                std::thread::spawn(move || {
+                    let Ok(stream) = Self::handshake(stream) else {
+                        return;
+                    };
+                    let (sender, receiver) = std::sync::mpsc::sync_channel(queue);
+                    if let Ok(mut clients) = clients.lock() {
+                        clients.push(sender);
+                    }
+                    Self::serve(stream, receiver);
+                });
+            }
+        });
+
+        Ok(server)
+    }
+
+    /// Reads the HTTP request of a new client and answers with the event stream headers.
+    fn handshake(mut stream: TcpStream) -> std::io::Result<TcpStream> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut reader = BufReader::new((&stream).take(MAX_HEADER));
+        let mut line = String::new();
+        while line != "\r\n" && line != "\n" {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "The request is incomplete or too large",
+                ));
+            }
+        }
+        stream.write_all(HEADERS)?;
+        stream.flush()?;
+        Ok(stream)
+    }
+
+    /// Writes the queued messages to a client until it disconnects or a write times out.
+    fn serve(mut stream: TcpStream, receiver: Receiver<Arc<str>>) {
+        for message in receiver {
+            if stream
+                .write_all(message.as_bytes())
+                .and_then(|_| stream.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.clients.lock().map(|clients| clients.len()).unwrap_or_default()
+    }
+
+    /// Broadcasts a message to the connected clients, dropping the disconnected or stalled ones.
+    ///
+    /// The message is only queued, it never waits for a client to read it.
+    ///
+    /// ### Arguments
+    /// * `event` - The SSE event name.
+    /// * `data` - The message, on a single line.
+    pub fn send(&self, event: &str, data: &str) -> Result<()> {
+        let message: Arc<str> = Arc::from(format!("event: {event}\ndata: {data}\n\n"));
+        let mut clients = self.clients.lock().map_err(|e| Error::EventSink(e.to_string()))?;
+        clients.retain(|client| client.try_send(Arc::clone(&message)).is_ok());
+        Ok(())
+    }
+
+    /// Broadcasts the progress of a run or an optimization.
+    ///
+    /// Clients receive `{"done": done, "total": total}` as a `progress` event.
+    pub fn progress(&self, done: usize, total: usize) {
+        // the progress is informative, a failure should not stop the run
+        let _ = self.send("progress", &format!(r#"{{"done":{done},"total":{total}}}"#));
+    }
+
+    /// Returns an event sink broadcasting the backtest events to the clients.
+    pub fn sink(&self) -> SseSink {
+        SseSink(self.clone())
+    }
+}
+
+/// Event sink broadcasting the events as JSON through an `SseServer`.
+#[derive(Debug, Clone)]
+pub struct SseSink(SseServer);
+
+impl EventSink for SseSink {
+    fn record(&mut self, event: Event) -> Result<()> {
+        let data = serde_json::to_string(&event).map_err(|e| Error::EventSink(e.to_string()))?;
+        self.0.send("event", &data)
+    }
+}
+
+#[cfg(test)]
+fn connect(server: &SseServer) -> BufReader<TcpStream> {
+    let mut client = TcpStream::connect(server.local_addr()).unwrap();
+    client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut reader = BufReader::new(client);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "HTTP/1.1 200 OK\r\n");
+    while line != "\r\n" {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+    reader
+}
+
+#[cfg(test)]
+#[test]
+fn sse_server_broadcast() {
+    let server = SseServer::bind("127.0.0.1:0").unwrap();
+    let reader = connect(&server);
+
+    while server.clients() == 0 {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    server.progress(1, 2);
+    let event = Event::WalletUpdate {
+        datetime: chrono::DateTime::default(),
+        pnl: 0.0,
+        fees: 0.0,
+        free: 1000.0,
+        locked: 0.0,
+        balance: 1000.0,
+    };
+    server.sink().record(event).unwrap();
+
+    let mut lines = reader.lines().map(|line| line.unwrap());
+    assert_eq!(lines.next().unwrap(), "event: progress");
+    assert_eq!(lines.next().unwrap(), r#"data: {"done":1,"total":2}"#);
+    assert_eq!(lines.next().unwrap(), "");
+    assert_eq!(lines.next().unwrap(), "event: event");
+    let data = lines.next().unwrap();
+    assert_eq!(
+        serde_json::from_str::<Event>(data.trim_start_matches("data: ")).unwrap(),
+        event
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn sse_server_drop_stalled_client() {
+    let server = SseServer::bind_with_queue("127.0.0.1:0", 1).unwrap();
+    // a silent client must not block the handshake of the others
+    let _silent = TcpStream::connect(server.local_addr()).unwrap();
+    let mut reader = connect(&server);
+    while server.clients() == 0 {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // a client not reading its queue, and a disconnected one
+    let (stalled, _receiver) = std::sync::mpsc::sync_channel(1);
+    let (disconnected, _) = std::sync::mpsc::sync_channel(1);
+    server.clients.lock().unwrap().extend([stalled, disconnected]);
+    assert_eq!(server.clients(), 3);
+
+    server.progress(1, 2);
+    assert_eq!(server.clients(), 2);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "event: progress\n");
+
+    // the queue of the stalled client is full, the reading client has taken its message
+    server.progress(2, 2);
+    assert_eq!(server.clients(), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn sse_server_header_limit() {
+    let server = SseServer::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(server.local_addr()).unwrap();
+    let _ = client.write_all(&[b'x'; MAX_HEADER as usize + 1]);
+    let mut response = Vec::new();
+    let _ = client.read_to_end(&mut response);
+    assert!(response.is_empty());
+    assert_eq!(server.clients(), 0);
+}
@@ -0,0 +1,570 @@
+//! REST service running backtests on behalf of remote clients.
+//!
+//! Strategies are Rust code, so the service only runs the strategies registered in its
+//! `StrategyRegistry`, configured by JSON parameters. Clients submit a run configuration
+//! (strategy name, parameters, candles, balance and fees) and poll its results.
+//!
+//! | Endpoint                 | Description                                        |
+//! |--------------------------|----------------------------------------------------|
+//! | `POST /runs`             | Submits a `RunConfig`, returns `{"id": ...}`.      |
+//! | `GET /runs/{id}`         | Returns the status of the run.                     |
+//! | `GET /runs/{id}/metrics` | Returns the performance metrics of a finished run. |
+//! | `GET /runs/{id}/trades`  | Returns the trade list of a finished run.          |
+//! | `GET /runs/{id}/events`  | Returns the events of a finished run.              |
+//! | `DELETE /runs/{id}`      | Deletes a finished run.                            |
+//! | `GET /strategies`        | Returns the names of the registered strategies.    |
+//!
+//! The service doesn't run parameter optimizations: the combinations of the `Optimizer` are defined
+//! at compile time by `ParameterCombination`, so a client searching parameters submits one run per
+//! parameter set.
+//!
+//! The requests are handled by a fixed number of workers (see `Service::with_workers`), and only the
+//! latest finished runs are kept (see `Service::with_max_runs`).
+//!
+//! It needs to enable `service` feature to use it. The `bts-service` binary serves the built-in strategies.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::engine::{Backtest, Candle};
+use crate::errors::{Error, Result};
+use crate::metrics::Metrics;
+
+use serde_json::{Value, json};
+
+/// Maximum size of a request body, larger requests are answered with `413 Payload Too Large`.
+pub const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// Maximum size of the request line and headers of a request.
+const MAX_HEADER: u64 = 8 * 1024;
+
+/// Time allowed to a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Callback receiving the errors of the connections (e.g., a failed accept or a client timing out).
+pub type ErrorHandler = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// A strategy ready to run, called for each candle.
+pub type BoxedStrategy = Box<dyn FnMut(&mut Backtest, &Candle) -> Result<()> + Send>;
+
+/// Builds a strategy from its JSON parameters.
+pub type StrategyFactory = Box<dyn Fn(&Value) -> Result<BoxedStrategy> + Send + Sync>;
+
+/// Strategies that can be run by the service, by name.
+#[derive(Default)]
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
+
+impl StrategyRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a strategy factory under a name.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use bts_rs::prelude::*;
+    ///
+    /// let registry = StrategyRegistry::new().register("noop", |_params| {
+    ///     let strategy: BoxedStrategy = Box::new(|_bt, _candle| Ok(()));
+    ///     Ok(strategy)
+    /// });
+    /// assert_eq!(registry.names(), vec!["noop"]);
+    /// ```
+    pub fn register<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn(&Value) -> Result<BoxedStrategy> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    /// Returns the sorted names of the registered strategies.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self.factories.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Builds the strategy registered under `name`.
+    pub fn build(&self, name: &str, params: &Value) -> Result<BoxedStrategy> {
+        let factory = self
+            .factories
+            .get(name)
+            .ok_or_else(|| Error::Msg(format!("Unknown strategy: {name}")))?;
+        factory(params)
+    }
+}
+
+/// Configuration of a submitted run.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RunConfig {
+    /// Name of the registered strategy.
+    pub strategy: String,
+    /// Parameters given to the strategy factory.
+    #[serde(default)]
+    pub params: Value,
+    /// Initial balance of the wallet.
+    pub initial_balance: f64,
+    /// Market fees, as `(market fee, limit fee)` percentages.
+    #[serde(default)]
+    pub market_fees: Option<(f64, f64)>,
+    /// Candles to run the strategy on.
+    pub candles: Vec<Candle>,
+}
+
+/// State of a submitted run.
+#[derive(Clone)]
+pub enum RunStatus {
+    /// The run is executing.
+    Running,
+    /// The run has finished.
+    Done(Box<Metrics>),
+    /// The run has failed.
+    Failed(String),
+}
+
+impl RunStatus {
+    fn to_json(&self) -> Value {
+        match self {
+            Self::Running => json!({ "status": "running" }),
+            Self::Done(_) => json!({ "status": "done" }),
+            Self::Failed(error) => json!({ "status": "failed", "error": error }),
+        }
+    }
+}
+
+/// REST service running the backtests in background threads.
+#[derive(Clone)]
+pub struct Service {
+    registry: Arc<StrategyRegistry>,
+    runs: Arc<Mutex<BTreeMap<u64, RunStatus>>>,
+    next_id: Arc<Mutex<u64>>,
+    max_runs: usize,
+    workers: usize,
+    error_handler: Option<ErrorHandler>,
+}
+
+impl Service {
+    /// Creates a new service over a strategy registry, keeping 100 runs and handling the requests
+    /// with 4 workers.
+    pub fn new(registry: StrategyRegistry) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            runs: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(Mutex::new(0)),
+            max_runs: 100,
+            workers: 4,
+            error_handler: None,
+        }
+    }
+
+    /// Sets the maximum number of runs kept: the oldest finished runs are deleted to make room for the
+    /// submitted ones, and a run is refused if all the kept runs are still running.
+    pub fn with_max_runs(mut self, max_runs: usize) -> Self {
+        self.max_runs = max_runs.max(1);
+        self
+    }
+
+    /// Sets the number of requests handled at once, the other clients wait to be accepted.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Sets a callback receiving the errors of the connections, which are ignored by default.
+    pub fn with_error_handler<F>(mut self, error_handler: F) -> Self
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(error_handler));
+        self
+    }
+
+    /// Submits a run, executed in a background thread.
+    ///
+    /// ### Returns
+    /// The identifier of the run, or an error if the strategy or the backtest can't be built.
+    pub fn submit(&self, config: RunConfig) -> Result<u64> {
+        let mut strategy = self.registry.build(&config.strategy, &config.params)?;
+        let mut backtest = Backtest::new(Arc::from(config.candles), config.initial_balance, config.market_fees)?;
+
+        let id = {
+            let mut next_id = self.next_id.lock().map_err(|e| Error::Msg(e.to_string()))?;
+            *next_id += 1;
+            *next_id
+        };
+        self.insert_run(id)?;
+
+        let service = self.clone();
+        std::thread::spawn(move || {
+            let status = match backtest.run(|bt, candle| strategy(bt, candle)) {
+                Ok(()) => RunStatus::Done(Box::new(Metrics::from(&backtest))),
+                Err(e) => RunStatus::Failed(e.to_string()),
+            };
+            let _ = service.set_status(id, status);
+        });
+
+        Ok(id)
+    }
+
+    /// Returns the status of a run.
+    pub fn status(&self, id: u64) -> Option<RunStatus> {
+        self.runs.lock().ok()?.get(&id).cloned()
+    }
+
+    /// Deletes a finished run.
+    ///
+    /// ### Returns
+    /// The status of the deleted run, `None` if it isn't found or is still running.
+    pub fn delete(&self, id: u64) -> Option<RunStatus> {
+        let mut runs = self.runs.lock().ok()?;
+        if matches!(runs.get(&id)?, RunStatus::Running) {
+            return None;
+        }
+        runs.remove(&id)
+    }
+
+    /// Adds a running run, deleting the oldest finished runs beyond the maximum number of runs.
+    fn insert_run(&self, id: u64) -> Result<()> {
+        let mut runs = self.runs.lock().map_err(|e| Error::Msg(e.to_string()))?;
+        while runs.len() >= self.max_runs {
+            let Some(oldest) = runs
+                .iter()
+                .find_map(|(id, status)| (!matches!(status, RunStatus::Running)).then_some(*id))
+            else {
+                return Err(Error::Msg(format!("The {} kept runs are still running", self.max_runs)));
+            };
+            runs.remove(&oldest);
+        }
+        runs.insert(id, RunStatus::Running);
+        Ok(())
+    }
+
+    fn set_status(&self, id: u64, status: RunStatus) -> Result<()> {
+        let mut runs = self.runs.lock().map_err(|e| Error::Msg(e.to_string()))?;
+        runs.insert(id, status);
+        Ok(())
+    }
+
+    /// Serves the REST endpoints, blocking the current thread.
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> Result<()> {
+        self.serve_on(TcpListener::bind(addr)?)
+    }
+
+    /// Serves the REST endpoints in a background thread.
+    ///
+    /// ### Returns
+    /// The address the service listens on.
+    pub fn spawn<A: ToSocketAddrs>(&self, addr: A) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let service = self.clone();
+        std::thread::spawn(move || service.serve_on(listener));
+        Ok(addr)
+    }
+
+    fn serve_on(&self, listener: TcpListener) -> Result<()> {
+        //? the accepted connections wait for a free worker, so the memory of the requests is bounded
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<TcpStream>(0);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..self.workers {
+            let (service, receiver) = (self.clone(), Arc::clone(&receiver));
+            std::thread::spawn(move || {
+                loop {
+                    let stream = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(stream) = stream else {
+                        return;
+                    };
+                    if let Err(e) = service.handle(stream) {
+                        service.report(&e);
+                    }
+                }
+            });
+        }
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => sender.send(stream).map_err(|e| Error::Msg(e.to_string()))?,
+                //? a failed accept (e.g., too many open files) only drops that connection
+                Err(e) => self.report(&e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn report(&self, error: &Error) {
+        if let Some(error_handler) = &self.error_handler {
+            error_handler(error);
+        }
+    }
+
+    /// Reads one HTTP request and writes its response.
+    fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new((&stream).take(MAX_HEADER));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut content_length = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().unwrap_or_default();
+            }
+            line.clear();
+        }
+        let (code, response) = if content_length > MAX_BODY {
+            (413, json!({ "error": format!("The body exceeds {MAX_BODY} bytes") }))
+        } else {
+            reader.get_mut().set_limit(content_length as u64);
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default();
+            let path = parts.next().unwrap_or_default();
+            self.route(method, path, &body)
+        };
+
+        let body = response.to_string();
+        let reason = match code {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            404 => "Not Found",
+            413 => "Payload Too Large",
+            _ => "Conflict",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {code} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        Ok(stream.flush()?)
+    }
+
+    /// Returns the status code and the JSON response of a request.
+    fn route(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        match (method, segments.as_slice()) {
+            ("GET", ["strategies"]) => (200, json!(self.registry.names())),
+            ("POST", ["runs"]) => {
+                match serde_json::from_slice::<RunConfig>(body)
+                    .map_err(|e| Error::Msg(e.to_string()))
+                    .and_then(|config| self.submit(config))
+                {
+                    Ok(id) => (202, json!({ "id": id })),
+                    Err(e) => (400, json!({ "error": e.to_string() })),
+                }
+            }
+            ("DELETE", ["runs", id]) => {
+                let Ok(id) = id.parse() else {
+                    return (404, json!({ "error": "Run not found" }));
+                };
+                match (self.delete(id), self.status(id)) {
+                    (Some(_), _) => (200, json!({ "id": id })),
+                    (None, Some(status)) => (409, status.to_json()),
+                    (None, None) => (404, json!({ "error": "Run not found" })),
+                }
+            }
+            ("GET", ["runs", id, rest @ ..]) => {
+                let Some(status) = id.parse().ok().and_then(|id| self.status(id)) else {
+                    return (404, json!({ "error": "Run not found" }));
+                };
+                match (rest, status) {
+                    ([], status) => (200, status.to_json()),
+                    ([resource], RunStatus::Done(metrics)) => match *resource {
+                        "metrics" => (200, Self::metrics_json(&metrics)),
+                        "trades" => (200, json!(metrics.trades())),
+                        "events" => (200, json!(metrics.events().collect::<Vec<_>>())),
+                        _ => (404, json!({ "error": "Not found" })),
+                    },
+                    (_, status) => (409, status.to_json()),
+                }
+            }
+            _ => (404, json!({ "error": "Not found" })),
+        }
+    }
+
+    fn metrics_json(metrics: &Metrics) -> Value {
+        let trades = metrics.trades();
+        json!({
+            "initial_balance": metrics.initial_balance(),
+            "balance": metrics.balance(),
+            "pnl": metrics.pnl(),
+            "fees": metrics.fees(),
+            "max_drawdown": metrics.max_drawdown(),
+            "profit_factor": metrics.profit_factor(),
+            "sharpe_ratio": metrics.sharpe_ratio(0.0),
+            "win_rate": metrics.win_rate(),
+            "trades": trades.len(),
+            "expectancy": trades.expectancy(),
+            "system_quality_number": trades.system_quality_number(),
+            "kelly_fraction": trades.kelly_fraction(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn service_run() {
+    use crate::engine::{CandleBuilder, OrderSide, OrderType};
+    use chrono::{DateTime, Duration};
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let code = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (code, serde_json::from_str(body).unwrap())
+    }
+
+    let registry = StrategyRegistry::new().register("buy", |params| {
+        let quantity = params["quantity"].as_f64().ok_or(Error::MissingField("quantity"))?;
+        let strategy: BoxedStrategy = Box::new(move |bt, candle| {
+            if bt.positions().count() == 0 && bt.orders().count() == 0 {
+                let order = (OrderType::Market(candle.close()), quantity, OrderSide::Buy);
+                bt.place_order(candle, order.into())?;
+            }
+            Ok(())
+        });
+        Ok(strategy)
+    });
+    let addr = Service::new(registry).spawn("127.0.0.1:0").unwrap();
+
+    let candles = (0..3)
+        .map(|i| {
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(100.0)
+                .volume(1.0)
+                .open_time(DateTime::default() + Duration::days(i))
+                .close_time(DateTime::default() + Duration::days(i + 1))
+                .build()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let config = json!({
+        "strategy": "buy",
+        "params": { "quantity": 1.0 },
+        "initial_balance": 1000.0,
+        "candles": candles,
+    });
+
+    assert_eq!(request(addr, "GET", "/strategies", ""), (200, json!(["buy"])));
+    let (code, _) = request(addr, "POST", "/runs", r#"{"strategy":"sell"}"#);
+    assert_eq!(code, 400);
+
+    let (code, response) = request(addr, "POST", "/runs", &config.to_string());
+    assert_eq!(code, 202);
+    let id = response["id"].as_u64().unwrap();
+
+    let path = format!("/runs/{id}");
+    while request(addr, "GET", &path, "").1["status"] == "running" {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(request(addr, "GET", &path, ""), (200, json!({ "status": "done" })));
+
+    let (code, metrics) = request(addr, "GET", &format!("{path}/metrics"), "");
+    assert_eq!(code, 200);
+    assert_eq!(metrics["initial_balance"], 1000.0);
+    let (code, events) = request(addr, "GET", &format!("{path}/events"), "");
+    assert_eq!(code, 200);
+    assert!(!events.as_array().unwrap().is_empty());
+    assert_eq!(request(addr, "GET", "/runs/42", "").0, 404);
+    assert_eq!(request(addr, "DELETE", &path, ""), (200, json!({ "id": id })));
+    assert_eq!(request(addr, "GET", &path, "").0, 404);
+    assert_eq!(request(addr, "DELETE", &path, "").0, 404);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "POST /runs HTTP/1.1\r\nContent-Length: {}\r\n\r\n", usize::MAX).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+}
+
+#[cfg(test)]
+#[test]
+fn service_max_runs() {
+    use crate::engine::CandleBuilder;
+    use chrono::{DateTime, Duration};
+
+    let (sender, receiver) = std::sync::mpsc::channel::<()>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    let registry = StrategyRegistry::new()
+        .register("noop", |_| {
+            let strategy: BoxedStrategy = Box::new(|_, _| Ok(()));
+            Ok(strategy)
+        })
+        .register("wait", move |_| {
+            let receiver = Arc::clone(&receiver);
+            let strategy: BoxedStrategy = Box::new(move |_, _| {
+                let _ = receiver.lock().unwrap().recv();
+                Ok(())
+            });
+            Ok(strategy)
+        });
+    let service = Service::new(registry).with_max_runs(2);
+
+    let candle = CandleBuilder::builder()
+        .open(100.0)
+        .high(110.0)
+        .low(90.0)
+        .close(100.0)
+        .volume(1.0)
+        .open_time(DateTime::default())
+        .close_time(DateTime::default() + Duration::days(1))
+        .build()
+        .unwrap();
+    let config = |strategy: &str| RunConfig {
+        strategy: strategy.to_string(),
+        params: Value::Null,
+        initial_balance: 1000.0,
+        market_fees: None,
+        candles: vec![candle],
+    };
+    let wait = |id| {
+        while matches!(service.status(id), Some(RunStatus::Running)) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    };
+
+    let first = service.submit(config("noop")).unwrap();
+    wait(first);
+    let running = service.submit(config("wait")).unwrap();
+    assert!(service.delete(running).is_none());
+    // the finished run makes room for the new one
+    let second = service.submit(config("wait")).unwrap();
+    assert!(service.status(first).is_none());
+    // both kept runs are running
+    assert!(service.submit(config("noop")).is_err());
+
+    sender.send(()).unwrap();
+    sender.send(()).unwrap();
+    wait(running);
+    wait(second);
+    assert!(service.delete(running).is_some());
+    assert!(service.status(running).is_none());
+}
@@ -0,0 +1,241 @@
+//! Persistence sinks for backtest events.
+//!
+//! By default, a `Backtest` keeps its events in memory. An `EventSink` configured with
+//! `Backtest::with_event_sink` receives the events instead, so that:
+//! - Huge runs can stream events to disk rather than RAM.
+//! - Live or paper runs can forward events to external dashboards in real time.
+//!
+//! Available sinks:
+//! - `Vec<Event>`: In-memory sink.
+//! - `Sender<Event>`: Forwards the events to a channel.
+//! - `JsonlSink`: Writes one JSON event per line (needs the `serde` feature).
+//! - `SqliteSink`: Inserts the events into a SQLite table (needs the `sqlite` feature).
+//!
+//! A sink shared behind `Arc<Mutex<_>>` is also a sink, which allows reading it back after the run.
+
+use std::sync::{Arc, Mutex, mpsc::Sender};
+
+use crate::errors::{Error, Result};
+use crate::metrics::Event;
+
+/// Destination of the events generated during a backtest.
+pub trait EventSink: Send {
+    /// Records an event.
+    fn record(&mut self, event: Event) -> Result<()>;
+
+    /// Flushes the buffered events, called at the end of a run.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl EventSink for Vec<Event> {
+    fn record(&mut self, event: Event) -> Result<()> {
+        self.push(event);
+        Ok(())
+    }
+}
+
+impl EventSink for Sender<Event> {
+    fn record(&mut self, event: Event) -> Result<()> {
+        self.send(event).map_err(|e| Error::EventSink(e.to_string()))
+    }
+}
+
+impl<S: EventSink + ?Sized> EventSink for Box<S> {
+    fn record(&mut self, event: Event) -> Result<()> {
+        (**self).record(event)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<S: EventSink + ?Sized> EventSink for Arc<Mutex<S>> {
+    fn record(&mut self, event: Event) -> Result<()> {
+        self.lock().map_err(|e| Error::EventSink(e.to_string()))?.record(event)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.lock().map_err(|e| Error::EventSink(e.to_string()))?.flush()
+    }
+}
+
+/// Sink writing the events as JSON lines.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+///
+/// let file = std::fs::File::create(std::env::temp_dir().join("events.jsonl")).unwrap();
+/// let sink = JsonlSink::new(std::io::BufWriter::new(file));
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct JsonlSink<W> {
+    writer: W,
+}
+
+#[cfg(feature = "serde")]
+impl<W: std::io::Write> JsonlSink<W> {
+    /// Creates a new JSON lines sink over a writer.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W: std::io::Write + Send> EventSink for JsonlSink<W> {
+    fn record(&mut self, event: Event) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, &event).map_err(|e| Error::EventSink(e.to_string()))?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Sink inserting the events into the `events` table of a SQLite database.
+///
+/// The table is created if needed, with the columns `datetime` (RFC 3339), `kind` and `payload` (JSON).
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteSink {
+    connection: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteSink {
+    /// Opens (or creates) the database at `path`.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let connection = rusqlite::Connection::open(path).map_err(|e| Error::EventSink(e.to_string()))?;
+        Self::new(connection)
+    }
+
+    /// Creates a new SQLite sink over a connection.
+    pub fn new(connection: rusqlite::Connection) -> Result<Self> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    datetime TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    payload TEXT NOT NULL
+                )",
+            )
+            .map_err(|e| Error::EventSink(e.to_string()))?;
+        Ok(Self { connection })
+    }
+
+    /// Returns the underlying connection.
+    pub fn connection(&self) -> &rusqlite::Connection {
+        &self.connection
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl EventSink for SqliteSink {
+    fn record(&mut self, event: Event) -> Result<()> {
+        let kind = match event {
+            Event::AddOrder(..) => "AddOrder",
+            Event::DelOrder(..) => "DelOrder",
+            Event::AmendOrder(..) => "AmendOrder",
+            Event::OrderExpired(..) => "OrderExpired",
+            Event::AddPosition(..) => "AddPosition",
+            Event::DelPosition(..) => "DelPosition",
+            Event::ModifyPosition(..) => "ModifyPosition",
+            Event::IncreasePosition(..) => "IncreasePosition",
+            Event::RiskRejected(..) => "RiskRejected",
+            Event::OrderRejected(..) => "OrderRejected",
+            Event::WalletUpdate { .. } => "WalletUpdate",
+        };
+        let payload = serde_json::to_string(&event).map_err(|e| Error::EventSink(e.to_string()))?;
+        self.connection
+            .execute(
+                "INSERT INTO events (datetime, kind, payload) VALUES (?1, ?2, ?3)",
+                (event.datetime().to_rfc3339(), kind, payload),
+            )
+            .map_err(|e| Error::EventSink(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn sinks_record() {
+    let event = Event::WalletUpdate {
+        datetime: chrono::DateTime::default(),
+        pnl: 0.0,
+        fees: 0.0,
+        free: 1000.0,
+        locked: 0.0,
+        balance: 1000.0,
+    };
+
+    let shared = Arc::new(Mutex::new(Vec::new()));
+    let mut sink = Arc::clone(&shared);
+    sink.record(event).unwrap();
+    assert_eq!(shared.lock().unwrap().as_slice(), &[event]);
+
+    let (mut sender, receiver) = std::sync::mpsc::channel();
+    sender.record(event).unwrap();
+    assert_eq!(receiver.recv().unwrap(), event);
+    drop(receiver);
+    assert!(sender.record(event).is_err());
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn jsonl_sink() {
+    let event = Event::WalletUpdate {
+        datetime: chrono::DateTime::default(),
+        pnl: 0.0,
+        fees: 0.0,
+        free: 1000.0,
+        locked: 0.0,
+        balance: 1000.0,
+    };
+
+    let mut sink = JsonlSink::new(Vec::new());
+    sink.record(event).unwrap();
+    sink.record(event).unwrap();
+    sink.flush().unwrap();
+
+    let output = String::from_utf8(sink.into_inner()).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(serde_json::from_str::<Event>(lines[0]).unwrap(), event);
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+#[test]
+fn sqlite_sink() {
+    let event = Event::WalletUpdate {
+        datetime: chrono::DateTime::default(),
+        pnl: 0.0,
+        fees: 0.0,
+        free: 1000.0,
+        locked: 0.0,
+        balance: 1000.0,
+    };
+
+    let mut sink = SqliteSink::new(rusqlite::Connection::open_in_memory().unwrap()).unwrap();
+    sink.record(event).unwrap();
+
+    let (kind, payload): (String, String) = sink
+        .connection()
+        .query_row("SELECT kind, payload FROM events", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(kind, "WalletUpdate");
+    assert_eq!(serde_json::from_str::<Event>(&payload).unwrap(), event);
+}
@@ -0,0 +1,395 @@
+//! Time-series cross-validation of candle data.
+//!
+//! A [`Split`] cuts a series of candles into folds of in-sample (train) and out-of-sample (test)
+//! candles without shuffling them, so the test candles are never used to tune the strategy. Run a
+//! strategy on each fold with [`validate`], or optimize it on the train candles and score the best
+//! parameters on the test candles with `Optimizer::cross_validate`.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::engine::{Backtest, Candle};
+use crate::errors::{Error, Result};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+
+use chrono::{DateTime, Utc};
+
+/// Time-series-aware splitter of candle data.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+/// use bts_rs::split::Split;
+/// use chrono::{DateTime, Duration};
+///
+/// let candles = (0..10)
+///     .map(|i| {
+///         CandleBuilder::builder()
+///             .open(100.0)
+///             .high(110.0)
+///             .low(95.0)
+///             .close(105.0)
+///             .volume(1.0)
+///             .open_time(DateTime::default() + Duration::days(i))
+///             .close_time(DateTime::default() + Duration::days(i + 1))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Vec<_>>();
+///
+/// let folds = Split::Expanding(4).folds(&candles).unwrap();
+/// assert_eq!(folds.len(), 4);
+/// assert_eq!(folds[0].train(), &[0..2]);
+/// assert_eq!(folds[0].test(), &(2..4));
+/// assert_eq!(folds[3].train(), &[0..8]);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Split {
+    /// Trains on the candles opened before a date and tests on the following ones.
+    ///
+    /// ### Arguments
+    /// * `0` - The open time of the first test candle.
+    Date(DateTime<Utc>),
+
+    /// Expanding-window folds: the candles are cut into `folds + 1` blocks, and each fold trains on
+    /// all the blocks before its test block.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of folds.
+    Expanding(usize),
+
+    /// Purged k-fold: the candles are cut into `folds` blocks, and each fold tests on one block and
+    /// trains on the others, except the candles right before and after the test block.
+    PurgedKFold {
+        /// The number of folds.
+        folds: usize,
+        /// The number of train candles removed before the test block, whose outcome overlaps it
+        /// (e.g., the holding period of the trades).
+        purge: usize,
+        /// The number of train candles removed after the test block, still correlated with it.
+        embargo: usize,
+    },
+}
+
+impl Split {
+    /// Returns the folds of a series of candles.
+    ///
+    /// ### Returns
+    /// The folds in the order of their test candles, or an error if there are not enough candles or
+    /// a fold would have no train or test candle.
+    pub fn folds(&self, data: &[Candle]) -> Result<Vec<Fold>> {
+        let len = data.len();
+        let folds = match *self {
+            Self::Date(date) => {
+                let index = data.partition_point(|c| c.open_time() < date);
+                vec![Fold::new(std::iter::once(0..index), index..len)]
+            }
+            Self::Expanding(folds) => {
+                let bounds = bounds(len, folds + 1);
+                (1..=folds)
+                    .map(|i| Fold::new(std::iter::once(0..bounds[i]), bounds[i]..bounds[i + 1]))
+                    .collect()
+            }
+            Self::PurgedKFold { folds, purge, embargo } => {
+                if folds < 2 {
+                    return Err(Error::Msg(format!(
+                        "Purged k-fold needs at least 2 folds (got: {folds})"
+                    )));
+                }
+                let bounds = bounds(len, folds);
+                (0..folds)
+                    .map(|i| {
+                        let (start, end) = (bounds[i], bounds[i + 1]);
+                        let train = [0..start.saturating_sub(purge), (end + embargo).min(len)..len];
+                        Fold::new(train, start..end)
+                    })
+                    .collect()
+            }
+        };
+
+        if folds.is_empty() || folds.iter().any(|fold| fold.train.is_empty() || fold.test.is_empty()) {
+            return Err(Error::Msg(format!("Not enough candles to split: {len} available")));
+        }
+        Ok(folds)
+    }
+}
+
+/// Returns the `blocks + 1` bounds cutting `len` items into blocks of (almost) equal sizes.
+fn bounds(len: usize, blocks: usize) -> Vec<usize> {
+    (0..=blocks).map(|i| i * len / blocks.max(1)).collect()
+}
+
+/// In-sample and out-of-sample candles of a split, as index ranges of the data.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fold {
+    train: Vec<Range<usize>>,
+    test: Range<usize>,
+}
+
+impl Fold {
+    /// Creates a fold, dropping the empty train ranges.
+    fn new(train: impl IntoIterator<Item = Range<usize>>, test: Range<usize>) -> Self {
+        let train = train.into_iter().filter(|range| !range.is_empty()).collect();
+        Self { train, test }
+    }
+
+    /// Returns the ranges of the train candles, in chronological order.
+    pub fn train(&self) -> &[Range<usize>] {
+        &self.train
+    }
+
+    /// Returns the range of the test candles.
+    pub fn test(&self) -> &Range<usize> {
+        &self.test
+    }
+
+    /// Returns the train candles of `data`.
+    ///
+    /// The ranges around the test candles of a purged k-fold are concatenated, so a backtest runs
+    /// over the gap as if the candles were consecutive.
+    pub fn train_data(&self, data: &[Candle]) -> Arc<[Candle]> {
+        self.train
+            .iter()
+            .flat_map(|range| data[range.clone()].iter().cloned())
+            .collect()
+    }
+
+    /// Returns the test candles of `data`.
+    pub fn test_data(&self, data: &[Candle]) -> Arc<[Candle]> {
+        Arc::from(&data[self.test.clone()])
+    }
+}
+
+/// In-sample and out-of-sample backtests of a fold.
+#[derive(Clone)]
+pub struct Validation {
+    fold: Fold,
+    in_sample: Backtest,
+    out_of_sample: Backtest,
+}
+
+impl Validation {
+    /// Returns the fold.
+    pub fn fold(&self) -> &Fold {
+        &self.fold
+    }
+
+    /// Returns the backtest run on the train candles.
+    pub fn in_sample(&self) -> &Backtest {
+        &self.in_sample
+    }
+
+    /// Returns the backtest run on the test candles.
+    pub fn out_of_sample(&self) -> &Backtest {
+        &self.out_of_sample
+    }
+
+    /// Returns the metrics of the in-sample and out-of-sample backtests.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> (Metrics, Metrics) {
+        (Metrics::from(&self.in_sample), Metrics::from(&self.out_of_sample))
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl std::fmt::Display for Validation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (in_sample, out_of_sample) = self.metrics();
+        let candles = |range: &Range<usize>| range.len();
+        writeln!(
+            f,
+            "=== Fold: {} train candles, {} test candles ===",
+            self.fold.train.iter().map(candles).sum::<usize>(),
+            candles(&self.fold.test)
+        )?;
+        writeln!(f, "{:<24}{:>14}{:>14}", "", "In-sample", "Out-of-sample")?;
+        let return_pct = |m: &Metrics| m.pnl() / m.initial_balance() * 100.0;
+        let rows = [
+            ("Return (%)", return_pct(&in_sample), return_pct(&out_of_sample)),
+            (
+                "Max Drawdown (%)",
+                in_sample.max_drawdown(),
+                out_of_sample.max_drawdown(),
+            ),
+            (
+                "Profit Factor",
+                in_sample.profit_factor(),
+                out_of_sample.profit_factor(),
+            ),
+            ("Win Rate (%)", in_sample.win_rate(), out_of_sample.win_rate()),
+            (
+                "Annualized Sharpe",
+                in_sample.annualized_sharpe_ratio(0.0),
+                out_of_sample.annualized_sharpe_ratio(0.0),
+            ),
+        ];
+        for (name, in_sample, out_of_sample) in rows {
+            writeln!(f, "{name:<24}{in_sample:>14.2}{out_of_sample:>14.2}")?;
+        }
+        writeln!(
+            f,
+            "{:<24}{:>14}{:>14}",
+            "Trades",
+            in_sample.trades().len(),
+            out_of_sample.trades().len()
+        )
+    }
+}
+
+/// Runs a strategy on the in-sample and out-of-sample candles of each fold of a split.
+///
+/// ### Arguments
+/// * `split` - The splitter of the candles.
+/// * `data` - The candles.
+/// * `backtest` - Creates the backtest of a set of candles, with its configuration (balance, fees...).
+/// * `strategy` - The strategy, cloned for each backtest.
+///
+/// ### Returns
+/// The backtests of each fold, or an error if the split or a backtest fails.
+///
+/// ### Example
+/// ```rust
+/// use bts_rs::prelude::*;
+/// use bts_rs::split::{Split, validate};
+/// use chrono::{DateTime, Duration};
+///
+/// let candles = (0..10)
+///     .map(|i| {
+///         CandleBuilder::builder()
+///             .open(100.0 + i as f64)
+///             .high(110.0 + i as f64)
+///             .low(95.0 + i as f64)
+///             .close(105.0 + i as f64)
+///             .volume(1.0)
+///             .open_time(DateTime::default() + Duration::days(i))
+///             .close_time(DateTime::default() + Duration::days(i + 1))
+///             .build()
+///             .unwrap()
+///     })
+///     .collect::<Vec<_>>();
+///
+/// let split = Split::Date(DateTime::default() + Duration::days(7));
+/// let validations = validate(
+///     &split,
+///     &candles,
+///     |candles| Backtest::new(candles, 1000.0, None),
+///     |bts, candle| {
+///         if bts.positions().count() == 0 {
+///             bts.place_order(candle, (OrderType::Market(candle.close()), 1.0, OrderSide::Buy).into())?;
+///         }
+///         Ok(())
+///     },
+/// )
+/// .unwrap();
+/// assert_eq!(validations[0].in_sample().candles().count(), 7);
+/// assert_eq!(validations[0].out_of_sample().candles().count(), 3);
+/// ```
+pub fn validate<B, S>(split: &Split, data: &[Candle], backtest: B, strategy: S) -> Result<Vec<Validation>>
+where
+    B: Fn(Arc<[Candle]>) -> Result<Backtest>,
+    S: FnMut(&mut Backtest, &Candle) -> Result<()> + Clone,
+{
+    split
+        .folds(data)?
+        .into_iter()
+        .map(|fold| {
+            let mut in_sample = backtest(fold.train_data(data))?;
+            in_sample.run(strategy.clone())?;
+            let mut out_of_sample = backtest(fold.test_data(data))?;
+            out_of_sample.run(strategy.clone())?;
+            Ok(Validation {
+                fold,
+                in_sample,
+                out_of_sample,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn get_data(len: i64) -> Vec<Candle> {
+    use crate::engine::CandleBuilder;
+    use chrono::Duration;
+
+    (0..len)
+        .map(|i| {
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(95.0)
+                .close(105.0)
+                .volume(1.0)
+                .open_time(DateTime::default() + Duration::days(i))
+                .close_time(DateTime::default() + Duration::days(i + 1))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+#[allow(clippy::single_range_in_vec_init)]
+fn split_folds() {
+    use chrono::Duration;
+
+    let data = get_data(10);
+
+    let folds = Split::Date(DateTime::default() + Duration::days(6))
+        .folds(&data)
+        .unwrap();
+    assert_eq!(folds, vec![Fold::new([0..6], 6..10)]);
+    assert!(Split::Date(DateTime::default()).folds(&data).is_err());
+
+    let folds = Split::Expanding(2).folds(&data).unwrap();
+    assert_eq!(folds[0], Fold::new([0..3], 3..6));
+    assert_eq!(folds[1], Fold::new([0..6], 6..10));
+    assert!(Split::Expanding(0).folds(&data).is_err());
+    assert!(Split::Expanding(20).folds(&data).is_err());
+
+    let split = Split::PurgedKFold {
+        folds: 5,
+        purge: 1,
+        embargo: 1,
+    };
+    let folds = split.folds(&data).unwrap();
+    assert_eq!(folds.len(), 5);
+    assert_eq!(folds[0], Fold::new([3..10], 0..2));
+    assert_eq!(folds[2], Fold::new([0..3, 7..10], 4..6));
+    assert_eq!(folds[4], Fold::new([0..7], 8..10));
+    assert_eq!(folds[2].train_data(&data).len(), 6);
+    assert_eq!(folds[2].test_data(&data)[0].open_time(), data[4].open_time());
+}
+
+#[cfg(all(test, feature = "metrics"))]
+#[test]
+fn split_validate() {
+    use crate::engine::{OrderSide, OrderType};
+
+    let data = get_data(10);
+    let validations = validate(
+        &Split::Expanding(2),
+        &data,
+        |candles| Backtest::new(candles, 1000.0, None),
+        |bt, candle| {
+            if bt.positions().count() == 0 && bt.orders().count() == 0 {
+                let exit = OrderType::TakeProfitAndStopLoss(110.0, 0.0);
+                bt.place_order(candle, (OrderType::Market(100.0), exit, 1.0, OrderSide::Buy).into())?;
+            }
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(validations.len(), 2);
+    assert_eq!(validations[1].in_sample().candles().count(), 6);
+    let (in_sample, out_of_sample) = validations[1].metrics();
+    assert_eq!(in_sample.trades().len(), 6);
+    assert_eq!(out_of_sample.trades().len(), 4);
+
+    let table = validations[1].to_string();
+    assert!(table.contains("=== Fold: 6 train candles, 4 test candles ==="));
+    assert!(table.contains("Trades"));
+}
@@ -1,6 +1,96 @@
 //! Utility functions and helpers.
 
-/// Generates a random ID.
-pub(crate) fn random_id() -> u32 {
-    rand::random()
+use std::cell::Cell;
+
+thread_local! {
+    /// Next sequential ID, `None` when the IDs are random.
+    static NEXT_ID: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Generates a new ID, random unless an `IdSequence` is active on this thread.
+pub(crate) fn new_id() -> u32 {
+    NEXT_ID.with(|next| match next.get() {
+        Some(id) => {
+            next.set(Some(id.wrapping_add(1)));
+            id
+        }
+        None => rand::random(),
+    })
+}
+
+/// Guard making `new_id` return sequential IDs (starting at 1) on this thread until it is dropped.
+pub(crate) struct IdSequence(Option<u32>);
+
+impl IdSequence {
+    /// Starts a new sequence.
+    pub(crate) fn start() -> Self {
+        Self(NEXT_ID.with(|next| next.replace(Some(1))))
+    }
+}
+
+impl Drop for IdSequence {
+    fn drop(&mut self) {
+        NEXT_ID.with(|next| next.set(self.0));
+    }
+}
+
+/// Rounds a value to a multiple of `step` with the `round` function (0.0 or less to keep the value).
+pub(crate) fn round_to(value: f64, step: f64, round: fn(f64) -> f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    // rounds the step count first to absorb the representation errors of the step
+    let steps = round((value / step * 1e9).round() / 1e9);
+    // the decimals of the step (e.g., 2 for 0.25)
+    let decimals = (0..12)
+        .find(|d| {
+            let scaled = step * 10f64.powi(*d);
+            (scaled - scaled.round()).abs() < 1e-9 * scaled.max(1.0)
+        })
+        .unwrap_or(12);
+    let factor = 10f64.powi(decimals);
+    (steps * step * factor).round() / factor
+}
+
+/// Offset basis of the 64-bit FNV-1a hash.
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Hashes bytes with the 64-bit FNV-1a function, which is stable across platforms and releases.
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Returns a uniform sample in `[0, 1)` derived from the seed (SplitMix64), so the draws are reproducible.
+pub(crate) fn unit_sample(seed: u64) -> f64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+#[test]
+fn id_sequence() {
+    {
+        let _ids = IdSequence::start();
+        assert_eq!(new_id(), 1);
+        assert_eq!(new_id(), 2);
+        {
+            let _ids = IdSequence::start();
+            assert_eq!(new_id(), 1);
+        }
+        assert_eq!(new_id(), 3);
+    }
+    assert_eq!(NEXT_ID.with(Cell::get), None);
+}
+
+#[cfg(test)]
+#[test]
+fn unit_sample_is_reproducible() {
+    assert_eq!(unit_sample(42), unit_sample(42));
+    assert_ne!(unit_sample(42), unit_sample(43));
+    assert!((0..1000).map(unit_sample).all(|sample| (0.0..1.0).contains(&sample)));
 }
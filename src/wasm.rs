@@ -0,0 +1,328 @@
+//! JavaScript bindings for the browser.
+//!
+//! This module exposes the engine to JavaScript with [`wasm-bindgen`](https://rustwasm.github.io/wasm-bindgen/),
+//! so a web UI can run backtests client-side:
+//! - `new Backtest(candles, initialBalance, takerFee, makerFee)` takes the candles as plain objects
+//!   (`{open, high, low, close, volume, bid, open_time, close_time}`, with RFC 3339 dates).
+//! - `backtest.run(strategy)` calls `strategy(ctx, candle)` on each candle. The strategy reads the
+//!   wallet and the open positions from the `Context` and places or closes orders with it; the
+//!   actions are applied once the strategy returns.
+//! - `backtest.metrics()` and `backtest.events()` return plain objects.
+//!
+//! Build it for the `wasm32-unknown-unknown` target with [`wasm-pack`](https://rustwasm.github.io/wasm-pack/);
+//! the `getrandom_backend` flag is set in `.cargo/config.toml`:
+//! ```bash
+//! wasm-pack build --target web --features wasm
+//! ```
+//!
+//! ```javascript
+//! import init, { Backtest } from "./pkg/bts_rs.js";
+//!
+//! await init();
+//! const bt = new Backtest(candles, 1000.0, 0.1, 0.05);
+//! bt.run((ctx, candle) => {
+//!   if (ctx.positions().length === 0 && ctx.orders().length === 0) {
+//!     ctx.placeOrder({ side: "buy", quantity: 1.0, price: candle.close, take_profit: candle.close * 1.05 });
+//!   }
+//! });
+//! console.log(bt.metrics());
+//! ```
+//!
+//! It needs to enable `wasm` feature to use it.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::engine::{Backtest, Candle, Order, OrderSide, OrderType};
+use crate::errors::{Error, Result};
+use crate::metrics::Metrics;
+
+use js_sys::Function;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+/// Converts a value to a plain JavaScript value (objects instead of maps).
+fn to_js<T: Serialize + ?Sized>(value: &T) -> std::result::Result<JsValue, JsError> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(value.serialize(&serializer)?)
+}
+
+/// An order described by the strategy, e.g. `{ side: "buy", quantity: 1.0, price: 100.0 }`.
+#[derive(Debug, Clone, Deserialize)]
+struct OrderSpec {
+    side: String,
+    quantity: f64,
+    price: f64,
+    #[serde(default)]
+    order_type: Option<String>,
+    #[serde(default)]
+    take_profit: Option<f64>,
+    #[serde(default)]
+    stop_loss: Option<f64>,
+    #[serde(default)]
+    trailing_stop: Option<f64>,
+}
+
+impl TryFrom<OrderSpec> for Order {
+    type Error = Error;
+
+    fn try_from(spec: OrderSpec) -> Result<Self> {
+        let side = match spec.side.to_lowercase().as_str() {
+            "buy" => OrderSide::Buy,
+            "sell" => OrderSide::Sell,
+            _ => return Err(Error::Msg(format!("Unknown order side: {}", spec.side))),
+        };
+        let price = spec.price;
+        let entry_type = match spec.order_type.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("market") => OrderType::Market(price),
+            Some("limit") => OrderType::Limit(price),
+            Some("stop") => OrderType::Stop(price),
+            Some(order_type) => return Err(Error::Msg(format!("Unknown order type: {order_type}"))),
+        };
+        let order = match (spec.take_profit, spec.stop_loss, spec.trailing_stop) {
+            (None, None, None) => (entry_type, spec.quantity, side).into(),
+            (_, _, Some(percent)) => (entry_type, OrderType::TrailingStop(price, percent), spec.quantity, side).into(),
+            (take_profit, stop_loss, None) => {
+                let exit_rule = OrderType::TakeProfitAndStopLoss(take_profit.unwrap_or(0.0), stop_loss.unwrap_or(0.0));
+                (entry_type, exit_rule, spec.quantity, side).into()
+            }
+        };
+        Ok(order)
+    }
+}
+
+/// An action requested by the JavaScript strategy.
+#[derive(Debug)]
+enum Action {
+    PlaceOrder(Box<Order>),
+    DeleteOrder(u32),
+    ClosePosition(u32, f64),
+    CloseAllPositions(f64),
+}
+
+/// Applies the actions of the strategy to the backtest.
+fn apply(bts: &mut Backtest, candle: &Candle, actions: Vec<Action>) -> Result<()> {
+    for action in actions {
+        match action {
+            Action::PlaceOrder(order) => bts.place_order(candle, *order)?,
+            Action::DeleteOrder(id) => {
+                let order = *bts.orders().find(|o| o.id() == id).ok_or(Error::OrderNotFound)?;
+                bts.delete_order(candle, &order, true)?;
+            }
+            Action::ClosePosition(id, exit_price) => {
+                let position = *bts.positions().find(|p| p.id() == id).ok_or(Error::PositionNotFound)?;
+                bts.close_position(candle, &position, exit_price, true)?;
+            }
+            Action::CloseAllPositions(exit_price) => bts.close_all_positions(candle, exit_price)?,
+        }
+    }
+    Ok(())
+}
+
+/// The state of the backtest given to the strategy, which queues its actions.
+#[wasm_bindgen]
+pub struct Context {
+    balance: f64,
+    free_balance: f64,
+    orders: Vec<Order>,
+    positions: Vec<crate::engine::Position>,
+    actions: Rc<RefCell<Vec<Action>>>,
+}
+
+#[wasm_bindgen]
+impl Context {
+    /// Returns the balance of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn balance(&self) -> f64 {
+        self.balance
+    }
+
+    /// Returns the balance not locked by the orders and positions.
+    #[wasm_bindgen(getter, js_name = freeBalance)]
+    pub fn free_balance(&self) -> f64 {
+        self.free_balance
+    }
+
+    /// Returns the pending orders.
+    pub fn orders(&self) -> std::result::Result<JsValue, JsError> {
+        to_js(&self.orders)
+    }
+
+    /// Returns the open positions.
+    pub fn positions(&self) -> std::result::Result<JsValue, JsError> {
+        to_js(&self.positions)
+    }
+
+    /// Places an order (e.g., `{ side: "sell", quantity: 1.0, price: 100.0, order_type: "limit" }`)
+    /// once the strategy returns.
+    #[wasm_bindgen(js_name = placeOrder)]
+    pub fn place_order(&self, order: JsValue) -> std::result::Result<(), JsError> {
+        let spec: OrderSpec = serde_wasm_bindgen::from_value(order)?;
+        self.actions
+            .borrow_mut()
+            .push(Action::PlaceOrder(Box::new(spec.try_into()?)));
+        Ok(())
+    }
+
+    /// Deletes a pending order by its id once the strategy returns.
+    #[wasm_bindgen(js_name = deleteOrder)]
+    pub fn delete_order(&self, id: u32) {
+        self.actions.borrow_mut().push(Action::DeleteOrder(id));
+    }
+
+    /// Closes a position by its id at `exit_price` once the strategy returns.
+    #[wasm_bindgen(js_name = closePosition)]
+    pub fn close_position(&self, id: u32, exit_price: f64) {
+        self.actions.borrow_mut().push(Action::ClosePosition(id, exit_price));
+    }
+
+    /// Closes all the positions at `exit_price` once the strategy returns.
+    #[wasm_bindgen(js_name = closeAllPositions)]
+    pub fn close_all_positions(&self, exit_price: f64) {
+        self.actions.borrow_mut().push(Action::CloseAllPositions(exit_price));
+    }
+}
+
+/// The backtest engine.
+#[wasm_bindgen(js_name = Backtest)]
+pub struct WasmBacktest(Backtest);
+
+#[wasm_bindgen(js_class = Backtest)]
+impl WasmBacktest {
+    /// Creates a backtest over the candles, with optional taker and maker fee percentages.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        candles: JsValue,
+        initial_balance: f64,
+        taker_fee: Option<f64>,
+        maker_fee: Option<f64>,
+    ) -> std::result::Result<WasmBacktest, JsError> {
+        let candles: Vec<Candle> = serde_wasm_bindgen::from_value(candles)?;
+        let market_fees = taker_fee.map(|taker| (taker, maker_fee.unwrap_or(taker)));
+        let bts = Backtest::new(candles.into_iter().collect::<Arc<[_]>>(), initial_balance, market_fees)?;
+        Ok(Self(bts))
+    }
+
+    /// Runs `strategy(ctx, candle)` on each candle.
+    ///
+    /// An exception thrown by the strategy stops the run and is thrown again.
+    pub fn run(&mut self, strategy: &Function) -> std::result::Result<(), JsValue> {
+        let mut thrown = None;
+        let result = self.0.run(|bts, candle| {
+            let actions = Rc::new(RefCell::new(Vec::new()));
+            let ctx = Context {
+                balance: bts.balance(),
+                free_balance: bts.free_balance()?,
+                orders: bts.orders().copied().collect(),
+                positions: bts.positions().copied().collect(),
+                actions: Rc::clone(&actions),
+            };
+            let js_candle = to_js(candle).map_err(|_| Error::Msg("Invalid candle".to_string()))?;
+            if let Err(e) = strategy.call2(&JsValue::NULL, &ctx.into(), &js_candle) {
+                thrown = Some(e);
+                return Err(Error::Msg("The strategy has thrown an exception".to_string()));
+            }
+            apply(bts, candle, actions.take())
+        });
+        match (result, thrown) {
+            (_, Some(e)) => Err(e),
+            (result, None) => result.map_err(|e| JsError::from(e).into()),
+        }
+    }
+
+    /// Resets the backtest to its initial state.
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    /// Returns the balance of the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn balance(&self) -> f64 {
+        self.0.balance()
+    }
+
+    /// Returns the fees paid.
+    #[wasm_bindgen(getter, js_name = feesPaid)]
+    pub fn fees_paid(&self) -> f64 {
+        self.0.fees_paid()
+    }
+
+    /// Returns the pending orders.
+    pub fn orders(&self) -> std::result::Result<JsValue, JsError> {
+        to_js(&self.0.orders().collect::<Vec<_>>())
+    }
+
+    /// Returns the open positions.
+    pub fn positions(&self) -> std::result::Result<JsValue, JsError> {
+        to_js(&self.0.positions().collect::<Vec<_>>())
+    }
+
+    /// Returns the events of the run.
+    pub fn events(&self) -> std::result::Result<JsValue, JsError> {
+        to_js(&self.0.events().collect::<Vec<_>>())
+    }
+
+    /// Returns the performance metrics of the run.
+    pub fn metrics(&self) -> std::result::Result<JsValue, JsError> {
+        let metrics = Metrics::from(&self.0);
+        to_js(&json!({
+            "initial_balance": metrics.initial_balance(),
+            "balance": metrics.balance(),
+            "fees": metrics.fees(),
+            "max_drawdown": metrics.max_drawdown(),
+            "profit_factor": metrics.profit_factor(),
+            "sharpe_ratio": metrics.sharpe_ratio(0.0),
+            "annualized_sharpe_ratio": metrics.annualized_sharpe_ratio(0.0),
+            "cagr": metrics.cagr(),
+            "win_rate": metrics.win_rate(),
+            "equity_curve": metrics.equity_curve(),
+        }))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn wasm_actions() {
+    use chrono::DateTime;
+
+    let spec = |json: &str| serde_json::from_str::<OrderSpec>(json).unwrap();
+    let order = Order::try_from(spec(
+        r#"{"side":"buy","quantity":1.0,"price":100.0,"take_profit":110.0}"#,
+    ))
+    .unwrap();
+    assert_eq!(order.entry_type(), &OrderType::Market(100.0));
+    assert_eq!(order.exit_rule(), Some(&OrderType::TakeProfitAndStopLoss(110.0, 0.0)));
+    let order = Order::try_from(spec(
+        r#"{"side":"SELL","quantity":1.0,"price":90.0,"order_type":"limit"}"#,
+    ))
+    .unwrap();
+    assert_eq!(order.entry_type(), &OrderType::Limit(90.0));
+    assert!(Order::try_from(spec(r#"{"side":"hold","quantity":1.0,"price":90.0}"#)).is_err());
+
+    let candle = crate::engine::CandleBuilder::builder()
+        .open(100.0)
+        .high(111.0)
+        .low(99.0)
+        .close(110.0)
+        .volume(1.0)
+        .open_time(DateTime::default())
+        .close_time(DateTime::default() + chrono::Duration::days(1))
+        .build()
+        .unwrap();
+    let mut bts = Backtest::new(Arc::from_iter(vec![candle; 2]), 1000.0, None).unwrap();
+    bts.run(|bts, candle| {
+        let actions = match bts.positions().next() {
+            None if bts.orders().count() == 0 => vec![Action::PlaceOrder(Box::new(
+                (OrderType::Market(100.0), 1.0, OrderSide::Buy).into(),
+            ))],
+            Some(position) => vec![Action::ClosePosition(position.id(), 110.0)],
+            None => vec![],
+        };
+        apply(bts, candle, actions)
+    })
+    .unwrap();
+    assert_eq!(bts.balance(), 1010.0);
+    assert!(apply(&mut bts, &candle, vec![Action::DeleteOrder(0)]).is_err());
+}